@@ -21,12 +21,15 @@ use std::{
 };
 
 use crate::{
-    data_model::objects::{Access, ClusterId, EndptId, Privilege},
+    data_model::{
+        objects::{Access, ClusterId, DeviceType, EndptId, Privilege},
+        system_model::access_control::{AccessControlEntryChanged, AccessControlExtensionChanged},
+    },
     error::Error,
     fabric,
     interaction_model::messages::GenericPath,
     sys::Psm,
-    tlv::{FromTLV, TLVElement, TLVList, TLVWriter, TagType, ToTLV},
+    tlv::{FromTLV, TLVArrayOwned, TLVElement, TLVList, TLVWriter, TagType, ToTLV},
     transport::session::MAX_CAT_IDS_PER_NOC,
     utils::writebuf::WriteBuf,
 };
@@ -37,6 +40,9 @@ use num_derive::FromPrimitive;
 pub const SUBJECTS_PER_ENTRY: usize = 4;
 pub const TARGETS_PER_ENTRY: usize = 3;
 pub const ENTRIES_PER_FABRIC: usize = 3;
+pub const EXTENSIONS_PER_FABRIC: usize = 1;
+/// The maximum length of the data of an ACL Extension
+pub const MAX_EXTENSION_DATA_LEN: usize = 128;
 
 // TODO: Check if this and the SessionMode can be combined into some generic data structure
 #[derive(FromPrimitive, Copy, Clone, PartialEq, Debug)]
@@ -185,6 +191,15 @@ impl Accessor {
             acl_mgr,
         }
     }
+
+    pub fn auth_mode(&self) -> AuthMode {
+        self.auth_mode
+    }
+
+    /// The node ID of the accessor, this is the first of its subjects
+    pub fn node_id(&self) -> u64 {
+        self.subjects.0[0]
+    }
 }
 
 #[derive(Debug)]
@@ -193,8 +208,9 @@ pub struct AccessDesc<'a> {
     path: &'a GenericPath,
     /// The target permissions
     target_perms: Option<Access>,
-    // The operation being done
-    // TODO: Currently this is Access, but we need a way to represent the 'invoke' somehow too
+    /// The device type of the target's endpoint
+    dev_type: Option<DeviceType>,
+    /// The operation being done: READ, WRITE or INVOKE
     operation: Access,
 }
 
@@ -215,6 +231,7 @@ impl<'a> AccessReq<'a> {
             object: AccessDesc {
                 path,
                 target_perms: None,
+                dev_type: None,
                 operation,
             },
         }
@@ -228,6 +245,13 @@ impl<'a> AccessReq<'a> {
         self.object.target_perms = Some(perms);
     }
 
+    /// Add the device type of the target's endpoint to the request
+    ///
+    /// This is what the ACL targets with a device type are matched against
+    pub fn set_target_dev_type(&mut self, dev_type: DeviceType) {
+        self.object.dev_type = Some(dev_type);
+    }
+
     /// Checks if access is allowed
     ///
     /// This checks all the ACL list to identify if any of the ACLs provides the
@@ -236,6 +260,14 @@ impl<'a> AccessReq<'a> {
     pub fn allow(&self) -> bool {
         self.accessor.acl_mgr.allow(self)
     }
+
+    /// Explains the access decision
+    ///
+    /// This is meant for debugging: unlike [AccessReq::allow], it evaluates every ACL
+    /// entry, and records why each of them did or didn't allow the access
+    pub fn explain(&self) -> AclDecision {
+        self.accessor.acl_mgr.explain(self)
+    }
 }
 
 #[derive(FromTLV, ToTLV, Copy, Clone, Debug, PartialEq)]
@@ -311,9 +343,13 @@ impl AclEntry {
         Ok(())
     }
 
-    fn match_accessor(&self, accessor: &Accessor) -> bool {
+    fn match_accessor(&self, accessor: &Accessor) -> Result<(), AclMismatch> {
+        if self.fab_idx != Some(accessor.fab_idx) {
+            return Err(AclMismatch::Fabric);
+        }
+
         if self.auth_mode != accessor.auth_mode {
-            return false;
+            return Err(AclMismatch::AuthMode);
         }
 
         let mut allow = false;
@@ -329,17 +365,22 @@ impl AclEntry {
             allow = true;
         }
 
-        // true if both are true
-        allow && self.fab_idx == Some(accessor.fab_idx)
+        if allow {
+            Ok(())
+        } else {
+            Err(AclMismatch::Subject)
+        }
     }
 
-    fn match_access_desc(&self, object: &AccessDesc) -> bool {
+    fn match_access_desc(&self, object: &AccessDesc) -> Result<(), AclMismatch> {
         let mut allow = false;
         let mut entries_exist = false;
         for t in self.targets.iter().flatten() {
             entries_exist = true;
             if (t.endpoint.is_none() || t.endpoint == object.path.endpoint)
                 && (t.cluster.is_none() || t.cluster == object.path.cluster)
+                && (t.device_type.is_none()
+                    || t.device_type == object.dev_type.map(|d| d.dtype as u32))
             {
                 allow = true
             }
@@ -349,40 +390,137 @@ impl AclEntry {
             allow = true;
         }
 
-        if allow {
-            // Check that the object's access allows this operation with this privilege
-            if let Some(access) = object.target_perms {
-                access.is_ok(object.operation, self.privilege)
-            } else {
-                false
-            }
-        } else {
-            false
+        if !allow {
+            return Err(AclMismatch::Target);
+        }
+
+        // Check that the object's access allows this operation with this privilege
+        match object.target_perms {
+            Some(access) if access.is_ok(object.operation, self.privilege) => Ok(()),
+            _ => Err(AclMismatch::Privilege),
         }
     }
 
+    /// Returns why this entry doesn't allow the request, None if it does
+    pub fn explain(&self, req: &AccessReq) -> Option<AclMismatch> {
+        self.match_accessor(req.accessor)
+            .and_then(|_| self.match_access_desc(&req.object))
+            .err()
+    }
+
     pub fn allow(&self, req: &AccessReq) -> bool {
-        self.match_accessor(req.accessor) && self.match_access_desc(&req.object)
+        self.explain(req).is_none()
+    }
+}
+
+/// The reason an ACL entry doesn't allow an access request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AclMismatch {
+    /// The entry belongs to another fabric
+    Fabric,
+    /// The entry is for another auth mode
+    AuthMode,
+    /// None of the entry's subjects (node IDs or CATs) is the accessor
+    Subject,
+    /// None of the entry's targets is the path
+    Target,
+    /// The entry's privilege isn't sufficient, or the target doesn't allow the operation at all
+    Privilege,
+}
+
+/// The evaluation of a single ACL entry for an access request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AclEntryDecision {
+    /// The fabric of the entry
+    pub fab_idx: Option<u8>,
+    /// The index of the entry in the ACL list of its fabric
+    pub index: usize,
+    /// The privilege that the entry grants
+    pub granted: Privilege,
+    /// Why the entry doesn't allow the request, None if it does
+    pub mismatch: Option<AclMismatch>,
+}
+
+/// The explanation of an access decision, as returned by [AccessReq::explain]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AclDecision {
+    pub allowed: bool,
+    /// The least privilege that the operation on the target requires, None if the
+    /// target doesn't allow the operation at all
+    pub required: Option<Privilege>,
+    /// PASE sessions are allowed implicitly, without considering any entries
+    pub implicit: bool,
+    /// All the entries that were considered, in order
+    pub entries: Vec<AclEntryDecision>,
+}
+
+/// An entry of the Extension attribute of the Access Control cluster
+///
+/// The data is opaque to us, it is only stored for the fabric that wrote it.
+#[derive(ToTLV, FromTLV, Clone, Debug, PartialEq)]
+#[tlvargs(start = 1)]
+pub struct AclExtension {
+    data: Vec<u8>,
+    #[tagval(0xFE)]
+    pub fab_idx: Option<u8>,
+}
+
+impl AclExtension {
+    pub fn new(fab_idx: u8, data: &[u8]) -> Result<Self, Error> {
+        if data.len() > MAX_EXTENSION_DATA_LEN {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(Self {
+            data: data.to_vec(),
+            fab_idx: Some(fab_idx),
+        })
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
     }
 }
 
 const MAX_ACL_ENTRIES: usize = ENTRIES_PER_FABRIC * fabric::MAX_SUPPORTED_FABRICS;
 type AclEntries = [Option<AclEntry>; MAX_ACL_ENTRIES];
+const MAX_EXTENSIONS: usize = EXTENSIONS_PER_FABRIC * fabric::MAX_SUPPORTED_FABRICS;
 
-#[derive(ToTLV, FromTLV, Debug)]
+#[derive(Debug)]
 struct AclMgrInner {
     entries: AclEntries,
+    extensions: Vec<AclExtension>,
 }
 
 const ACL_KV_ENTRY: &str = "acl";
 const ACL_KV_MAX_SIZE: usize = 300;
+const ACL_EXT_KV_ENTRY: &str = "aclext";
+// The data, and the tags and control bytes around it
+const ACL_EXT_KV_MAX_SIZE: usize = MAX_EXTENSIONS * (MAX_EXTENSION_DATA_LEN + 10) + 2;
 impl AclMgrInner {
+    fn new() -> Self {
+        const INIT: Option<AclEntry> = None;
+        Self {
+            entries: [INIT; MAX_ACL_ENTRIES],
+            extensions: Vec::new(),
+        }
+    }
+
     pub fn store(&self, psm: &MutexGuard<Psm>) -> Result<(), Error> {
         let mut acl_tlvs = [0u8; ACL_KV_MAX_SIZE];
         let mut wb = WriteBuf::new(&mut acl_tlvs, ACL_KV_MAX_SIZE);
         let mut tw = TLVWriter::new(&mut wb);
         self.entries.to_tlv(&mut tw, TagType::Anonymous)?;
-        psm.set_kv_slice(ACL_KV_ENTRY, wb.as_slice())
+        psm.set_kv_slice(ACL_KV_ENTRY, wb.as_slice())?;
+
+        let mut ext_tlvs = [0u8; ACL_EXT_KV_MAX_SIZE];
+        let mut wb = WriteBuf::new(&mut ext_tlvs, ACL_EXT_KV_MAX_SIZE);
+        let mut tw = TLVWriter::new(&mut wb);
+        tw.start_array(TagType::Anonymous)?;
+        for e in &self.extensions {
+            e.to_tlv(&mut tw, TagType::Anonymous)?;
+        }
+        tw.end_container()?;
+        psm.set_kv_slice(ACL_EXT_KV_ENTRY, wb.as_slice())
     }
 
     pub fn load(psm: &MutexGuard<Psm>) -> Result<Self, Error> {
@@ -393,9 +531,62 @@ impl AclMgrInner {
             .next()
             .ok_or(Error::Invalid)?;
 
-        Ok(Self {
+        let mut inner = Self {
             entries: AclEntries::from_tlv(&root)?,
-        })
+            // Older versions didn't store any extensions
+            extensions: Self::load_extensions(psm).unwrap_or_default(),
+        };
+        // Older versions could also leave holes in the table
+        inner.compact();
+        Ok(inner)
+    }
+
+    fn load_extensions(psm: &MutexGuard<Psm>) -> Result<Vec<AclExtension>, Error> {
+        let mut ext_tlvs = Vec::new();
+        psm.get_kv_slice(ACL_EXT_KV_ENTRY, &mut ext_tlvs)?;
+        let root = TLVList::new(&ext_tlvs)
+            .iter()
+            .next()
+            .ok_or(Error::Invalid)?;
+        let extensions = TLVArrayOwned::<AclExtension>::from_tlv(&root)?;
+        Ok(extensions.iter().cloned().collect())
+    }
+
+    /// Traverse the fabric specific extensions to find the index
+    fn ext_index_in_fabric(&self, index: u8, fab_idx: u8) -> Result<usize, Error> {
+        self.extensions
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.fab_idx == Some(fab_idx))
+            .nth(index as usize)
+            .map(|(i, _)| i)
+            .ok_or(Error::NotFound)
+    }
+
+    fn delete_entries_for_fabric(&mut self, fab_idx: u8) {
+        for i in 0..MAX_ACL_ENTRIES {
+            if self.entries[i]
+                .filter(|e| e.fab_idx == Some(fab_idx))
+                .is_some()
+            {
+                self.entries[i] = None;
+            }
+        }
+        self.compact();
+    }
+
+    /// Move all the entries to the front of the table
+    ///
+    /// List writes expect an added entry to be appended at the end of the fabric's list,
+    /// so deletions shouldn't leave holes that a later add could fill
+    fn compact(&mut self) {
+        let mut next = 0;
+        for i in 0..MAX_ACL_ENTRIES {
+            if let Some(entry) = self.entries[i].take() {
+                self.entries[next] = Some(entry);
+                next += 1;
+            }
+        }
     }
 
     /// Traverse fabric specific entries to find the index
@@ -422,11 +613,24 @@ impl AclMgrInner {
     }
 }
 
+/// An audit hook for the application: it is called with every change that an
+/// administrator makes to the ACL, for example to keep an audit log
+///
+/// The changes carry the fields of the AccessControlEntryChanged and
+/// AccessControlExtensionChanged events of the Access Control cluster. The same
+/// events are also kept in the [EventLog](crate::data_model::objects::EventLog) of
+/// the Data Model, where they can be read through the Interaction Model.
+pub trait AclAuditListener: Send + Sync {
+    fn entry_changed(&self, event: &AccessControlEntryChanged);
+    fn extension_changed(&self, _event: &AccessControlExtensionChanged) {}
+}
+
 pub struct AclMgr {
     inner: RwLock<AclMgrInner>,
     // The Option<> is solely because test execution is faster
     // Doing this here adds the least overhead during ACL verification
     psm: Option<Arc<Mutex<Psm>>>,
+    listeners: RwLock<Vec<Arc<dyn AclAuditListener>>>,
 }
 
 impl AclMgr {
@@ -435,13 +639,10 @@ impl AclMgr {
     }
 
     pub fn new_with(psm_support: bool) -> Result<Self, Error> {
-        const INIT: Option<AclEntry> = None;
         let mut psm = None;
 
         let inner = if !psm_support {
-            AclMgrInner {
-                entries: [INIT; MAX_ACL_ENTRIES],
-            }
+            AclMgrInner::new()
         } else {
             let psm_handle = Psm::get()?;
             let inner = {
@@ -450,24 +651,48 @@ impl AclMgr {
             };
 
             psm = Some(psm_handle);
-            inner.unwrap_or({
-                // Error loading from PSM
-                AclMgrInner {
-                    entries: [INIT; MAX_ACL_ENTRIES],
+            match inner {
+                Ok(inner) => inner,
+                // Nothing was stored yet
+                Err(Error::StdIoError) => AclMgrInner::new(),
+                // Don't start with an empty ACL that would then be stored over
+                // the one we couldn't read
+                Err(e) => {
+                    error!("Error loading the ACL: {:?}", e);
+                    return Err(e);
                 }
-            })
+            }
         };
         Ok(Self {
             inner: RwLock::new(inner),
             psm,
+            listeners: RwLock::new(Vec::new()),
         })
     }
 
+    /// Register _listener_ to audit the changes made through the Access Control cluster
+    pub fn add_audit_listener(&self, listener: Arc<dyn AclAuditListener>) {
+        self.listeners.write().unwrap().push(listener);
+    }
+
+    pub(crate) fn notify_entry_changed(&self, event: &AccessControlEntryChanged) {
+        for listener in self.listeners.read().unwrap().iter() {
+            listener.entry_changed(event);
+        }
+    }
+
+    pub(crate) fn notify_extension_changed(&self, event: &AccessControlExtensionChanged) {
+        for listener in self.listeners.read().unwrap().iter() {
+            listener.extension_changed(event);
+        }
+    }
+
     pub fn erase_all(&self) {
         let mut inner = self.inner.write().unwrap();
         for i in 0..MAX_ACL_ENTRIES {
             inner.entries[i] = None;
         }
+        inner.extensions.clear();
         if let Some(psm) = self.psm.as_ref() {
             let psm = psm.lock().unwrap();
             let _ = inner.store(&psm).map_err(|e| {
@@ -520,6 +745,7 @@ impl AclMgr {
         let mut inner = self.inner.write().unwrap();
         let old = inner.for_index_in_fabric(index, fab_idx)?;
         *old = None;
+        inner.compact();
 
         if let Some(psm) = self.psm.as_ref() {
             let psm = psm.lock().unwrap();
@@ -529,18 +755,68 @@ impl AclMgr {
         }
     }
 
+    /// Delete the entries and the extensions of the fabric _fab_idx_
     pub fn delete_for_fabric(&self, fab_idx: u8) -> Result<(), Error> {
         let mut inner = self.inner.write().unwrap();
+        inner.delete_entries_for_fabric(fab_idx);
+        inner.extensions.retain(|e| e.fab_idx != Some(fab_idx));
+        self.store(&inner)
+    }
 
-        for i in 0..MAX_ACL_ENTRIES {
-            if inner.entries[i]
-                .filter(|e| e.fab_idx == Some(fab_idx))
-                .is_some()
-            {
-                inner.entries[i] = None;
-            }
+    /// Delete the entries of the fabric _fab_idx_, keeping its extensions
+    pub fn delete_entries_for_fabric(&self, fab_idx: u8) -> Result<(), Error> {
+        let mut inner = self.inner.write().unwrap();
+        inner.delete_entries_for_fabric(fab_idx);
+        self.store(&inner)
+    }
+
+    pub fn add_extension(&self, ext: AclExtension) -> Result<(), Error> {
+        let mut inner = self.inner.write().unwrap();
+        let cnt = inner
+            .extensions
+            .iter()
+            .filter(|e| e.fab_idx == ext.fab_idx)
+            .count();
+        if cnt >= EXTENSIONS_PER_FABRIC {
+            return Err(Error::NoSpace);
         }
+        inner.extensions.push(ext);
+        self.store(&inner)
+    }
 
+    // Like the entries, the index is only for extensions with the matching fabric index
+    pub fn edit_extension(&self, index: u8, fab_idx: u8, new: AclExtension) -> Result<(), Error> {
+        let mut inner = self.inner.write().unwrap();
+        let i = inner.ext_index_in_fabric(index, fab_idx)?;
+        inner.extensions[i] = new;
+        self.store(&inner)
+    }
+
+    pub fn delete_extension(&self, index: u8, fab_idx: u8) -> Result<(), Error> {
+        let mut inner = self.inner.write().unwrap();
+        let i = inner.ext_index_in_fabric(index, fab_idx)?;
+        inner.extensions.remove(i);
+        self.store(&inner)
+    }
+
+    pub fn delete_extensions_for_fabric(&self, fab_idx: u8) -> Result<(), Error> {
+        let mut inner = self.inner.write().unwrap();
+        inner.extensions.retain(|e| e.fab_idx != Some(fab_idx));
+        self.store(&inner)
+    }
+
+    pub fn for_each_extension<T>(&self, mut f: T) -> Result<(), Error>
+    where
+        T: FnMut(&AclExtension),
+    {
+        let inner = self.inner.read().unwrap();
+        for ext in inner.extensions.iter() {
+            f(ext)
+        }
+        Ok(())
+    }
+
+    fn store(&self, inner: &AclMgrInner) -> Result<(), Error> {
         if let Some(psm) = self.psm.as_ref() {
             let psm = psm.lock().unwrap();
             inner.store(&psm)
@@ -578,6 +854,53 @@ impl AclMgr {
         error!("{}", self);
         false
     }
+
+    /// Explains the decision that [AclMgr::allow] makes for _req_
+    pub fn explain(&self, req: &AccessReq) -> AclDecision {
+        let required = req
+            .object
+            .target_perms
+            .and_then(|p| p.required_privilege(req.object.operation));
+        let mut decision = AclDecision {
+            allowed: false,
+            required,
+            implicit: false,
+            entries: Vec::new(),
+        };
+
+        // PASE Sessions have implicit access grant
+        if req.accessor.auth_mode == AuthMode::Pase {
+            decision.allowed = true;
+            decision.implicit = true;
+            return decision;
+        }
+
+        let inner = self.inner.read().unwrap();
+        for e in inner.entries.iter().flatten() {
+            let index = decision
+                .entries
+                .iter()
+                .filter(|d| d.fab_idx == e.fab_idx)
+                .count();
+            let mismatch = e.explain(req);
+            decision.allowed |= mismatch.is_none();
+            decision.entries.push(AclEntryDecision {
+                fab_idx: e.fab_idx,
+                index,
+                granted: e.privilege,
+                mismatch,
+            });
+        }
+        decision
+    }
+}
+
+impl fabric::FabricListener for AclMgr {
+    fn fabric_removed(&self, fab_idx: u8) {
+        if let Err(e) = self.delete_for_fabric(fab_idx) {
+            error!("Error removing the ACLs of fabric {}: {}", fab_idx, e);
+        }
+    }
 }
 
 impl std::fmt::Display for AclMgr {
@@ -596,12 +919,16 @@ impl std::fmt::Display for AclMgr {
 mod tests {
     use crate::{
         acl::{gen_noc_cat, AccessorSubjects},
-        data_model::objects::{Access, Privilege},
+        data_model::objects::{Access, DeviceType, Privilege},
+        error::Error,
         interaction_model::messages::GenericPath,
     };
     use std::sync::Arc;
 
-    use super::{AccessReq, Accessor, AclEntry, AclMgr, AuthMode, Target};
+    use super::{
+        AccessReq, Accessor, AclDecision, AclEntry, AclEntryDecision, AclExtension, AclMgr,
+        AclMismatch, AuthMode, Target, MAX_EXTENSION_DATA_LEN,
+    };
 
     #[test]
     fn test_basic_empty_subject_target() {
@@ -784,6 +1111,42 @@ mod tests {
         assert_eq!(req.allow(), true);
     }
 
+    #[test]
+    fn test_target_device_type() {
+        let am = Arc::new(AclMgr::new_with(false).unwrap());
+        am.erase_all();
+        let accessor = Accessor::new(2, AccessorSubjects::new(112233), AuthMode::Case, am.clone());
+        let path = GenericPath::new(Some(1), Some(1234), None);
+        let mut req = AccessReq::new(&accessor, &path, Access::READ);
+        req.set_target_perms(Access::RWVA);
+
+        let mut new = AclEntry::new(2, Privilege::VIEW, AuthMode::Case);
+        new.add_target(Target {
+            cluster: None,
+            endpoint: None,
+            device_type: Some(0x0100),
+        })
+        .unwrap();
+        am.add(new).unwrap();
+
+        // Deny if the device type of the endpoint isn't known
+        assert_eq!(req.allow(), false);
+
+        // Deny for device type mismatch
+        req.set_target_dev_type(DeviceType {
+            dtype: 0x0016,
+            drev: 1,
+        });
+        assert_eq!(req.allow(), false);
+
+        // Allow for device type match
+        req.set_target_dev_type(DeviceType {
+            dtype: 0x0100,
+            drev: 2,
+        });
+        assert_eq!(req.allow(), true);
+    }
+
     #[test]
     fn test_privilege() {
         let am = Arc::new(AclMgr::new_with(false).unwrap());
@@ -854,4 +1217,113 @@ mod tests {
         assert_eq!(req2.allow(), false);
         assert_eq!(req3.allow(), true);
     }
+
+    #[test]
+    fn test_add_after_delete() {
+        let am = Arc::new(AclMgr::new_with(false).unwrap());
+        am.erase_all();
+
+        for subject in [1, 2, 3] {
+            let mut new = AclEntry::new(2, Privilege::VIEW, AuthMode::Case);
+            new.add_subject(subject).unwrap();
+            am.add(new).unwrap();
+        }
+
+        // Deleting index 0 shifts the remaining entries, and an add is appended at the end
+        am.delete(0, 2).unwrap();
+        let mut new = AclEntry::new(2, Privilege::VIEW, AuthMode::Case);
+        new.add_subject(4).unwrap();
+        am.add(new).unwrap();
+
+        let mut subjects = Vec::new();
+        am.for_each_acl(|e| subjects.push(e.subjects[0])).unwrap();
+        assert_eq!(subjects, [Some(2), Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn test_explain() {
+        let am = Arc::new(AclMgr::new_with(false).unwrap());
+        am.erase_all();
+        let accessor = Accessor::new(2, AccessorSubjects::new(112233), AuthMode::Case, am.clone());
+        let path = GenericPath::new(Some(1), Some(1234), None);
+        let mut req = AccessReq::new(&accessor, &path, Access::WRITE);
+        req.set_target_perms(Access::RWVA);
+
+        am.add(AclEntry::new(3, Privilege::ADMIN, AuthMode::Case))
+            .unwrap();
+        am.add(AclEntry::new(2, Privilege::ADMIN, AuthMode::Group))
+            .unwrap();
+        let mut new = AclEntry::new(2, Privilege::ADMIN, AuthMode::Case);
+        new.add_target(Target::new(Some(5), None, None)).unwrap();
+        am.add(new).unwrap();
+        am.add(AclEntry::new(2, Privilege::VIEW, AuthMode::Case))
+            .unwrap();
+
+        let entry = |fab_idx, index, granted, mismatch| AclEntryDecision {
+            fab_idx: Some(fab_idx),
+            index,
+            granted,
+            mismatch: Some(mismatch),
+        };
+        assert_eq!(
+            req.explain(),
+            AclDecision {
+                allowed: false,
+                required: Some(Privilege::ADMIN),
+                implicit: false,
+                entries: vec![
+                    entry(3, 0, Privilege::ADMIN, AclMismatch::Fabric),
+                    entry(2, 0, Privilege::ADMIN, AclMismatch::AuthMode),
+                    entry(2, 1, Privilege::ADMIN, AclMismatch::Target),
+                    entry(2, 2, Privilege::VIEW, AclMismatch::Privilege),
+                ],
+            }
+        );
+
+        am.delete_for_fabric(2).unwrap();
+        let mut new = AclEntry::new(2, Privilege::ADMIN, AuthMode::Case);
+        new.add_subject(112232).unwrap();
+        am.add(new).unwrap();
+        am.add(AclEntry::new(2, Privilege::ADMIN, AuthMode::Case))
+            .unwrap();
+
+        let decision = req.explain();
+        assert_eq!(decision.allowed, req.allow());
+        assert_eq!(decision.allowed, true);
+        assert_eq!(decision.entries[1].mismatch, Some(AclMismatch::Subject));
+        assert_eq!(decision.entries[2].mismatch, None);
+    }
+
+    #[test]
+    fn test_extensions() {
+        let am = Arc::new(AclMgr::new_with(false).unwrap());
+        am.erase_all();
+
+        let too_long = [0u8; MAX_EXTENSION_DATA_LEN + 1];
+        assert_eq!(AclExtension::new(2, &too_long), Err(Error::InvalidArgument));
+
+        am.add_extension(AclExtension::new(2, &[1, 2, 3]).unwrap())
+            .unwrap();
+        am.add_extension(AclExtension::new(3, &[4, 5]).unwrap())
+            .unwrap();
+        // Only a single extension per fabric
+        assert_eq!(
+            am.add_extension(AclExtension::new(2, &[6]).unwrap()),
+            Err(Error::NoSpace)
+        );
+
+        // Index 0 of fabric 3 is its own extension, not that of fabric 2
+        am.edit_extension(0, 3, AclExtension::new(3, &[7]).unwrap())
+            .unwrap();
+        let mut exts = Vec::new();
+        am.for_each_extension(|e| exts.push((e.fab_idx, e.data().to_vec())))
+            .unwrap();
+        assert_eq!(exts, [(Some(2), vec![1, 2, 3]), (Some(3), vec![7])]);
+
+        // The extensions go with the fabric
+        am.delete_for_fabric(2).unwrap();
+        let mut exts = Vec::new();
+        am.for_each_extension(|e| exts.push(e.fab_idx)).unwrap();
+        assert_eq!(exts, [Some(3)]);
+    }
 }
@@ -0,0 +1,269 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! The Certification Declaration
+//!
+//! The Certification Declaration is a TLV structure, wrapped in a CMS (RFC 5652)
+//! SignedData envelope with a single signer that is identified by its Subject Key
+//! Identifier, and without signed attributes.
+
+use log::error;
+
+use crate::{
+    cert::{
+        asn1_reader::{TAG_CTX, TAG_CTX_CONSTRUCTED},
+        decode_ecdsa_signature, ASN1Reader, OID_ECDSA_WITH_SHA256,
+    },
+    crypto,
+    error::Error,
+    tlv::{self, FromTLV, TLVArrayOwned, TLVElement},
+};
+
+use super::AttestationTrustStore;
+
+// 1.2.840.113549.1.7.2
+const OID_SIGNED_DATA: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+// 1.2.840.113549.1.7.1
+const OID_DATA: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+// 2.16.840.1.101.3.4.2.1
+const OID_SHA256: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+
+const CMS_VERSION: u8 = 3;
+const CD_FORMAT_VERSION: u16 = 1;
+
+/// The contents of a Certification Declaration
+#[derive(FromTLV)]
+pub struct CertificationDeclaration {
+    pub format_version: u16,
+    pub vendor_id: u16,
+    pub product_id_array: TLVArrayOwned<u16>,
+    pub device_type_id: u32,
+    pub certificate_id: String,
+    pub security_level: u8,
+    pub security_information: u16,
+    pub version_number: u16,
+    pub certification_type: u8,
+    pub dac_origin_vendor_id: Option<u16>,
+    pub dac_origin_product_id: Option<u16>,
+    pub authorized_paa_list: Option<TLVArrayOwned<Vec<u8>>>,
+}
+
+impl CertificationDeclaration {
+    /// Decode the TLV encoded contents of a Certification Declaration
+    pub fn new(tlv: &[u8]) -> Result<Self, Error> {
+        let root = tlv::get_root_node_struct(tlv)?;
+        let cd = Self::from_tlv(&root)?;
+        if cd.format_version != CD_FORMAT_VERSION {
+            error!(
+                "Unsupported Certification Declaration format {}",
+                cd.format_version
+            );
+            return Err(Error::Invalid);
+        }
+        Ok(cd)
+    }
+
+    /// Decode the CMS envelope _der_, and verify that it was signed by one of the
+    /// Certification Declaration signers of _store_
+    pub fn from_cms(der: &[u8], store: &AttestationTrustStore) -> Result<Self, Error> {
+        let signed = SignedData::new(der)?;
+        let pubkey = store.find_cd_signer(signed.signer_key_id).ok_or_else(|| {
+            error!(
+                "Unknown Certification Declaration signer {:x?}",
+                signed.signer_key_id
+            );
+            Error::UntrustedCert
+        })?;
+        let k = crypto::get_provider().key_pair_from_public(pubkey)?;
+        k.verify_msg(signed.content, &signed.signature)
+            .inspect_err(|_| {
+                error!("Error in signature verification of the Certification Declaration")
+            })?;
+        Self::new(signed.content)
+    }
+
+    /// Whether _pid_ is one of the Product IDs that this declaration covers
+    pub fn has_product_id(&self, pid: u16) -> bool {
+        self.product_id_array.iter().any(|p| *p == pid)
+    }
+
+    /// Whether the PAA with the Subject Key Identifier _key_id_ may attest products
+    /// of this declaration
+    pub fn is_paa_authorized(&self, key_id: &[u8]) -> bool {
+        match &self.authorized_paa_list {
+            Some(list) => list.iter().any(|k| k == key_id),
+            None => true,
+        }
+    }
+}
+
+// The parts of the SignedData that Matter uses
+struct SignedData<'a> {
+    content: &'a [u8],
+    signer_key_id: &'a [u8],
+    signature: Vec<u8>,
+}
+
+impl<'a> SignedData<'a> {
+    fn new(der: &'a [u8]) -> Result<Self, Error> {
+        let mut r = ASN1Reader::new(der);
+        let mut content_info = r.seq()?;
+        r.end()?;
+        if content_info.oid()? != OID_SIGNED_DATA {
+            error!("Not a CMS SignedData");
+            return Err(Error::Invalid);
+        }
+        let mut explicit = content_info.ctx(0)?;
+        content_info.end()?;
+        let mut signed_data = explicit.seq()?;
+        explicit.end()?;
+
+        if signed_data.integer()? != [CMS_VERSION] {
+            return Err(Error::Invalid);
+        }
+        let mut digest_algos = signed_data.set()?;
+        expect_algo(&mut digest_algos, &OID_SHA256)?;
+        digest_algos.end()?;
+
+        let mut encap = signed_data.seq()?;
+        if encap.oid()? != OID_DATA {
+            return Err(Error::Invalid);
+        }
+        let mut explicit = encap.ctx(0)?;
+        let content = explicit.octet_str()?;
+        explicit.end()?;
+        encap.end()?;
+
+        // Any certificates and CRLs are of no use, the signer must be known already
+        signed_data.optional(TAG_CTX_CONSTRUCTED)?;
+        signed_data.optional(TAG_CTX_CONSTRUCTED | 1)?;
+
+        let mut signer_infos = signed_data.set()?;
+        signed_data.end()?;
+        let mut signer_info = signer_infos.seq()?;
+        if !signer_infos.is_empty() {
+            error!("Only a single signer is supported");
+            return Err(Error::Invalid);
+        }
+        if signer_info.integer()? != [CMS_VERSION] {
+            return Err(Error::Invalid);
+        }
+        let signer_key_id = signer_info.expect(TAG_CTX)?;
+        expect_algo(&mut signer_info, &OID_SHA256)?;
+        if signer_info.peek_tag() == Some(TAG_CTX_CONSTRUCTED) {
+            error!("Signed attributes are not supported");
+            return Err(Error::Invalid);
+        }
+        expect_algo(&mut signer_info, &OID_ECDSA_WITH_SHA256)?;
+        let signature = decode_ecdsa_signature(signer_info.octet_str()?)?;
+        signer_info.end()?;
+
+        Ok(Self {
+            content,
+            signer_key_id,
+            signature,
+        })
+    }
+}
+
+// An AlgorithmIdentifier, whose parameters must be absent or NULL
+fn expect_algo(r: &mut ASN1Reader, oid: &[u8]) -> Result<(), Error> {
+    const TAG_NULL: u8 = 0x05;
+
+    let mut algo = r.seq()?;
+    if algo.oid()? != oid {
+        error!("Unsupported algorithm in CMS SignedData");
+        return Err(Error::Invalid);
+    }
+    algo.optional(TAG_NULL)?;
+    algo.end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CertificationDeclaration, SignedData};
+    use crate::{attestation::AttestationTrustStore, error::Error};
+
+    #[test]
+    fn test_decode() {
+        let signed = SignedData::new(&test_vectors::CD).unwrap();
+        assert_eq!(&signed.signer_key_id[..2], &[0x62, 0xFA]);
+        let cd = CertificationDeclaration::new(signed.content).unwrap();
+        assert_eq!(cd.vendor_id, 0xFFF1);
+        assert_eq!(cd.device_type_id, 0x16);
+        assert_eq!(cd.certificate_id, "ZIG20142ZB330003-24");
+        assert!(cd.has_product_id(0x8000));
+        assert!(cd.has_product_id(0x8063));
+        assert!(!cd.has_product_id(0x8064));
+        assert_eq!(cd.dac_origin_vendor_id, None);
+        assert!(cd.is_paa_authorized(&[0; 20]));
+    }
+
+    #[test]
+    fn test_unknown_signer() {
+        let store = AttestationTrustStore::new();
+        assert_eq!(
+            CertificationDeclaration::from_cms(&test_vectors::CD, &store).map(|_| ()),
+            Err(Error::UntrustedCert)
+        );
+    }
+
+    mod test_vectors {
+        // The Certification Declaration of the onoff_light example
+        pub const CD: [u8; 541] = [
+            0x30, 0x82, 0x02, 0x19, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07,
+            0x02, 0xa0, 0x82, 0x02, 0x0a, 0x30, 0x82, 0x02, 0x06, 0x02, 0x01, 0x03, 0x31, 0x0d,
+            0x30, 0x0b, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x30,
+            0x82, 0x01, 0x71, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01,
+            0xa0, 0x82, 0x01, 0x62, 0x04, 0x82, 0x01, 0x5e, 0x15, 0x24, 0x00, 0x01, 0x25, 0x01,
+            0xf1, 0xff, 0x36, 0x02, 0x05, 0x00, 0x80, 0x05, 0x01, 0x80, 0x05, 0x02, 0x80, 0x05,
+            0x03, 0x80, 0x05, 0x04, 0x80, 0x05, 0x05, 0x80, 0x05, 0x06, 0x80, 0x05, 0x07, 0x80,
+            0x05, 0x08, 0x80, 0x05, 0x09, 0x80, 0x05, 0x0a, 0x80, 0x05, 0x0b, 0x80, 0x05, 0x0c,
+            0x80, 0x05, 0x0d, 0x80, 0x05, 0x0e, 0x80, 0x05, 0x0f, 0x80, 0x05, 0x10, 0x80, 0x05,
+            0x11, 0x80, 0x05, 0x12, 0x80, 0x05, 0x13, 0x80, 0x05, 0x14, 0x80, 0x05, 0x15, 0x80,
+            0x05, 0x16, 0x80, 0x05, 0x17, 0x80, 0x05, 0x18, 0x80, 0x05, 0x19, 0x80, 0x05, 0x1a,
+            0x80, 0x05, 0x1b, 0x80, 0x05, 0x1c, 0x80, 0x05, 0x1d, 0x80, 0x05, 0x1e, 0x80, 0x05,
+            0x1f, 0x80, 0x05, 0x20, 0x80, 0x05, 0x21, 0x80, 0x05, 0x22, 0x80, 0x05, 0x23, 0x80,
+            0x05, 0x24, 0x80, 0x05, 0x25, 0x80, 0x05, 0x26, 0x80, 0x05, 0x27, 0x80, 0x05, 0x28,
+            0x80, 0x05, 0x29, 0x80, 0x05, 0x2a, 0x80, 0x05, 0x2b, 0x80, 0x05, 0x2c, 0x80, 0x05,
+            0x2d, 0x80, 0x05, 0x2e, 0x80, 0x05, 0x2f, 0x80, 0x05, 0x30, 0x80, 0x05, 0x31, 0x80,
+            0x05, 0x32, 0x80, 0x05, 0x33, 0x80, 0x05, 0x34, 0x80, 0x05, 0x35, 0x80, 0x05, 0x36,
+            0x80, 0x05, 0x37, 0x80, 0x05, 0x38, 0x80, 0x05, 0x39, 0x80, 0x05, 0x3a, 0x80, 0x05,
+            0x3b, 0x80, 0x05, 0x3c, 0x80, 0x05, 0x3d, 0x80, 0x05, 0x3e, 0x80, 0x05, 0x3f, 0x80,
+            0x05, 0x40, 0x80, 0x05, 0x41, 0x80, 0x05, 0x42, 0x80, 0x05, 0x43, 0x80, 0x05, 0x44,
+            0x80, 0x05, 0x45, 0x80, 0x05, 0x46, 0x80, 0x05, 0x47, 0x80, 0x05, 0x48, 0x80, 0x05,
+            0x49, 0x80, 0x05, 0x4a, 0x80, 0x05, 0x4b, 0x80, 0x05, 0x4c, 0x80, 0x05, 0x4d, 0x80,
+            0x05, 0x4e, 0x80, 0x05, 0x4f, 0x80, 0x05, 0x50, 0x80, 0x05, 0x51, 0x80, 0x05, 0x52,
+            0x80, 0x05, 0x53, 0x80, 0x05, 0x54, 0x80, 0x05, 0x55, 0x80, 0x05, 0x56, 0x80, 0x05,
+            0x57, 0x80, 0x05, 0x58, 0x80, 0x05, 0x59, 0x80, 0x05, 0x5a, 0x80, 0x05, 0x5b, 0x80,
+            0x05, 0x5c, 0x80, 0x05, 0x5d, 0x80, 0x05, 0x5e, 0x80, 0x05, 0x5f, 0x80, 0x05, 0x60,
+            0x80, 0x05, 0x61, 0x80, 0x05, 0x62, 0x80, 0x05, 0x63, 0x80, 0x18, 0x24, 0x03, 0x16,
+            0x2c, 0x04, 0x13, 0x5a, 0x49, 0x47, 0x32, 0x30, 0x31, 0x34, 0x32, 0x5a, 0x42, 0x33,
+            0x33, 0x30, 0x30, 0x30, 0x33, 0x2d, 0x32, 0x34, 0x24, 0x05, 0x00, 0x24, 0x06, 0x00,
+            0x25, 0x07, 0x94, 0x26, 0x24, 0x08, 0x00, 0x18, 0x31, 0x7d, 0x30, 0x7b, 0x02, 0x01,
+            0x03, 0x80, 0x14, 0x62, 0xfa, 0x82, 0x33, 0x59, 0xac, 0xfa, 0xa9, 0x96, 0x3e, 0x1c,
+            0xfa, 0x14, 0x0a, 0xdd, 0xf5, 0x04, 0xf3, 0x71, 0x60, 0x30, 0x0b, 0x06, 0x09, 0x60,
+            0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86,
+            0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x04, 0x47, 0x30, 0x45, 0x02, 0x20, 0x24, 0xe5,
+            0xd1, 0xf4, 0x7a, 0x7d, 0x7b, 0x0d, 0x20, 0x6a, 0x26, 0xef, 0x69, 0x9b, 0x7c, 0x97,
+            0x57, 0xb7, 0x2d, 0x46, 0x90, 0x89, 0xde, 0x31, 0x92, 0xe6, 0x78, 0xc7, 0x45, 0xe7,
+            0xf6, 0x0c, 0x02, 0x21, 0x00, 0xf8, 0xaa, 0x2f, 0xa7, 0x11, 0xfc, 0xb7, 0x9b, 0x97,
+            0xe3, 0x97, 0xce, 0xda, 0x66, 0x7b, 0xae, 0x46, 0x4e, 0x2b, 0xd3, 0xff, 0xdf, 0xc3,
+            0xcc, 0xed, 0x7a, 0xa8, 0xca, 0x5f, 0x4c, 0x1a, 0x7c,
+        ];
+    }
+}
@@ -0,0 +1,80 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use log::error;
+
+use crate::{
+    cert::{AttestationCert, Csr},
+    crypto,
+    error::Error,
+    tlv::{self, FromTLV, OctetStr, TLVElement},
+};
+
+/// The CSR Response that a device returned to the commissioner
+pub struct CsrResponseInfo<'a> {
+    /// The TLV encoded fields of the CSRResponse command
+    pub csr_response: &'a [u8],
+    /// The nonce that was sent in the CSRRequest
+    pub csr_nonce: &'a [u8],
+    /// The Attestation Challenge of the session
+    pub attestation_challenge: &'a [u8],
+    /// The DER encoded DAC of the device, as verified by
+    /// [AttestationVerifier](super::AttestationVerifier)
+    pub dac: &'a [u8],
+}
+
+#[derive(FromTLV)]
+#[tlvargs(lifetime = "'a")]
+struct CsrResp<'a> {
+    nocsr_elements: OctetStr<'a>,
+    attestation_signature: OctetStr<'a>,
+}
+
+#[derive(FromTLV)]
+#[tlvargs(start = 1, lifetime = "'a")]
+struct NocsrElements<'a> {
+    csr: OctetStr<'a>,
+    csr_nonce: OctetStr<'a>,
+}
+
+/// Verify the CSR Response of a device, returning its CSR
+///
+/// The NOCSR Elements must be signed by the DAC, and bound to the CSR nonce of
+/// the request. The CSR itself must be signed with the key that it carries, so
+/// the NOC is only issued for a key that the device possesses.
+pub fn verify_csr_response(info: &CsrResponseInfo) -> Result<Csr, Error> {
+    let root = tlv::get_root_node_struct(info.csr_response)?;
+    let resp = CsrResp::from_tlv(&root)?;
+
+    let dac = AttestationCert::new(info.dac)?;
+    let mut msg = resp.nocsr_elements.0.to_vec();
+    msg.extend_from_slice(info.attestation_challenge);
+    let k = crypto::get_provider().key_pair_from_public(dac.get_pubkey())?;
+    k.verify_msg(&msg, resp.attestation_signature.0)
+        .inspect_err(|_| error!("Error in verification of the NOCSR Elements signature"))?;
+
+    let root = tlv::get_root_node_struct(resp.nocsr_elements.0)?;
+    let elements = NocsrElements::from_tlv(&root)?;
+    if elements.csr_nonce.0 != info.csr_nonce {
+        error!("The CSR Nonce doesn't match the request");
+        return Err(Error::InvalidData);
+    }
+
+    let csr = Csr::new(elements.csr.0)?;
+    csr.verify()?;
+    Ok(csr)
+}
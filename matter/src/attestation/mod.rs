@@ -0,0 +1,224 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! Verification of device attestation, as performed by a commissioner
+//!
+//! The device proves that it is a certified product by returning its DAC and PAI,
+//! and by signing the Attestation Elements, which carry the Certification
+//! Declaration and the commissioner's nonce, together with the Attestation
+//! Challenge of the PASE session. The CSR that it returns for its NOC is signed by
+//! the DAC in the same way.
+
+use log::error;
+
+use crate::{
+    cert::{AttestationCert, AttestationCertType},
+    crypto,
+    data_model::cluster_basic_information::BasicInfoConfig,
+    error::Error,
+    tlv,
+    utils::epoch::{self, ValidationTime},
+};
+
+mod cd;
+pub use cd::CertificationDeclaration;
+
+mod csr;
+pub use csr::{verify_csr_response, CsrResponseInfo};
+
+mod trust_store;
+pub use trust_store::AttestationTrustStore;
+
+/// The attestation that a device returned to the commissioner
+pub struct AttestationInfo<'a> {
+    /// The DER encoded DAC
+    pub dac: &'a [u8],
+    /// The DER encoded PAI
+    pub pai: &'a [u8],
+    /// The TLV encoded Attestation Elements of the Attestation Response
+    pub attestation_elements: &'a [u8],
+    /// The Attestation Signature of the Attestation Response
+    pub attestation_signature: &'a [u8],
+    /// The nonce that was sent in the Attestation Request
+    pub attestation_nonce: &'a [u8],
+    /// The Attestation Challenge of the session
+    pub attestation_challenge: &'a [u8],
+}
+
+// The fields of the Attestation Elements that are verified
+struct AttestationElements<'a> {
+    cd: &'a [u8],
+    nonce: &'a [u8],
+}
+
+impl<'a> AttestationElements<'a> {
+    fn new(tlv: &'a [u8]) -> Result<Self, Error> {
+        let root = tlv::get_root_node_struct(tlv)?;
+        Ok(Self {
+            cd: root.find_tag(1)?.slice()?,
+            nonce: root.find_tag(2)?.slice()?,
+        })
+    }
+}
+
+/// Verifies device attestation against a trust store
+pub struct AttestationVerifier {
+    store: AttestationTrustStore,
+}
+
+impl AttestationVerifier {
+    pub fn new(store: AttestationTrustStore) -> Self {
+        Self { store }
+    }
+
+    pub fn trust_store(&self) -> &AttestationTrustStore {
+        &self.store
+    }
+
+    /// Verify the attestation of a device that reports _basic_info_ in its Basic
+    /// Information cluster
+    ///
+    /// Returns the Certification Declaration of the device.
+    pub fn verify(
+        &self,
+        info: &AttestationInfo,
+        basic_info: &BasicInfoConfig,
+    ) -> Result<CertificationDeclaration, Error> {
+        self.verify_at(info, basic_info, epoch::get_validation_time())
+    }
+
+    /// Verify the attestation, with the validity periods checked against _time_
+    pub fn verify_at(
+        &self,
+        info: &AttestationInfo,
+        basic_info: &BasicInfoConfig,
+        time: ValidationTime,
+    ) -> Result<CertificationDeclaration, Error> {
+        let dac = AttestationCert::new(info.dac)?;
+        dac.check_profile(AttestationCertType::Dac)?;
+        let pai = AttestationCert::new(info.pai)?;
+        pai.check_profile(AttestationCertType::Pai)?;
+
+        // The signature comes first, nothing else in the attestation can be trusted
+        // without it
+        let mut msg = info.attestation_elements.to_vec();
+        msg.extend_from_slice(info.attestation_challenge);
+        let k = crypto::get_provider().key_pair_from_public(dac.get_pubkey())?;
+        k.verify_msg(&msg, info.attestation_signature)
+            .inspect_err(|_| error!("Error in verification of the Attestation Signature"))?;
+
+        let elements = AttestationElements::new(info.attestation_elements)?;
+        if elements.nonce != info.attestation_nonce {
+            error!("The Attestation Nonce doesn't match the request");
+            return Err(Error::InvalidData);
+        }
+
+        let paa = self.verify_chain(&dac, &pai, time)?;
+        let cd = CertificationDeclaration::from_cms(elements.cd, &self.store)?;
+        check_cd(&cd, &dac, &pai, paa)?;
+        check_basic_info(&cd, &dac, basic_info)?;
+        Ok(cd)
+    }
+
+    // Verify the DAC -> PAI -> PAA chain, returning the PAA
+    fn verify_chain(
+        &self,
+        dac: &AttestationCert,
+        pai: &AttestationCert,
+        time: ValidationTime,
+    ) -> Result<&AttestationCert, Error> {
+        let paa = pai
+            .get_auth_key_id()
+            .and_then(|id| self.store.find_paa(id))
+            .ok_or_else(|| {
+                error!("No trusted PAA for PAI {:x?}", pai.get_subject_key_id());
+                Error::UntrustedCert
+            })?;
+
+        for cert in [dac, pai, paa] {
+            cert.check_validity(time).inspect_err(|e| {
+                error!(
+                    "Attestation certificate {:x?} is outside its validity period: {}",
+                    cert.get_subject_key_id(),
+                    e
+                )
+            })?;
+        }
+        dac.verify_issued_by(pai)?;
+        pai.verify_issued_by(paa)?;
+
+        // A PAI without a PID may issue DACs for all the products of its vendor
+        if dac.get_vid() != pai.get_vid()
+            || paa.get_vid().is_some_and(|vid| Some(vid) != pai.get_vid())
+            || pai.get_pid().is_some_and(|pid| Some(pid) != dac.get_pid())
+        {
+            error!("The Vendor and Product IDs of the attestation chain don't match");
+            return Err(Error::VidPidMismatch);
+        }
+        Ok(paa)
+    }
+}
+
+// Check the Certification Declaration against the attestation chain
+fn check_cd(
+    cd: &CertificationDeclaration,
+    dac: &AttestationCert,
+    pai: &AttestationCert,
+    paa: &AttestationCert,
+) -> Result<(), Error> {
+    let matches = match (cd.dac_origin_vendor_id, cd.dac_origin_product_id) {
+        // The DAC was issued for another vendor's product
+        (Some(vid), Some(pid)) => {
+            dac.get_vid() == Some(vid) && pai.get_vid() == Some(vid) && dac.get_pid() == Some(pid)
+        }
+        (None, None) => {
+            dac.get_vid() == Some(cd.vendor_id)
+                && dac.get_pid().is_some_and(|pid| cd.has_product_id(pid))
+        }
+        _ => {
+            error!("The DAC origin Vendor and Product IDs must both be present");
+            return Err(Error::Invalid);
+        }
+    };
+    if !matches {
+        error!("The Certification Declaration doesn't match the DAC");
+        return Err(Error::VidPidMismatch);
+    }
+    if !cd.is_paa_authorized(paa.get_subject_key_id()) {
+        error!("The PAA isn't authorized by the Certification Declaration");
+        return Err(Error::UntrustedCert);
+    }
+    Ok(())
+}
+
+// Check the Basic Information of the device against its attestation
+fn check_basic_info(
+    cd: &CertificationDeclaration,
+    dac: &AttestationCert,
+    basic_info: &BasicInfoConfig,
+) -> Result<(), Error> {
+    let dac_matches = cd.dac_origin_vendor_id.is_some()
+        || (dac.get_vid() == Some(basic_info.vid) && dac.get_pid() == Some(basic_info.pid));
+    if !dac_matches || cd.vendor_id != basic_info.vid || !cd.has_product_id(basic_info.pid) {
+        error!(
+            "The attestation doesn't match the device's Vendor ID {:x} and Product ID {:x}",
+            basic_info.vid, basic_info.pid
+        );
+        return Err(Error::VidPidMismatch);
+    }
+    Ok(())
+}
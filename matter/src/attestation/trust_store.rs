@@ -0,0 +1,110 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use std::{fs, path::Path};
+
+use log::{error, info};
+
+use crate::{
+    cert::{AttestationCert, AttestationCertType},
+    crypto,
+    error::Error,
+    utils::pem,
+};
+
+/// The trust anchors of device attestation: the PAAs, and the keys that sign
+/// Certification Declarations
+#[derive(Default)]
+pub struct AttestationTrustStore {
+    paas: Vec<AttestationCert>,
+    // The Subject Key Identifier and public key of each signer
+    cd_signers: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl AttestationTrustStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the PAA with the X.509 DER certificate _der_
+    pub fn add_paa(&mut self, der: &[u8]) -> Result<(), Error> {
+        let paa = AttestationCert::new(der)?;
+        paa.check_profile(AttestationCertType::Paa)?;
+        if self.find_paa(paa.get_subject_key_id()).is_none() {
+            self.paas.push(paa);
+        }
+        Ok(())
+    }
+
+    /// Trust all the PAA certificates in the directory _dir_
+    ///
+    /// Files ending in .der are read as DER, and files ending in .pem as PEM. Files
+    /// that can't be read or don't hold a valid PAA are skipped. Returns the number
+    /// of PAAs that were added.
+    pub fn load_paa_dir(&mut self, dir: &Path) -> Result<usize, Error> {
+        let mut count = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let der = match path.extension().and_then(|e| e.to_str()) {
+                Some("der") => fs::read(&path).map_err(Error::from),
+                Some("pem") => fs::read_to_string(&path)
+                    .map_err(Error::from)
+                    .and_then(|pem| pem::decode_label(pem::LABEL_CERTIFICATE, &pem)),
+                _ => continue,
+            };
+            match der.and_then(|der| self.add_paa(&der)) {
+                Ok(()) => count += 1,
+                Err(e) => error!("Skipping PAA {}: {}", path.display(), e),
+            }
+        }
+        info!("Loaded {} PAAs from {}", count, dir.display());
+        Ok(count)
+    }
+
+    /// Trust the Certification Declaration signer with the X.509 DER certificate
+    /// _der_
+    pub fn add_cd_signer(&mut self, der: &[u8]) -> Result<(), Error> {
+        let cert = AttestationCert::new(der)?;
+        self.add_cd_signer_key(cert.get_subject_key_id(), cert.get_pubkey())
+    }
+
+    /// Trust the Certification Declaration signer with the Subject Key Identifier
+    /// _key_id_ and the public key _pubkey_
+    pub fn add_cd_signer_key(&mut self, key_id: &[u8], pubkey: &[u8]) -> Result<(), Error> {
+        if pubkey.len() != crypto::EC_POINT_LEN_BYTES {
+            return Err(Error::InvalidKeyLength);
+        }
+        if self.find_cd_signer(key_id).is_none() {
+            self.cd_signers.push((key_id.to_vec(), pubkey.to_vec()));
+        }
+        Ok(())
+    }
+
+    /// The PAA with the Subject Key Identifier _key_id_
+    pub fn find_paa(&self, key_id: &[u8]) -> Option<&AttestationCert> {
+        self.paas.iter().find(|p| p.get_subject_key_id() == key_id)
+    }
+
+    /// The public key of the Certification Declaration signer with the Subject Key
+    /// Identifier _key_id_
+    pub fn find_cd_signer(&self, key_id: &[u8]) -> Option<&[u8]> {
+        self.cd_signers
+            .iter()
+            .find(|(id, _)| id == key_id)
+            .map(|(_, pubkey)| pubkey.as_slice())
+    }
+}
@@ -0,0 +1,249 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use crate::error::Error;
+use chrono::NaiveDateTime;
+use log::error;
+use std::convert::TryFrom;
+
+use crate::utils::epoch::MATTER_EPOCH_SECS;
+
+pub const TAG_BOOL: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STR: u8 = 0x03;
+pub const TAG_OCTET_STR: u8 = 0x04;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STR: u8 = 0x0c;
+pub const TAG_PRINTABLE_STR: u8 = 0x13;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_SEQ: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+pub const TAG_CTX: u8 = 0x80;
+pub const TAG_CTX_CONSTRUCTED: u8 = 0xA0;
+
+/// The X.509 Not After of a certificate that has no well-defined expiration date
+pub const NO_EXPIRY_TIME: &str = "99991231235959Z";
+
+/// A reader for the subset of DER that is used in X.509 certificates
+///
+/// Only single byte tags and lengths of up to 2 bytes are supported, which is
+/// all that a Matter certificate requires.
+pub struct ASN1Reader<'a> {
+    buf: &'a [u8],
+    // The current read offset in the buffer
+    offset: usize,
+}
+
+impl<'a> ASN1Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.buf.len()
+    }
+
+    /// The tag of the next element, if any
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.buf.get(self.offset).copied()
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        let val = *self.buf.get(self.offset).ok_or(Error::TruncatedPacket)?;
+        self.offset += 1;
+        Ok(val)
+    }
+
+    fn read_len(&mut self) -> Result<usize, Error> {
+        let first = self.read_u8()?;
+        match first {
+            0..=0x7f => Ok(first as usize),
+            0x81 => Ok(self.read_u8()? as usize),
+            0x82 => Ok(((self.read_u8()? as usize) << 8) | self.read_u8()? as usize),
+            _ => {
+                error!("Unsupported ASN1 length encoding {:x}", first);
+                Err(Error::Invalid)
+            }
+        }
+    }
+
+    /// Read the next element
+    ///
+    /// Returns the tag, the contents and the complete encoding of the element
+    pub fn next_raw(&mut self) -> Result<(u8, &'a [u8], &'a [u8]), Error> {
+        let start = self.offset;
+        let tag = self.read_u8()?;
+        let len = self.read_len()?;
+        let end = self.offset.checked_add(len).ok_or(Error::Invalid)?;
+        let contents = self
+            .buf
+            .get(self.offset..end)
+            .ok_or(Error::TruncatedPacket)?;
+        self.offset = end;
+        Ok((tag, contents, &self.buf[start..end]))
+    }
+
+    /// Read the contents of the next element, which must have the tag _tag_
+    pub fn expect(&mut self, tag: u8) -> Result<&'a [u8], Error> {
+        let (t, contents, _) = self.next_raw()?;
+        if t != tag {
+            error!("Expected ASN1 tag {:x}, found {:x}", tag, t);
+            return Err(Error::Invalid);
+        }
+        Ok(contents)
+    }
+
+    /// Read the contents of the next element, if it has the tag _tag_
+    pub fn optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, Error> {
+        if self.peek_tag() == Some(tag) {
+            self.expect(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn seq(&mut self) -> Result<ASN1Reader<'a>, Error> {
+        self.expect(TAG_SEQ).map(ASN1Reader::new)
+    }
+
+    pub fn set(&mut self) -> Result<ASN1Reader<'a>, Error> {
+        self.expect(TAG_SET).map(ASN1Reader::new)
+    }
+
+    /// A constructed context-specific element, like the [0] Version of a certificate
+    pub fn ctx(&mut self, id: u8) -> Result<ASN1Reader<'a>, Error> {
+        self.expect(TAG_CTX_CONSTRUCTED | id).map(ASN1Reader::new)
+    }
+
+    pub fn integer(&mut self) -> Result<&'a [u8], Error> {
+        self.expect(TAG_INTEGER)
+    }
+
+    pub fn oid(&mut self) -> Result<&'a [u8], Error> {
+        self.expect(TAG_OID)
+    }
+
+    pub fn octet_str(&mut self) -> Result<&'a [u8], Error> {
+        self.expect(TAG_OCTET_STR)
+    }
+
+    /// Read a BIT STRING, returning the number of unused bits and the bits
+    pub fn bit_str(&mut self) -> Result<(u8, &'a [u8]), Error> {
+        let contents = self.expect(TAG_BIT_STR)?;
+        let (unused, bits) = contents.split_first().ok_or(Error::Invalid)?;
+        if *unused > 7 {
+            return Err(Error::Invalid);
+        }
+        Ok((*unused, bits))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, Error> {
+        match self.expect(TAG_BOOL)? {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(Error::Invalid),
+        }
+    }
+
+    /// Read a UTCTime or GeneralizedTime, as seconds since the Matter epoch
+    ///
+    /// The no well-defined expiration date time is returned as 0.
+    pub fn time(&mut self) -> Result<u32, Error> {
+        let (tag, contents, _) = self.next_raw()?;
+        let time_str = std::str::from_utf8(contents)?;
+        if !time_str.is_ascii() {
+            return Err(Error::InvalidTime);
+        }
+        let time_str = match tag {
+            TAG_UTC_TIME if time_str.len() == 13 => {
+                // Two digit years from 50 onwards are in the 20th century
+                let century = if &time_str[..2] >= "50" { "19" } else { "20" };
+                format!("{}{}", century, time_str)
+            }
+            TAG_GENERALIZED_TIME if time_str.len() == 15 => {
+                if time_str == NO_EXPIRY_TIME {
+                    return Ok(0);
+                }
+                time_str.to_owned()
+            }
+            _ => {
+                error!("Unsupported ASN1 time {:x} {}", tag, time_str);
+                return Err(Error::InvalidTime);
+            }
+        };
+        let dt = NaiveDateTime::parse_from_str(&time_str, "%Y%m%d%H%M%SZ")
+            .map_err(|_| Error::InvalidTime)?;
+        dt.and_utc()
+            .timestamp()
+            .checked_sub(MATTER_EPOCH_SECS as i64)
+            .and_then(|t| u32::try_from(t).ok())
+            .ok_or(Error::InvalidTime)
+    }
+
+    /// Confirm that all the contents have been consumed
+    pub fn end(&self) -> Result<(), Error> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            error!("Unexpected trailing data in ASN1 element");
+            Err(Error::Invalid)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ASN1Reader;
+    use crate::error::Error;
+
+    #[test]
+    fn test_read_nested() {
+        // SEQ { INTEGER 5, OCTET STRING 'ab' }, BOOL true
+        let der = [
+            0x30, 0x07, 0x02, 0x01, 0x05, 0x04, 0x02, 0x61, 0x62, 0x01, 0x01, 0xff,
+        ];
+        let mut r = ASN1Reader::new(&der);
+        let mut seq = r.seq().unwrap();
+        assert_eq!(seq.integer().unwrap(), &[5]);
+        assert_eq!(seq.octet_str().unwrap(), b"ab");
+        seq.end().unwrap();
+        assert!(r.bool().unwrap());
+        r.end().unwrap();
+    }
+
+    #[test]
+    fn test_truncated() {
+        let der = [0x30, 0x07, 0x02, 0x01];
+        let mut r = ASN1Reader::new(&der);
+        assert_eq!(r.seq().map(|_| ()), Err(Error::TruncatedPacket));
+    }
+
+    #[test]
+    fn test_time() {
+        // 2021-01-01 00:00:00
+        let der = [
+            0x17, 0x0d, b'2', b'1', b'0', b'1', b'0', b'1', b'0', b'0', b'0', b'0', b'0', b'0',
+            b'Z',
+        ];
+        assert_eq!(ASN1Reader::new(&der).time(), Ok(662774400));
+
+        let mut der = vec![0x18, 0x0f];
+        der.extend_from_slice(super::NO_EXPIRY_TIME.as_bytes());
+        assert_eq!(ASN1Reader::new(&der).time(), Ok(0));
+    }
+}
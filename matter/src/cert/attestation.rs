@@ -0,0 +1,491 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! X.509 certificates of the device attestation PKI
+//!
+//! Unlike operational certificates, the PAA, PAI and DAC only ever exist in their
+//! X.509 form, so they are kept as DER and aren't held to the Matter certificate
+//! encoding.
+
+use log::error;
+
+use crate::{crypto, error::Error, utils::epoch::ValidationTime};
+
+use super::{
+    asn1_reader::{TAG_BOOL, TAG_CTX, TAG_INTEGER, TAG_PRINTABLE_STR, TAG_SEQ, TAG_UTF8_STR},
+    bitstring_to_int, decode_ecdsa_signature, decode_sign_algo, ASN1Reader, KEY_USAGE_CRL_SIGN,
+    KEY_USAGE_DIGITAL_SIGN, KEY_USAGE_KEY_CERT_SIGN, OID_AUTH_KEY_ID, OID_BASIC_CONSTRAINTS,
+    OID_EC_TYPE_PRIME256V1, OID_KEY_USAGE, OID_PUB_KEY_ECPUBKEY, OID_SUBJ_KEY_IDENTIFIER,
+};
+
+const OID_MATTER_VID: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x02, 0x01,
+];
+const OID_MATTER_PID: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x02, 0x02,
+];
+
+/// The role of a certificate in the device attestation PKI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationCertType {
+    /// Product Attestation Authority
+    Paa,
+    /// Product Attestation Intermediate
+    Pai,
+    /// Device Attestation Certificate
+    Dac,
+}
+
+/// A PAA, PAI or DAC
+#[derive(Debug, Clone)]
+pub struct AttestationCert {
+    // The signed part of the certificate
+    tbs: Vec<u8>,
+    // The DER encoded issuer and subject names
+    issuer: Vec<u8>,
+    subject: Vec<u8>,
+    vid: Option<u16>,
+    pid: Option<u16>,
+    not_before: u32,
+    not_after: u32,
+    pubkey: Vec<u8>,
+    is_ca: bool,
+    path_len: Option<u8>,
+    key_usage: u16,
+    subj_key_id: Vec<u8>,
+    auth_key_id: Option<Vec<u8>>,
+    signature: Vec<u8>,
+}
+
+impl AttestationCert {
+    /// Parse an X.509 DER certificate
+    pub fn new(der: &[u8]) -> Result<Self, Error> {
+        let mut r = ASN1Reader::new(der);
+        let mut cert_seq = r.seq()?;
+        r.end()?;
+
+        let (tag, tbs_contents, tbs_der) = cert_seq.next_raw()?;
+        if tag != TAG_SEQ {
+            return Err(Error::Invalid);
+        }
+        let mut tbs = ASN1Reader::new(tbs_contents);
+        let mut version = tbs.ctx(0)?;
+        if version.integer()? != [2] {
+            error!("Only X.509 v3 certificates are supported");
+            return Err(Error::InvalidCertProfile);
+        }
+        version.end()?;
+        tbs.integer()?;
+        decode_sign_algo(&mut tbs)?;
+        let issuer = read_name(&mut tbs)?;
+        let mut validity = tbs.seq()?;
+        let not_before = validity.time()?;
+        let not_after = validity.time()?;
+        validity.end()?;
+        let subject = read_name(&mut tbs)?;
+        let (vid, pid) = decode_vid_pid(subject)?;
+
+        let mut pubkey_info = tbs.seq()?;
+        let mut pubkey_algo = pubkey_info.seq()?;
+        if pubkey_algo.oid()? != OID_PUB_KEY_ECPUBKEY
+            || pubkey_algo.oid()? != OID_EC_TYPE_PRIME256V1
+        {
+            error!("Only P-256 public keys are supported");
+            return Err(Error::InvalidCertProfile);
+        }
+        pubkey_algo.end()?;
+        let (unused, pubkey) = pubkey_info.bit_str()?;
+        if unused != 0 || pubkey.len() != crypto::EC_POINT_LEN_BYTES {
+            return Err(Error::InvalidCertProfile);
+        }
+        pubkey_info.end()?;
+
+        let mut cert = Self {
+            tbs: tbs_der.to_vec(),
+            issuer: issuer.to_vec(),
+            subject: subject.to_vec(),
+            vid,
+            pid,
+            not_before,
+            not_after,
+            pubkey: pubkey.to_vec(),
+            is_ca: false,
+            path_len: None,
+            key_usage: 0,
+            subj_key_id: Vec::new(),
+            auth_key_id: None,
+            signature: Vec::new(),
+        };
+
+        let mut ext_ctx = tbs.ctx(3)?;
+        cert.decode_extensions(ext_ctx.seq()?)?;
+        ext_ctx.end()?;
+        tbs.end()?;
+
+        decode_sign_algo(&mut cert_seq)?;
+        let (unused, signature) = cert_seq.bit_str()?;
+        if unused != 0 {
+            return Err(Error::Invalid);
+        }
+        cert.signature = decode_ecdsa_signature(signature)?;
+        cert_seq.end()?;
+        Ok(cert)
+    }
+
+    // The Basic Constraints, Key Usage and Subject Key Identifier are mandatory for
+    // all attestation certificates. Other extensions are skipped, unless they are
+    // critical.
+    fn decode_extensions(&mut self, mut r: ASN1Reader) -> Result<(), Error> {
+        let mut basic_const = false;
+        let mut key_usage = false;
+        while !r.is_empty() {
+            let mut e = r.seq()?;
+            let oid = e.oid()?;
+            let critical = if e.peek_tag() == Some(TAG_BOOL) {
+                e.bool()?
+            } else {
+                false
+            };
+            let mut value = ASN1Reader::new(e.octet_str()?);
+            e.end()?;
+
+            if oid == &OID_BASIC_CONSTRAINTS[..] {
+                let mut seq = value.seq()?;
+                if seq.peek_tag() == Some(TAG_BOOL) {
+                    self.is_ca = seq.bool()?;
+                }
+                self.path_len = match seq.optional(TAG_INTEGER)? {
+                    Some([path]) => Some(*path),
+                    Some(_) => return Err(Error::Invalid),
+                    None => None,
+                };
+                seq.end()?;
+                basic_const = true;
+            } else if oid == &OID_KEY_USAGE[..] {
+                let (_, bits) = value.bit_str()?;
+                self.key_usage = bitstring_to_int(bits)?;
+                key_usage = true;
+            } else if oid == &OID_SUBJ_KEY_IDENTIFIER[..] {
+                self.subj_key_id = value.octet_str()?.to_vec();
+            } else if oid == &OID_AUTH_KEY_ID[..] {
+                // The issuer name and serial number may follow the key identifier
+                let mut seq = value.seq()?;
+                self.auth_key_id = seq.optional(TAG_CTX)?.map(|id| id.to_vec());
+                continue;
+            } else if critical {
+                error!("Unsupported critical extension {:x?}", oid);
+                return Err(Error::InvalidCertProfile);
+            } else {
+                continue;
+            }
+            value.end()?;
+        }
+
+        if !basic_const || !key_usage || self.subj_key_id.is_empty() {
+            error!("An attestation certificate needs the Basic Constraints, Key Usage and Subject Key Identifier extensions");
+            return Err(Error::InvalidCertProfile);
+        }
+        Ok(())
+    }
+
+    /// The Vendor ID in the subject
+    pub fn get_vid(&self) -> Option<u16> {
+        self.vid
+    }
+
+    /// The Product ID in the subject
+    pub fn get_pid(&self) -> Option<u16> {
+        self.pid
+    }
+
+    pub fn get_pubkey(&self) -> &[u8] {
+        &self.pubkey
+    }
+
+    pub fn get_subject_key_id(&self) -> &[u8] {
+        &self.subj_key_id
+    }
+
+    pub fn get_auth_key_id(&self) -> Option<&[u8]> {
+        self.auth_key_id.as_deref()
+    }
+
+    pub fn get_not_before(&self) -> u32 {
+        self.not_before
+    }
+
+    pub fn get_not_after(&self) -> u32 {
+        self.not_after
+    }
+
+    /// Check the validity period of the certificate against _time_
+    pub fn check_validity(&self, time: ValidationTime) -> Result<(), Error> {
+        time.check(self.not_before, self.not_after)
+    }
+
+    fn profile_error(&self, reason: &str) -> Result<(), Error> {
+        error!(
+            "Attestation certificate {:x?} violates the Matter profile: {}",
+            self.subj_key_id, reason
+        );
+        Err(Error::InvalidCertProfile)
+    }
+
+    /// Check the basic constraints, key usage and Vendor and Product IDs of the
+    /// certificate for its role
+    pub fn check_profile(&self, cert_type: AttestationCertType) -> Result<(), Error> {
+        match cert_type {
+            AttestationCertType::Dac => {
+                if self.is_ca {
+                    return self.profile_error("a DAC must not be a CA");
+                }
+                if self.key_usage & KEY_USAGE_DIGITAL_SIGN == 0
+                    || self.key_usage & (KEY_USAGE_KEY_CERT_SIGN | KEY_USAGE_CRL_SIGN) != 0
+                {
+                    return self.profile_error("invalid key usage for a DAC");
+                }
+                if self.vid.is_none() || self.pid.is_none() {
+                    return self.profile_error("a DAC needs a Vendor ID and a Product ID");
+                }
+            }
+            AttestationCertType::Pai | AttestationCertType::Paa => {
+                if !self.is_ca {
+                    return self.profile_error("an issuer must be a CA");
+                }
+                if self.key_usage & KEY_USAGE_KEY_CERT_SIGN == 0
+                    || self.key_usage & KEY_USAGE_CRL_SIGN == 0
+                {
+                    return self
+                        .profile_error("an issuer needs the keyCertSign and cRLSign key usages");
+                }
+                if cert_type == AttestationCertType::Pai {
+                    if self.path_len != Some(0) {
+                        return self.profile_error("a PAI must have a path length of 0");
+                    }
+                    if self.vid.is_none() {
+                        return self.profile_error("a PAI needs a Vendor ID");
+                    }
+                } else {
+                    if !matches!(self.path_len, None | Some(1)) {
+                        return self.profile_error("a PAA must have a path length of 1");
+                    }
+                    if self.pid.is_some() {
+                        return self.profile_error("a PAA must not have a Product ID");
+                    }
+                }
+            }
+        }
+        if cert_type != AttestationCertType::Paa && self.auth_key_id.is_none() {
+            return self.profile_error("an issued certificate needs an Authority Key Identifier");
+        }
+        Ok(())
+    }
+
+    /// Confirm that this certificate was issued and signed by _issuer_
+    pub fn verify_issued_by(&self, issuer: &AttestationCert) -> Result<(), Error> {
+        if self.issuer != issuer.subject
+            || self.get_auth_key_id() != Some(issuer.get_subject_key_id())
+        {
+            error!(
+                "Attestation certificate {:x?} wasn't issued by {:x?}",
+                self.subj_key_id, issuer.subj_key_id
+            );
+            return Err(Error::InvalidAuthKey);
+        }
+        let k = crypto::get_provider().key_pair_from_public(issuer.get_pubkey())?;
+        k.verify_msg(&self.tbs, &self.signature).inspect_err(|_| {
+            error!(
+                "Error in signature verification of attestation certificate: {:x?}",
+                self.subj_key_id
+            )
+        })
+    }
+}
+
+// Read a Name, returning its complete encoding
+fn read_name<'a>(r: &mut ASN1Reader<'a>) -> Result<&'a [u8], Error> {
+    let (tag, _, raw) = r.next_raw()?;
+    if tag != TAG_SEQ {
+        return Err(Error::Invalid);
+    }
+    Ok(raw)
+}
+
+// The Vendor and Product IDs are encoded in the Name as 4 upper case hex digits
+fn decode_vid_pid(name: &[u8]) -> Result<(Option<u16>, Option<u16>), Error> {
+    let mut vid = None;
+    let mut pid = None;
+    let mut r = ASN1Reader::new(name);
+    let mut name = r.seq()?;
+    while !name.is_empty() {
+        let mut rdn = name.set()?;
+        while !rdn.is_empty() {
+            let mut attr = rdn.seq()?;
+            let oid = attr.oid()?;
+            let field = if oid == &OID_MATTER_VID[..] {
+                &mut vid
+            } else if oid == &OID_MATTER_PID[..] {
+                &mut pid
+            } else {
+                continue;
+            };
+            let (tag, value, _) = attr.next_raw()?;
+            attr.end()?;
+            if (tag != TAG_UTF8_STR && tag != TAG_PRINTABLE_STR)
+                || value.len() != 4
+                || !value
+                    .iter()
+                    .all(|c| c.is_ascii_digit() || (b'A'..=b'F').contains(c))
+            {
+                error!("Invalid Vendor or Product ID in certificate {:x?}", value);
+                return Err(Error::InvalidCertProfile);
+            }
+            if field.is_some() {
+                error!("Duplicate Vendor or Product ID in certificate");
+                return Err(Error::InvalidCertProfile);
+            }
+            // Only ASCII hex digits by now
+            let value = std::str::from_utf8(value)?;
+            *field = Some(u16::from_str_radix(value, 16).map_err(|_| Error::Invalid)?);
+        }
+    }
+    Ok((vid, pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttestationCert, AttestationCertType};
+    use crate::error::Error;
+
+    #[test]
+    fn test_parse_pai() {
+        let pai = AttestationCert::new(&test_vectors::PAI).unwrap();
+        assert_eq!(pai.get_vid(), Some(0xFFF1));
+        assert_eq!(pai.get_pid(), None);
+        assert_eq!(pai.get_not_after(), 0);
+        assert_eq!(&pai.get_subject_key_id()[..2], &[0x63, 0x54]);
+        assert_eq!(&pai.get_auth_key_id().unwrap()[..2], &[0x6A, 0xFD]);
+        pai.check_profile(AttestationCertType::Pai).unwrap();
+        assert_eq!(
+            pai.check_profile(AttestationCertType::Dac),
+            Err(Error::InvalidCertProfile)
+        );
+    }
+
+    #[test]
+    fn test_parse_dac() {
+        let pai = AttestationCert::new(&test_vectors::PAI).unwrap();
+        let dac = AttestationCert::new(&test_vectors::DAC).unwrap();
+        assert_eq!(dac.get_vid(), Some(0xFFF1));
+        assert_eq!(dac.get_pid(), Some(0x8002));
+        dac.check_profile(AttestationCertType::Dac).unwrap();
+        assert_eq!(
+            dac.check_profile(AttestationCertType::Pai),
+            Err(Error::InvalidCertProfile)
+        );
+        dac.verify_issued_by(&pai).unwrap();
+        assert_eq!(pai.verify_issued_by(&dac), Err(Error::InvalidAuthKey));
+    }
+
+    #[test]
+    fn test_tampered_dac() {
+        let pai = AttestationCert::new(&test_vectors::PAI).unwrap();
+        let mut dac = test_vectors::DAC;
+        // Change a byte of the public key
+        dac[250] ^= 0x01;
+        let dac = AttestationCert::new(&dac).unwrap();
+        assert_eq!(dac.verify_issued_by(&pai), Err(Error::InvalidSignature));
+    }
+
+    mod test_vectors {
+        // The test PAI and DAC of the onoff_light example
+        pub const PAI: [u8; 463] = [
+            0x30, 0x82, 0x01, 0xcb, 0x30, 0x82, 0x01, 0x71, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02,
+            0x08, 0x56, 0xad, 0x82, 0x22, 0xad, 0x94, 0x5b, 0x64, 0x30, 0x0a, 0x06, 0x08, 0x2a,
+            0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30, 0x30, 0x31, 0x18, 0x30, 0x16, 0x06,
+            0x03, 0x55, 0x04, 0x03, 0x0c, 0x0f, 0x4d, 0x61, 0x74, 0x74, 0x65, 0x72, 0x20, 0x54,
+            0x65, 0x73, 0x74, 0x20, 0x50, 0x41, 0x41, 0x31, 0x14, 0x30, 0x12, 0x06, 0x0a, 0x2b,
+            0x06, 0x01, 0x04, 0x01, 0x82, 0xa2, 0x7c, 0x02, 0x01, 0x0c, 0x04, 0x46, 0x46, 0x46,
+            0x31, 0x30, 0x20, 0x17, 0x0d, 0x32, 0x32, 0x30, 0x32, 0x30, 0x35, 0x30, 0x30, 0x30,
+            0x30, 0x30, 0x30, 0x5a, 0x18, 0x0f, 0x39, 0x39, 0x39, 0x39, 0x31, 0x32, 0x33, 0x31,
+            0x32, 0x33, 0x35, 0x39, 0x35, 0x39, 0x5a, 0x30, 0x3d, 0x31, 0x25, 0x30, 0x23, 0x06,
+            0x03, 0x55, 0x04, 0x03, 0x0c, 0x1c, 0x4d, 0x61, 0x74, 0x74, 0x65, 0x72, 0x20, 0x44,
+            0x65, 0x76, 0x20, 0x50, 0x41, 0x49, 0x20, 0x30, 0x78, 0x46, 0x46, 0x46, 0x31, 0x20,
+            0x6e, 0x6f, 0x20, 0x50, 0x49, 0x44, 0x31, 0x14, 0x30, 0x12, 0x06, 0x0a, 0x2b, 0x06,
+            0x01, 0x04, 0x01, 0x82, 0xa2, 0x7c, 0x02, 0x01, 0x0c, 0x04, 0x46, 0x46, 0x46, 0x31,
+            0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06,
+            0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0x41,
+            0x9a, 0x93, 0x15, 0xc2, 0x17, 0x3e, 0x0c, 0x8c, 0x87, 0x6d, 0x03, 0xcc, 0xfc, 0x94,
+            0x48, 0x52, 0x64, 0x7f, 0x7f, 0xec, 0x5e, 0x50, 0x82, 0xf4, 0x05, 0x99, 0x28, 0xec,
+            0xa8, 0x94, 0xc5, 0x94, 0x15, 0x13, 0x09, 0xac, 0x63, 0x1e, 0x4c, 0xb0, 0x33, 0x92,
+            0xaf, 0x68, 0x4b, 0x0b, 0xaf, 0xb7, 0xe6, 0x5b, 0x3b, 0x81, 0x62, 0xc2, 0xf5, 0x2b,
+            0xf9, 0x31, 0xb8, 0xe7, 0x7a, 0xaa, 0x82, 0xa3, 0x66, 0x30, 0x64, 0x30, 0x12, 0x06,
+            0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff, 0x04, 0x08, 0x30, 0x06, 0x01, 0x01, 0xff,
+            0x02, 0x01, 0x00, 0x30, 0x0e, 0x06, 0x03, 0x55, 0x1d, 0x0f, 0x01, 0x01, 0xff, 0x04,
+            0x04, 0x03, 0x02, 0x01, 0x06, 0x30, 0x1d, 0x06, 0x03, 0x55, 0x1d, 0x0e, 0x04, 0x16,
+            0x04, 0x14, 0x63, 0x54, 0x0e, 0x47, 0xf6, 0x4b, 0x1c, 0x38, 0xd1, 0x38, 0x84, 0xa4,
+            0x62, 0xd1, 0x6c, 0x19, 0x5d, 0x8f, 0xfb, 0x3c, 0x30, 0x1f, 0x06, 0x03, 0x55, 0x1d,
+            0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0x6a, 0xfd, 0x22, 0x77, 0x1f, 0x51, 0x1f,
+            0xec, 0xbf, 0x16, 0x41, 0x97, 0x67, 0x10, 0xdc, 0xdc, 0x31, 0xa1, 0x71, 0x7e, 0x30,
+            0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x48, 0x00,
+            0x30, 0x45, 0x02, 0x21, 0x00, 0xb2, 0xef, 0x27, 0xf4, 0x9a, 0xe9, 0xb5, 0x0f, 0xb9,
+            0x1e, 0xea, 0xc9, 0x4c, 0x4d, 0x0b, 0xdb, 0xb8, 0xd7, 0x92, 0x9c, 0x6c, 0xb8, 0x8f,
+            0xac, 0xe5, 0x29, 0x36, 0x8d, 0x12, 0x05, 0x4c, 0x0c, 0x02, 0x20, 0x65, 0x5d, 0xc9,
+            0x2b, 0x86, 0xbd, 0x90, 0x98, 0x82, 0xa6, 0xc6, 0x21, 0x77, 0xb8, 0x25, 0xd7, 0xd0,
+            0x5e, 0xdb, 0xe7, 0xc2, 0x2f, 0x9f, 0xea, 0x71, 0x22, 0x0e, 0x7e, 0xa7, 0x03, 0xf8,
+            0x91,
+        ];
+        pub const DAC: [u8; 492] = [
+            0x30, 0x82, 0x01, 0xe8, 0x30, 0x82, 0x01, 0x8e, 0xa0, 0x03, 0x02, 0x01, 0x02, 0x02,
+            0x08, 0x52, 0x72, 0x4d, 0x21, 0xe2, 0xc1, 0x74, 0xaf, 0x30, 0x0a, 0x06, 0x08, 0x2a,
+            0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x30, 0x3d, 0x31, 0x25, 0x30, 0x23, 0x06,
+            0x03, 0x55, 0x04, 0x03, 0x0c, 0x1c, 0x4d, 0x61, 0x74, 0x74, 0x65, 0x72, 0x20, 0x44,
+            0x65, 0x76, 0x20, 0x50, 0x41, 0x49, 0x20, 0x30, 0x78, 0x46, 0x46, 0x46, 0x31, 0x20,
+            0x6e, 0x6f, 0x20, 0x50, 0x49, 0x44, 0x31, 0x14, 0x30, 0x12, 0x06, 0x0a, 0x2b, 0x06,
+            0x01, 0x04, 0x01, 0x82, 0xa2, 0x7c, 0x02, 0x01, 0x0c, 0x04, 0x46, 0x46, 0x46, 0x31,
+            0x30, 0x20, 0x17, 0x0d, 0x32, 0x32, 0x30, 0x32, 0x30, 0x35, 0x30, 0x30, 0x30, 0x30,
+            0x30, 0x30, 0x5a, 0x18, 0x0f, 0x39, 0x39, 0x39, 0x39, 0x31, 0x32, 0x33, 0x31, 0x32,
+            0x33, 0x35, 0x39, 0x35, 0x39, 0x5a, 0x30, 0x53, 0x31, 0x25, 0x30, 0x23, 0x06, 0x03,
+            0x55, 0x04, 0x03, 0x0c, 0x1c, 0x4d, 0x61, 0x74, 0x74, 0x65, 0x72, 0x20, 0x44, 0x65,
+            0x76, 0x20, 0x44, 0x41, 0x43, 0x20, 0x30, 0x78, 0x46, 0x46, 0x46, 0x31, 0x2f, 0x30,
+            0x78, 0x38, 0x30, 0x30, 0x32, 0x31, 0x14, 0x30, 0x12, 0x06, 0x0a, 0x2b, 0x06, 0x01,
+            0x04, 0x01, 0x82, 0xa2, 0x7c, 0x02, 0x01, 0x0c, 0x04, 0x46, 0x46, 0x46, 0x31, 0x31,
+            0x14, 0x30, 0x12, 0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0xa2, 0x7c, 0x02,
+            0x02, 0x0c, 0x04, 0x38, 0x30, 0x30, 0x32, 0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a,
+            0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03,
+            0x01, 0x07, 0x03, 0x42, 0x00, 0x04, 0xda, 0x93, 0xf1, 0x67, 0x36, 0x25, 0x67, 0x50,
+            0xd9, 0x03, 0xb0, 0x34, 0xba, 0x45, 0x88, 0xab, 0xaf, 0x58, 0x95, 0x4f, 0x77, 0xaa,
+            0x9f, 0xd9, 0x98, 0x9d, 0xfd, 0x40, 0x0d, 0x7a, 0xb3, 0xfd, 0xc9, 0x75, 0x3b, 0x3b,
+            0x92, 0x1b, 0x29, 0x4c, 0x95, 0x0f, 0xd9, 0xd2, 0x80, 0xd1, 0x4c, 0x43, 0x86, 0x2f,
+            0x16, 0xdc, 0x85, 0x4b, 0x00, 0xed, 0x39, 0xe7, 0x50, 0xba, 0xbf, 0x1d, 0xc4, 0xca,
+            0xa3, 0x60, 0x30, 0x5e, 0x30, 0x0c, 0x06, 0x03, 0x55, 0x1d, 0x13, 0x01, 0x01, 0xff,
+            0x04, 0x02, 0x30, 0x00, 0x30, 0x0e, 0x06, 0x03, 0x55, 0x1d, 0x0f, 0x01, 0x01, 0xff,
+            0x04, 0x04, 0x03, 0x02, 0x07, 0x80, 0x30, 0x1d, 0x06, 0x03, 0x55, 0x1d, 0x0e, 0x04,
+            0x16, 0x04, 0x14, 0xef, 0x06, 0x56, 0x11, 0x9c, 0x1c, 0x91, 0xa7, 0x9a, 0x94, 0xe6,
+            0xdc, 0xf3, 0x79, 0x79, 0xdb, 0xd0, 0x7f, 0xf8, 0xa3, 0x30, 0x1f, 0x06, 0x03, 0x55,
+            0x1d, 0x23, 0x04, 0x18, 0x30, 0x16, 0x80, 0x14, 0x63, 0x54, 0x0e, 0x47, 0xf6, 0x4b,
+            0x1c, 0x38, 0xd1, 0x38, 0x84, 0xa4, 0x62, 0xd1, 0x6c, 0x19, 0x5d, 0x8f, 0xfb, 0x3c,
+            0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02, 0x03, 0x48,
+            0x00, 0x30, 0x45, 0x02, 0x20, 0x46, 0x86, 0x81, 0x07, 0x33, 0xbf, 0x0d, 0xc8, 0xff,
+            0x4c, 0xb5, 0x14, 0x5a, 0x6b, 0xfa, 0x1a, 0xec, 0xff, 0xa8, 0xb6, 0xda, 0xb6, 0xc3,
+            0x51, 0xaa, 0xee, 0xcd, 0xaf, 0xb8, 0xbe, 0x95, 0x7d, 0x02, 0x21, 0x00, 0xe8, 0xc2,
+            0x8d, 0x6b, 0xfc, 0xc8, 0x7a, 0x7d, 0x54, 0x2e, 0xad, 0x6e, 0xda, 0xca, 0x14, 0x8d,
+            0x5f, 0xa5, 0x06, 0x1e, 0x51, 0x7c, 0xbe, 0x4f, 0x24, 0xa7, 0x20, 0xe1, 0xc0, 0x59,
+            0xde, 0x1a,
+        ];
+    }
+}
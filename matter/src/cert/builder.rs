@@ -0,0 +1,395 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use log::error;
+use rand::Rng;
+
+use crate::{
+    crypto::{self, CryptoKeyPair},
+    error::Error,
+    tlv::TLVArrayOwned,
+    transport::session::MAX_CAT_IDS_PER_NOC,
+};
+
+use super::{
+    BasicConstraints, Cert, CertFields, CertType, DistNameValue, DistNames, DnTags, EcCurveIdValue,
+    Extensions, PubKeyAlgoValue, SignAlgoValue, EXT_KEY_USAGE_CLIENT_AUTH,
+    EXT_KEY_USAGE_SERVER_AUTH, KEY_USAGE_CRL_SIGN, KEY_USAGE_DIGITAL_SIGN, KEY_USAGE_KEY_CERT_SIGN,
+};
+
+const SERIAL_NUM_LEN: usize = 8;
+const KEY_ID_LEN: usize = 20;
+
+/// Builds and signs operational certificates: RCACs, ICACs and NOCs
+///
+/// The extensions are preset as the Matter certificate profile requires them for
+/// the certificate type. A RCAC that isn't given an issuer is self-signed.
+///
+/// ```ignore
+/// let noc = CertBuilder::new(CertType::Noc)
+///     .subject_node_id(node_id)
+///     .subject_fabric_id(fabric_id)
+///     .validity(not_before, not_after)
+///     .public_key(&noc_pub_key)
+///     .issuer(&icac)
+///     .sign(&icac_key_pair)?;
+/// let len = noc.as_tlv(&mut buf)?;
+/// ```
+pub struct CertBuilder {
+    cert_type: CertType,
+    cert: CertFields,
+}
+
+impl CertBuilder {
+    pub fn new(cert_type: CertType) -> Self {
+        let (basic_const, key_usage, ext_key_usage) = match cert_type {
+            CertType::Rcac | CertType::Icac => (
+                BasicConstraints {
+                    is_ca: true,
+                    path: None,
+                },
+                KEY_USAGE_KEY_CERT_SIGN | KEY_USAGE_CRL_SIGN,
+                None,
+            ),
+            CertType::Noc => (
+                BasicConstraints {
+                    is_ca: false,
+                    path: None,
+                },
+                KEY_USAGE_DIGITAL_SIGN,
+                Some(TLVArrayOwned::new(vec![
+                    EXT_KEY_USAGE_CLIENT_AUTH,
+                    EXT_KEY_USAGE_SERVER_AUTH,
+                ])),
+            ),
+        };
+
+        // A random, positive serial number
+        let mut serial_no = vec![0; SERIAL_NUM_LEN];
+        rand::thread_rng().fill(serial_no.as_mut_slice());
+        serial_no[0] &= 0x7f;
+        serial_no[0] |= 0x01;
+
+        Self {
+            cert_type,
+            cert: CertFields {
+                serial_no,
+                sign_algo: SignAlgoValue::ECDSAWithSHA256 as u8,
+                pubkey_algo: PubKeyAlgoValue::EcPubKey as u8,
+                ec_curve_id: EcCurveIdValue::Prime256V1 as u8,
+                extensions: Extensions {
+                    basic_const: Some(basic_const),
+                    key_usage: Some(key_usage),
+                    ext_key_usage,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn serial_no(mut self, serial_no: &[u8]) -> Self {
+        self.cert.serial_no = serial_no.to_vec();
+        self
+    }
+
+    /// The validity period in seconds since the Matter epoch, a _not_after_ of 0
+    /// means the certificate doesn't expire
+    pub fn validity(mut self, not_before: u32, not_after: u32) -> Self {
+        self.cert.not_before = not_before;
+        self.cert.not_after = not_after;
+        self
+    }
+
+    /// Limit the number of ICACs that can be below this CA certificate
+    pub fn path_len(mut self, path_len: u8) -> Self {
+        if let Some(b) = self.cert.extensions.basic_const.as_mut() {
+            b.path = Some(path_len);
+        }
+        self
+    }
+
+    pub fn subject_rcac_id(mut self, id: u64) -> Self {
+        push_u64(&mut self.cert.subject, DnTags::RootCaId, id);
+        self
+    }
+
+    pub fn subject_icac_id(mut self, id: u64) -> Self {
+        push_u64(&mut self.cert.subject, DnTags::IcaId, id);
+        self
+    }
+
+    pub fn subject_node_id(mut self, node_id: u64) -> Self {
+        push_u64(&mut self.cert.subject, DnTags::NodeId, node_id);
+        self
+    }
+
+    pub fn subject_fabric_id(mut self, fabric_id: u64) -> Self {
+        push_u64(&mut self.cert.subject, DnTags::FabricId, fabric_id);
+        self
+    }
+
+    /// Add a CASE Authenticated Tag to the subject, a NOC can carry up to 3 of these
+    ///
+    /// Signing a certificate with more fails with [Error::InvalidArgument].
+    pub fn subject_cat(mut self, cat: u32) -> Self {
+        push_u64(&mut self.cert.subject, DnTags::NocCat, cat as u64);
+        self
+    }
+
+    /// The certificate that issues this one
+    ///
+    /// Its subject becomes our issuer and its Subject Key ID our Authority Key ID.
+    pub fn issuer(mut self, issuer: &Cert) -> Self {
+        self.cert.issuer = issuer.fields.subject.clone();
+        self.cert.extensions.auth_key_id = issuer.fields.extensions.subj_key_id.clone();
+        self
+    }
+
+    /// The public key that the certificate is issued for
+    ///
+    /// The Subject Key ID is derived from this.
+    pub fn public_key(mut self, pub_key: &[u8]) -> Result<Self, Error> {
+        let mut hash = crypto::get_provider().sha256()?;
+        hash.update(pub_key)?;
+        let mut digest = [0u8; crypto::SHA256_HASH_LEN_BYTES];
+        hash.finish(&mut digest)?;
+
+        self.cert.pubkey = pub_key.to_vec();
+        self.cert.extensions.subj_key_id = Some(digest[..KEY_ID_LEN].to_vec());
+        Ok(self)
+    }
+
+    /// Sign the certificate with the issuer's key pair
+    ///
+    /// Use [Cert::as_tlv] on the result for the Matter TLV encoding.
+    pub fn sign(mut self, issuer_key: &dyn CryptoKeyPair) -> Result<Cert, Error> {
+        if self.cert.subject.cert_type() != Some(self.cert_type) {
+            error!("The subject doesn't identify a {:?}", self.cert_type);
+            return Err(Error::InvalidCertProfile);
+        }
+        if self.cert_type == CertType::Noc && self.cert.subject.u64(DnTags::FabricId).is_none() {
+            error!("A NOC needs a Fabric ID");
+            return Err(Error::InvalidCertProfile);
+        }
+        let cats = self
+            .cert
+            .subject
+            .dn
+            .iter()
+            .filter(|(tag, _)| *tag == DnTags::NocCat as u8)
+            .count();
+        if cats > MAX_CAT_IDS_PER_NOC {
+            error!(
+                "{} CASE Authenticated Tags, a NOC can carry up to {}",
+                cats, MAX_CAT_IDS_PER_NOC
+            );
+            return Err(Error::InvalidArgument);
+        }
+        if self.cert.pubkey.is_empty() {
+            error!("No public key");
+            return Err(Error::InvalidCertProfile);
+        }
+        if self.cert.issuer.dn.is_empty() {
+            if self.cert_type != CertType::Rcac {
+                error!("Only a RCAC can be self-signed");
+                return Err(Error::InvalidCertProfile);
+            }
+            self.cert.issuer = self.cert.subject.clone();
+            self.cert.extensions.auth_key_id = self.cert.extensions.subj_key_id.clone();
+        }
+
+        let asn1 = self.cert.to_asn1()?;
+        let mut signature = [0u8; crypto::EC_SIGNATURE_LEN_BYTES];
+        let sig_len = issuer_key.sign_msg(&asn1, &mut signature)?;
+        self.cert.signature = signature[..sig_len].to_vec();
+        Cert::from_fields(self.cert, asn1)
+    }
+}
+
+fn push_u64(dn: &mut DistNames, tag: DnTags, value: u64) {
+    dn.dn.push((tag as u8, DistNameValue::Uint(value)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CertBuilder;
+    use crate::cert::{Cert, CertType};
+    use crate::crypto::{get_provider, CryptoKeyPair, EC_POINT_LEN_BYTES};
+    use crate::error::Error;
+    use crate::utils::epoch::ValidationTime;
+
+    const NOT_BEFORE: u32 = 700000000;
+    const NOT_AFTER: u32 = 800000000;
+
+    fn pub_key(key: &dyn CryptoKeyPair) -> Vec<u8> {
+        let mut pub_key = [0u8; EC_POINT_LEN_BYTES];
+        let len = key.get_public_key(&mut pub_key).unwrap();
+        pub_key[..len].to_vec()
+    }
+
+    fn verify(noc: &Cert, icac: Option<&Cert>, rcac: &Cert) -> Result<(), Error> {
+        let mut verifier = noc.verify_chain_start_at(ValidationTime::Current(NOT_BEFORE + 1));
+        if let Some(icac) = icac {
+            verifier = verifier.add_cert(icac)?;
+        }
+        verifier.add_cert(rcac)?.finalise()
+    }
+
+    #[test]
+    fn test_build_chain() {
+        let rcac_key = get_provider().generate_key_pair().unwrap();
+        let icac_key = get_provider().generate_key_pair().unwrap();
+        let noc_key = get_provider().generate_key_pair().unwrap();
+
+        let rcac = CertBuilder::new(CertType::Rcac)
+            .subject_rcac_id(1)
+            .validity(NOT_BEFORE, 0)
+            .public_key(&pub_key(rcac_key.as_ref()))
+            .unwrap()
+            .sign(rcac_key.as_ref())
+            .unwrap();
+        let icac = CertBuilder::new(CertType::Icac)
+            .subject_icac_id(2)
+            .subject_fabric_id(0xfab)
+            .validity(NOT_BEFORE, NOT_AFTER)
+            .public_key(&pub_key(icac_key.as_ref()))
+            .unwrap()
+            .issuer(&rcac)
+            .sign(rcac_key.as_ref())
+            .unwrap();
+        let noc = CertBuilder::new(CertType::Noc)
+            .subject_node_id(0x1234)
+            .subject_fabric_id(0xfab)
+            .subject_cat(0x0001_0001)
+            .validity(NOT_BEFORE, NOT_AFTER)
+            .public_key(&pub_key(noc_key.as_ref()))
+            .unwrap()
+            .issuer(&icac)
+            .sign(icac_key.as_ref())
+            .unwrap();
+        assert_eq!(Ok(()), verify(&noc, Some(&icac), &rcac));
+
+        // The chain survives the TLV encoding
+        let reparse = |cert: &Cert| {
+            let mut buf = [0u8; 1024];
+            let len = cert.as_tlv(&mut buf).unwrap();
+            Cert::new(&buf[..len]).unwrap()
+        };
+        let noc = reparse(&noc);
+        assert_eq!(0x1234, noc.get_node_id().unwrap());
+        assert_eq!(0xfab, noc.get_fabric_id().unwrap());
+        let mut cats = [0u32; 3];
+        noc.get_cat_ids(&mut cats);
+        assert_eq!(0x0001_0001, cats[0]);
+        assert_eq!(Ok(()), verify(&noc, Some(&reparse(&icac)), &reparse(&rcac)));
+    }
+
+    #[test]
+    fn test_build_noc_from_root() {
+        let rcac_key = get_provider().generate_key_pair().unwrap();
+        let noc_key = get_provider().generate_key_pair().unwrap();
+
+        let rcac = CertBuilder::new(CertType::Rcac)
+            .subject_rcac_id(1)
+            .path_len(0)
+            .public_key(&pub_key(rcac_key.as_ref()))
+            .unwrap()
+            .sign(rcac_key.as_ref())
+            .unwrap();
+        let noc = CertBuilder::new(CertType::Noc)
+            .subject_node_id(5)
+            .subject_fabric_id(1)
+            // verify_noc_chain() checks against the current time
+            .validity(NOT_BEFORE, 0)
+            .public_key(&pub_key(noc_key.as_ref()))
+            .unwrap()
+            .issuer(&rcac)
+            .sign(rcac_key.as_ref())
+            .unwrap();
+        assert_eq!(Ok(()), verify(&noc, None, &rcac));
+        assert_eq!(Ok(()), noc.verify_noc_chain(None, &rcac));
+
+        // Signed by the wrong key
+        let noc = CertBuilder::new(CertType::Noc)
+            .subject_node_id(5)
+            .subject_fabric_id(1)
+            .public_key(&pub_key(noc_key.as_ref()))
+            .unwrap()
+            .issuer(&rcac)
+            .sign(noc_key.as_ref())
+            .unwrap();
+        assert!(verify(&noc, None, &rcac).is_err());
+    }
+
+    #[test]
+    fn test_build_incomplete() {
+        let key = get_provider().generate_key_pair().unwrap();
+
+        // No Fabric ID
+        let noc = CertBuilder::new(CertType::Noc)
+            .subject_node_id(5)
+            .public_key(&pub_key(key.as_ref()))
+            .unwrap()
+            .sign(key.as_ref());
+        assert_eq!(Err(Error::InvalidCertProfile), noc.map(|_| ()));
+
+        // No issuer
+        let icac = CertBuilder::new(CertType::Icac)
+            .subject_icac_id(1)
+            .public_key(&pub_key(key.as_ref()))
+            .unwrap()
+            .sign(key.as_ref());
+        assert_eq!(Err(Error::InvalidCertProfile), icac.map(|_| ()));
+
+        // No public key
+        let rcac = CertBuilder::new(CertType::Rcac)
+            .subject_rcac_id(1)
+            .sign(key.as_ref());
+        assert_eq!(Err(Error::InvalidCertProfile), rcac.map(|_| ()));
+    }
+
+    #[test]
+    fn test_build_too_many_cats() {
+        let key = get_provider().generate_key_pair().unwrap();
+        let rcac = CertBuilder::new(CertType::Rcac)
+            .subject_rcac_id(1)
+            .public_key(&pub_key(key.as_ref()))
+            .unwrap()
+            .sign(key.as_ref())
+            .unwrap();
+        let noc = |cats: &[u32]| {
+            let mut builder = CertBuilder::new(CertType::Noc)
+                .subject_node_id(5)
+                .subject_fabric_id(0xfab);
+            for cat in cats {
+                builder = builder.subject_cat(*cat);
+            }
+            builder
+                .public_key(&pub_key(key.as_ref()))
+                .unwrap()
+                .issuer(&rcac)
+                .sign(key.as_ref())
+                .map(|_| ())
+        };
+        assert_eq!(Ok(()), noc(&[0x0001_0001, 0x0002_0001, 0x0003_0001]));
+        assert_eq!(
+            Err(Error::InvalidArgument),
+            noc(&[0x0001_0001, 0x0002_0001, 0x0003_0001, 0x0004_0001])
+        );
+    }
+}
@@ -0,0 +1,158 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! PKCS#10 (RFC 2986) certificate signing requests
+
+use log::error;
+
+use crate::{crypto, error::Error};
+
+use super::{
+    asn1_reader::{TAG_CTX_CONSTRUCTED, TAG_SEQ},
+    decode_ecdsa_signature, decode_sign_algo, ASN1Reader, OID_EC_TYPE_PRIME256V1,
+    OID_PUB_KEY_ECPUBKEY,
+};
+
+/// A decoded CSR, as generated by [CryptoKeyPair::get_csr](crate::crypto::CryptoKeyPair::get_csr)
+pub struct Csr {
+    // The signed CertificationRequestInfo
+    info: Vec<u8>,
+    pubkey: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Csr {
+    /// Decode a DER CSR
+    ///
+    /// Only P-256 keys with ECDSA with SHA256 signatures are supported. The subject
+    /// and attributes are ignored, the NOC that is issued for the CSR doesn't use
+    /// them.
+    pub fn new(der: &[u8]) -> Result<Self, Error> {
+        let mut r = ASN1Reader::new(der);
+        let mut csr_seq = r.seq()?;
+        r.end()?;
+
+        let (tag, info_contents, info_der) = csr_seq.next_raw()?;
+        if tag != TAG_SEQ {
+            return Err(Error::Invalid);
+        }
+        let mut info = ASN1Reader::new(info_contents);
+        if info.integer()? != [0] {
+            error!("Only version 1 CSRs are supported");
+            return Err(Error::Invalid);
+        }
+        info.seq()?;
+
+        let mut pubkey_info = info.seq()?;
+        let mut pubkey_algo = pubkey_info.seq()?;
+        if pubkey_algo.oid()? != OID_PUB_KEY_ECPUBKEY
+            || pubkey_algo.oid()? != OID_EC_TYPE_PRIME256V1
+        {
+            error!("Only P-256 public keys are supported");
+            return Err(Error::Invalid);
+        }
+        pubkey_algo.end()?;
+        let (unused, pubkey) = pubkey_info.bit_str()?;
+        if unused != 0 || pubkey.len() != crypto::EC_POINT_LEN_BYTES {
+            return Err(Error::InvalidKeyLength);
+        }
+        pubkey_info.end()?;
+        // Some generators leave out the empty attributes
+        info.optional(TAG_CTX_CONSTRUCTED)?;
+        info.end()?;
+
+        decode_sign_algo(&mut csr_seq)?;
+        let (unused, signature) = csr_seq.bit_str()?;
+        if unused != 0 {
+            return Err(Error::Invalid);
+        }
+        let signature = decode_ecdsa_signature(signature)?;
+        csr_seq.end()?;
+
+        Ok(Self {
+            info: info_der.to_vec(),
+            pubkey: pubkey.to_vec(),
+            signature,
+        })
+    }
+
+    /// The public key that the NOC should be issued for
+    pub fn get_pubkey(&self) -> &[u8] {
+        &self.pubkey
+    }
+
+    /// Verify the signature of the CSR with its own public key
+    ///
+    /// This proves that the requester possesses the private key.
+    pub fn verify(&self) -> Result<(), Error> {
+        let k = crypto::get_provider().key_pair_from_public(&self.pubkey)?;
+        k.verify_msg(&self.info, &self.signature).map_err(|e| {
+            error!("Error in signature verification of the CSR");
+            e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Csr;
+    use crate::crypto::{csr_from_pem, get_provider, EC_POINT_LEN_BYTES};
+    use crate::error::Error;
+
+    #[test]
+    fn test_generated_csr() {
+        let key = get_provider().generate_key_pair().unwrap();
+        let mut buf = [0u8; 1024];
+        let csr = Csr::new(key.get_csr(&mut buf).unwrap()).unwrap();
+        csr.verify().unwrap();
+
+        let mut pubkey = [0u8; EC_POINT_LEN_BYTES];
+        key.get_public_key(&mut pubkey).unwrap();
+        assert_eq!(csr.get_pubkey(), pubkey);
+    }
+
+    #[test]
+    fn test_openssl_csr() {
+        // openssl req -new -key <P-256 key> -subj "/O=CSR" -sha256
+        const CSR: &str = "-----BEGIN CERTIFICATE REQUEST-----
+MIHIMHACAQAwDjEMMAoGA1UECgwDQ1NSMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcD
+QgAEJLppSvBT111+TVolonKd5kgIP9gtVlySVtu0CR6TMwGNNfcFVw0ErF2FMN0I
+3PIbrUXASGfDjNQ7wWZsPUR4xKAAMAoGCCqGSM49BAMCA0gAMEUCIQCgsU2wWA5n
+X8DxxxD7HMBaLRiV0S92f9U60b/ZOG0oUAIgK5suZdeDST1HSKcJzDhjSlSCF7d0
+w2UdgsvDIT99Mjs=
+-----END CERTIFICATE REQUEST-----
+";
+        let der = csr_from_pem(CSR).unwrap();
+        let csr = Csr::new(&der).unwrap();
+        csr.verify().unwrap();
+        assert_eq!(&csr.get_pubkey()[..4], &[0x04, 0x24, 0xba, 0x69]);
+
+        // The signature no longer matches once the subject changes
+        let mut tampered = der;
+        let pos = tampered.windows(3).position(|w| w == b"CSR").unwrap();
+        tampered[pos] = b'X';
+        assert_eq!(
+            Csr::new(&tampered).unwrap().verify(),
+            Err(Error::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_not_a_csr() {
+        assert!(Csr::new(&[0x30, 0x03, 0x02, 0x01, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! A JSON form of certificates, so that tools can compare them
+//!
+//! The schema is fixed, so that certificates can be compared member by member,
+//! and the member names don't depend on the labels of the human readable printer:
+//!
+//! - The DNs are objects that are named by their attributes. The attributes that
+//!   may be repeated, like the CATs of a subject, are always arrays.
+//! - The extensions are always an array of objects with the members "extension",
+//!   "critical" and "value", and they are named by their OIDs.
+//! - The times have both the epoch and the ISO form, the "iso" of a certificate
+//!   without expiry is null.
+//! - Identifiers, keys and signatures are hex strings.
+
+use std::fmt::Write;
+
+use log::error;
+
+use super::{
+    bitstring_to_int, printer::to_utc, CertConsumer, DN_ENCODING, EXT_KEY_USAGE_ENCODING,
+    KEY_USAGE_CRL_SIGN, KEY_USAGE_DATA_ENCIPHERMENT, KEY_USAGE_DECIPHER_ONLY,
+    KEY_USAGE_DIGITAL_SIGN, KEY_USAGE_ENCIPHER_ONLY, KEY_USAGE_KEY_AGREEMENT,
+    KEY_USAGE_KEY_CERT_SIGN, KEY_USAGE_KEY_ENCIPHERMENT, KEY_USAGE_NON_REPUDIATION,
+    OID_AUTH_KEY_ID, OID_BASIC_CONSTRAINTS, OID_ECDSA_WITH_SHA256, OID_EC_TYPE_PRIME256V1,
+    OID_EXT_KEY_USAGE, OID_KEY_USAGE, OID_PUB_KEY_ECPUBKEY, OID_SUBJ_KEY_IDENTIFIER,
+};
+use crate::error::Error;
+
+// The names of the DN attributes, in the order of DN_ENCODING
+const DN_KEYS: [&str; 22] = [
+    "common_name",
+    "surname",
+    "serial_number",
+    "country_name",
+    "locality_name",
+    "state_name",
+    "organization_name",
+    "organizational_unit_name",
+    "title",
+    "name",
+    "given_name",
+    "initials",
+    "generation_qualifier",
+    "dn_qualifier",
+    "pseudonym",
+    "domain_component",
+    "node_id",
+    "firmware_signing_id",
+    "icac_id",
+    "rcac_id",
+    "fabric_id",
+    "noc_cats",
+];
+
+// The DN attributes that may appear more than once
+const REPEATABLE_DN_KEYS: [&str; 3] = ["organizational_unit_name", "domain_component", "noc_cats"];
+
+// The names of the extended key usages, in the order of EXT_KEY_USAGE_ENCODING
+const EXT_KEY_USAGE_KEYS: [&str; 7] = [
+    "",
+    "server_auth",
+    "client_auth",
+    "code_signing",
+    "email_protection",
+    "time_stamping",
+    "ocsp_signing",
+];
+
+const KEY_USAGE_KEYS: [(u16, &str); 9] = [
+    (KEY_USAGE_DIGITAL_SIGN, "digital_signature"),
+    (KEY_USAGE_NON_REPUDIATION, "non_repudiation"),
+    (KEY_USAGE_KEY_ENCIPHERMENT, "key_encipherment"),
+    (KEY_USAGE_DATA_ENCIPHERMENT, "data_encipherment"),
+    (KEY_USAGE_KEY_AGREEMENT, "key_agreement"),
+    (KEY_USAGE_KEY_CERT_SIGN, "key_cert_sign"),
+    (KEY_USAGE_CRL_SIGN, "crl_sign"),
+    (KEY_USAGE_ENCIPHER_ONLY, "encipher_only"),
+    (KEY_USAGE_DECIPHER_ONLY, "decipher_only"),
+];
+
+type Members = Vec<(&'static str, Value)>;
+
+enum Value {
+    Null,
+    Bool(bool),
+    Num(u64),
+    Str(String),
+    Array(Vec<Value>),
+    Object(Members),
+}
+
+impl Value {
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(b) => {
+                let _ = write!(out, "{}", b);
+            }
+            Value::Num(n) => {
+                let _ = write!(out, "{}", n);
+            }
+            Value::Str(s) => write_str(out, s),
+            Value::Array(items) if items.is_empty() => out.push_str("[]"),
+            Value::Array(items) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    item.write(out, indent + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.push(']');
+            }
+            Value::Object(members) if members.is_empty() => out.push_str("{}"),
+            Value::Object(members) => {
+                out.push_str("{\n");
+                for (i, (key, value)) in members.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    write_str(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                    out.push_str(if i + 1 < members.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.push('}');
+            }
+        }
+    }
+
+    // Replace the member _key_ of an object
+    fn set(&mut self, key: &str, value: Value) -> Result<(), Error> {
+        match self {
+            Value::Object(members) => {
+                let member = members
+                    .iter_mut()
+                    .find(|(k, _)| *k == key)
+                    .ok_or(Error::Invalid)?;
+                member.1 = value;
+                Ok(())
+            }
+            _ => Err(Error::Invalid),
+        }
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn hex(data: &[u8]) -> Value {
+    let mut s = String::with_capacity(data.len() * 2);
+    for b in data {
+        let _ = write!(s, "{:02x}", b);
+    }
+    Value::Str(s)
+}
+
+fn time(epoch: u32) -> Result<Value, Error> {
+    let iso = to_utc(epoch)?.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    Ok(Value::Object(vec![
+        ("epoch", Value::Num(epoch as u64)),
+        ("iso", Value::Str(iso)),
+    ]))
+}
+
+// The name of the extension with the OID _oid_, and the value that it starts from
+fn extension(oid: &[u8]) -> Option<(&'static str, Value)> {
+    let extension = match oid {
+        o if o == OID_BASIC_CONSTRAINTS => (
+            "basic_constraints",
+            Value::Object(vec![("ca", Value::Bool(false)), ("path_len", Value::Null)]),
+        ),
+        o if o == OID_KEY_USAGE => ("key_usage", Value::Array(Vec::new())),
+        o if o == OID_EXT_KEY_USAGE => ("extended_key_usage", Value::Array(Vec::new())),
+        o if o == OID_SUBJ_KEY_IDENTIFIER => ("subject_key_id", Value::Null),
+        o if o == OID_AUTH_KEY_ID => ("authority_key_id", Value::Null),
+        _ => return None,
+    };
+    Some(extension)
+}
+
+fn add_dn_attr(attrs: &mut Members, key: &'static str, value: Value) -> Result<(), Error> {
+    let existing = attrs.iter_mut().find(|(k, _)| *k == key);
+    if !REPEATABLE_DN_KEYS.contains(&key) {
+        if existing.is_some() {
+            error!("The DN attribute {} is repeated", key);
+            return Err(Error::Invalid);
+        }
+        attrs.push((key, value));
+        return Ok(());
+    }
+    match existing {
+        Some((_, Value::Array(values))) => values.push(value),
+        _ => attrs.push((key, Value::Array(vec![value]))),
+    }
+    Ok(())
+}
+
+// Where the values of the certificate go
+enum Ctx {
+    // A container that only groups the values in it
+    Group,
+    Version,
+    SignatureAlgorithm,
+    PublicKeyAlgorithm,
+    Validity(Members),
+    // The DN, and the attribute that its next value is for
+    Dn(&'static str, Members, Option<&'static str>),
+    Extensions(Vec<Value>),
+    // An extension is unnamed until its OID arrives
+    Extension {
+        name: &'static str,
+        critical: bool,
+        value: Value,
+    },
+}
+
+// The context that the next value goes into, None being the certificate itself
+fn innermost(stack: &mut [Ctx]) -> Option<&mut Ctx> {
+    stack.iter_mut().rev().find(|c| !matches!(c, Ctx::Group))
+}
+
+/// A [CertConsumer] that emits JSON
+pub struct CertJsonPrinter {
+    members: Members,
+    stack: Vec<Ctx>,
+}
+
+impl CertJsonPrinter {
+    pub fn new() -> Self {
+        Self {
+            members: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// The JSON of everything that was consumed
+    pub fn into_json(self) -> String {
+        let mut out = String::new();
+        Value::Object(self.members).write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn start(&mut self, tag: &str) -> Result<(), Error> {
+        let ctx = match tag {
+            // The value of an extension is built in the extension
+            "" | "value:" => Ctx::Group,
+            "Version:" => Ctx::Version,
+            "Signature Algorithm:" => Ctx::SignatureAlgorithm,
+            "Issuer:" => Ctx::Dn("issuer", Vec::new(), None),
+            "Validity:" => Ctx::Validity(Vec::new()),
+            "Subject:" => Ctx::Dn("subject", Vec::new(), None),
+            "Public Key Algorithm" => Ctx::PublicKeyAlgorithm,
+            "X509v3 extensions:" => Ctx::Extensions(Vec::new()),
+            _ if matches!(innermost(&mut self.stack), Some(Ctx::Extensions(_))) => Ctx::Extension {
+                name: "",
+                critical: false,
+                value: Value::Null,
+            },
+            _ => return Err(Self::unexpected(tag)),
+        };
+        self.stack.push(ctx);
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        match self.stack.pop().ok_or(Error::Invalid)? {
+            Ctx::Validity(members) => self.members.push(("validity", Value::Object(members))),
+            Ctx::Dn(key, attrs, _) => self.members.push((key, Value::Object(attrs))),
+            Ctx::Extensions(items) => self.members.push(("extensions", Value::Array(items))),
+            Ctx::Extension {
+                name,
+                critical,
+                value,
+            } => match innermost(&mut self.stack) {
+                Some(Ctx::Extensions(items)) if !name.is_empty() => {
+                    items.push(Value::Object(vec![
+                        ("extension", Value::Str(name.to_string())),
+                        ("critical", Value::Bool(critical)),
+                        ("value", value),
+                    ]))
+                }
+                _ => return Err(Error::Invalid),
+            },
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // A value that has no other meaning than its bytes
+    fn add(&mut self, tag: &str, value: Value) -> Result<(), Error> {
+        match innermost(&mut self.stack) {
+            None => {
+                let key = match tag {
+                    "Serial Num:" => "serial_number",
+                    "Public-Key:" => "public_key",
+                    "Signature" => "signature",
+                    _ => return Err(Self::unexpected(tag)),
+                };
+                self.members.push((key, value));
+            }
+            Some(Ctx::Dn(_, attrs, attr)) => {
+                let key = attr.take().ok_or(Error::Invalid)?;
+                add_dn_attr(attrs, key, value)?;
+            }
+            Some(Ctx::Extension {
+                name: "subject_key_id" | "authority_key_id",
+                value: ext_value,
+                ..
+            }) => *ext_value = value,
+            _ => return Err(Self::unexpected(tag)),
+        }
+        Ok(())
+    }
+
+    fn unexpected(tag: &str) -> Error {
+        error!("Unexpected certificate field {:?}", tag);
+        Error::Invalid
+    }
+}
+
+impl Default for CertJsonPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CertConsumer for CertJsonPrinter {
+    fn start_seq(&mut self, tag: &str) -> Result<(), Error> {
+        self.start(tag)
+    }
+    fn end_seq(&mut self) -> Result<(), Error> {
+        self.end()
+    }
+    fn integer(&mut self, tag: &str, i: &[u8]) -> Result<(), Error> {
+        let num = || i.iter().fold(0, |n, b| (n << 8) | *b as u64);
+        match innermost(&mut self.stack) {
+            // The version is encoded as one less than the X.509 version
+            Some(Ctx::Version) => self.members.push(("version", Value::Num(num() + 1))),
+            Some(Ctx::Extension {
+                name: "basic_constraints",
+                value,
+                ..
+            }) => value.set("path_len", Value::Num(num()))?,
+            _ => return self.add(tag, hex(i)),
+        }
+        Ok(())
+    }
+    fn printstr(&mut self, tag: &str, s: &str) -> Result<(), Error> {
+        self.add(tag, Value::Str(s.to_string()))
+    }
+    fn utf8str(&mut self, tag: &str, s: &str) -> Result<(), Error> {
+        self.add(tag, Value::Str(s.to_string()))
+    }
+    fn bitstr(&mut self, tag: &str, truncate: bool, s: &[u8]) -> Result<(), Error> {
+        if !truncate {
+            return self.add(tag, hex(s));
+        }
+        match innermost(&mut self.stack) {
+            Some(Ctx::Extension {
+                name: "key_usage",
+                value: Value::Array(flags),
+                ..
+            }) => {
+                let key_usage = bitstring_to_int(s)?;
+                for (bit, key) in KEY_USAGE_KEYS {
+                    if key_usage & bit != 0 {
+                        flags.push(Value::Str(key.to_string()));
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(Self::unexpected(tag)),
+        }
+    }
+    fn ostr(&mut self, tag: &str, s: &[u8]) -> Result<(), Error> {
+        self.add(tag, hex(s))
+    }
+    fn start_compound_ostr(&mut self, tag: &str) -> Result<(), Error> {
+        self.start(tag)
+    }
+    fn end_compound_ostr(&mut self) -> Result<(), Error> {
+        self.end()
+    }
+    fn bool(&mut self, tag: &str, b: bool) -> Result<(), Error> {
+        match (innermost(&mut self.stack), tag) {
+            (Some(Ctx::Extension { critical, .. }), "critical:") => *critical = b,
+            (
+                Some(Ctx::Extension {
+                    name: "basic_constraints",
+                    value,
+                    ..
+                }),
+                "CA:",
+            ) => value.set("ca", Value::Bool(b))?,
+            _ => return Err(Self::unexpected(tag)),
+        }
+        Ok(())
+    }
+    fn start_set(&mut self, tag: &str) -> Result<(), Error> {
+        self.start(tag)
+    }
+    fn end_set(&mut self) -> Result<(), Error> {
+        self.end()
+    }
+    fn ctx(&mut self, tag: &str, _id: u8, val: &[u8]) -> Result<(), Error> {
+        self.add(tag, hex(val))
+    }
+    fn start_ctx(&mut self, tag: &str, _id: u8) -> Result<(), Error> {
+        self.start(tag)
+    }
+    fn end_ctx(&mut self) -> Result<(), Error> {
+        self.end()
+    }
+    fn oid(&mut self, tag: &str, oid: &[u8]) -> Result<(), Error> {
+        match innermost(&mut self.stack) {
+            Some(Ctx::SignatureAlgorithm) if oid == OID_ECDSA_WITH_SHA256 => self.members.push((
+                "signature_algorithm",
+                Value::Str("ecdsa_with_sha256".into()),
+            )),
+            Some(Ctx::PublicKeyAlgorithm) if oid == OID_PUB_KEY_ECPUBKEY => self
+                .members
+                .push(("public_key_algorithm", Value::Str("ec_public_key".into()))),
+            Some(Ctx::PublicKeyAlgorithm) if oid == OID_EC_TYPE_PRIME256V1 => self
+                .members
+                .push(("curve", Value::Str("prime256v1".into()))),
+            Some(Ctx::Dn(_, _, attr)) => {
+                let index = DN_ENCODING
+                    .iter()
+                    .position(|(_, o, _)| *o == oid)
+                    .ok_or_else(|| Self::unexpected(tag))?;
+                *attr = Some(DN_KEYS[index]);
+            }
+            Some(Ctx::Extension { name, value, .. }) if name.is_empty() => {
+                let (ext_name, ext_value) = extension(oid).ok_or_else(|| Self::unexpected(tag))?;
+                *name = ext_name;
+                *value = ext_value;
+            }
+            Some(Ctx::Extension {
+                name: "extended_key_usage",
+                value: Value::Array(usages),
+                ..
+            }) => {
+                let index = EXT_KEY_USAGE_ENCODING
+                    .iter()
+                    .position(|(_, o)| *o == oid)
+                    .ok_or_else(|| Self::unexpected(tag))?;
+                usages.push(Value::Str(EXT_KEY_USAGE_KEYS[index].to_string()));
+            }
+            _ => return Err(Self::unexpected(tag)),
+        }
+        Ok(())
+    }
+    fn utctime(&mut self, tag: &str, epoch: u32) -> Result<(), Error> {
+        let value = time(epoch)?;
+        match (innermost(&mut self.stack), tag) {
+            (Some(Ctx::Validity(members)), "Not Before:") => members.push(("not_before", value)),
+            (Some(Ctx::Validity(members)), "Not After:") => members.push(("not_after", value)),
+            _ => return Err(Self::unexpected(tag)),
+        }
+        Ok(())
+    }
+    fn no_expiry_time(&mut self, tag: &str) -> Result<(), Error> {
+        match (innermost(&mut self.stack), tag) {
+            (Some(Ctx::Validity(members)), "Not After:") => members.push((
+                "not_after",
+                Value::Object(vec![("epoch", Value::Num(0)), ("iso", Value::Null)]),
+            )),
+            _ => return Err(Self::unexpected(tag)),
+        }
+        Ok(())
+    }
+}
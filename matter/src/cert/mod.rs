@@ -15,25 +15,35 @@
  *    limitations under the License.
  */
 
-use std::fmt;
+use std::{fmt, sync::OnceLock};
 
 use crate::{
-    crypto::{CryptoKeyPair, KeyPair},
+    crypto,
     error::Error,
     tlv::{self, FromTLV, TLVArrayOwned, TLVElement, TLVWriter, TagType, ToTLV},
-    utils::writebuf::WriteBuf,
+    utils::{
+        epoch::{self, ValidationTime},
+        pem,
+        writebuf::WriteBuf,
+    },
 };
 use log::error;
 use num_derive::FromPrimitive;
 
+pub use self::asn1_reader::ASN1Reader;
+use self::asn1_reader::{TAG_BOOL, TAG_CTX, TAG_INTEGER, TAG_PRINTABLE_STR, TAG_SEQ, TAG_UTF8_STR};
 pub use self::asn1_writer::ASN1Writer;
+pub use self::attestation::{AttestationCert, AttestationCertType};
+pub use self::builder::CertBuilder;
+pub use self::csr::Csr;
+use self::json::CertJsonPrinter;
 use self::printer::CertPrinter;
 
 // As per https://datatracker.ietf.org/doc/html/rfc5280
 
-const OID_PUB_KEY_ECPUBKEY: [u8; 7] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
-const OID_EC_TYPE_PRIME256V1: [u8; 8] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
-const OID_ECDSA_WITH_SHA256: [u8; 8] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
+pub(crate) const OID_PUB_KEY_ECPUBKEY: [u8; 7] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+pub(crate) const OID_EC_TYPE_PRIME256V1: [u8; 8] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+pub(crate) const OID_ECDSA_WITH_SHA256: [u8; 8] = [0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
 
 #[derive(FromPrimitive)]
 pub enum CertTags {
@@ -87,6 +97,9 @@ const KEY_USAGE_CRL_SIGN: u16 = 0x0040;
 const KEY_USAGE_ENCIPHER_ONLY: u16 = 0x0080;
 const KEY_USAGE_DECIPHER_ONLY: u16 = 0x0100;
 
+const EXT_KEY_USAGE_SERVER_AUTH: u8 = 1;
+const EXT_KEY_USAGE_CLIENT_AUTH: u8 = 2;
+
 fn reverse_byte(byte: u8) -> u8 {
     const LOOKUP: [u8; 16] = [
         0x00, 0x08, 0x04, 0x0c, 0x02, 0x0a, 0x06, 0x0e, 0x01, 0x09, 0x05, 0x0d, 0x03, 0x0b, 0x07,
@@ -103,6 +116,14 @@ fn int_to_bitstring(mut a: u16, buf: &mut [u8]) {
     }
 }
 
+fn bitstring_to_int(buf: &[u8]) -> Result<u16, Error> {
+    match buf {
+        [b0] => Ok(reverse_byte(*b0) as u16),
+        [b0, b1] => Ok(reverse_byte(*b0) as u16 | ((reverse_byte(*b1) as u16) << 8)),
+        _ => Err(Error::Invalid),
+    }
+}
+
 macro_rules! add_if {
     ($key:ident, $bit:ident,$str:literal) => {
         if ($key & $bit) != 0 {
@@ -136,31 +157,32 @@ fn encode_key_usage(key_usage: u16, w: &mut dyn CertConsumer) -> Result<(), Erro
     Ok(())
 }
 
+const OID_SERVER_AUTH: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01];
+const OID_CLIENT_AUTH: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02];
+const OID_CODE_SIGN: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x03];
+const OID_EMAIL_PROT: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x04];
+const OID_TIMESTAMP: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x08];
+const OID_OCSP_SIGN: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x09];
+// Indexed by the Matter extended key usage value
+const EXT_KEY_USAGE_ENCODING: [(&str, &[u8; 8]); 7] = [
+    ("", &[0; 8]),
+    ("ServerAuth", &OID_SERVER_AUTH),
+    ("ClientAuth", &OID_CLIENT_AUTH),
+    ("CodeSign", &OID_CODE_SIGN),
+    ("EmailProtection", &OID_EMAIL_PROT),
+    ("Timestamp", &OID_TIMESTAMP),
+    ("OCSPSign", &OID_OCSP_SIGN),
+];
+
 fn encode_extended_key_usage(
     list: &TLVArrayOwned<u8>,
     w: &mut dyn CertConsumer,
 ) -> Result<(), Error> {
-    const OID_SERVER_AUTH: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01];
-    const OID_CLIENT_AUTH: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x02];
-    const OID_CODE_SIGN: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x03];
-    const OID_EMAIL_PROT: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x04];
-    const OID_TIMESTAMP: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x08];
-    const OID_OCSP_SIGN: [u8; 8] = [0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x09];
-    let encoding = [
-        ("", &[0; 8]),
-        ("ServerAuth", &OID_SERVER_AUTH),
-        ("ClientAuth", &OID_CLIENT_AUTH),
-        ("CodeSign", &OID_CODE_SIGN),
-        ("EmailProtection", &OID_EMAIL_PROT),
-        ("Timestamp", &OID_TIMESTAMP),
-        ("OCSPSign", &OID_OCSP_SIGN),
-    ];
-
     w.start_seq("")?;
     for t in list.iter() {
         let t = *t as usize;
-        if t > 0 && t <= encoding.len() {
-            w.oid(encoding[t].0, encoding[t].1)?;
+        if t > 0 && t < EXT_KEY_USAGE_ENCODING.len() {
+            w.oid(EXT_KEY_USAGE_ENCODING[t].0, EXT_KEY_USAGE_ENCODING[t].1)?;
         } else {
             error!("Skipping encoding key usage out of bounds");
         }
@@ -209,6 +231,12 @@ fn encode_extension_end(w: &mut dyn CertConsumer) -> Result<(), Error> {
     w.end_seq()
 }
 
+const OID_BASIC_CONSTRAINTS: [u8; 3] = [0x55, 0x1D, 0x13];
+const OID_KEY_USAGE: [u8; 3] = [0x55, 0x1D, 0x0F];
+const OID_EXT_KEY_USAGE: [u8; 3] = [0x55, 0x1D, 0x25];
+const OID_SUBJ_KEY_IDENTIFIER: [u8; 3] = [0x55, 0x1D, 0x0E];
+const OID_AUTH_KEY_ID: [u8; 3] = [0x55, 0x1D, 0x23];
+
 #[derive(FromTLV, ToTLV, Default)]
 #[tlvargs(start = 1, datatype = "list")]
 struct Extensions {
@@ -222,12 +250,6 @@ struct Extensions {
 
 impl Extensions {
     fn encode(&self, w: &mut dyn CertConsumer) -> Result<(), Error> {
-        const OID_BASIC_CONSTRAINTS: [u8; 3] = [0x55, 0x1D, 0x13];
-        const OID_KEY_USAGE: [u8; 3] = [0x55, 0x1D, 0x0F];
-        const OID_EXT_KEY_USAGE: [u8; 3] = [0x55, 0x1D, 0x25];
-        const OID_SUBJ_KEY_IDENTIFIER: [u8; 3] = [0x55, 0x1D, 0x0E];
-        const OID_AUTH_KEY_ID: [u8; 3] = [0x55, 0x1D, 0x23];
-
         w.start_ctx("X509v3 extensions:", 3)?;
         w.start_seq("")?;
         if let Some(t) = &self.basic_const {
@@ -264,9 +286,161 @@ impl Extensions {
         w.end_ctx()?;
         Ok(())
     }
+
+    fn from_asn1(mut r: ASN1Reader) -> Result<Self, Error> {
+        fn set_once<T>(field: &mut Option<T>, value: T) -> Result<(), Error> {
+            if field.is_some() {
+                error!("Duplicate extension");
+                return Err(Error::Invalid);
+            }
+            *field = Some(value);
+            Ok(())
+        }
+
+        let mut ext = Self::default();
+        while !r.is_empty() {
+            let mut e = r.seq()?;
+            let oid = e.oid()?;
+            if e.peek_tag() == Some(TAG_BOOL) {
+                // The criticality is implied by the extension type in Matter
+                e.bool()?;
+            }
+            let mut value = ASN1Reader::new(e.octet_str()?);
+            e.end()?;
+
+            if oid == &OID_BASIC_CONSTRAINTS[..] {
+                let mut seq = value.seq()?;
+                let is_ca = if seq.peek_tag() == Some(TAG_BOOL) {
+                    seq.bool()?
+                } else {
+                    false
+                };
+                let path = match seq.optional(TAG_INTEGER)? {
+                    Some([path]) => Some(*path),
+                    Some(_) => return Err(Error::Invalid),
+                    None => None,
+                };
+                seq.end()?;
+                set_once(&mut ext.basic_const, BasicConstraints { is_ca, path })?;
+            } else if oid == &OID_KEY_USAGE[..] {
+                let (_, bits) = value.bit_str()?;
+                set_once(&mut ext.key_usage, bitstring_to_int(bits)?)?;
+            } else if oid == &OID_EXT_KEY_USAGE[..] {
+                let mut seq = value.seq()?;
+                let mut list = Vec::new();
+                while !seq.is_empty() {
+                    let oid = seq.oid()?;
+                    let usage = EXT_KEY_USAGE_ENCODING
+                        .iter()
+                        .skip(1)
+                        .position(|(_, o)| oid == &o[..])
+                        .ok_or(Error::Invalid)?;
+                    list.push((usage + 1) as u8);
+                }
+                set_once(&mut ext.ext_key_usage, TLVArrayOwned::new(list))?;
+            } else if oid == &OID_SUBJ_KEY_IDENTIFIER[..] {
+                set_once(&mut ext.subj_key_id, value.octet_str()?.to_vec())?;
+            } else if oid == &OID_AUTH_KEY_ID[..] {
+                let mut seq = value.seq()?;
+                set_once(&mut ext.auth_key_id, seq.expect(TAG_CTX)?.to_vec())?;
+                seq.end()?;
+            } else {
+                error!("Unsupported extension {:x?}", oid);
+                return Err(Error::Invalid);
+            }
+            value.end()?;
+        }
+        Ok(ext)
+    }
 }
 const MAX_DN_ENTRIES: usize = 5;
 
+const OID_COMMON_NAME: [u8; 3] = [0x55_u8, 0x04, 0x03];
+const OID_SURNAME: [u8; 3] = [0x55_u8, 0x04, 0x04];
+const OID_SERIAL_NUMBER: [u8; 3] = [0x55_u8, 0x04, 0x05];
+const OID_COUNTRY_NAME: [u8; 3] = [0x55_u8, 0x04, 0x06];
+const OID_LOCALITY_NAME: [u8; 3] = [0x55_u8, 0x04, 0x07];
+const OID_STATE_NAME: [u8; 3] = [0x55_u8, 0x04, 0x08];
+const OID_ORGANIZATION_NAME: [u8; 3] = [0x55_u8, 0x04, 0x0A];
+const OID_ORGANIZATIONAL_UNIT_NAME: [u8; 3] = [0x55_u8, 0x04, 0x0B];
+const OID_TITLE: [u8; 3] = [0x55_u8, 0x04, 0x0C];
+const OID_NAME: [u8; 3] = [0x55_u8, 0x04, 0x29];
+const OID_GIVEN_NAME: [u8; 3] = [0x55_u8, 0x04, 0x2A];
+const OID_INITIALS: [u8; 3] = [0x55_u8, 0x04, 0x2B];
+const OID_GENERATION_QUALIFIER: [u8; 3] = [0x55_u8, 0x04, 0x2C];
+const OID_DN_QUALIFIER: [u8; 3] = [0x55_u8, 0x04, 0x2E];
+const OID_PSEUDONYM: [u8; 3] = [0x55_u8, 0x04, 0x41];
+const OID_DOMAIN_COMPONENT: [u8; 10] = [
+    0x09_u8, 0x92, 0x26, 0x89, 0x93, 0xF2, 0x2C, 0x64, 0x01, 0x19,
+];
+const OID_MATTER_NODE_ID: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x01,
+];
+const OID_MATTER_FW_SIGNING_ID: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x02,
+];
+const OID_MATTER_ICAC_ID: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x03,
+];
+const OID_MATTER_RCAC_ID: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x04,
+];
+const OID_MATTER_FABRIC_ID: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x05,
+];
+const OID_MATTER_CASE_AUTH_TAG: [u8; 10] = [
+    0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x06,
+];
+
+const DN_ENCODING: [(&str, &[u8], Option<IntToStringLen>); 22] = [
+    ("Common Name:", &OID_COMMON_NAME, None),
+    ("Surname:", &OID_SURNAME, None),
+    ("Serial Number", &OID_SERIAL_NUMBER, None),
+    ("Country Name", &OID_COUNTRY_NAME, None),
+    ("Locality name", &OID_LOCALITY_NAME, None),
+    ("State Name", &OID_STATE_NAME, None),
+    ("Org Name", &OID_ORGANIZATION_NAME, None),
+    ("OU Name", &OID_ORGANIZATIONAL_UNIT_NAME, None),
+    ("Title", &OID_TITLE, None),
+    ("Name", &OID_NAME, None),
+    ("Given Name", &OID_GIVEN_NAME, None),
+    ("Initials", &OID_INITIALS, None),
+    ("Gen Qualifier", &OID_GENERATION_QUALIFIER, None),
+    ("DN Qualifier", &OID_DN_QUALIFIER, None),
+    ("Pseudonym", &OID_PSEUDONYM, None),
+    ("Domain Component", &OID_DOMAIN_COMPONENT, None),
+    (
+        "Chip Node Id:",
+        &OID_MATTER_NODE_ID,
+        Some(IntToStringLen::Len16),
+    ),
+    (
+        "Chip Firmware Signing Id:",
+        &OID_MATTER_FW_SIGNING_ID,
+        Some(IntToStringLen::Len16),
+    ),
+    (
+        "Chip ICA Id:",
+        &OID_MATTER_ICAC_ID,
+        Some(IntToStringLen::Len16),
+    ),
+    (
+        "Chip Root CA Id:",
+        &OID_MATTER_RCAC_ID,
+        Some(IntToStringLen::Len16),
+    ),
+    (
+        "Chip Fabric Id:",
+        &OID_MATTER_FABRIC_ID,
+        Some(IntToStringLen::Len16),
+    ),
+    (
+        "Chip NOC CAT Id:",
+        &OID_MATTER_CASE_AUTH_TAG,
+        Some(IntToStringLen::Len8),
+    ),
+];
+
 #[derive(FromPrimitive, Copy, Clone)]
 enum DnTags {
     CommonName = 1,
@@ -293,13 +467,14 @@ enum DnTags {
     NocCat = 22,
 }
 
+#[derive(Clone)]
 enum DistNameValue {
     Uint(u64),
     Utf8Str(Vec<u8>),
     PrintableStr(Vec<u8>),
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 struct DistNames {
     // The order in which the DNs arrive is important, as the signing
     // requires that the ASN1 notation retains the same order
@@ -320,16 +495,28 @@ impl DistNames {
             })
     }
 
-    fn u32_arr(&self, match_id: DnTags, output: &mut [u32]) {
-        let mut out_index = 0;
-        for (_, val) in self.dn.iter().filter(|(id, _)| *id == match_id as u8) {
-            if let DistNameValue::Uint(a) = val {
-                if out_index < output.len() {
-                    // CatIds are actually just 32-bit
-                    output[out_index] = *a as u32;
-                    out_index += 1;
-                }
-            }
+    fn u32_list(&self, match_id: DnTags) -> Vec<u32> {
+        self.dn
+            .iter()
+            .filter(|(id, _)| *id == match_id as u8)
+            .filter_map(|(_, value)| match value {
+                // CatIds are actually just 32-bit
+                DistNameValue::Uint(a) => Some(*a as u32),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The type of the certificate that this is the subject of
+    fn cert_type(&self) -> Option<CertType> {
+        if self.u64(DnTags::NodeId).is_some() {
+            Some(CertType::Noc)
+        } else if self.u64(DnTags::IcaId).is_some() {
+            Some(CertType::Icac)
+        } else if self.u64(DnTags::RootCaId).is_some() {
+            Some(CertType::Rcac)
+        } else {
+            None
         }
     }
 }
@@ -380,93 +567,41 @@ impl ToTLV for DistNames {
 }
 
 impl DistNames {
-    fn encode(&self, tag: &str, w: &mut dyn CertConsumer) -> Result<(), Error> {
-        const OID_COMMON_NAME: [u8; 3] = [0x55_u8, 0x04, 0x03];
-        const OID_SURNAME: [u8; 3] = [0x55_u8, 0x04, 0x04];
-        const OID_SERIAL_NUMBER: [u8; 3] = [0x55_u8, 0x04, 0x05];
-        const OID_COUNTRY_NAME: [u8; 3] = [0x55_u8, 0x04, 0x06];
-        const OID_LOCALITY_NAME: [u8; 3] = [0x55_u8, 0x04, 0x07];
-        const OID_STATE_NAME: [u8; 3] = [0x55_u8, 0x04, 0x08];
-        const OID_ORGANIZATION_NAME: [u8; 3] = [0x55_u8, 0x04, 0x0A];
-        const OID_ORGANIZATIONAL_UNIT_NAME: [u8; 3] = [0x55_u8, 0x04, 0x0B];
-        const OID_TITLE: [u8; 3] = [0x55_u8, 0x04, 0x0C];
-        const OID_NAME: [u8; 3] = [0x55_u8, 0x04, 0x29];
-        const OID_GIVEN_NAME: [u8; 3] = [0x55_u8, 0x04, 0x2A];
-        const OID_INITIALS: [u8; 3] = [0x55_u8, 0x04, 0x2B];
-        const OID_GENERATION_QUALIFIER: [u8; 3] = [0x55_u8, 0x04, 0x2C];
-        const OID_DN_QUALIFIER: [u8; 3] = [0x55_u8, 0x04, 0x2E];
-        const OID_PSEUDONYM: [u8; 3] = [0x55_u8, 0x04, 0x41];
-        const OID_DOMAIN_COMPONENT: [u8; 10] = [
-            0x09_u8, 0x92, 0x26, 0x89, 0x93, 0xF2, 0x2C, 0x64, 0x01, 0x19,
-        ];
-        const OID_MATTER_NODE_ID: [u8; 10] = [
-            0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x01,
-        ];
-        const OID_MATTER_FW_SIGNING_ID: [u8; 10] = [
-            0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x02,
-        ];
-        const OID_MATTER_ICAC_ID: [u8; 10] = [
-            0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x03,
-        ];
-        const OID_MATTER_RCAC_ID: [u8; 10] = [
-            0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x04,
-        ];
-        const OID_MATTER_FABRIC_ID: [u8; 10] = [
-            0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x05,
-        ];
-        const OID_MATTER_CASE_AUTH_TAG: [u8; 10] = [
-            0x2B_u8, 0x06, 0x01, 0x04, 0x01, 0x82, 0xA2, 0x7C, 0x01, 0x06,
-        ];
-
-        const DN_ENCODING: [(&str, &[u8], Option<IntToStringLen>); 22] = [
-            ("Common Name:", &OID_COMMON_NAME, None),
-            ("Surname:", &OID_SURNAME, None),
-            ("Serial Number", &OID_SERIAL_NUMBER, None),
-            ("Country Name", &OID_COUNTRY_NAME, None),
-            ("Locality name", &OID_LOCALITY_NAME, None),
-            ("State Name", &OID_STATE_NAME, None),
-            ("Org Name", &OID_ORGANIZATION_NAME, None),
-            ("OU Name", &OID_ORGANIZATIONAL_UNIT_NAME, None),
-            ("Title", &OID_TITLE, None),
-            ("Name", &OID_NAME, None),
-            ("Given Name", &OID_GIVEN_NAME, None),
-            ("Initials", &OID_INITIALS, None),
-            ("Gen Qualifier", &OID_GENERATION_QUALIFIER, None),
-            ("DN Qualifier", &OID_DN_QUALIFIER, None),
-            ("Pseudonym", &OID_PSEUDONYM, None),
-            ("Domain Component", &OID_DOMAIN_COMPONENT, None),
-            (
-                "Chip Node Id:",
-                &OID_MATTER_NODE_ID,
-                Some(IntToStringLen::Len16),
-            ),
-            (
-                "Chip Firmware Signing Id:",
-                &OID_MATTER_FW_SIGNING_ID,
-                Some(IntToStringLen::Len16),
-            ),
-            (
-                "Chip ICA Id:",
-                &OID_MATTER_ICAC_ID,
-                Some(IntToStringLen::Len16),
-            ),
-            (
-                "Chip Root CA Id:",
-                &OID_MATTER_RCAC_ID,
-                Some(IntToStringLen::Len16),
-            ),
-            (
-                "Chip Fabric Id:",
-                &OID_MATTER_FABRIC_ID,
-                Some(IntToStringLen::Len16),
-            ),
-            (
-                "Chip NOC CAT Id:",
-                &OID_MATTER_CASE_AUTH_TAG,
-                Some(IntToStringLen::Len8),
-            ),
-        ];
+    fn from_asn1(mut r: ASN1Reader) -> Result<Self, Error> {
+        let mut d = Self {
+            dn: Vec::with_capacity(MAX_DN_ENTRIES),
+        };
+        while !r.is_empty() {
+            // Matter only allows a single attribute in each RDN
+            let mut set = r.set()?;
+            let mut seq = set.seq()?;
+            set.end()?;
+            let oid = seq.oid()?;
+            let (tag, value, _) = seq.next_raw()?;
+            seq.end()?;
+
+            let index = DN_ENCODING
+                .iter()
+                .position(|(_, o, _)| *o == oid)
+                .ok_or_else(|| {
+                    error!("Unsupported DN {:x?}", oid);
+                    Error::Invalid
+                })?;
+            let value = match (tag, DN_ENCODING[index].2) {
+                (TAG_UTF8_STR, Some(len)) => DistNameValue::Uint(decode_dn_int(value, len)?),
+                (TAG_UTF8_STR, None) => DistNameValue::Utf8Str(value.to_vec()),
+                (TAG_PRINTABLE_STR, None) => DistNameValue::PrintableStr(value.to_vec()),
+                _ => {
+                    error!("Invalid value type {:x} for DN {:x?}", tag, oid);
+                    return Err(Error::Invalid);
+                }
+            };
+            d.dn.push(((index + 1) as u8, value));
+        }
+        Ok(d)
+    }
 
+    fn encode(&self, tag: &str, w: &mut dyn CertConsumer) -> Result<(), Error> {
         w.start_seq(tag)?;
         for (id, value) in &self.dn {
             let tag: Option<DnTags> = num::FromPrimitive::from_u8(*id);
@@ -496,6 +631,17 @@ enum IntToStringLen {
     Len8,
 }
 
+fn decode_dn_int(value: &[u8], expected_len: IntToStringLen) -> Result<u64, Error> {
+    let len = match expected_len {
+        IntToStringLen::Len16 => 16,
+        IntToStringLen::Len8 => 8,
+    };
+    if value.len() != len {
+        return Err(Error::Invalid);
+    }
+    u64::from_str_radix(std::str::from_utf8(value)?, 16).map_err(|_| Error::Invalid)
+}
+
 fn encode_dn_value(
     value: &DistNameValue,
     name: &str,
@@ -529,9 +675,18 @@ fn encode_dn_value(
     w.end_set()
 }
 
+/// The role of a certificate in the operational PKI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertType {
+    Rcac,
+    Icac,
+    Noc,
+}
+
+// The TLV structure of a Matter certificate
 #[derive(FromTLV, ToTLV, Default)]
 #[tlvargs(start = 1)]
-pub struct Cert {
+struct CertFields {
     serial_no: Vec<u8>,
     sign_algo: u8,
     issuer: DistNames,
@@ -545,36 +700,174 @@ pub struct Cert {
     signature: Vec<u8>,
 }
 
-// TODO: Instead of parsing the TLVs everytime, we should just cache this, but the encoding
-// rules in terms of sequence may get complicated. Need to look into this
+impl CertFields {
+    fn to_tlv_vec(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = [0u8; MAX_CERT_TLV_LEN];
+        let mut wb = WriteBuf::new(&mut buf, MAX_CERT_TLV_LEN);
+        let mut tw = TLVWriter::new(&mut wb);
+        self.to_tlv(&mut tw, TagType::Anonymous)?;
+        Ok(wb.as_slice().to_vec())
+    }
+
+    /// The TBSCertificate of the X.509 encoding
+    fn to_asn1(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = [0u8; MAX_ASN1_CERT_SIZE];
+        let mut w = ASN1Writer::new(&mut buf);
+        self.encode(&mut w)?;
+        Ok(w.as_slice().to_vec())
+    }
+
+    fn encode(&self, w: &mut dyn CertConsumer) -> Result<(), Error> {
+        w.start_seq("")?;
+
+        w.start_ctx("Version:", 0)?;
+        w.integer("", &[2])?;
+        w.end_ctx()?;
+
+        w.integer("Serial Num:", self.serial_no.as_slice())?;
+
+        w.start_seq("Signature Algorithm:")?;
+        let (str, oid) = match get_sign_algo(self.sign_algo).ok_or(Error::Invalid)? {
+            SignAlgoValue::ECDSAWithSHA256 => ("ECDSA with SHA256", OID_ECDSA_WITH_SHA256),
+        };
+        w.oid(str, &oid)?;
+        w.end_seq()?;
+
+        self.issuer.encode("Issuer:", w)?;
+
+        w.start_seq("Validity:")?;
+        w.utctime("Not Before:", self.not_before)?;
+        if self.not_after == 0 {
+            w.no_expiry_time("Not After:")?;
+        } else {
+            w.utctime("Not After:", self.not_after)?;
+        }
+        w.end_seq()?;
+
+        self.subject.encode("Subject:", w)?;
+
+        w.start_seq("")?;
+        w.start_seq("Public Key Algorithm")?;
+        let (str, pub_key) = match get_pubkey_algo(self.pubkey_algo).ok_or(Error::Invalid)? {
+            PubKeyAlgoValue::EcPubKey => ("ECPubKey", OID_PUB_KEY_ECPUBKEY),
+        };
+        w.oid(str, &pub_key)?;
+        let (str, curve_id) = match get_ec_curve_id(self.ec_curve_id).ok_or(Error::Invalid)? {
+            EcCurveIdValue::Prime256V1 => ("Prime256v1", OID_EC_TYPE_PRIME256V1),
+        };
+        w.oid(str, &curve_id)?;
+        w.end_seq()?;
+
+        w.bitstr("Public-Key:", false, self.pubkey.as_slice())?;
+        w.end_seq()?;
+
+        self.extensions.encode(w)?;
+
+        // We do not encode the Signature in the DER certificate
+
+        w.end_seq()
+    }
+}
+
+/// A Matter operational certificate
+///
+/// The certificate is decoded once, the values that every CASE session looks up
+/// are extracted up front, and the TBSCertificate that the signature covers is
+/// only encoded the first time that it is needed.
+#[derive(Default)]
+pub struct Cert {
+    fields: CertFields,
+    // The TLV encoding, as it was received
+    tlv: Vec<u8>,
+    node_id: Option<u64>,
+    fabric_id: Option<u64>,
+    cat_ids: Vec<u32>,
+    cert_type: Option<CertType>,
+    asn1: OnceLock<Vec<u8>>,
+}
+
 impl Cert {
     pub fn new(cert_bin: &[u8]) -> Result<Self, Error> {
         let root = tlv::get_root_node(cert_bin)?;
-        Cert::from_tlv(&root)
+        let fields = CertFields::from_tlv(&root)?;
+        Ok(Self::decoded(fields, cert_bin.to_vec()))
+    }
+
+    // A certificate that was built or converted, rather than received in TLV, along
+    // with its TBSCertificate
+    fn from_fields(fields: CertFields, asn1: Vec<u8>) -> Result<Self, Error> {
+        let tlv = fields.to_tlv_vec()?;
+        let cert = Self::decoded(fields, tlv);
+        let _ = cert.asn1.set(asn1);
+        Ok(cert)
+    }
+
+    fn decoded(fields: CertFields, tlv: Vec<u8>) -> Self {
+        let subject = &fields.subject;
+        Self {
+            node_id: subject.u64(DnTags::NodeId),
+            fabric_id: subject.u64(DnTags::FabricId),
+            cat_ids: subject.u32_list(DnTags::NocCat),
+            cert_type: subject.cert_type(),
+            fields,
+            tlv,
+            asn1: OnceLock::new(),
+        }
     }
 
     pub fn get_node_id(&self) -> Result<u64, Error> {
-        self.subject.u64(DnTags::NodeId).ok_or(Error::NoNodeId)
+        self.node_id.ok_or(Error::NoNodeId)
     }
 
     pub fn get_cat_ids(&self, output: &mut [u32]) {
-        self.subject.u32_arr(DnTags::NocCat, output)
+        for (out, cat_id) in output.iter_mut().zip(self.cat_ids.iter()) {
+            *out = *cat_id;
+        }
     }
 
     pub fn get_fabric_id(&self) -> Result<u64, Error> {
-        self.subject.u64(DnTags::FabricId).ok_or(Error::NoFabricId)
+        self.fabric_id.ok_or(Error::NoFabricId)
+    }
+
+    /// The type of the certificate, as identified by its subject DN
+    pub fn get_cert_type(&self) -> Option<CertType> {
+        self.cert_type
+    }
+
+    fn is_ca(&self) -> bool {
+        matches!(&self.fields.extensions.basic_const, Some(b) if b.is_ca)
+    }
+
+    fn get_path_len(&self) -> Option<u8> {
+        self.fields
+            .extensions
+            .basic_const
+            .as_ref()
+            .and_then(|b| b.path)
+    }
+
+    fn has_key_usage(&self, usage: u16) -> bool {
+        (self.fields.extensions.key_usage.unwrap_or(0) & usage) == usage
+    }
+
+    fn has_ext_key_usage(&self, usage: u8) -> bool {
+        matches!(&self.fields.extensions.ext_key_usage, Some(l) if l.iter().any(|u| *u == usage))
     }
 
     pub fn get_pubkey(&self) -> &[u8] {
-        self.pubkey.as_slice()
+        self.fields.pubkey.as_slice()
     }
 
     pub fn get_subject_key_id(&self) -> Result<&[u8], Error> {
-        self.extensions.subj_key_id.as_deref().ok_or(Error::Invalid)
+        self.fields
+            .extensions
+            .subj_key_id
+            .as_deref()
+            .ok_or(Error::Invalid)
     }
 
     pub fn is_authority(&self, their: &Cert) -> Result<bool, Error> {
-        if let Some(our_auth_key) = &self.extensions.auth_key_id {
+        if let Some(our_auth_key) = &self.fields.extensions.auth_key_id {
             let their_subject = their.get_subject_key_id()?;
             if our_auth_key == their_subject {
                 Ok(true)
@@ -586,72 +879,193 @@ impl Cert {
         }
     }
 
+    /// The fields of the certificate as a JSON object, for tools that compare
+    /// certificates
+    pub fn to_json(&self) -> Result<String, Error> {
+        let mut printer = CertJsonPrinter::new();
+        self.fields.encode(&mut printer)?;
+        // Signature is not encoded by the Cert Decoder
+        printer.ostr("Signature", self.get_signature())?;
+        Ok(printer.into_json())
+    }
+
     pub fn get_signature(&self) -> &[u8] {
-        self.signature.as_slice()
+        self.fields.signature.as_slice()
     }
 
-    pub fn as_tlv(&self, buf: &mut [u8]) -> Result<usize, Error> {
-        let mut wb = WriteBuf::new(buf, buf.len());
-        let mut tw = TLVWriter::new(&mut wb);
-        self.to_tlv(&mut tw, TagType::Anonymous)?;
-        Ok(wb.as_slice().len())
+    pub fn get_not_before(&self) -> u32 {
+        self.fields.not_before
     }
 
-    pub fn as_asn1(&self, buf: &mut [u8]) -> Result<usize, Error> {
-        let mut w = ASN1Writer::new(buf);
-        self.encode(&mut w)?;
-        Ok(w.as_slice().len())
+    pub fn get_not_after(&self) -> u32 {
+        self.fields.not_after
     }
 
-    pub fn verify_chain_start(&self) -> CertVerifier {
-        CertVerifier::new(self)
+    /// Check the validity period of the certificate against _time_
+    pub fn check_validity(&self, time: ValidationTime) -> Result<(), Error> {
+        time.check(self.fields.not_before, self.fields.not_after)
     }
 
-    fn encode(&self, w: &mut dyn CertConsumer) -> Result<(), Error> {
-        w.start_seq("")?;
+    /// The TLV encoding of the certificate, exactly as it was received
+    pub fn as_tlv(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        let buf = buf.get_mut(..self.tlv.len()).ok_or(Error::BufferTooSmall)?;
+        buf.copy_from_slice(&self.tlv);
+        Ok(self.tlv.len())
+    }
 
-        w.start_ctx("Version:", 0)?;
-        w.integer("", &[2])?;
-        w.end_ctx()?;
+    /// Convert an X.509 DER certificate to a Matter certificate
+    ///
+    /// The certificate must follow the Matter certificate profile, which ensures that
+    /// it converts back to exactly the same DER, otherwise its signature couldn't be
+    /// verified.
+    pub fn from_asn1(der: &[u8]) -> Result<Self, Error> {
+        let mut r = ASN1Reader::new(der);
+        let mut cert_seq = r.seq()?;
+        r.end()?;
+
+        let (tag, tbs_contents, tbs_der) = cert_seq.next_raw()?;
+        if tag != TAG_SEQ {
+            return Err(Error::Invalid);
+        }
+        let mut tbs = ASN1Reader::new(tbs_contents);
+        let mut version = tbs.ctx(0)?;
+        if version.integer()? != [2] {
+            error!("Only X.509 v3 certificates are supported");
+            return Err(Error::InvalidCertProfile);
+        }
+        version.end()?;
+        let serial_no = tbs.integer()?;
+        if serial_no.len() > MAX_SERIAL_NUM_LEN {
+            return Err(Error::InvalidCertProfile);
+        }
+        decode_sign_algo(&mut tbs)?;
+        let issuer = DistNames::from_asn1(tbs.seq()?)?;
+        let mut validity = tbs.seq()?;
+        let not_before = validity.time()?;
+        let not_after = validity.time()?;
+        validity.end()?;
+        let subject = DistNames::from_asn1(tbs.seq()?)?;
+
+        let mut pubkey_info = tbs.seq()?;
+        let mut pubkey_algo = pubkey_info.seq()?;
+        if pubkey_algo.oid()? != OID_PUB_KEY_ECPUBKEY
+            || pubkey_algo.oid()? != OID_EC_TYPE_PRIME256V1
+        {
+            error!("Only P-256 public keys are supported");
+            return Err(Error::InvalidCertProfile);
+        }
+        pubkey_algo.end()?;
+        let (unused, pubkey) = pubkey_info.bit_str()?;
+        if unused != 0 || pubkey.len() != crypto::EC_POINT_LEN_BYTES {
+            return Err(Error::InvalidCertProfile);
+        }
+        pubkey_info.end()?;
 
-        w.integer("Serial Num:", self.serial_no.as_slice())?;
+        let mut ext_ctx = tbs.ctx(3)?;
+        let extensions = Extensions::from_asn1(ext_ctx.seq()?)?;
+        ext_ctx.end()?;
+        tbs.end()?;
 
-        w.start_seq("Signature Algorithm:")?;
-        let (str, oid) = match get_sign_algo(self.sign_algo).ok_or(Error::Invalid)? {
-            SignAlgoValue::ECDSAWithSHA256 => ("ECDSA with SHA256", OID_ECDSA_WITH_SHA256),
+        decode_sign_algo(&mut cert_seq)?;
+        let (unused, signature) = cert_seq.bit_str()?;
+        if unused != 0 {
+            return Err(Error::Invalid);
+        }
+        let signature = decode_ecdsa_signature(signature)?;
+        cert_seq.end()?;
+
+        let fields = CertFields {
+            serial_no: serial_no.to_vec(),
+            sign_algo: SignAlgoValue::ECDSAWithSHA256 as u8,
+            issuer,
+            not_before,
+            not_after,
+            subject,
+            pubkey_algo: PubKeyAlgoValue::EcPubKey as u8,
+            ec_curve_id: EcCurveIdValue::Prime256V1 as u8,
+            pubkey: pubkey.to_vec(),
+            extensions,
+            signature,
         };
-        w.oid(str, &oid)?;
-        w.end_seq()?;
 
-        self.issuer.encode("Issuer:", w)?;
+        let asn1 = fields.to_asn1()?;
+        if asn1 != tbs_der {
+            error!("The certificate doesn't follow the Matter certificate encoding");
+            return Err(Error::InvalidCertProfile);
+        }
+        Self::from_fields(fields, asn1)
+    }
 
-        w.start_seq("Validity:")?;
-        w.utctime("Not Before:", self.not_before)?;
-        w.utctime("Not After:", self.not_after)?;
-        w.end_seq()?;
+    /// Convert a PEM encoded X.509 certificate to a Matter certificate
+    pub fn from_pem(pem: &str) -> Result<Self, Error> {
+        Self::from_asn1(&pem::decode_label(pem::LABEL_CERTIFICATE, pem)?)
+    }
 
-        self.subject.encode("Subject:", w)?;
+    /// The PEM encoding of the X.509 form of the certificate
+    pub fn to_pem(&self) -> Result<String, Error> {
+        let mut der = [0u8; MAX_ASN1_CERT_SIZE];
+        let len = self.as_x509(&mut der)?;
+        Ok(pem::encode(pem::LABEL_CERTIFICATE, &der[..len]))
+    }
 
+    /// The TBSCertificate of the X.509 encoding, this is what the signature covers
+    pub fn as_asn1(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        let asn1 = self.get_asn1()?;
+        let buf = buf.get_mut(..asn1.len()).ok_or(Error::BufferTooSmall)?;
+        buf.copy_from_slice(asn1);
+        Ok(asn1.len())
+    }
+
+    /// The TBSCertificate, encoded on the first call
+    pub fn get_asn1(&self) -> Result<&[u8], Error> {
+        if let Some(asn1) = self.asn1.get() {
+            return Ok(asn1);
+        }
+        let asn1 = self.fields.to_asn1()?;
+        Ok(self.asn1.get_or_init(|| asn1))
+    }
+
+    /// The complete X.509 DER encoding of the certificate, including the signature
+    pub fn as_x509(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut sig_der = [0u8; MAX_ECDSA_SIG_DER_SIZE];
+        let sig_len = encode_ecdsa_signature(self.get_signature(), &mut sig_der)?;
+
+        let mut w = ASN1Writer::new(buf);
         w.start_seq("")?;
-        w.start_seq("Public Key Algorithm")?;
-        let (str, pub_key) = match get_pubkey_algo(self.pubkey_algo).ok_or(Error::Invalid)? {
-            PubKeyAlgoValue::EcPubKey => ("ECPubKey", OID_PUB_KEY_ECPUBKEY),
-        };
-        w.oid(str, &pub_key)?;
-        let (str, curve_id) = match get_ec_curve_id(self.ec_curve_id).ok_or(Error::Invalid)? {
-            EcCurveIdValue::Prime256V1 => ("Prime256v1", OID_EC_TYPE_PRIME256V1),
-        };
-        w.oid(str, &curve_id)?;
+        self.fields.encode(&mut w)?;
+        w.start_seq("")?;
+        w.oid("", &OID_ECDSA_WITH_SHA256)?;
         w.end_seq()?;
-
-        w.bitstr("Public-Key:", false, self.pubkey.as_slice())?;
+        w.bitstr("", false, &sig_der[..sig_len])?;
         w.end_seq()?;
+        Ok(w.as_slice().len())
+    }
 
-        self.extensions.encode(w)?;
+    pub fn verify_chain_start(&self) -> CertVerifier {
+        CertVerifier::new(self, epoch::get_validation_time())
+    }
 
-        // We do not encode the Signature in the DER certificate
+    /// Start verifying the chain, with the validity periods checked against _time_
+    pub fn verify_chain_start_at(&self, time: ValidationTime) -> CertVerifier {
+        CertVerifier::new(self, time)
+    }
 
-        w.end_seq()
+    /// Verify the operational chain of this NOC up to the root CA, through the
+    /// optional ICAC
+    pub fn verify_noc_chain(&self, icac: Option<&Cert>, rcac: &Cert) -> Result<(), Error> {
+        if self.get_cert_type() != Some(CertType::Noc) {
+            error!("Not a NOC");
+            return Err(Error::InvalidCertProfile);
+        }
+        let mut verifier = self.verify_chain_start();
+        if let Some(icac) = icac {
+            if icac.get_cert_type() != Some(CertType::Icac) {
+                error!("Not an ICAC");
+                return Err(Error::InvalidCertProfile);
+            }
+            verifier = verifier.add_cert(icac)?;
+        }
+        verifier.add_cert(rcac)?.finalise()
     }
 }
 
@@ -659,6 +1073,7 @@ impl fmt::Display for Cert {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut printer = CertPrinter::new(f);
         let _ = self
+            .fields
             .encode(&mut printer)
             .map_err(|e| error!("Error decoding certificate: {}", e));
         // Signature is not encoded by the Cert Decoder
@@ -668,37 +1083,126 @@ impl fmt::Display for Cert {
 
 pub struct CertVerifier<'a> {
     cert: &'a Cert,
+    time: ValidationTime,
+    // The number of certificates below this one in the chain
+    depth: u8,
+    // The Fabric ID that all the certificates in the chain must agree on
+    fabric_id: Option<u64>,
 }
 
 impl<'a> CertVerifier<'a> {
-    pub fn new(cert: &'a Cert) -> Self {
-        Self { cert }
+    pub fn new(cert: &'a Cert, time: ValidationTime) -> Self {
+        Self {
+            cert,
+            time,
+            depth: 0,
+            fabric_id: None,
+        }
     }
 
-    pub fn add_cert(self, parent: &'a Cert) -> Result<CertVerifier<'a>, Error> {
-        if !self.cert.is_authority(parent)? {
-            return Err(Error::InvalidAuthKey);
+    fn profile_error(&self, reason: &str) -> Result<(), Error> {
+        error!(
+            "Certificate {:x?} violates the Matter profile: {}",
+            self.cert.get_subject_key_id(),
+            reason
+        );
+        Err(Error::InvalidCertProfile)
+    }
+
+    /// Check the basic constraints, key usage, extended key usage and Fabric ID of
+    /// the certificate for its position in the chain
+    fn check_profile(&mut self) -> Result<(), Error> {
+        let cert = self.cert;
+        match cert.get_cert_type() {
+            None => return self.profile_error("unknown certificate type"),
+            Some(CertType::Noc) => {
+                if self.depth != 0 {
+                    return self.profile_error("a NOC can't issue certificates");
+                }
+                if cert.fields.extensions.basic_const.is_none() || cert.is_ca() {
+                    return self.profile_error("a NOC must not be a CA");
+                }
+                if !cert.has_key_usage(KEY_USAGE_DIGITAL_SIGN)
+                    || cert.has_key_usage(KEY_USAGE_KEY_CERT_SIGN)
+                {
+                    return self.profile_error("invalid key usage for a NOC");
+                }
+                if !cert.has_ext_key_usage(EXT_KEY_USAGE_CLIENT_AUTH)
+                    || !cert.has_ext_key_usage(EXT_KEY_USAGE_SERVER_AUTH)
+                {
+                    return self.profile_error("a NOC needs the clientAuth and serverAuth EKUs");
+                }
+                if cert.get_fabric_id().is_err() {
+                    return self.profile_error("a NOC must have a Fabric ID");
+                }
+            }
+            Some(cert_type) => {
+                if cert_type == CertType::Icac && self.depth == 0 {
+                    return self.profile_error("the chain must start with a NOC");
+                }
+                if !cert.is_ca() {
+                    return self.profile_error("an issuer must be a CA");
+                }
+                if !cert.has_key_usage(KEY_USAGE_KEY_CERT_SIGN) {
+                    return self.profile_error("an issuer needs the keyCertSign key usage");
+                }
+                // The leaf NOC doesn't count towards the path length
+                if let Some(path_len) = cert.get_path_len() {
+                    if self.depth.saturating_sub(1) > path_len {
+                        return self.profile_error("path length exceeded");
+                    }
+                }
+            }
         }
-        let mut asn1 = [0u8; MAX_ASN1_CERT_SIZE];
-        let len = self.cert.as_asn1(&mut asn1)?;
-        let asn1 = &asn1[..len];
 
-        let k = KeyPair::new_from_public(parent.get_pubkey())?;
-        k.verify_msg(asn1, self.cert.get_signature()).map_err(|e| {
+        if let Ok(fabric_id) = cert.get_fabric_id() {
+            match self.fabric_id {
+                Some(expected) if expected != fabric_id => {
+                    return self.profile_error("Fabric ID doesn't match the rest of the chain")
+                }
+                _ => self.fabric_id = Some(fabric_id),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add_cert(mut self, parent: &'a Cert) -> Result<CertVerifier<'a>, Error> {
+        self.check_profile()?;
+        self.cert.check_validity(self.time).inspect_err(|e| {
             error!(
-                "Error in signature verification of certificate: {:x?}",
-                self.cert.get_subject_key_id()
-            );
-            e
+                "Certificate {:x?} is outside its validity period: {}",
+                self.cert.get_subject_key_id(),
+                e
+            )
         })?;
-
-        // TODO: other validation checks
-        Ok(CertVerifier::new(parent))
+        if !self.cert.is_authority(parent)? {
+            return Err(Error::InvalidAuthKey);
+        }
+        let k = crypto::get_provider().key_pair_from_public(parent.get_pubkey())?;
+        k.verify_msg(self.cert.get_asn1()?, self.cert.get_signature())
+            .map_err(|e| {
+                error!(
+                    "Error in signature verification of certificate: {:x?}",
+                    self.cert.get_subject_key_id()
+                );
+                e
+            })?;
+
+        Ok(CertVerifier {
+            cert: parent,
+            time: self.time,
+            depth: self.depth.saturating_add(1),
+            fabric_id: self.fabric_id,
+        })
     }
 
     pub fn finalise(self) -> Result<(), Error> {
         let cert = self.cert;
         self.add_cert(cert)?;
+        if cert.get_cert_type() != Some(CertType::Rcac) {
+            error!("The chain doesn't end with a root CA");
+            return Err(Error::InvalidCertProfile);
+        }
         Ok(())
     }
 }
@@ -721,21 +1225,104 @@ pub trait CertConsumer {
     fn end_ctx(&mut self) -> Result<(), Error>;
     fn oid(&mut self, tag: &str, oid: &[u8]) -> Result<(), Error>;
     fn utctime(&mut self, tag: &str, epoch: u32) -> Result<(), Error>;
+    /// The X.509 time for no well-defined expiration date
+    fn no_expiry_time(&mut self, tag: &str) -> Result<(), Error>;
+}
+
+fn decode_sign_algo(r: &mut ASN1Reader) -> Result<(), Error> {
+    let mut algo = r.seq()?;
+    if algo.oid()? != OID_ECDSA_WITH_SHA256 {
+        error!("Only ECDSA with SHA256 signatures are supported");
+        return Err(Error::InvalidCertProfile);
+    }
+    algo.end()
+}
+
+// An ECDSA signature is DER encoded as a sequence of the r and s integers, Matter
+// certificates carry the raw r || s instead
+pub(crate) fn decode_ecdsa_signature(der: &[u8]) -> Result<Vec<u8>, Error> {
+    const INT_LEN: usize = crypto::EC_SIGNATURE_LEN_BYTES / 2;
+
+    let mut r = ASN1Reader::new(der);
+    let mut seq = r.seq()?;
+    r.end()?;
+    let mut signature = vec![0u8; crypto::EC_SIGNATURE_LEN_BYTES];
+    for i in 0..2 {
+        let int = seq.integer()?;
+        // Skip the padding that keeps the integer positive
+        let int = match int {
+            [0, rest @ ..] if !rest.is_empty() => rest,
+            _ => int,
+        };
+        if int.len() > INT_LEN {
+            return Err(Error::InvalidSignature);
+        }
+        let end = (i + 1) * INT_LEN;
+        signature[end - int.len()..end].copy_from_slice(int);
+    }
+    seq.end()?;
+    Ok(signature)
+}
+
+fn encode_ecdsa_signature(signature: &[u8], buf: &mut [u8]) -> Result<usize, Error> {
+    const INT_LEN: usize = crypto::EC_SIGNATURE_LEN_BYTES / 2;
+
+    if signature.len() != crypto::EC_SIGNATURE_LEN_BYTES {
+        return Err(Error::InvalidSignature);
+    }
+    let mut w = ASN1Writer::new(buf);
+    w.start_seq("")?;
+    for int in signature.chunks(INT_LEN) {
+        let start = int.iter().position(|b| *b != 0).unwrap_or(INT_LEN - 1);
+        let int = &int[start..];
+        if int[0] & 0x80 != 0 {
+            let mut padded = [0u8; INT_LEN + 1];
+            padded[1..=int.len()].copy_from_slice(int);
+            w.integer("", &padded[..=int.len()])?;
+        } else {
+            w.integer("", int)?;
+        }
+    }
+    w.end_seq()?;
+    Ok(w.as_slice().len())
 }
 
 const MAX_DEPTH: usize = 10;
 const MAX_ASN1_CERT_SIZE: usize = 1000;
-
+// As per the Matter specification
+const MAX_CERT_TLV_LEN: usize = 400;
+const MAX_SERIAL_NUM_LEN: usize = 20;
+// A sequence of two 33 byte integers, the ASN1Writer needs room for the 3
+// bytes it reserves for the sequence length
+const MAX_ECDSA_SIG_DER_SIZE: usize = 1 + 3 + 2 * (2 + 33);
+
+pub(crate) mod asn1_reader;
 mod asn1_writer;
+mod attestation;
+mod builder;
+mod csr;
+mod json;
 mod printer;
 
 #[cfg(test)]
 mod tests {
-    use crate::cert::Cert;
+    use crate::cert::{Cert, CertFields, CertType, DistNameValue, DnTags};
     use crate::error::Error;
     use crate::tlv::{self, FromTLV, TLVWriter, TagType, ToTLV};
+    use crate::utils::epoch::ValidationTime;
     use crate::utils::writebuf::WriteBuf;
 
+    // The test vectors are valid from 2021-01-01 to 2030-12-30
+    const VALID_TIME: ValidationTime = ValidationTime::Current(700000000);
+
+    // Decode _input_ with its fields changed by _f_
+    fn modified(input: &[u8], f: impl FnOnce(&mut CertFields)) -> Cert {
+        let mut fields = Cert::new(input).unwrap().fields;
+        f(&mut fields);
+        let asn1 = fields.to_asn1().unwrap();
+        Cert::from_fields(fields, asn1).unwrap()
+    }
+
     #[test]
     fn test_asn1_encode_success() {
         {
@@ -765,7 +1352,7 @@ mod tests {
         let noc = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
         let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
         let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
-        let a = noc.verify_chain_start();
+        let a = noc.verify_chain_start_at(VALID_TIME);
         a.add_cert(&icac)
             .unwrap()
             .add_cert(&rca)
@@ -779,7 +1366,7 @@ mod tests {
         // The chain doesn't lead up to a self-signed certificate
         let noc = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
         let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
-        let a = noc.verify_chain_start();
+        let a = noc.verify_chain_start_at(VALID_TIME);
         assert_eq!(
             Err(Error::InvalidAuthKey),
             a.add_cert(&icac).unwrap().finalise()
@@ -790,7 +1377,7 @@ mod tests {
     fn test_auth_key_chain_incorrect() {
         let noc = Cert::new(&test_vectors::NOC1_AUTH_KEY_FAIL).unwrap();
         let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
-        let a = noc.verify_chain_start();
+        let a = noc.verify_chain_start_at(VALID_TIME);
         assert_eq!(Err(Error::InvalidAuthKey), a.add_cert(&icac).map(|_| ()));
     }
 
@@ -798,10 +1385,229 @@ mod tests {
     fn test_cert_corrupted() {
         let noc = Cert::new(&test_vectors::NOC1_CORRUPT_CERT).unwrap();
         let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
-        let a = noc.verify_chain_start();
+        let a = noc.verify_chain_start_at(VALID_TIME);
         assert_eq!(Err(Error::InvalidSignature), a.add_cert(&icac).map(|_| ()));
     }
 
+    #[test]
+    fn test_verify_chain_validity_period() {
+        let noc = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
+        let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
+        let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
+        let verify = |time| {
+            noc.verify_chain_start_at(time)
+                .add_cert(&icac)?
+                .add_cert(&rca)?
+                .finalise()
+        };
+
+        assert_eq!(
+            Err(Error::CertNotYetValid),
+            verify(ValidationTime::Current(600000000))
+        );
+        assert_eq!(
+            Err(Error::CertExpired),
+            verify(ValidationTime::Current(990000000))
+        );
+        assert_eq!(
+            Err(Error::CertExpired),
+            verify(ValidationTime::LastKnownGood(990000000))
+        );
+        // The Not Before can't be checked against the Last Known Good UTC Time
+        assert_eq!(Ok(()), verify(ValidationTime::LastKnownGood(600000000)));
+        assert_eq!(Ok(()), verify(ValidationTime::Unknown));
+    }
+
+    #[test]
+    fn test_validity_no_expiry() {
+        let rca = modified(&test_vectors::RCA1_SUCCESS, |f| f.not_after = 0);
+        assert_eq!(
+            Ok(()),
+            rca.check_validity(ValidationTime::Current(u32::MAX))
+        );
+        assert_eq!(
+            Err(Error::CertNotYetValid),
+            rca.check_validity(ValidationTime::Current(0))
+        );
+    }
+
+    fn verify(noc: &Cert, icac: &Cert, rca: &Cert) -> Result<(), Error> {
+        noc.verify_chain_start_at(VALID_TIME)
+            .add_cert(icac)?
+            .add_cert(rca)?
+            .finalise()
+    }
+
+    #[test]
+    fn test_cert_types() {
+        let noc = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
+        let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
+        let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
+        assert_eq!(Some(CertType::Noc), noc.get_cert_type());
+        assert_eq!(Some(CertType::Icac), icac.get_cert_type());
+        assert_eq!(Some(CertType::Rcac), rca.get_cert_type());
+        assert_eq!(Ok(()), noc.verify_noc_chain(Some(&icac), &rca));
+    }
+
+    #[test]
+    fn test_noc_as_ca() {
+        let noc = modified(&test_vectors::NOC1_SUCCESS, |f| {
+            f.extensions.basic_const.as_mut().unwrap().is_ca = true
+        });
+        let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
+        let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
+        assert_eq!(Err(Error::InvalidCertProfile), verify(&noc, &icac, &rca));
+    }
+
+    #[test]
+    fn test_noc_key_usage() {
+        let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
+        let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
+
+        let noc = modified(&test_vectors::NOC1_SUCCESS, |f| {
+            f.extensions.key_usage = Some(super::KEY_USAGE_KEY_AGREEMENT)
+        });
+        assert_eq!(Err(Error::InvalidCertProfile), verify(&noc, &icac, &rca));
+
+        let noc = modified(&test_vectors::NOC1_SUCCESS, |f| {
+            f.extensions.ext_key_usage = None
+        });
+        assert_eq!(Err(Error::InvalidCertProfile), verify(&noc, &icac, &rca));
+    }
+
+    #[test]
+    fn test_issuer_constraints() {
+        let noc = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
+        let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
+
+        let icac = modified(&test_vectors::ICAC1_SUCCESS, |f| {
+            f.extensions.key_usage = Some(super::KEY_USAGE_DIGITAL_SIGN)
+        });
+        assert_eq!(Err(Error::InvalidCertProfile), verify(&noc, &icac, &rca));
+
+        let icac = modified(&test_vectors::ICAC1_SUCCESS, |f| {
+            f.extensions.basic_const.as_mut().unwrap().is_ca = false
+        });
+        assert_eq!(Err(Error::InvalidCertProfile), verify(&noc, &icac, &rca));
+
+        // The root only allows NOCs directly below it
+        let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
+        let rca = modified(&test_vectors::RCA1_SUCCESS, |f| {
+            f.extensions.basic_const.as_mut().unwrap().path = Some(0)
+        });
+        assert_eq!(Err(Error::InvalidCertProfile), verify(&noc, &icac, &rca));
+    }
+
+    #[test]
+    fn test_chain_start_not_noc() {
+        let icac = Cert::new(&test_vectors::ICAC1_SUCCESS).unwrap();
+        let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
+        assert_eq!(
+            Err(Error::InvalidCertProfile),
+            icac.verify_chain_start_at(VALID_TIME)
+                .add_cert(&rca)
+                .map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_fabric_id_mismatch() {
+        // Tamper with the ICAC, a tampered NOC would fail its own signature
+        // check before the fabric IDs get compared
+        let icac = modified(&test_vectors::ICAC1_SUCCESS, |f| {
+            for (id, value) in f.subject.dn.iter_mut() {
+                if *id == DnTags::FabricId as u8 {
+                    *value = DistNameValue::Uint(0xdead);
+                }
+            }
+        });
+        let noc = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
+        let rca = Cert::new(&test_vectors::RCA1_SUCCESS).unwrap();
+        assert_eq!(0xdead, icac.get_fabric_id().unwrap());
+        assert_eq!(Err(Error::InvalidCertProfile), verify(&noc, &icac, &rca));
+    }
+
+    #[test]
+    fn test_x509_round_trip() {
+        let test_input: [&[u8]; 3] = [
+            &test_vectors::NOC1_SUCCESS,
+            &test_vectors::ICAC1_SUCCESS,
+            &test_vectors::RCA1_SUCCESS,
+        ];
+
+        for input in test_input.iter() {
+            let cert = Cert::new(input).unwrap();
+            let mut der = [0u8; 1000];
+            let der_len = cert.as_x509(&mut der).unwrap();
+
+            let converted = Cert::from_asn1(&der[..der_len]).unwrap();
+            let mut tlv = [0u8; 1000];
+            let tlv_len = converted.as_tlv(&mut tlv).unwrap();
+            assert_eq!(*input, &tlv[..tlv_len]);
+        }
+    }
+
+    #[test]
+    fn test_x509_chain_verifies() {
+        let to_x509 = |input: &[u8]| {
+            let mut der = [0u8; 1000];
+            let len = Cert::new(input).unwrap().as_x509(&mut der).unwrap();
+            Cert::from_asn1(&der[..len]).unwrap()
+        };
+        let noc = to_x509(&test_vectors::NOC1_SUCCESS);
+        let icac = to_x509(&test_vectors::ICAC1_SUCCESS);
+        let rca = to_x509(&test_vectors::RCA1_SUCCESS);
+        assert_eq!(Ok(()), verify(&noc, &icac, &rca));
+    }
+
+    #[test]
+    fn test_x509_no_expiry() {
+        let rca = modified(&test_vectors::RCA1_SUCCESS, |f| f.not_after = 0);
+        let mut der = [0u8; 1000];
+        let len = rca.as_x509(&mut der).unwrap();
+        assert_eq!(0, Cert::from_asn1(&der[..len]).unwrap().get_not_after());
+    }
+
+    #[test]
+    fn test_x509_invalid() {
+        let cert = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
+        let mut der = [0u8; 1000];
+        let len = cert.as_x509(&mut der).unwrap();
+
+        // Truncated
+        assert!(Cert::from_asn1(&der[..len - 1]).is_err());
+
+        // Trailing data
+        assert_eq!(
+            Err(Error::Invalid),
+            Cert::from_asn1(&der[..len + 1]).map(|_| ())
+        );
+
+        // ECDSA with SHA384
+        let mut sha384 = der;
+        let oid_pos = sha384[..len]
+            .windows(super::OID_ECDSA_WITH_SHA256.len())
+            .position(|w| w == super::OID_ECDSA_WITH_SHA256)
+            .unwrap();
+        sha384[oid_pos + super::OID_ECDSA_WITH_SHA256.len() - 1] = 0x03;
+        assert_eq!(
+            Err(Error::InvalidCertProfile),
+            Cert::from_asn1(&sha384[..len]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_pem_round_trip() {
+        let cert = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
+        let pem = cert.to_pem().unwrap();
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+
+        let converted = Cert::from_pem(&pem).unwrap();
+        let mut tlv = [0u8; 1000];
+        let len = converted.as_tlv(&mut tlv).unwrap();
+        assert_eq!(&test_vectors::NOC1_SUCCESS, &tlv[..len]);
+    }
+
     #[test]
     fn test_tlv_conversions() {
         let test_input: [&[u8]; 3] = [
@@ -813,16 +1619,117 @@ mod tests {
         for input in test_input.iter() {
             println!("Testing next input...");
             let root = tlv::get_root_node(input).unwrap();
-            let cert = Cert::from_tlv(&root).unwrap();
+            let fields = CertFields::from_tlv(&root).unwrap();
             let mut buf = [0u8; 1024];
             let buf_len = buf.len();
             let mut wb = WriteBuf::new(&mut buf, buf_len);
             let mut tw = TLVWriter::new(&mut wb);
-            cert.to_tlv(&mut tw, TagType::Anonymous).unwrap();
+            fields.to_tlv(&mut tw, TagType::Anonymous).unwrap();
             assert_eq!(*input, wb.as_slice());
+
+            let cert = Cert::new(input).unwrap();
+            let mut buf = [0u8; 1024];
+            let len = cert.as_tlv(&mut buf).unwrap();
+            assert_eq!(*input, &buf[..len]);
+            assert_eq!(Err(Error::BufferTooSmall), cert.as_tlv(&mut buf[..len - 1]));
         }
     }
 
+    #[test]
+    fn test_json() {
+        let json = Cert::new(&test_vectors::NOC1_SUCCESS)
+            .unwrap()
+            .to_json()
+            .unwrap();
+        assert!(json.starts_with(
+            r#"{
+  "version": 3,
+  "serial_number": "#
+        ));
+        assert!(json.contains(r#""signature_algorithm": "ecdsa_with_sha256""#));
+        assert!(json.contains(r#""node_id": "0000000000BC5C02""#));
+        assert!(json.contains(r#""fabric_id": "0000000000000001""#));
+        assert!(json.contains(
+            r#""not_before": {
+      "epoch": 662774400,
+      "iso": "2021-01-01T00:00:00Z"
+    }"#
+        ));
+        assert!(json.contains(
+            r#"{
+      "extension": "basic_constraints",
+      "critical": true,
+      "value": {
+        "ca": false,
+        "path_len": null
+      }
+    }"#
+        ));
+        assert!(json.contains(
+            r#""value": [
+        "client_auth",
+        "server_auth"
+      ]"#
+        ));
+        assert!(json.contains(r#""signature": ""#));
+    }
+
+    // The member names of _json_, in order
+    fn json_keys(json: &str) -> Vec<&str> {
+        json.lines()
+            .filter_map(|l| l.trim_start().strip_prefix('"'))
+            .filter_map(|l| l.split_once("\": ").map(|(key, _)| key))
+            .collect()
+    }
+
+    #[test]
+    fn test_json_repeated_attributes() {
+        let with_cats = |cats: &[u64]| {
+            modified(&test_vectors::NOC1_SUCCESS, |f| {
+                for cat in cats {
+                    f.subject
+                        .dn
+                        .push((DnTags::NocCat as u8, DistNameValue::Uint(*cat)));
+                }
+            })
+            .to_json()
+            .unwrap()
+        };
+
+        // A single CAT is an array too, so that both have the same shape
+        let one = with_cats(&[0x0001_0001]);
+        let three = with_cats(&[0x0001_0001, 0x0002_0001, 0x0003_0001]);
+        assert!(one.contains(
+            r#""noc_cats": [
+      "00010001"
+    ]"#
+        ));
+        assert!(three.contains(
+            r#""noc_cats": [
+      "00010001",
+      "00020001",
+      "00030001"
+    ]"#
+        ));
+        assert_eq!(json_keys(&one), json_keys(&three));
+    }
+
+    #[test]
+    fn test_decoded_once() {
+        let noc = Cert::new(&test_vectors::NOC1_SUCCESS).unwrap();
+        assert_eq!(Some(CertType::Noc), noc.get_cert_type());
+        assert_eq!(0xbc5c02, noc.get_node_id().unwrap());
+        assert_eq!(1, noc.get_fabric_id().unwrap());
+        let mut cats = [0u32; 3];
+        noc.get_cat_ids(&mut cats);
+        assert_eq!([0; 3], cats);
+
+        // The TBSCertificate is encoded on the first use only
+        assert!(noc.asn1.get().is_none());
+        let asn1 = noc.get_asn1().unwrap().as_ptr();
+        assert_eq!(asn1, noc.get_asn1().unwrap().as_ptr());
+    }
+
     mod test_vectors {
         // Group 1
         pub const NOC1_SUCCESS: [u8; 247] = [
@@ -17,7 +17,7 @@
 
 use super::{CertConsumer, MAX_DEPTH};
 use crate::error::Error;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use log::warn;
 use std::fmt;
 
@@ -32,6 +32,25 @@ impl<'a, 'b> CertPrinter<'a, 'b> {
     }
 }
 
+/// The UTC time of _epoch_ seconds since the Matter epoch
+pub(super) fn to_utc(epoch: u32) -> Result<DateTime<Utc>, Error> {
+    let mut matter_epoch = Utc
+        .with_ymd_and_hms(2000, 1, 1, 0, 0, 0)
+        .unwrap()
+        .timestamp();
+
+    matter_epoch += epoch as i64;
+
+    match Utc.timestamp_opt(matter_epoch, 0) {
+        chrono::LocalResult::None => Err(Error::InvalidTime),
+        chrono::LocalResult::Single(s) => Ok(s),
+        chrono::LocalResult::Ambiguous(_, a) => {
+            warn!("Ambiguous time for epoch {epoch}; returning latest timestamp: {a}");
+            Ok(a)
+        }
+    }
+}
+
 const SPACE: [&str; MAX_DEPTH] = [
     "",
     "",
@@ -123,23 +142,13 @@ impl<'a, 'b> CertConsumer for CertPrinter<'a, 'b> {
         Ok(())
     }
     fn utctime(&mut self, tag: &str, epoch: u32) -> Result<(), Error> {
-        let mut matter_epoch = Utc
-            .with_ymd_and_hms(2000, 1, 1, 0, 0, 0)
-            .unwrap()
-            .timestamp();
-
-        matter_epoch += epoch as i64;
-
-        let dt = match Utc.timestamp_opt(matter_epoch, 0) {
-            chrono::LocalResult::None => return Err(Error::InvalidTime),
-            chrono::LocalResult::Single(s) => s,
-            chrono::LocalResult::Ambiguous(_, a) => {
-                warn!("Ambiguous time for epoch {epoch}; returning latest timestamp: {a}");
-                a
-            }
-        };
-
+        let dt = to_utc(epoch)?;
         let _ = writeln!(self.f, "{} {} {}", SPACE[self.level], tag, dt);
         Ok(())
     }
+
+    fn no_expiry_time(&mut self, tag: &str) -> Result<(), Error> {
+        let _ = writeln!(self.f, "{} {} No Expiry", SPACE[self.level], tag);
+        Ok(())
+    }
 }
@@ -16,7 +16,7 @@
  */
 
 use crate::{
-    acl::AclMgr,
+    acl::{AclAuditListener, AclMgr},
     data_model::{
         cluster_basic_information::BasicInfoConfig, core::DataModel,
         sdm::dev_att::DevAttDataFetcher,
@@ -24,14 +24,15 @@ use crate::{
     error::*,
     fabric::FabricMgr,
     interaction_model::InteractionModel,
-    mdns::Mdns,
+    mdns::{CommissioningMode, Mdns},
     pairing::{print_pairing_code_and_qr, DiscoveryCapabilities},
     secure_channel::{core::SecureChannel, pake::PaseMgr, spake2p::VerifierData},
-    transport,
+    transport::{self, group::GroupRx, queue::WorkQ},
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 /// Device Commissioning Data
+#[derive(Clone)]
 pub struct CommissioningData {
     /// The data like password or verifier that is required to authenticate
     pub verifier: VerifierData,
@@ -44,6 +45,9 @@ pub struct Matter {
     transport_mgr: transport::mgr::Mgr,
     data_model: DataModel,
     fabric_mgr: Arc<FabricMgr>,
+    acl_mgr: Arc<AclMgr>,
+    pase_mgr: PaseMgr,
+    dev_comm: CommissioningData,
 }
 
 impl Matter {
@@ -53,6 +57,9 @@ impl Matter {
     /// * dev_att: An object that implements the trait [DevAttDataFetcher]. Any Matter device
     /// requires a set of device attestation certificates and keys. It is the responsibility of
     /// this object to return the device attestation details when queried upon.
+    ///
+    /// The cryptographic operations are serviced by the process-wide provider, which
+    /// can be replaced with [set_provider](crate::crypto::set_provider) before this is called.
     pub fn new(
         dev_det: BasicInfoConfig,
         dev_att: Box<dyn DevAttDataFetcher>,
@@ -68,23 +75,38 @@ impl Matter {
         }
 
         let acl_mgr = Arc::new(AclMgr::new()?);
-        let mut pase = PaseMgr::new();
-        let data_model =
-            DataModel::new(dev_det, dev_att, fabric_mgr.clone(), acl_mgr, pase.clone())?;
+        let pase = PaseMgr::new();
+        let data_model = DataModel::new(
+            dev_det,
+            dev_att,
+            fabric_mgr.clone(),
+            acl_mgr.clone(),
+            pase.clone(),
+        )?;
         let mut matter = Box::new(Matter {
             transport_mgr: transport::mgr::Mgr::new()?,
             data_model,
             fabric_mgr,
+            acl_mgr,
+            pase_mgr: pase.clone(),
+            dev_comm,
         });
+        matter.fabric_mgr.add_listener(Arc::new(WorkQ::get()?));
         let interaction_model =
             Box::new(InteractionModel::new(Box::new(matter.data_model.clone())));
         matter.transport_mgr.register_protocol(interaction_model)?;
 
+        // Until the device is commissioned, its window stays open
         if open_comm_window {
-            pase.enable_pase_session(dev_comm.verifier, dev_comm.discriminator)?;
+            matter.open_comm_window(None)?;
         }
 
-        let secure_channel = Box::new(SecureChannel::new(pase, matter.fabric_mgr.clone()));
+        let mut secure_channel = Box::new(SecureChannel::new(pase, matter.fabric_mgr.clone()));
+        let group_rx = GroupRx::new(
+            matter.fabric_mgr.clone(),
+            secure_channel.msg_ctr_sync().clone(),
+        );
+        matter.transport_mgr.set_group_rx(group_rx);
         matter.transport_mgr.register_protocol(secure_channel)?;
         Ok(matter)
     }
@@ -98,6 +120,43 @@ impl Matter {
         self.data_model.clone()
     }
 
+    /// Registers _listener_ to audit the changes that administrators make to the ACL
+    ///
+    /// The events of the Access Control cluster are reported to the Read Requests of
+    /// the Interaction Model either way, but not to its subscribers.
+    pub fn add_acl_audit_listener(&self, listener: Arc<dyn AclAuditListener>) {
+        self.acl_mgr.add_audit_listener(listener);
+    }
+
+    /// Opens a Basic Commissioning Window with the passcode of the device
+    ///
+    /// The window is closed after _timeout_, or once the device is commissioned if
+    /// there is no _timeout_.
+    pub fn open_comm_window(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.pase_mgr.clone().enable_pase_session(
+            self.dev_comm.verifier.clone(),
+            self.dev_comm.discriminator,
+            CommissioningMode::Basic,
+            timeout,
+            None,
+        )
+    }
+
+    /// Closes the commissioning window, if one is open
+    pub fn close_comm_window(&self) {
+        self.pase_mgr.clone().disable_pase_session();
+    }
+
+    /// Removes all the fabrics, and everything that is scoped to them
+    ///
+    /// The device is then no longer commissioned, and its commissioning window is
+    /// opened again, like on the first start.
+    pub fn factory_reset(&self) -> Result<(), Error> {
+        self.fabric_mgr.remove_all()?;
+        self.close_comm_window();
+        self.open_comm_window(None)
+    }
+
     /// Starts the Matter daemon
     ///
     /// This call does NOT return
@@ -93,7 +93,7 @@ impl CryptoKeyPair for KeyPair {
         error!("This API should never get called");
         Err(Error::Invalid)
     }
-    fn derive_secret(self, _peer_pub_key: &[u8], _secret: &mut [u8]) -> Result<usize, Error> {
+    fn derive_secret(&self, _peer_pub_key: &[u8], _secret: &mut [u8]) -> Result<usize, Error> {
         error!("This API should never get called");
         Err(Error::Invalid)
     }
@@ -131,3 +131,9 @@ pub fn decrypt_in_place(
 ) -> Result<usize, Error> {
     Ok(0)
 }
+
+impl_crypto_provider!(
+    EspMbedTlsCryptoProvider,
+    "esp-mbedtls",
+    crate::secure_channel::crypto_esp_mbedtls::CryptoEspMbedTls
+);
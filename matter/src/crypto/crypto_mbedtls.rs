@@ -131,7 +131,7 @@ impl CryptoKeyPair for KeyPair {
         Ok(len)
     }
 
-    fn derive_secret(self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error> {
+    fn derive_secret(&self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error> {
         // mbedtls requires a 'mut' key. Instead of making a change in our Trait,
         // we just clone the key this way
 
@@ -347,3 +347,9 @@ impl Sha256 {
         Ok(())
     }
 }
+
+impl_crypto_provider!(
+    MbedTlsCryptoProvider,
+    "mbedtls",
+    crate::secure_channel::crypto_mbedtls::CryptoMbedTLS
+);
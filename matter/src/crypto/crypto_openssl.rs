@@ -135,7 +135,7 @@ impl CryptoKeyPair for KeyPair {
         Ok(len)
     }
 
-    fn derive_secret(self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error> {
+    fn derive_secret(&self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error> {
         let self_pkey = PKey::from_ec_key(self.private_key()?.clone())?;
 
         let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
@@ -384,3 +384,9 @@ impl Sha256 {
         Ok(())
     }
 }
+
+impl_crypto_provider!(
+    OpenSslCryptoProvider,
+    "openssl",
+    crate::secure_channel::crypto_openssl::CryptoOpenSSL
+);
@@ -231,7 +231,7 @@ impl CryptoKeyPair for KeyPair {
         pub_key[..len].copy_from_slice(bytes);
         Ok(len)
     }
-    fn derive_secret(self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error> {
+    fn derive_secret(&self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error> {
         let encoded_point = EncodedPoint::from_bytes(peer_pub_key).unwrap();
         let peer_pubkey = PublicKey::from_encoded_point(&encoded_point).unwrap();
         let private_key = self.private_key()?;
@@ -336,3 +336,9 @@ pub fn decrypt_in_place(
 
     Ok(len)
 }
+
+impl_crypto_provider!(
+    RustCryptoProvider,
+    "rustcrypto",
+    crate::secure_channel::crypto_rustcrypto::CryptoRustCrypto
+);
@@ -0,0 +1,206 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use std::sync::{Arc, RwLock};
+
+use log::error;
+
+use crate::{error::Error, sys::FileKeyStore};
+
+use super::{get_provider, CryptoKeyPair, EC_POINT_LEN_BYTES};
+
+/// An opaque reference to a key that is held in a [KeyStore]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyHandle(pub u32);
+
+/// A store for the operational keys of the fabrics
+///
+/// The private keys are generated within the store and never leave it, all the
+/// operations that require the private key are performed by the store itself. This
+/// allows the keys to be kept in a secure element.
+pub trait KeyStore: Send + Sync {
+    /// Generate a new P-256 key pair
+    fn generate(&self) -> Result<KeyHandle, Error>;
+    /// Import an existing key pair, this is only used for migrating keys that were
+    /// stored in plain-text by older versions
+    fn import(&self, _pub_key: &[u8], _priv_key: &[u8]) -> Result<KeyHandle, Error> {
+        Err(Error::Invalid)
+    }
+    fn get_public_key(&self, handle: KeyHandle, pub_key: &mut [u8]) -> Result<usize, Error>;
+    fn get_csr<'a>(&self, handle: KeyHandle, csr: &'a mut [u8]) -> Result<&'a [u8], Error>;
+    fn sign_msg(&self, handle: KeyHandle, msg: &[u8], signature: &mut [u8])
+        -> Result<usize, Error>;
+    fn derive_secret(
+        &self,
+        handle: KeyHandle,
+        peer_pub_key: &[u8],
+        secret: &mut [u8],
+    ) -> Result<usize, Error>;
+    /// Permanently delete the key
+    fn delete(&self, handle: KeyHandle) -> Result<(), Error>;
+}
+
+/// A key pair whose private key is held in a [KeyStore]
+pub struct StoredKeyPair {
+    store: Arc<dyn KeyStore>,
+    handle: KeyHandle,
+}
+
+impl StoredKeyPair {
+    pub fn new(store: Arc<dyn KeyStore>, handle: KeyHandle) -> Self {
+        Self { store, handle }
+    }
+
+    /// Generate a new key pair in the given store
+    pub fn generate(store: Arc<dyn KeyStore>) -> Result<Self, Error> {
+        let handle = store.generate()?;
+        Ok(Self::new(store, handle))
+    }
+
+    pub fn handle(&self) -> KeyHandle {
+        self.handle
+    }
+
+    /// Delete the key pair from its store
+    pub fn delete(self) -> Result<(), Error> {
+        self.store.delete(self.handle)
+    }
+}
+
+impl CryptoKeyPair for StoredKeyPair {
+    fn get_csr<'a>(&self, csr: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        self.store.get_csr(self.handle, csr)
+    }
+
+    fn get_public_key(&self, pub_key: &mut [u8]) -> Result<usize, Error> {
+        self.store.get_public_key(self.handle, pub_key)
+    }
+
+    fn get_private_key(&self, _priv_key: &mut [u8]) -> Result<usize, Error> {
+        error!("The private key can't be read out of the key store");
+        Err(Error::Invalid)
+    }
+
+    fn derive_secret(&self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error> {
+        self.store.derive_secret(self.handle, peer_pub_key, secret)
+    }
+
+    fn sign_msg(&self, msg: &[u8], signature: &mut [u8]) -> Result<usize, Error> {
+        self.store.sign_msg(self.handle, msg, signature)
+    }
+
+    fn verify_msg(&self, msg: &[u8], signature: &[u8]) -> Result<(), Error> {
+        let mut pub_key = [0_u8; EC_POINT_LEN_BYTES];
+        let len = self.get_public_key(&mut pub_key)?;
+        get_provider()
+            .key_pair_from_public(&pub_key[..len])?
+            .verify_msg(msg, signature)
+    }
+}
+
+// The default key store is only created when it is first needed, since creating
+// it can fail
+static G_KEY_STORE: RwLock<Option<Arc<dyn KeyStore>>> = RwLock::new(None);
+
+/// Get the key store that is currently in use
+pub fn get_key_store() -> Result<Arc<dyn KeyStore>, Error> {
+    if let Some(key_store) = G_KEY_STORE.read()?.as_ref() {
+        return Ok(key_store.clone());
+    }
+    let mut key_store = G_KEY_STORE.write()?;
+    if let Some(key_store) = key_store.as_ref() {
+        return Ok(key_store.clone());
+    }
+    let default: Arc<dyn KeyStore> = Arc::new(FileKeyStore::new()?);
+    *key_store = Some(default.clone());
+    Ok(default)
+}
+
+/// Replace the key store that holds the operational keys
+///
+/// This should be called before the [Matter](crate::Matter) object is created, so
+/// that the fabrics are loaded with the keys from this store.
+pub fn set_key_store(key_store: Arc<dyn KeyStore>) {
+    *G_KEY_STORE.write().unwrap() = Some(key_store);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{KeyStore, StoredKeyPair};
+    use crate::{
+        crypto::{
+            get_provider, CryptoKeyPair, ECDH_SHARED_SECRET_LEN_BYTES, EC_POINT_LEN_BYTES,
+            EC_SIGNATURE_LEN_BYTES,
+        },
+        sys::FileKeyStore,
+    };
+
+    fn test_store(name: &str) -> Arc<dyn KeyStore> {
+        let dir = std::env::temp_dir().join(format!("matter_keys_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Arc::new(FileKeyStore::new_at(dir.to_str().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let store = test_store("sign");
+        let key = StoredKeyPair::generate(store).unwrap();
+
+        let msg = b"Signed inside the key store";
+        let mut signature = [0u8; EC_SIGNATURE_LEN_BYTES];
+        key.sign_msg(msg, &mut signature).unwrap();
+        key.verify_msg(msg, &signature).unwrap();
+
+        let mut priv_key = [0u8; 32];
+        assert!(key.get_private_key(&mut priv_key).is_err());
+    }
+
+    #[test]
+    fn test_derive_secret() {
+        let store = test_store("ecdh");
+        let key = StoredKeyPair::generate(store).unwrap();
+        let peer = get_provider().generate_key_pair().unwrap();
+
+        let mut key_pub = [0u8; EC_POINT_LEN_BYTES];
+        let key_pub_len = key.get_public_key(&mut key_pub).unwrap();
+        let mut peer_pub = [0u8; EC_POINT_LEN_BYTES];
+        let peer_pub_len = peer.get_public_key(&mut peer_pub).unwrap();
+
+        let mut secret1 = [0u8; ECDH_SHARED_SECRET_LEN_BYTES];
+        let mut secret2 = [0u8; ECDH_SHARED_SECRET_LEN_BYTES];
+        key.derive_secret(&peer_pub[..peer_pub_len], &mut secret1)
+            .unwrap();
+        peer.derive_secret(&key_pub[..key_pub_len], &mut secret2)
+            .unwrap();
+        assert_eq!(secret1, secret2);
+    }
+
+    #[test]
+    fn test_delete() {
+        let store = test_store("delete");
+        let key = StoredKeyPair::generate(store.clone()).unwrap();
+        let handle = key.handle();
+        key.delete().unwrap();
+
+        let mut pub_key = [0u8; EC_POINT_LEN_BYTES];
+        assert!(store.get_public_key(handle, &mut pub_key).is_err());
+        // Handles are not reused after a delete
+        assert_ne!(store.generate().unwrap(), handle);
+    }
+}
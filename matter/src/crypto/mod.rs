@@ -40,11 +40,26 @@ pub trait CryptoKeyPair {
     fn get_csr<'a>(&self, csr: &'a mut [u8]) -> Result<&'a [u8], Error>;
     fn get_public_key(&self, pub_key: &mut [u8]) -> Result<usize, Error>;
     fn get_private_key(&self, priv_key: &mut [u8]) -> Result<usize, Error>;
-    fn derive_secret(self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error>;
+    fn derive_secret(&self, peer_pub_key: &[u8], secret: &mut [u8]) -> Result<usize, Error>;
     fn sign_msg(&self, msg: &[u8], signature: &mut [u8]) -> Result<usize, Error>;
     fn verify_msg(&self, msg: &[u8], signature: &[u8]) -> Result<(), Error>;
+
+    /// The private key, PEM encoded
+    fn to_pem(&self, format: KeyFormat) -> Result<String, Error> {
+        key_pair_to_pem(self, format)
+    }
 }
 
+#[macro_use]
+mod provider;
+pub use provider::{get_provider, set_provider, CryptoHmacSha256, CryptoProvider, CryptoSha256};
+
+mod key_store;
+pub use key_store::{get_key_store, set_key_store, KeyHandle, KeyStore, StoredKeyPair};
+
+mod pem;
+pub use pem::{csr_from_pem, csr_to_pem, key_pair_from_pem, key_pair_to_pem, KeyFormat};
+
 #[cfg(feature = "crypto_esp_mbedtls")]
 mod crypto_esp_mbedtls;
 #[cfg(feature = "crypto_esp_mbedtls")]
@@ -67,6 +82,36 @@ pub use self::crypto_rustcrypto::*;
 
 pub mod crypto_dummy;
 
+// The free functions below shadow the ones of the backend, so that they are
+// always serviced by the crypto provider that is currently installed
+
+pub fn pbkdf2_hmac(pass: &[u8], iter: usize, salt: &[u8], key: &mut [u8]) -> Result<(), Error> {
+    get_provider().pbkdf2_hmac(pass, iter, salt, key)
+}
+
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], key: &mut [u8]) -> Result<(), Error> {
+    get_provider().hkdf_sha256(salt, ikm, info, key)
+}
+
+pub fn encrypt_in_place(
+    key: &[u8],
+    nonce: &[u8],
+    ad: &[u8],
+    data: &mut [u8],
+    data_len: usize,
+) -> Result<usize, Error> {
+    get_provider().encrypt_in_place(key, nonce, ad, data, data_len)
+}
+
+pub fn decrypt_in_place(
+    key: &[u8],
+    nonce: &[u8],
+    ad: &[u8],
+    data: &mut [u8],
+) -> Result<usize, Error> {
+    get_provider().decrypt_in_place(key, nonce, ad, data)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::error::Error;
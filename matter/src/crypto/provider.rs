@@ -0,0 +1,431 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use std::sync::{Arc, OnceLock, RwLock};
+
+use log::info;
+
+use crate::{error::Error, secure_channel::crypto::CryptoSpake2};
+
+use super::CryptoKeyPair;
+
+/// A SHA256 hash context
+pub trait CryptoSha256 {
+    fn update(&mut self, data: &[u8]) -> Result<(), Error>;
+    fn finish(self: Box<Self>, digest: &mut [u8]) -> Result<(), Error>;
+    /// A copy of the context, to get the digest of what was hashed so far
+    fn box_clone(&self) -> Box<dyn CryptoSha256>;
+}
+
+/// A HMAC-SHA256 context
+pub trait CryptoHmacSha256 {
+    fn update(&mut self, data: &[u8]) -> Result<(), Error>;
+    fn finish(self: Box<Self>, out: &mut [u8]) -> Result<(), Error>;
+}
+
+/// All the cryptographic primitives that Matter requires
+///
+/// Every crypto backend of this crate implements this trait. The provider that is
+/// used by the stack is the backend selected through the cargo features, unless
+/// another one is installed with [set_provider].
+pub trait CryptoProvider: Send + Sync {
+    /// A short name for the provider, used for logging
+    fn name(&self) -> &'static str;
+
+    /// Generate a new random P-256 key pair
+    fn generate_key_pair(&self) -> Result<Box<dyn CryptoKeyPair>, Error>;
+    /// Create a key pair from its uncompressed public key and raw private key
+    fn key_pair_from_components(
+        &self,
+        pub_key: &[u8],
+        priv_key: &[u8],
+    ) -> Result<Box<dyn CryptoKeyPair>, Error>;
+    /// Create a key pair that only has the public key, useful for verifying signatures
+    fn key_pair_from_public(&self, pub_key: &[u8]) -> Result<Box<dyn CryptoKeyPair>, Error>;
+
+    fn sha256(&self) -> Result<Box<dyn CryptoSha256>, Error>;
+    fn hmac_sha256(&self, key: &[u8]) -> Result<Box<dyn CryptoHmacSha256>, Error>;
+    fn hkdf_sha256(
+        &self,
+        salt: &[u8],
+        ikm: &[u8],
+        info: &[u8],
+        key: &mut [u8],
+    ) -> Result<(), Error>;
+    fn pbkdf2_hmac(
+        &self,
+        pass: &[u8],
+        iter: usize,
+        salt: &[u8],
+        key: &mut [u8],
+    ) -> Result<(), Error>;
+
+    /// AES-CCM encryption, the MIC is appended after _data_len_ bytes of _data_
+    fn encrypt_in_place(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        ad: &[u8],
+        data: &mut [u8],
+        data_len: usize,
+    ) -> Result<usize, Error>;
+    /// AES-CCM decryption, _data_ includes the MIC
+    fn decrypt_in_place(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        ad: &[u8],
+        data: &mut [u8],
+    ) -> Result<usize, Error>;
+
+    /// Create the EC operations for a new Spake2+ exchange
+    fn spake2(&self) -> Result<Box<dyn CryptoSpake2>, Error>;
+}
+
+static G_PROVIDER: OnceLock<RwLock<Arc<dyn CryptoProvider>>> = OnceLock::new();
+
+fn provider_lock() -> &'static RwLock<Arc<dyn CryptoProvider>> {
+    G_PROVIDER.get_or_init(|| RwLock::new(Arc::new(super::DefaultCryptoProvider::default())))
+}
+
+/// Get the crypto provider that is currently in use
+pub fn get_provider() -> Arc<dyn CryptoProvider> {
+    provider_lock().read().unwrap().clone()
+}
+
+/// Replace the crypto provider that is used by the stack
+///
+/// The provider is global to the process, every [Matter](crate::Matter) object
+/// uses the last one that was set. This should be called before the
+/// [Matter](crate::Matter) object is created, since any keys that were created by
+/// the previous provider continue to use that provider.
+pub fn set_provider(provider: Arc<dyn CryptoProvider>) {
+    info!("Using crypto provider: {}", provider.name());
+    *provider_lock().write().unwrap() = provider;
+}
+
+// Implements the CryptoProvider for a backend module, in terms of the KeyPair,
+// Sha256, HmacSha256 and the free functions that the module defines
+macro_rules! impl_crypto_provider {
+    ($provider:ident, $name:expr, $spake2:ty) => {
+        #[derive(Default)]
+        pub struct $provider;
+
+        pub type DefaultCryptoProvider = $provider;
+
+        impl $crate::crypto::CryptoSha256 for Sha256 {
+            fn update(&mut self, data: &[u8]) -> Result<(), $crate::error::Error> {
+                Sha256::update(self, data)
+            }
+
+            fn finish(self: Box<Self>, digest: &mut [u8]) -> Result<(), $crate::error::Error> {
+                Sha256::finish(*self, digest)
+            }
+
+            fn box_clone(&self) -> Box<dyn $crate::crypto::CryptoSha256> {
+                Box::new(self.clone())
+            }
+        }
+
+        impl $crate::crypto::CryptoHmacSha256 for HmacSha256 {
+            fn update(&mut self, data: &[u8]) -> Result<(), $crate::error::Error> {
+                HmacSha256::update(self, data)
+            }
+
+            fn finish(self: Box<Self>, out: &mut [u8]) -> Result<(), $crate::error::Error> {
+                HmacSha256::finish(*self, out)
+            }
+        }
+
+        impl $crate::crypto::CryptoProvider for $provider {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn generate_key_pair(
+                &self,
+            ) -> Result<Box<dyn $crate::crypto::CryptoKeyPair>, $crate::error::Error> {
+                Ok(Box::new(KeyPair::new()?))
+            }
+
+            fn key_pair_from_components(
+                &self,
+                pub_key: &[u8],
+                priv_key: &[u8],
+            ) -> Result<Box<dyn $crate::crypto::CryptoKeyPair>, $crate::error::Error> {
+                Ok(Box::new(KeyPair::new_from_components(pub_key, priv_key)?))
+            }
+
+            fn key_pair_from_public(
+                &self,
+                pub_key: &[u8],
+            ) -> Result<Box<dyn $crate::crypto::CryptoKeyPair>, $crate::error::Error> {
+                Ok(Box::new(KeyPair::new_from_public(pub_key)?))
+            }
+
+            fn sha256(
+                &self,
+            ) -> Result<Box<dyn $crate::crypto::CryptoSha256>, $crate::error::Error> {
+                Ok(Box::new(Sha256::new()?))
+            }
+
+            fn hmac_sha256(
+                &self,
+                key: &[u8],
+            ) -> Result<Box<dyn $crate::crypto::CryptoHmacSha256>, $crate::error::Error> {
+                Ok(Box::new(HmacSha256::new(key)?))
+            }
+
+            fn hkdf_sha256(
+                &self,
+                salt: &[u8],
+                ikm: &[u8],
+                info: &[u8],
+                key: &mut [u8],
+            ) -> Result<(), $crate::error::Error> {
+                hkdf_sha256(salt, ikm, info, key)
+            }
+
+            fn pbkdf2_hmac(
+                &self,
+                pass: &[u8],
+                iter: usize,
+                salt: &[u8],
+                key: &mut [u8],
+            ) -> Result<(), $crate::error::Error> {
+                pbkdf2_hmac(pass, iter, salt, key)
+            }
+
+            fn encrypt_in_place(
+                &self,
+                key: &[u8],
+                nonce: &[u8],
+                ad: &[u8],
+                data: &mut [u8],
+                data_len: usize,
+            ) -> Result<usize, $crate::error::Error> {
+                encrypt_in_place(key, nonce, ad, data, data_len)
+            }
+
+            fn decrypt_in_place(
+                &self,
+                key: &[u8],
+                nonce: &[u8],
+                ad: &[u8],
+                data: &mut [u8],
+            ) -> Result<usize, $crate::error::Error> {
+                decrypt_in_place(key, nonce, ad, data)
+            }
+
+            fn spake2(
+                &self,
+            ) -> Result<Box<dyn $crate::secure_channel::crypto::CryptoSpake2>, $crate::error::Error>
+            {
+                use $crate::secure_channel::crypto::CryptoSpake2;
+                Ok(Box::new(<$spake2>::new()?))
+            }
+        }
+    };
+}
+
+// The conformance tests that every crypto provider compiled into the crate must pass
+#[cfg(test)]
+mod tests {
+    use crate::crypto::{
+        AEAD_MIC_LEN_BYTES, AEAD_NONCE_LEN_BYTES, BIGNUM_LEN_BYTES, EC_POINT_LEN_BYTES,
+        EC_SIGNATURE_LEN_BYTES, SHA256_HASH_LEN_BYTES, SYMM_KEY_LEN_BYTES,
+    };
+    use crate::error::Error;
+
+    use super::CryptoProvider;
+
+    fn test_sha256(p: &dyn CryptoProvider) {
+        let mut h = p.sha256().unwrap();
+        h.update(b"ab").unwrap();
+        let mut copy = h.box_clone();
+        h.update(b"c").unwrap();
+        let mut digest = [0u8; SHA256_HASH_LEN_BYTES];
+        h.finish(&mut digest).unwrap();
+        assert_eq!(digest, test_vectors::SHA256_ABC);
+
+        // The copy is independent of the original
+        copy.update(b"c").unwrap();
+        let mut digest = [0u8; SHA256_HASH_LEN_BYTES];
+        copy.finish(&mut digest).unwrap();
+        assert_eq!(digest, test_vectors::SHA256_ABC);
+    }
+
+    fn test_hmac_sha256(p: &dyn CryptoProvider) {
+        let mut mac = p.hmac_sha256(b"Jefe").unwrap();
+        mac.update(b"what do ya want ").unwrap();
+        mac.update(b"for nothing?").unwrap();
+        let mut out = [0u8; SHA256_HASH_LEN_BYTES];
+        mac.finish(&mut out).unwrap();
+        assert_eq!(out, test_vectors::HMAC_SHA256_JEFE);
+    }
+
+    fn test_hkdf_sha256(p: &dyn CryptoProvider) {
+        let ikm = [0x0b_u8; 22];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let mut okm = [0u8; 42];
+        p.hkdf_sha256(&salt, &ikm, &info, &mut okm).unwrap();
+        assert_eq!(okm, test_vectors::HKDF_OKM);
+    }
+
+    fn test_pbkdf2_hmac(p: &dyn CryptoProvider) {
+        let mut key = [0u8; 32];
+        p.pbkdf2_hmac(b"password", 1, b"salt", &mut key).unwrap();
+        assert_eq!(key, test_vectors::PBKDF2_KEY);
+    }
+
+    fn test_aead(p: &dyn CryptoProvider) {
+        let key = [0x42_u8; SYMM_KEY_LEN_BYTES];
+        let nonce = [0x24_u8; AEAD_NONCE_LEN_BYTES];
+        let ad = [1_u8, 2, 3, 4];
+        let plain_text = b"Matter over the wire";
+
+        let mut data = [0u8; 20 + AEAD_MIC_LEN_BYTES];
+        data[..plain_text.len()].copy_from_slice(plain_text);
+        let len = p
+            .encrypt_in_place(&key, &nonce, &ad, &mut data, plain_text.len())
+            .unwrap();
+        assert_eq!(len, plain_text.len() + AEAD_MIC_LEN_BYTES);
+        assert_ne!(&data[..plain_text.len()], plain_text);
+
+        let mut tampered = data;
+        tampered[0] ^= 0x01;
+        assert!(p
+            .decrypt_in_place(&key, &nonce, &ad, &mut tampered)
+            .is_err());
+
+        let len = p.decrypt_in_place(&key, &nonce, &ad, &mut data).unwrap();
+        assert_eq!(&data[..len], plain_text);
+    }
+
+    fn test_key_pair(p: &dyn CryptoProvider) {
+        let msg = b"Some message to sign";
+        let key = p.generate_key_pair().unwrap();
+        let mut signature = [0u8; EC_SIGNATURE_LEN_BYTES];
+        let len = key.sign_msg(msg, &mut signature).unwrap();
+        assert_eq!(len, EC_SIGNATURE_LEN_BYTES);
+
+        let mut pub_key = [0u8; EC_POINT_LEN_BYTES];
+        let len = key.get_public_key(&mut pub_key).unwrap();
+        let verifier = p.key_pair_from_public(&pub_key[..len]).unwrap();
+        verifier.verify_msg(msg, &signature).unwrap();
+        assert_eq!(
+            verifier.verify_msg(b"Some other message", &signature),
+            Err(Error::InvalidSignature)
+        );
+    }
+
+    fn test_key_pair_components(p: &dyn CryptoProvider) {
+        let key = p.generate_key_pair().unwrap();
+        let mut pub_key = [0u8; EC_POINT_LEN_BYTES];
+        let pub_len = key.get_public_key(&mut pub_key).unwrap();
+        let mut priv_key = [0u8; BIGNUM_LEN_BYTES];
+        let priv_len = key.get_private_key(&mut priv_key).unwrap();
+
+        let restored = p
+            .key_pair_from_components(&pub_key[..pub_len], &priv_key[..priv_len])
+            .unwrap();
+        let msg = b"Signed by the restored key";
+        let mut signature = [0u8; EC_SIGNATURE_LEN_BYTES];
+        restored.sign_msg(msg, &mut signature).unwrap();
+        key.verify_msg(msg, &signature).unwrap();
+    }
+
+    #[allow(non_snake_case)]
+    fn test_spake2(p: &dyn CryptoProvider) {
+        let mut spake2 = p.spake2().unwrap();
+        let mut pB = [0u8; EC_POINT_LEN_BYTES];
+        spake2.set_w0(&[0x01; 32]).unwrap();
+        spake2.set_L(&test_vectors::SPAKE2_L).unwrap();
+        spake2.get_pB(&mut pB).unwrap();
+        assert_eq!(pB[0], 0x04);
+    }
+
+    fn conformance(p: &dyn CryptoProvider) {
+        test_sha256(p);
+        test_hmac_sha256(p);
+        test_hkdf_sha256(p);
+        test_pbkdf2_hmac(p);
+        test_aead(p);
+        test_key_pair(p);
+        test_key_pair_components(p);
+        test_spake2(p);
+    }
+
+    #[cfg(feature = "crypto_openssl")]
+    #[test]
+    fn test_openssl_provider() {
+        conformance(&super::super::crypto_openssl::OpenSslCryptoProvider);
+    }
+
+    #[cfg(feature = "crypto_mbedtls")]
+    #[test]
+    fn test_mbedtls_provider() {
+        conformance(&super::super::crypto_mbedtls::MbedTlsCryptoProvider);
+    }
+
+    #[cfg(feature = "crypto_rustcrypto")]
+    #[test]
+    fn test_rustcrypto_provider() {
+        conformance(&super::super::crypto_rustcrypto::RustCryptoProvider);
+    }
+
+    #[test]
+    fn test_default_provider() {
+        conformance(super::get_provider().as_ref());
+    }
+
+    mod test_vectors {
+        // SHA256("abc") from FIPS 180-2
+        pub const SHA256_ABC: [u8; 32] = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ];
+        // RFC 4231, Test Case 2
+        pub const HMAC_SHA256_JEFE: [u8; 32] = [
+            0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95,
+            0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9,
+            0x64, 0xec, 0x38, 0x43,
+        ];
+        // RFC 5869, Test Case 1
+        pub const HKDF_OKM: [u8; 42] = [
+            0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36,
+            0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56,
+            0xec, 0xc4, 0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+        ];
+        // PBKDF2-HMAC-SHA256("password", "salt", 1 iteration)
+        pub const PBKDF2_KEY: [u8; 32] = [
+            0x12, 0x0f, 0xb6, 0xcf, 0xfc, 0xf8, 0xb3, 0x2c, 0x43, 0xe7, 0x22, 0x52, 0x56, 0xc4,
+            0xf8, 0x37, 0xa8, 0x65, 0x48, 0xc9, 0x2c, 0xcc, 0x35, 0x48, 0x08, 0x05, 0x98, 0x7c,
+            0xb7, 0x0b, 0xe1, 0x7b,
+        ];
+        // The P-256 generator, used as a well-formed L
+        pub const SPAKE2_L: [u8; 65] = [
+            0x04, 0x6b, 0x17, 0xd1, 0xf2, 0xe1, 0x2c, 0x42, 0x47, 0xf8, 0xbc, 0xe6, 0xe5, 0x63,
+            0xa4, 0x40, 0xf2, 0x77, 0x03, 0x7d, 0x81, 0x2d, 0xeb, 0x33, 0xa0, 0xf4, 0xa1, 0x39,
+            0x45, 0xd8, 0x98, 0xc2, 0x96, 0x4f, 0xe3, 0x42, 0xe2, 0xfe, 0x1a, 0x7f, 0x9b, 0x8e,
+            0xe7, 0xeb, 0x4a, 0x7c, 0x0f, 0x9e, 0x16, 0x2b, 0xce, 0x33, 0x57, 0x6b, 0x31, 0x5e,
+            0xce, 0xcb, 0xb6, 0x40, 0x68, 0x37, 0xbf, 0x51, 0xf5,
+        ];
+    }
+}
@@ -179,6 +179,19 @@ impl MediaPlaybackCluster {
             // Options - probably want a custom type here for mapping cluster options to TLV bitmask
         ];
         cluster.base.add_attributes(&attrs)?;
+        cluster.base.add_commands(&[
+            Command::new(Commands::Play as CmdId, Access::IO),
+            Command::new(Commands::Pause as CmdId, Access::IO),
+            Command::new(Commands::Stop as CmdId, Access::IO),
+            Command::new(Commands::StartOver as CmdId, Access::IO),
+            Command::new(Commands::Previous as CmdId, Access::IO),
+            Command::new(Commands::Next as CmdId, Access::IO),
+            Command::new(Commands::Rewind as CmdId, Access::IO),
+            Command::new(Commands::FastForward as CmdId, Access::IO),
+            Command::new(Commands::SkipForward as CmdId, Access::IO),
+            Command::new(Commands::SkipBackward as CmdId, Access::IO),
+            Command::new(Commands::Seek as CmdId, Access::IO),
+        ])?;
 
         // For now disable all features by default
         cluster.base.set_feature_map(0)?;
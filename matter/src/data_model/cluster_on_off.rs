@@ -57,6 +57,11 @@ impl OnOffCluster {
             base: Cluster::new(ID)?,
         });
         cluster.base.add_attribute(attr_on_off_new())?;
+        cluster.base.add_commands(&[
+            Command::new(Commands::Off as CmdId, Access::IO),
+            Command::new(Commands::On as CmdId, Access::IO),
+            Command::new(Commands::Toggle as CmdId, Access::IO),
+        ])?;
         Ok(cluster)
     }
 }
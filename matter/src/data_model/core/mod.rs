@@ -21,7 +21,7 @@ use super::{
     cluster_basic_information::BasicInfoConfig,
     device_types::device_type_add_root_node,
     objects::{self, *},
-    sdm::dev_att::DevAttDataFetcher,
+    sdm::{dev_att::DevAttDataFetcher, failsafe::FailSafe},
     system_model::descriptor::DescriptorCluster,
 };
 use crate::{
@@ -52,6 +52,8 @@ use std::sync::{Arc, RwLock};
 pub struct DataModel {
     pub node: Arc<RwLock<Box<Node>>>,
     acl_mgr: Arc<AclMgr>,
+    failsafe: Arc<FailSafe>,
+    events: Arc<EventLog>,
 }
 
 impl DataModel {
@@ -65,7 +67,10 @@ impl DataModel {
         let dm = DataModel {
             node: Arc::new(RwLock::new(Node::new()?)),
             acl_mgr: acl_mgr.clone(),
+            failsafe: Arc::new(FailSafe::new(fabric_mgr.clone())),
+            events: Arc::new(EventLog::new()),
         };
+        fabric_mgr.add_listener(acl_mgr.clone());
         {
             let mut node = dm.node.write()?;
             node.set_changes_cb(Box::new(dm.clone()));
@@ -76,6 +81,8 @@ impl DataModel {
                 fabric_mgr,
                 acl_mgr,
                 pase_mgr,
+                dm.failsafe.clone(),
+                dm.events.clone(),
             )?;
         }
         Ok(dm)
@@ -117,14 +124,11 @@ impl DataModel {
             //    to be taken care of.
             encoder.skip_error();
         }
-        let mut attr = AttrDetails {
-            // will be udpated in the loop below
-            attr_id: 0,
-            list_index: attr_data.path.list_index,
-            fab_filter: false,
-            fab_idx: accessor.fab_idx,
-        };
+        // The attr_id will be udpated in the loop below
+        let mut attr = AttrDetails::new(accessor, false);
+        attr.list_index = attr_data.path.list_index;
 
+        let dev_types = node.get_dev_types();
         let result = node.for_each_cluster_mut(&gen_path, |path, c| {
             if attr_data.data_ver.is_some() && Some(c.base().get_dataver()) != attr_data.data_ver {
                 encoder.encode_status(IMStatusCode::DataVersionMismatch, 0);
@@ -134,6 +138,7 @@ impl DataModel {
             attr.attr_id = path.leaf.unwrap_or_default() as u16;
             encoder.set_path(*path);
             let mut access_req = AccessReq::new(accessor, path, Access::WRITE);
+            Self::set_target_dev_type(&mut access_req, &dev_types, path);
             let r = match Cluster::write_attribute(c, &mut access_req, write_data, &attr) {
                 Ok(_) => IMStatusCode::Success,
                 Err(e) => e,
@@ -147,19 +152,39 @@ impl DataModel {
         }
     }
 
+    // ACL targets may be device types, these are looked up before the node is traversed
+    fn set_target_dev_type(
+        access_req: &mut AccessReq,
+        dev_types: &[Option<DeviceType>],
+        path: &GenericPath,
+    ) {
+        if let Some(Some(dev_type)) = path.endpoint.and_then(|e| dev_types.get(e as usize)) {
+            access_req.set_target_dev_type(*dev_type);
+        }
+    }
+
     // Handle command from a path that may or may not be wildcard
-    fn handle_command_path(node: &mut Node, cmd_req: &mut CommandReq) {
+    fn handle_command_path(
+        node: &mut Node,
+        accessor: &Accessor,
+        cmd_req: &mut CommandReq,
+        timed: bool,
+    ) {
         let wildcard = cmd_req.cmd.path.is_wildcard();
         let path = cmd_req.cmd.path;
 
+        let dev_types = node.get_dev_types();
         let result = node.for_each_cluster_mut(&path, |path, c| {
             cmd_req.cmd.path = *path;
-            let result = c.handle_command(cmd_req);
+            let mut access_req = AccessReq::new(accessor, path, Access::INVOKE);
+            Self::set_target_dev_type(&mut access_req, &dev_types, path);
+            let result = Cluster::invoke_command(c, &mut access_req, cmd_req, timed);
             if let Err(e) = result {
-                // It is likely that we might have to do an 'Access' aware traversal
-                // if there are other conditions in the wildcard scenario that shouldn't be
-                // encoded as CmdStatus
-                if !(wildcard && e == IMStatusCode::UnsupportedCommand) {
+                // The clusters that don't have the command, or that the accessor has no
+                // access to, are silently skipped in the wildcard scenario
+                let skip =
+                    e == IMStatusCode::UnsupportedCommand || e == IMStatusCode::UnsupportedAccess;
+                if !(wildcard && skip) {
                     let invoke_resp = ib::InvResp::status_new(cmd_req.cmd, e, 0);
                     let _ = invoke_resp.to_tlv(cmd_req.resp, TagType::Anonymous);
                 }
@@ -200,6 +225,13 @@ impl DataModel {
                 AuthMode::Invalid,
                 self.acl_mgr.clone(),
             ),
+            // The subject of a group message is its group, not the sending node
+            SessionMode::Group(g) => Accessor::new(
+                g.fab_idx,
+                AccessorSubjects::new(g.group_id as u64),
+                AuthMode::Group,
+                self.acl_mgr.clone(),
+            ),
         }
     }
 
@@ -288,6 +320,9 @@ impl InteractionConsumer for DataModel {
         tw: &mut TLVWriter,
     ) -> Result<(), Error> {
         let mut node = self.node.write().unwrap();
+        let accessor = self.sess_to_accessor(trans.session);
+        // The Invoke Request was already matched against the Timed Request, if any
+        let timed = inv_req_msg.timed_request == Some(true);
         if let Some(inv_requests) = &inv_req_msg.inv_requests {
             // Array of InvokeResponse IBs
             tw.start_array(TagType::Context(msg::InvRespTag::InvokeResponses as u8))?;
@@ -304,7 +339,7 @@ impl InteractionConsumer for DataModel {
                     trans,
                     resp: tw,
                 };
-                DataModel::handle_command_path(&mut node, &mut cmd_req);
+                DataModel::handle_command_path(&mut node, &accessor, &mut cmd_req, timed);
             }
             tw.end_container()?;
         }
@@ -350,6 +385,10 @@ impl InteractionConsumer for DataModel {
             .set_data_boxed(Box::new(ResumeReq::Subscribe(ctx)));
         Ok((OpCode::ReportData, ResponseRequired::Yes))
     }
+
+    fn handle_timer_tick(&self) {
+        self.failsafe.handle_timer_tick();
+    }
 }
 
 /// Encoder for generating a response to a write request
@@ -160,6 +160,7 @@ impl DataModel {
         // Skip error reporting for wildcard paths, don't for concrete paths
         attr_encoder.skip_error(path.is_wildcard());
 
+        let dev_types = node.get_dev_types();
         let result = node.for_each_attribute(&path, |path, c| {
             // Ignore processing if data filter matches.
             // For a wildcard attribute, this may end happening unnecessarily for all attributes, although
@@ -188,6 +189,7 @@ impl DataModel {
             // Set the cluster's data version
             attr_encoder.set_data_ver(cluster_data_ver);
             let mut access_req = AccessReq::new(accessor, path, Access::READ);
+            Self::set_target_dev_type(&mut access_req, &dev_types, path);
             Cluster::read_attribute(c, &mut access_req, attr_encoder, attr_details);
             if attr_encoder.is_buffer_full() {
                 // Buffer is full, next time resume from this attribute
@@ -232,7 +234,7 @@ impl DataModel {
 
         if let Some(attr_requests) = &read_req.attr_requests {
             let accessor = self.sess_to_accessor(trans.session);
-            let mut attr_details = AttrDetails::new(accessor.fab_idx, read_req.fabric_filtered);
+            let mut attr_details = AttrDetails::new(&accessor, read_req.fabric_filtered);
             let node = self.node.read().unwrap();
             attr_encoder
                 .tw
@@ -271,6 +273,81 @@ impl DataModel {
         Ok(())
     }
 
+    /// Process an array of Event Read Requests
+    ///
+    /// The events in the EventLog that match any of the paths, and that are newer than the
+    /// event_min of the filters, are reported. The events that don't fit in the buffer
+    /// are left out.
+    pub(super) fn handle_read_event_array(
+        &self,
+        read_req: &ReadReq,
+        trans: &mut Transaction,
+        old_tw: &mut TLVWriter,
+    ) -> Result<(), Error> {
+        let event_requests = match &read_req.event_requests {
+            Some(e) => e,
+            None => return Ok(()),
+        };
+        let event_min = read_req.event_filters.as_ref().map_or(0, |filters| {
+            filters
+                .iter()
+                .filter_map(|f| f.event_min)
+                .max()
+                .unwrap_or(0)
+        });
+        let accessor = self.sess_to_accessor(trans.session);
+
+        let old_wb = old_tw.get_buf();
+        // The space for the end of the EventReports, and the rest of the Report Data
+        const RESERVE_SIZE: usize = 8;
+        let mut new_wb = wb_shrink!(old_wb, RESERVE_SIZE);
+        let mut tw = TLVWriter::new(&mut new_wb);
+        tw.start_array(TagType::Context(msg::ReportDataTag::EventReports as u8))?;
+
+        let _ = self.events.for_each(|event| {
+            if event.number < event_min
+                || !event_requests
+                    .iter()
+                    .any(|p| p.matches(event.endpoint, event.cluster, event.id))
+            {
+                return Ok(());
+            }
+            // A fabric-sensitive event is only reported to its own fabric
+            if event.fab_idx.is_some_and(|f| f != accessor.fab_idx) {
+                return Ok(());
+            }
+            let path = GenericPath::new(Some(event.endpoint), Some(event.cluster), Some(event.id));
+            let mut access_req = AccessReq::new(&accessor, &path, Access::READ);
+            access_req.set_target_perms(event.access);
+            if !access_req.allow() {
+                return Ok(());
+            }
+
+            let resp = ib::EventResp::Data(ib::EventData {
+                path: ib::EventPath::new(Some(event.endpoint), Some(event.cluster), Some(event.id)),
+                event_number: event.number,
+                priority: event.priority as u8,
+                epoch_timestamp: Some(event.timestamp),
+                system_timestamp: None,
+                delta_epoch_timestamp: None,
+                delta_system_timestamp: None,
+                data: EncodeValue::Value(&*event.data),
+            });
+            let anchor = tw.get_tail();
+            if let Err(e) = resp.to_tlv(&mut tw, TagType::Anonymous) {
+                error!(
+                    "No space for event {}, leaving out the newer events",
+                    event.number
+                );
+                tw.rewind_to(anchor);
+                return Err(e);
+            }
+            Ok(())
+        });
+        wb_unshrink!(old_wb, new_wb);
+        old_tw.end_container()
+    }
+
     /// Handle a read request
     ///
     /// This could be called from an actual read request or a resumed read request. Subscription
@@ -289,6 +366,8 @@ impl DataModel {
         self.handle_read_attr_array(read_req, trans, tw, resume_from)?;
 
         if resume_from.is_none() {
+            // The events are reported in the last chunk
+            self.handle_read_event_array(read_req, trans, tw)?;
             tw.bool(TagType::Context(SupressResponse as u8), true)?;
             // Mark transaction complete, if not chunked
             trans.complete();
@@ -21,7 +21,9 @@ use super::cluster_on_off::OnOffCluster;
 use super::objects::*;
 use super::sdm::admin_commissioning::AdminCommCluster;
 use super::sdm::dev_att::DevAttDataFetcher;
+use super::sdm::failsafe::FailSafe;
 use super::sdm::general_commissioning::GenCommCluster;
+use super::sdm::group_key_management::GrpKeyMgmtCluster;
 use super::sdm::noc::NocCluster;
 use super::sdm::nw_commissioning::NwCommCluster;
 use super::system_model::access_control::AccessControlCluster;
@@ -39,6 +41,7 @@ pub const DEV_TYPE_ROOT_NODE: DeviceType = DeviceType {
 
 type WriteNode<'a> = RwLockWriteGuard<'a, Box<Node>>;
 
+#[allow(clippy::too_many_arguments)]
 pub fn device_type_add_root_node(
     node: &mut WriteNode,
     dev_info: BasicInfoConfig,
@@ -46,6 +49,8 @@ pub fn device_type_add_root_node(
     fabric_mgr: Arc<FabricMgr>,
     acl_mgr: Arc<AclMgr>,
     pase_mgr: PaseMgr,
+    failsafe: Arc<FailSafe>,
+    events: Arc<EventLog>,
 ) -> Result<EndptId, Error> {
     // Add the root endpoint
     let endpoint = node.add_endpoint(DEV_TYPE_ROOT_NODE)?;
@@ -55,16 +60,15 @@ pub fn device_type_add_root_node(
     };
     // Add the mandatory clusters
     node.add_cluster(0, BasicInfoCluster::new(dev_info)?)?;
-    let general_commissioning = GenCommCluster::new()?;
-    let failsafe = general_commissioning.failsafe();
-    node.add_cluster(0, general_commissioning)?;
+    node.add_cluster(0, GenCommCluster::new(failsafe.clone())?)?;
     node.add_cluster(0, NwCommCluster::new()?)?;
-    node.add_cluster(0, AdminCommCluster::new(pase_mgr)?)?;
+    node.add_cluster(0, AdminCommCluster::new(pase_mgr, fabric_mgr.clone())?)?;
     node.add_cluster(
         0,
-        NocCluster::new(dev_att, fabric_mgr, acl_mgr.clone(), failsafe)?,
+        NocCluster::new(dev_att, fabric_mgr.clone(), acl_mgr.clone(), failsafe)?,
     )?;
-    node.add_cluster(0, AccessControlCluster::new(acl_mgr)?)?;
+    node.add_cluster(0, GrpKeyMgmtCluster::new(fabric_mgr)?)?;
+    node.add_cluster(0, AccessControlCluster::new(acl_mgr, events)?)?;
     Ok(endpoint)
 }
 
@@ -39,6 +39,7 @@ bitflags! {
         const FAB_SCOPED = 0x0040;
         const FAB_SENSITIVE = 0x0080;
         const TIMED_ONLY = 0x0100;
+        const INVOKE = 0x0200;
 
         const READ_PRIVILEGE_MASK = Self::NEED_VIEW.bits | Self::NEED_MANAGE.bits | Self::NEED_OPERATE.bits | Self::NEED_ADMIN.bits;
         const WRITE_PRIVILEGE_MASK = Self::NEED_MANAGE.bits | Self::NEED_OPERATE.bits | Self::NEED_ADMIN.bits;
@@ -46,19 +47,15 @@ bitflags! {
         const RWVA = Self::READ.bits | Self::WRITE.bits | Self::NEED_VIEW.bits | Self::NEED_ADMIN.bits;
         const RWFA = Self::READ.bits | Self::WRITE.bits | Self::FAB_SCOPED.bits | Self::NEED_ADMIN.bits;
         const RWVM = Self::READ.bits | Self::WRITE.bits | Self::NEED_VIEW.bits | Self::NEED_MANAGE.bits;
+        const IO = Self::INVOKE.bits | Self::NEED_OPERATE.bits;
+        const IM = Self::INVOKE.bits | Self::NEED_MANAGE.bits;
+        const IA = Self::INVOKE.bits | Self::NEED_ADMIN.bits;
     }
 }
 
 impl Access {
     pub fn is_ok(&self, operation: Access, privilege: Privilege) -> bool {
-        let required = if operation.contains(Access::READ) {
-            *self & Access::READ_PRIVILEGE_MASK
-        } else if operation.contains(Access::WRITE) {
-            *self & Access::WRITE_PRIVILEGE_MASK
-        } else {
-            return false;
-        };
-
+        let required = self.privilege_mask(operation);
         if required.is_empty() {
             // There must be some required privilege for any object
             return false;
@@ -70,6 +67,33 @@ impl Access {
 
         self.contains(operation)
     }
+
+    /// The least privilege with which _operation_ is allowed on an object with these permissions
+    pub fn required_privilege(&self, operation: Access) -> Option<Privilege> {
+        if !self.contains(operation) {
+            return None;
+        }
+        // The privileges are hierarchical, the lowest of the required bits is sufficient
+        let required = self.privilege_mask(operation).bits();
+        match Access::from_bits_truncate(required & required.wrapping_neg()) {
+            Access::NEED_VIEW => Some(Privilege::VIEW),
+            Access::NEED_OPERATE => Some(Privilege::OPERATE),
+            Access::NEED_MANAGE => Some(Privilege::MANAGE),
+            Access::NEED_ADMIN => Some(Privilege::ADMIN),
+            _ => None,
+        }
+    }
+
+    fn privilege_mask(&self, operation: Access) -> Access {
+        if operation.contains(Access::READ) {
+            *self & Access::READ_PRIVILEGE_MASK
+        } else if operation.contains(Access::WRITE) || operation.contains(Access::INVOKE) {
+            // Invoking a command needs at least Operate, like a write
+            *self & Access::WRITE_PRIVILEGE_MASK
+        } else {
+            Access::empty()
+        }
+    }
 }
 
 bitflags! {
@@ -270,4 +294,19 @@ mod tests {
         assert_eq!(c.is_ok(Access::WRITE, Privilege::MANAGE), true);
         assert_eq!(c.is_ok(Access::WRITE, Privilege::ADMIN), true);
     }
+
+    #[test]
+    fn test_required_privilege() {
+        let c = Access::RWVA;
+        assert_eq!(c.required_privilege(Access::READ), Some(Privilege::VIEW));
+        assert_eq!(c.required_privilege(Access::WRITE), Some(Privilege::ADMIN));
+        // Not an operation that this object allows
+        assert_eq!(c.required_privilege(Access::INVOKE), None);
+
+        let c = Access::IO;
+        assert_eq!(
+            c.required_privilege(Access::INVOKE),
+            Some(Privilege::OPERATE)
+        );
+    }
 }
@@ -16,22 +16,22 @@
  */
 
 use crate::{
-    acl::AccessReq,
-    data_model::objects::{Access, AttrValue, Attribute, EncodeValue, Quality},
+    acl::{AccessReq, Accessor, AuthMode},
+    data_model::objects::{Access, AttrValue, Attribute, Command, EncodeValue, Quality},
     error::*,
     interaction_model::{command::CommandReq, core::IMStatusCode},
     // TODO: This layer shouldn't really depend on the TLV layer, should create an abstraction layer
     tlv::{Nullable, TLVElement, TLVWriter, TagType},
 };
-use log::error;
+use log::{debug, error, log_enabled, Level};
 use num_derive::FromPrimitive;
 use rand::Rng;
 use std::fmt::{self, Debug};
 
-use super::{AttrId, ClusterId, Encoder};
+use super::{AttrId, ClusterId, CmdId, Encoder};
 
 pub const ATTRS_PER_CLUSTER: usize = 10;
-pub const CMDS_PER_CLUSTER: usize = 8;
+pub const CMDS_PER_CLUSTER: usize = 12;
 
 #[derive(FromPrimitive, Debug)]
 pub enum GlobalElements {
@@ -57,15 +57,21 @@ pub struct AttrDetails {
     pub list_index: Option<Nullable<u16>>,
     /// The actual attribute ID
     pub attr_id: AttrId,
+    /// The Authmode of the accessor
+    pub auth_mode: AuthMode,
+    /// The node ID of the accessor, only meaningful for CASE sessions
+    pub node_id: u64,
 }
 
 impl AttrDetails {
-    pub fn new(fab_idx: u8, fab_filter: bool) -> Self {
+    pub fn new(accessor: &Accessor, fab_filter: bool) -> Self {
         Self {
             fab_filter,
-            fab_idx,
+            fab_idx: accessor.fab_idx,
             list_index: None,
             attr_id: 0,
+            auth_mode: accessor.auth_mode(),
+            node_id: accessor.node_id(),
         }
     }
 }
@@ -104,6 +110,7 @@ pub trait ClusterType {
 pub struct Cluster {
     pub(super) id: ClusterId,
     attributes: Vec<Attribute>,
+    commands: Vec<Command>,
     data_ver: u32,
 }
 
@@ -112,6 +119,7 @@ impl Cluster {
         let mut c = Cluster {
             id,
             attributes: Vec::with_capacity(ATTRS_PER_CLUSTER),
+            commands: Vec::with_capacity(CMDS_PER_CLUSTER),
             data_ver: rand::thread_rng().gen_range(0..0xFFFFFFFF),
         };
         c.add_default_attributes()?;
@@ -167,6 +175,19 @@ impl Cluster {
         }
     }
 
+    pub fn add_commands(&mut self, cmds: &[Command]) -> Result<(), Error> {
+        if self.commands.len() + cmds.len() <= self.commands.capacity() {
+            self.commands.extend_from_slice(cmds);
+            Ok(())
+        } else {
+            Err(Error::NoSpace)
+        }
+    }
+
+    fn get_command(&self, cmd_id: CmdId) -> Option<&Command> {
+        self.commands.iter().find(|c| c.id == cmd_id)
+    }
+
     fn get_attribute_index(&self, attr_id: AttrId) -> Option<usize> {
         self.attributes.iter().position(|c| c.id == attr_id)
     }
@@ -221,7 +242,7 @@ impl Cluster {
         }
 
         access_req.set_target_perms(a.access);
-        if !access_req.allow() {
+        if !Self::access_allowed(access_req) {
             error = IMStatusCode::UnsupportedAccess;
         }
 
@@ -236,6 +257,50 @@ impl Cluster {
         }
     }
 
+    // With debug logs enabled, a denial also logs how each ACL entry was evaluated
+    fn access_allowed(access_req: &AccessReq) -> bool {
+        if access_req.allow() {
+            return true;
+        }
+        if log_enabled!(Level::Debug) {
+            debug!("Access denied: {:?}", access_req.explain());
+        }
+        false
+    }
+
+    /// Invoke a command, if the accessor has the privilege that the command needs
+    ///
+    /// _timed_ is whether the invoke is part of a timed interaction. The commands
+    /// that aren't declared with [Cluster::add_commands] need the Operate privilege,
+    /// and it is up to [ClusterType::handle_command] whether they exist.
+    pub fn invoke_command(
+        c: &mut dyn ClusterType,
+        access_req: &mut AccessReq,
+        cmd_req: &mut CommandReq,
+        timed: bool,
+    ) -> Result<(), IMStatusCode> {
+        let cmd_id = cmd_req
+            .cmd
+            .path
+            .leaf
+            .ok_or(IMStatusCode::UnsupportedCommand)?;
+        let access = c
+            .base()
+            .get_command(cmd_id)
+            .map_or(Access::IO, |cmd| cmd.access);
+
+        access_req.set_target_perms(access);
+        if !Self::access_allowed(access_req) {
+            return Err(IMStatusCode::UnsupportedAccess);
+        }
+
+        if access.contains(Access::TIMED_ONLY) && !timed {
+            return Err(IMStatusCode::NeedsTimedInteraction);
+        }
+
+        c.handle_command(cmd_req)
+    }
+
     fn encode_attribute_ids(&self, tag: TagType, tw: &mut TLVWriter) {
         let _ = tw.start_array(tag);
         for a in &self.attributes {
@@ -291,7 +356,7 @@ impl Cluster {
         }
 
         access_req.set_target_perms(a.access);
-        if !access_req.allow() {
+        if !Self::access_allowed(access_req) {
             return Err(IMStatusCode::UnsupportedAccess);
         }
 
@@ -0,0 +1,35 @@
+/*
+ *
+ *    Copyright (c) 2020-2022 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use super::{Access, CmdId};
+
+/// A command that a cluster accepts
+///
+/// The access of a command is [Access::INVOKE] along with the privilege that is
+/// required to invoke it, like [Access::IO], and [Access::TIMED_ONLY] if it may only
+/// be invoked in a timed interaction.
+#[derive(Debug, Clone, Copy)]
+pub struct Command {
+    pub id: CmdId,
+    pub access: Access,
+}
+
+impl Command {
+    pub fn new(id: CmdId, access: Access) -> Self {
+        Self { id, access }
+    }
+}
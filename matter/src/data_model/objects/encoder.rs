@@ -116,7 +116,7 @@ pub trait Encoder {
     fn encode_status(&mut self, status: IMStatusCode, cluster_status: u16);
 }
 
-#[derive(ToTLV, Copy, Clone)]
+#[derive(ToTLV, Copy, Clone, Debug, PartialEq)]
 pub struct DeviceType {
     pub dtype: u16,
     pub drev: u16,
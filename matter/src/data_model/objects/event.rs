@@ -0,0 +1,175 @@
+/*
+ *
+ *    Copyright (c) 2020-2022 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    error::Error,
+    tlv::{TLVWriter, TagType, ToTLV},
+};
+
+use super::{Access, ClusterId, EndptId};
+
+pub type EventId = u32;
+
+/// The number of events that are kept, the oldest ones are dropped first
+pub const MAX_EVENTS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventPriority {
+    Debug = 0,
+    Info = 1,
+    Critical = 2,
+}
+
+impl ToTLV for EventPriority {
+    fn to_tlv(&self, tw: &mut TLVWriter, tag: TagType) -> Result<(), Error> {
+        tw.u8(tag, *self as u8)
+    }
+}
+
+/// An event that a cluster raised
+pub struct Event {
+    /// The event number, which increases with every event
+    pub number: u64,
+    pub priority: EventPriority,
+    /// The time of the event, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub endpoint: EndptId,
+    pub cluster: ClusterId,
+    pub id: EventId,
+    /// The privilege that is needed to read the event, like [Access::RV]
+    pub access: Access,
+    /// The fabric of a fabric-sensitive event, which is only reported to that fabric
+    pub fab_idx: Option<u8>,
+    pub data: Box<dyn ToTLV + Send + Sync>,
+}
+
+/// The events that were raised since the device started, as they are read through
+/// the Interaction Model
+///
+/// The event numbers start again from 0 when the device restarts.
+pub struct EventLog {
+    inner: Mutex<EventLogInner>,
+}
+
+struct EventLogInner {
+    next_number: u64,
+    events: VecDeque<Event>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(EventLogInner {
+                next_number: 0,
+                events: VecDeque::with_capacity(MAX_EVENTS),
+            }),
+        }
+    }
+
+    /// Record the event _id_ of _cluster_ on _endpoint_, with the fields _data_,
+    /// returning its event number
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &self,
+        endpoint: EndptId,
+        cluster: ClusterId,
+        id: EventId,
+        priority: EventPriority,
+        access: Access,
+        fab_idx: Option<u8>,
+        data: Box<dyn ToTLV + Send + Sync>,
+    ) -> u64 {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut inner = self.inner.lock().unwrap();
+        let number = inner.next_number;
+        inner.next_number += 1;
+        if inner.events.len() == MAX_EVENTS {
+            inner.events.pop_front();
+        }
+        inner.events.push_back(Event {
+            number,
+            priority,
+            timestamp,
+            endpoint,
+            cluster,
+            id,
+            access,
+            fab_idx,
+            data,
+        });
+        number
+    }
+
+    /// Call _f_ with every event that is kept, from the oldest one, until it
+    /// returns an error
+    pub fn for_each<F>(&self, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&Event) -> Result<(), Error>,
+    {
+        let inner = self.inner.lock().unwrap();
+        for event in inner.events.iter() {
+            f(event)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data_model::objects::Access;
+
+    use super::{EventLog, EventPriority, MAX_EVENTS};
+
+    #[test]
+    fn test_event_log() {
+        let log = EventLog::new();
+        for i in 0..MAX_EVENTS + 2 {
+            let number = log.record(
+                0,
+                0x1f,
+                0,
+                EventPriority::Info,
+                Access::RV,
+                Some(1),
+                Box::new(i as u8),
+            );
+            assert_eq!(number, i as u64);
+        }
+
+        // The oldest events are dropped
+        let mut numbers = Vec::new();
+        log.for_each(|e| {
+            numbers.push(e.number);
+            Ok(())
+        })
+        .unwrap();
+        let expected: Vec<u64> = (2..MAX_EVENTS as u64 + 2).collect();
+        assert_eq!(numbers, expected);
+    }
+}
@@ -26,9 +26,15 @@ pub use attribute::*;
 mod cluster;
 pub use cluster::*;
 
+mod command;
+pub use command::*;
+
 mod endpoint;
 pub use endpoint::*;
 
+mod event;
+pub use event::*;
+
 mod node;
 pub use node::*;
 
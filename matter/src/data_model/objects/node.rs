@@ -75,6 +75,15 @@ impl Node {
         Ok(index as EndptId)
     }
 
+    /// The device type of every endpoint, indexed by the endpoint ID
+    pub fn get_dev_types(&self) -> [Option<DeviceType>; ENDPTS_PER_ACC] {
+        let mut dev_types = [None; ENDPTS_PER_ACC];
+        for (dev_type, e) in dev_types.iter_mut().zip(self.endpoints.iter()) {
+            *dev_type = e.as_ref().map(|e| *e.get_dev_type());
+        }
+        dev_types
+    }
+
     pub fn get_endpoint(&self, endpoint_id: EndptId) -> Result<&Endpoint, Error> {
         if (endpoint_id as usize) < ENDPTS_PER_ACC {
             let endpoint = self.endpoints[endpoint_id as usize]
@@ -15,18 +15,36 @@
  *    limitations under the License.
  */
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use crate::cmd_enter;
 use crate::data_model::objects::*;
+use crate::fabric::FabricMgr;
 use crate::interaction_model::core::IMStatusCode;
-use crate::secure_channel::pake::PaseMgr;
-use crate::secure_channel::spake2p::VerifierData;
-use crate::tlv::{FromTLV, Nullable, OctetStr, TLVElement};
+use crate::interaction_model::messages::ib;
+use crate::mdns::CommissioningMode;
+use crate::secure_channel::pake::{PaseMgr, MAX_COMM_WINDOW_TIMEOUT, MIN_COMM_WINDOW_TIMEOUT};
+use crate::secure_channel::spake2p::{VerifierData, MAX_SALT_SIZE_BYTES, VERIFIER_SIZE_BYTES};
+use crate::tlv::{FromTLV, Nullable, OctetStr, TLVElement, TagType, ToTLV};
 use crate::{error::*, interaction_model::command::CommandReq};
 use log::{error, info};
 use num_derive::FromPrimitive;
 
 pub const ID: u32 = 0x003C;
 
+const MIN_PBKDF_ITERATIONS: u32 = 1000;
+const MAX_PBKDF_ITERATIONS: u32 = 100000;
+const MIN_SALT_SIZE_BYTES: usize = 16;
+
+// The cluster specific status codes
+#[derive(Clone, Copy)]
+enum AdminCommStatus {
+    Busy = 2,
+    PAKEParameterError = 3,
+    WindowNotOpen = 4,
+}
+
 #[derive(FromPrimitive, Debug, Copy, Clone, PartialEq)]
 pub enum WindowStatus {
     WindowNotOpen = 0,
@@ -77,6 +95,7 @@ fn attr_admin_vid_new() -> Attribute {
 
 pub struct AdminCommCluster {
     pase_mgr: PaseMgr,
+    fabric_mgr: Arc<FabricMgr>,
     base: Cluster,
 }
 
@@ -91,17 +110,26 @@ impl ClusterType for AdminCommCluster {
     fn read_custom_attribute(&self, encoder: &mut dyn Encoder, attr: &AttrDetails) {
         match num::FromPrimitive::from_u16(attr.attr_id) {
             Some(Attributes::WindowStatus) => {
-                let status = 1_u8;
+                let status = match self.pase_mgr.get_comm_window_mode() {
+                    CommissioningMode::Disabled => WindowStatus::WindowNotOpen,
+                    CommissioningMode::Basic => WindowStatus::BasicWindowOpen,
+                    CommissioningMode::Enhanced => WindowStatus::EnhancedWindowOpen,
+                } as u8;
                 encoder.encode(EncodeValue::Value(&status))
             }
             Some(Attributes::AdminVendorId) => {
-                let vid = Nullable::NotNull(1_u8);
-
+                let vid = match self.get_admin_vendor_id() {
+                    Some(vid) => Nullable::NotNull(vid),
+                    None => Nullable::Null,
+                };
                 encoder.encode(EncodeValue::Value(&vid))
             }
             Some(Attributes::AdminFabricIndex) => {
-                let vid = Nullable::NotNull(1_u8);
-                encoder.encode(EncodeValue::Value(&vid))
+                let fab_idx = match self.pase_mgr.get_comm_window_admin() {
+                    Some(fab_idx) => Nullable::NotNull(fab_idx),
+                    None => Nullable::Null,
+                };
+                encoder.encode(EncodeValue::Value(&fab_idx))
             }
             _ => {
                 error!("Unsupported Attribute: this shouldn't happen");
@@ -118,23 +146,51 @@ impl ClusterType for AdminCommCluster {
             .ok_or(IMStatusCode::UnsupportedCommand)?;
         match cmd {
             Commands::OpenCommWindow => self.handle_command_opencomm_win(cmd_req),
+            Commands::RevokeComm => self.handle_command_revoke_comm(cmd_req),
             _ => Err(IMStatusCode::UnsupportedCommand),
         }
     }
 }
 
 impl AdminCommCluster {
-    pub fn new(pase_mgr: PaseMgr) -> Result<Box<Self>, Error> {
+    pub fn new(pase_mgr: PaseMgr, fabric_mgr: Arc<FabricMgr>) -> Result<Box<Self>, Error> {
         let mut c = Box::new(AdminCommCluster {
             pase_mgr,
+            fabric_mgr,
             base: Cluster::new(ID)?,
         });
         c.base.add_attribute(attr_window_status_new())?;
         c.base.add_attribute(attr_admin_fabid_new())?;
         c.base.add_attribute(attr_admin_vid_new())?;
+        c.base.add_commands(&[
+            Command::new(
+                Commands::OpenCommWindow as CmdId,
+                Access::IA | Access::TIMED_ONLY,
+            ),
+            Command::new(
+                Commands::OpenBasicCommWindow as CmdId,
+                Access::IA | Access::TIMED_ONLY,
+            ),
+            Command::new(
+                Commands::RevokeComm as CmdId,
+                Access::IA | Access::TIMED_ONLY,
+            ),
+        ])?;
         Ok(c)
     }
 
+    fn get_admin_vendor_id(&self) -> Option<u16> {
+        let fab_idx = self.pase_mgr.get_comm_window_admin()?;
+        let fabric = self.fabric_mgr.get_fabric(fab_idx).ok()?;
+        Some(fabric.get_vendor_id())
+    }
+
+    fn send_cluster_status(cmd_req: &mut CommandReq, status: IMStatusCode, cluster_status: u16) {
+        let invoke_resp = ib::InvResp::status_new(cmd_req.cmd, status, cluster_status);
+        let _ = invoke_resp.to_tlv(cmd_req.resp, TagType::Anonymous);
+        cmd_req.trans.complete();
+    }
+
     fn handle_command_opencomm_win(
         &mut self,
         cmd_req: &mut CommandReq,
@@ -142,9 +198,56 @@ impl AdminCommCluster {
         cmd_enter!("Open Commissioning Window");
         let req =
             OpenCommWindowReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
+
+        let timeout = Duration::from_secs(req.timeout as u64);
+        if !(MIN_COMM_WINDOW_TIMEOUT..=MAX_COMM_WINDOW_TIMEOUT).contains(&timeout) {
+            error!("Invalid commissioning timeout: {}", req.timeout);
+            return Err(IMStatusCode::InvalidCommand);
+        }
+
+        if self.pase_mgr.get_comm_window_mode() != CommissioningMode::Disabled {
+            error!("Commissioning window is already open");
+            Self::send_cluster_status(cmd_req, IMStatusCode::Failure, AdminCommStatus::Busy as u16);
+            return Ok(());
+        }
+
+        if !(MIN_PBKDF_ITERATIONS..=MAX_PBKDF_ITERATIONS).contains(&req.iterations)
+            || !(MIN_SALT_SIZE_BYTES..=MAX_SALT_SIZE_BYTES).contains(&req.salt.0.len())
+            || req.verifier.0.len() != VERIFIER_SIZE_BYTES
+        {
+            error!("Invalid PAKE parameters");
+            Self::send_cluster_status(
+                cmd_req,
+                IMStatusCode::Failure,
+                AdminCommStatus::PAKEParameterError as u16,
+            );
+            return Ok(());
+        }
+
+        let admin_fab_idx = cmd_req.trans.session.get_local_fabric_idx();
         let verifier = VerifierData::new(req.verifier.0, req.iterations, req.salt.0);
-        self.pase_mgr
-            .enable_pase_session(verifier, req.discriminator)?;
+        self.pase_mgr.enable_pase_session(
+            verifier,
+            req.discriminator,
+            CommissioningMode::Enhanced,
+            Some(timeout),
+            admin_fab_idx,
+        )?;
+        Err(IMStatusCode::Success)
+    }
+
+    fn handle_command_revoke_comm(&mut self, cmd_req: &mut CommandReq) -> Result<(), IMStatusCode> {
+        cmd_enter!("Revoke Commissioning");
+        if self.pase_mgr.get_comm_window_mode() == CommissioningMode::Disabled {
+            error!("No commissioning window is open");
+            Self::send_cluster_status(
+                cmd_req,
+                IMStatusCode::Failure,
+                AdminCommStatus::WindowNotOpen as u16,
+            );
+            return Ok(());
+        }
+        self.pase_mgr.disable_pase_session();
         Err(IMStatusCode::Success)
     }
 }
@@ -152,7 +255,7 @@ impl AdminCommCluster {
 #[derive(FromTLV)]
 #[tlvargs(lifetime = "'a")]
 pub struct OpenCommWindowReq<'a> {
-    _timeout: u16,
+    timeout: u16,
     verifier: OctetStr<'a>,
     discriminator: u16,
     iterations: u32,
@@ -15,12 +15,23 @@
  *    limitations under the License.
  */
 
-use crate::{error::Error, transport::session::SessionMode};
-use log::error;
-use std::sync::RwLock;
+use crate::{
+    error::Error,
+    fabric::FabricMgr,
+    transport::{
+        queue::{Msg, WorkQ},
+        session::SessionMode,
+    },
+};
+use log::{error, info};
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use super::general_commissioning::RegLocationType;
 
 #[derive(PartialEq)]
-#[allow(dead_code)]
 #[allow(clippy::enum_variant_names)]
 enum NocState {
     NocNotRecvd,
@@ -32,8 +43,12 @@ enum NocState {
 #[derive(PartialEq)]
 pub struct ArmedCtx {
     session_mode: SessionMode,
-    timeout: u8,
+    expiry: Instant,
     noc_state: NocState,
+    // Whether AddTrustedRootCert was received
+    root_added: bool,
+    // The Regulatory Config to restore on expiry
+    reg_config: u8,
 }
 
 #[derive(PartialEq)]
@@ -44,40 +59,95 @@ pub enum State {
 
 pub struct FailSafeInner {
     state: State,
+    // The Breadcrumb attribute of the General Commissioning cluster
+    breadcrumb: u64,
+    // The RegulatoryConfig attribute of the General Commissioning cluster
+    reg_config: u8,
 }
 
+/// The fail-safe context of commissioning
+///
+/// Once armed, the changes of the commissioner are rolled back unless
+/// CommissioningComplete is received before the timer expires. The timer is
+/// checked on every [FailSafe::handle_timer_tick].
 pub struct FailSafe {
     state: RwLock<FailSafeInner>,
+    // What is changed under the fail-safe is committed or rolled back here
+    fabric_mgr: Arc<FabricMgr>,
 }
 
 impl FailSafe {
-    pub fn new() -> Self {
+    pub fn new(fabric_mgr: Arc<FabricMgr>) -> Self {
         Self {
-            state: RwLock::new(FailSafeInner { state: State::Idle }),
+            state: RwLock::new(FailSafeInner {
+                state: State::Idle,
+                breadcrumb: 0,
+                // TODO: Arch-Specific
+                reg_config: RegLocationType::IndoorOutdoor as u8,
+            }),
+            fabric_mgr,
         }
     }
 
-    pub fn arm(&self, timeout: u8, session_mode: SessionMode) -> Result<(), Error> {
-        let mut inner = self.state.write()?;
+    /// Arm the fail-safe for _timeout_ seconds, or extend it if it is already
+    /// armed by the same session
+    pub fn arm(
+        &self,
+        timeout: u16,
+        breadcrumb: u64,
+        session_mode: SessionMode,
+    ) -> Result<(), Error> {
+        let mut guard = self.state.write()?;
+        let inner = &mut *guard;
+        let expiry = Instant::now() + Duration::from_secs(timeout.into());
         match &mut inner.state {
+            // Arming with a zero timeout expires the fail-safe, which is a no-op
+            // when it isn't armed
+            State::Idle if timeout == 0 => (),
             State::Idle => {
                 inner.state = State::Armed(ArmedCtx {
                     session_mode,
-                    timeout,
+                    expiry,
                     noc_state: NocState::NocNotRecvd,
+                    root_added: false,
+                    reg_config: inner.reg_config,
                 })
             }
             State::Armed(c) => {
                 if c.session_mode != session_mode {
                     return Err(Error::Invalid);
                 }
-                // re-arm
-                c.timeout = timeout;
+                if timeout != 0 {
+                    // re-arm
+                    c.expiry = expiry;
+                }
+            }
+        }
+        if timeout == 0 {
+            if let Some(c) = Self::take_armed(inner) {
+                drop(guard);
+                self.roll_back(c);
+                return Ok(());
             }
         }
+        inner.breadcrumb = breadcrumb;
         Ok(())
     }
 
+    /// Expire the fail-safe once its timer runs out
+    pub fn handle_timer_tick(&self) {
+        let armed = {
+            let mut inner = self.state.write().unwrap();
+            match &inner.state {
+                State::Armed(c) if Instant::now() >= c.expiry => Self::take_armed(&mut inner),
+                _ => None,
+            }
+        };
+        if let Some(c) = armed {
+            self.roll_back(c);
+        }
+    }
+
     pub fn disarm(&self, session_mode: SessionMode) -> Result<(), Error> {
         let mut inner = self.state.write()?;
         match &mut inner.state {
@@ -86,7 +156,7 @@ impl FailSafe {
                 return Err(Error::Invalid);
             }
             State::Armed(c) => {
-                match c.noc_state {
+                let fab_idx = match c.noc_state {
                     NocState::NocNotRecvd => return Err(Error::Invalid),
                     NocState::AddNocRecvd(idx) | NocState::UpdateNocRecvd(idx) => {
                         if let SessionMode::Case(c) = session_mode {
@@ -100,14 +170,82 @@ impl FailSafe {
                             error!("Received disarm in a non-CASE session");
                             return Err(Error::Invalid);
                         }
+                        idx
                     }
-                }
+                };
+                let updated = c.noc_state == NocState::UpdateNocRecvd(fab_idx);
                 inner.state = State::Idle;
+                inner.breadcrumb = 0;
+                if updated {
+                    self.fabric_mgr.commit_update(fab_idx)?;
+                }
             }
         }
         Ok(())
     }
 
+    // Expire the fail-safe, restoring the attributes that it tracks
+    //
+    // The rest is rolled back by [FailSafe::roll_back], which must run once the
+    // state lock is released: removing a fabric notifies its listeners, and they
+    // may call back into the fail-safe
+    fn take_armed(inner: &mut FailSafeInner) -> Option<ArmedCtx> {
+        let c = match std::mem::replace(&mut inner.state, State::Idle) {
+            State::Armed(c) => c,
+            State::Idle => return None,
+        };
+        inner.reg_config = c.reg_config;
+        inner.breadcrumb = 0;
+        Some(c)
+    }
+
+    // Roll back what was done under the fail-safe
+    fn roll_back(&self, c: ArmedCtx) {
+        match c.noc_state {
+            NocState::AddNocRecvd(fab_idx) => {
+                // This also removes the ACLs that were added for the fabric
+                if let Err(e) = self.fabric_mgr.remove(fab_idx) {
+                    error!("Error removing the added fabric {}: {}", fab_idx, e);
+                }
+            }
+            NocState::UpdateNocRecvd(fab_idx) => {
+                if let Err(e) = self.fabric_mgr.revert_update(fab_idx) {
+                    error!(
+                        "Error reverting the NOC update of fabric {}: {}",
+                        fab_idx, e
+                    );
+                }
+            }
+            NocState::NocNotRecvd => (),
+        }
+        if let Err(e) = self.fabric_mgr.trust_store().clear_pending_root() {
+            error!("Error clearing the pending trusted root: {}", e);
+        }
+
+        // This runs in the transport's loop, which is the receiver of the queue
+        if let Err(e) = WorkQ::get().and_then(|q| q.try_send(Msg::ClosePaseSessions)) {
+            error!("Error closing the PASE sessions: {}", e);
+        }
+        info!("Fail-Safe expired, the commissioning changes were rolled back");
+    }
+
+    pub fn breadcrumb(&self) -> u64 {
+        self.state.read().unwrap().breadcrumb
+    }
+
+    pub fn set_breadcrumb(&self, breadcrumb: u64) {
+        self.state.write().unwrap().breadcrumb = breadcrumb;
+    }
+
+    pub fn reg_config(&self) -> u8 {
+        self.state.read().unwrap().reg_config
+    }
+
+    /// Set the Regulatory Config, which is restored if the fail-safe expires
+    pub fn set_reg_config(&self, reg_config: u8) {
+        self.state.write().unwrap().reg_config = reg_config;
+    }
+
     pub fn is_armed(&self) -> bool {
         self.state.read().unwrap().state != State::Idle
     }
@@ -127,6 +265,37 @@ impl FailSafe {
         }
     }
 
+    pub fn record_update_noc(&self, fabric_index: u8) -> Result<(), Error> {
+        let mut inner = self.state.write()?;
+        match &mut inner.state {
+            State::Armed(c) if c.noc_state == NocState::NocNotRecvd => {
+                c.noc_state = NocState::UpdateNocRecvd(fabric_index);
+                Ok(())
+            }
+            _ => Err(Error::Invalid),
+        }
+    }
+
+    /// Whether AddTrustedRootCert is allowed: only a single root can be added in
+    /// each fail-safe context, before the NOC
+    pub fn allow_root_add(&self) -> bool {
+        match &self.state.read().unwrap().state {
+            State::Idle => false,
+            State::Armed(c) => !c.root_added && c.noc_state == NocState::NocNotRecvd,
+        }
+    }
+
+    pub fn record_add_root(&self) -> Result<(), Error> {
+        let mut inner = self.state.write()?;
+        match &mut inner.state {
+            State::Armed(c) if !c.root_added && c.noc_state == NocState::NocNotRecvd => {
+                c.root_added = true;
+                Ok(())
+            }
+            _ => Err(Error::Invalid),
+        }
+    }
+
     pub fn allow_noc_change(&self) -> Result<bool, Error> {
         let mut inner = self.state.write()?;
         let allow = match &mut inner.state {
@@ -137,8 +306,117 @@ impl FailSafe {
     }
 }
 
-impl Default for FailSafe {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Instant};
+
+    use crate::{
+        data_model::sdm::general_commissioning::RegLocationType,
+        fabric::{tests::TestCa, FabricMgr},
+        transport::session::{CaseDetails, SessionMode},
+    };
+
+    use super::{FailSafe, State};
+
+    // Let the timer of the fail-safe run out
+    fn run_out(fs: &FailSafe) {
+        if let State::Armed(c) = &mut fs.state.write().unwrap().state {
+            c.expiry = Instant::now();
+        }
+        fs.handle_timer_tick();
+    }
+
+    #[test]
+    fn test_expiry_removes_added_fabric() {
+        let fm = Arc::new(FabricMgr::new_with(false).unwrap());
+        let fs = FailSafe::new(fm.clone());
+        let reg_config = fs.reg_config();
+        let ca = TestCa::new(1, 0xfab);
+
+        fs.arm(60, 1, SessionMode::Pase).unwrap();
+        fs.set_reg_config(RegLocationType::Indoor as u8);
+        let mut rcac = [0u8; 1024];
+        let len = ca.rcac().as_tlv(&mut rcac).unwrap();
+        fm.trust_store().add_pending_root(&rcac[..len]).unwrap();
+        fs.record_add_root().unwrap();
+        let fab_idx = fm.add(ca.fabric(0x1234)).unwrap();
+        fs.record_add_noc(fab_idx).unwrap();
+        fs.set_breadcrumb(2);
+
+        // The timer hasn't run out yet
+        fs.handle_timer_tick();
+        assert!(fs.is_armed());
+        assert!(fm.get_fabric(fab_idx).is_ok());
+
+        run_out(&fs);
+        assert!(!fs.is_armed());
+        assert!(fm.get_fabric(fab_idx).is_err());
+        assert!(fm.trust_store().get_root(fab_idx).is_err());
+        assert!(fm.trust_store().pending_root().is_err());
+        assert_eq!(fs.reg_config(), reg_config);
+        assert_eq!(fs.breadcrumb(), 0);
+    }
+
+    #[test]
+    fn test_arm_zero_removes_added_fabric() {
+        let fm = Arc::new(FabricMgr::new_with(false).unwrap());
+        let fs = FailSafe::new(fm.clone());
+        let ca = TestCa::new(2, 0xfab);
+
+        fs.arm(60, 1, SessionMode::Pase).unwrap();
+        let fab_idx = fm.add(ca.fabric(0x1234)).unwrap();
+        fs.record_add_noc(fab_idx).unwrap();
+
+        // Arming with a zero timeout expires the fail-safe right away
+        fs.arm(0, 3, SessionMode::Pase).unwrap();
+        assert!(!fs.is_armed());
+        assert!(fm.get_fabric(fab_idx).is_err());
+        assert_eq!(fs.breadcrumb(), 0);
+    }
+
+    #[test]
+    fn test_expiry_reverts_updated_noc() {
+        let fm = Arc::new(FabricMgr::new_with(false).unwrap());
+        let fs = FailSafe::new(fm.clone());
+        let ca = TestCa::new(3, 0xfab);
+        let fab_idx = fm.add(ca.fabric(0x1234)).unwrap();
+
+        let session_mode = SessionMode::Case(CaseDetails::new(fab_idx, &[0; 3]));
+        fs.arm(60, 1, session_mode).unwrap();
+        let (key_pair, noc) = ca.noc(0x5678);
+        fm.update(fab_idx, key_pair, None, noc).unwrap();
+        fs.record_update_noc(fab_idx).unwrap();
+        assert_eq!(fm.get_fabric(fab_idx).unwrap().get_node_id(), 0x5678);
+
+        run_out(&fs);
+        assert!(!fs.is_armed());
+        assert_eq!(fm.get_fabric(fab_idx).unwrap().get_node_id(), 0x1234);
+        // Nothing is left pending
+        assert!(fm.commit_update(fab_idx).is_err());
+
+        fm.remove(fab_idx).unwrap();
+    }
+
+    #[test]
+    fn test_disarm_commits_updated_noc() {
+        let fm = Arc::new(FabricMgr::new_with(false).unwrap());
+        let fs = FailSafe::new(fm.clone());
+        let ca = TestCa::new(4, 0xfab);
+        let fab_idx = fm.add(ca.fabric(0x1234)).unwrap();
+
+        let session_mode = SessionMode::Case(CaseDetails::new(fab_idx, &[0; 3]));
+        fs.arm(60, 1, session_mode).unwrap();
+        let (key_pair, noc) = ca.noc(0x5678);
+        fm.update(fab_idx, key_pair, None, noc).unwrap();
+        fs.record_update_noc(fab_idx).unwrap();
+        fs.disarm(session_mode).unwrap();
+
+        // Expiring now doesn't undo the committed update
+        run_out(&fs);
+        assert_eq!(fm.get_fabric(fab_idx).unwrap().get_node_id(), 0x5678);
+        // Nothing is left to revert
+        assert!(fm.revert_update(fab_idx).is_err());
+
+        fm.remove(fab_idx).unwrap();
     }
 }
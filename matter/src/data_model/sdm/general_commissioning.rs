@@ -62,19 +62,19 @@ pub enum RegLocationType {
     IndoorOutdoor = 2,
 }
 
-fn attr_bread_crumb_new(bread_crumb: u64) -> Attribute {
+fn attr_bread_crumb_new() -> Attribute {
     Attribute::new(
         Attributes::BreadCrumb as u16,
-        AttrValue::Uint64(bread_crumb),
+        AttrValue::Custom,
         Access::READ | Access::WRITE | Access::NEED_ADMIN,
         Quality::NONE,
     )
 }
 
-fn attr_reg_config_new(reg_config: RegLocationType) -> Attribute {
+fn attr_reg_config_new() -> Attribute {
     Attribute::new(
         Attributes::RegConfig as u16,
-        AttrValue::Uint8(reg_config as u8),
+        AttrValue::Custom,
         Access::RV,
         Quality::NONE,
     )
@@ -100,8 +100,8 @@ fn attr_comm_info_new() -> Attribute {
 
 #[derive(FromTLV, ToTLV)]
 struct FailSafeParams {
-    expiry_len: u8,
-    bread_crumb: u8,
+    expiry_len: u16,
+    bread_crumb: u64,
 }
 
 pub struct GenCommCluster {
@@ -127,6 +127,12 @@ impl ClusterType for GenCommCluster {
                     let _ = tw.end_container();
                 }))
             }
+            Some(Attributes::BreadCrumb) => {
+                encoder.encode(EncodeValue::Value(&self.failsafe.breadcrumb()))
+            }
+            Some(Attributes::RegConfig) => {
+                encoder.encode(EncodeValue::Value(&self.failsafe.reg_config()))
+            }
             _ => {
                 error!("Unsupported Attribute: this shouldn't happen");
             }
@@ -148,34 +154,47 @@ impl ClusterType for GenCommCluster {
             _ => Err(IMStatusCode::UnsupportedCommand),
         }
     }
+
+    fn write_attribute(
+        &mut self,
+        attr: &AttrDetails,
+        data: &TLVElement,
+    ) -> Result<(), IMStatusCode> {
+        match num::FromPrimitive::from_u16(attr.attr_id) {
+            Some(Attributes::BreadCrumb) => {
+                let breadcrumb = data.u64().map_err(|_| IMStatusCode::ConstraintError)?;
+                self.failsafe.set_breadcrumb(breadcrumb);
+                self.base.cluster_changed();
+                Ok(())
+            }
+            _ => self.base.write_attribute_from_tlv(attr.attr_id, data),
+        }
+    }
 }
 
 impl GenCommCluster {
-    pub fn new() -> Result<Box<Self>, Error> {
-        let failsafe = Arc::new(FailSafe::new());
-
+    pub fn new(failsafe: Arc<FailSafe>) -> Result<Box<Self>, Error> {
         let mut c = Box::new(GenCommCluster {
             // TODO: Arch-Specific
             expiry_len: 120,
             failsafe,
             base: Cluster::new(ID)?,
         });
-        c.base.add_attribute(attr_bread_crumb_new(0))?;
-        // TODO: Arch-Specific
-        c.base
-            .add_attribute(attr_reg_config_new(RegLocationType::IndoorOutdoor))?;
+        c.base.add_attribute(attr_bread_crumb_new())?;
+        c.base.add_attribute(attr_reg_config_new())?;
         // TODO: Arch-Specific
         c.base
             .add_attribute(attr_location_capability_new(RegLocationType::IndoorOutdoor))?;
         c.base.add_attribute(attr_comm_info_new())?;
+        c.base.add_commands(&[
+            Command::new(Commands::ArmFailsafe as CmdId, Access::IA),
+            Command::new(Commands::SetRegulatoryConfig as CmdId, Access::IA),
+            Command::new(Commands::CommissioningComplete as CmdId, Access::IA),
+        ])?;
 
         Ok(c)
     }
 
-    pub fn failsafe(&self) -> Arc<FailSafe> {
-        self.failsafe.clone()
-    }
-
     fn handle_command_armfailsafe(&mut self, cmd_req: &mut CommandReq) -> Result<(), IMStatusCode> {
         cmd_enter!("ARM Fail Safe");
 
@@ -184,7 +203,11 @@ impl GenCommCluster {
 
         if self
             .failsafe
-            .arm(p.expiry_len, cmd_req.trans.session.get_session_mode())
+            .arm(
+                p.expiry_len,
+                p.bread_crumb,
+                cmd_req.trans.session.get_session_mode(),
+            )
             .is_err()
         {
             status = CommissioningError::ErrBusyWithOtherAdmin as u8;
@@ -210,16 +233,38 @@ impl GenCommCluster {
         cmd_req: &mut CommandReq,
     ) -> Result<(), IMStatusCode> {
         cmd_enter!("Set Regulatory Config");
+        let reg_config = cmd_req
+            .data
+            .find_tag(0)
+            .map_err(|_| IMStatusCode::InvalidCommand)?
+            .u8()
+            .map_err(|_| IMStatusCode::InvalidCommand)?;
         let country_code = cmd_req
             .data
             .find_tag(1)
             .map_err(|_| IMStatusCode::InvalidCommand)?
             .slice()
             .map_err(|_| IMStatusCode::InvalidCommand)?;
+        let breadcrumb = cmd_req
+            .data
+            .find_tag(2)
+            .map_err(|_| IMStatusCode::InvalidCommand)?
+            .u64()
+            .map_err(|_| IMStatusCode::InvalidCommand)?;
         info!("Received country code: {:?}", country_code);
 
+        let mut status = CommissioningError::Ok as u8;
+        // The location capability is IndoorOutdoor, so any of the locations can be
+        // configured
+        if reg_config > RegLocationType::IndoorOutdoor as u8 {
+            status = CommissioningError::ErrValueOutsideRange as u8;
+        } else {
+            self.failsafe.set_reg_config(reg_config);
+            self.failsafe.set_breadcrumb(breadcrumb);
+        }
+
         let cmd_data = CommonResponse {
-            error_code: 0,
+            error_code: status,
             debug_txt: "".to_owned(),
         };
         let resp = ib::InvResp::cmd_new(
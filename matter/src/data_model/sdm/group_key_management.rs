@@ -0,0 +1,479 @@
+/*
+ *
+ *    Copyright (c) 2020-2022 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use crate::data_model::objects::*;
+use crate::fabric::FabricMgr;
+use crate::group_keys::{
+    GroupKeyMapEntry, GroupKeys, KeySet, IPK_KEY_SET_ID, MAX_GROUPS_PER_FABRIC,
+    MAX_GROUP_KEYS_PER_FABRIC,
+};
+use crate::interaction_model::command::CommandReq;
+use crate::interaction_model::core::IMStatusCode;
+use crate::interaction_model::messages::ib::{self, attr_list_write, ListOperation};
+use crate::tlv::{FromTLV, TLVElement, TagType, ToTLV};
+use crate::{cmd_enter, error::*};
+use log::{error, info};
+use num_derive::FromPrimitive;
+
+// Group Key Management Cluster
+
+pub const ID: u32 = 0x003F;
+
+#[derive(FromPrimitive)]
+pub enum Attributes {
+    GroupKeyMap = 0,
+    GroupTable = 1,
+    MaxGroupsPerFabric = 2,
+    MaxGroupKeysPerFabric = 3,
+}
+
+#[derive(FromPrimitive)]
+pub enum Commands {
+    KeySetWrite = 0,
+    KeySetRead = 1,
+    KeySetReadResp = 2,
+    KeySetRemove = 3,
+    KeySetReadAllIndices = 4,
+    KeySetReadAllIndicesResp = 5,
+}
+
+#[derive(FromTLV)]
+struct KeySetIdReq {
+    key_set_id: u16,
+}
+
+// How the failures of the group keys are reported to the client
+fn status_from(e: Error) -> IMStatusCode {
+    match e {
+        Error::InvalidArgument | Error::Invalid | Error::Duplicate => IMStatusCode::ConstraintError,
+        Error::NoSpace => IMStatusCode::ResourceExhausted,
+        Error::NotFound => IMStatusCode::NotFound,
+        _ => IMStatusCode::Failure,
+    }
+}
+
+pub struct GrpKeyMgmtCluster {
+    base: Cluster,
+    fabric_mgr: Arc<FabricMgr>,
+}
+
+impl GrpKeyMgmtCluster {
+    pub fn new(fabric_mgr: Arc<FabricMgr>) -> Result<Box<Self>, Error> {
+        let mut c = Box::new(Self {
+            base: Cluster::new(ID)?,
+            fabric_mgr,
+        });
+        c.base.add_attribute(attr_group_key_map_new())?;
+        c.base.add_attribute(attr_group_table_new())?;
+        c.base.add_attribute(attr_max_groups_per_fabric_new())?;
+        c.base.add_attribute(attr_max_group_keys_per_fabric_new())?;
+        c.base.add_commands(&[
+            Command::new(Commands::KeySetWrite as CmdId, Access::IA),
+            Command::new(Commands::KeySetRead as CmdId, Access::IA),
+            Command::new(Commands::KeySetRemove as CmdId, Access::IA),
+            Command::new(Commands::KeySetReadAllIndices as CmdId, Access::IA),
+        ])?;
+        Ok(c)
+    }
+
+    // The key sets are scoped to the fabric of the accessing CASE session
+    fn accessing_fabric(cmd_req: &CommandReq) -> Result<u8, IMStatusCode> {
+        cmd_req
+            .trans
+            .session
+            .get_local_fabric_idx()
+            .ok_or(IMStatusCode::UnsupportedAccess)
+    }
+
+    fn handle_command_keyset_write(
+        &mut self,
+        cmd_req: &mut CommandReq,
+    ) -> Result<(), IMStatusCode> {
+        cmd_enter!("KeySetWrite");
+        let fab_idx = Self::accessing_fabric(cmd_req)?;
+        let compressed_id = self
+            .fabric_mgr
+            .get_fabric(fab_idx)
+            .map_err(|_| IMStatusCode::UnsupportedAccess)?
+            .get_compressed_fabric_id()
+            .to_owned();
+
+        let key_set = cmd_req
+            .data
+            .find_tag(0)
+            .map_err(|_| IMStatusCode::InvalidCommand)?;
+        let key_set = KeySet::parse(&key_set, &compressed_id).map_err(|e| match e {
+            Error::InvalidArgument => IMStatusCode::ConstraintError,
+            _ => IMStatusCode::InvalidCommand,
+        })?;
+        if key_set.id() == IPK_KEY_SET_ID {
+            error!("The IPK can't be written with KeySetWrite");
+            return Err(IMStatusCode::InvalidCommand);
+        }
+
+        info!("Writing key set {} of fabric {}", key_set.id(), fab_idx);
+        self.fabric_mgr
+            .update_group_keys(fab_idx, |gk| gk.set_key_set(key_set))
+            .map_err(status_from)?;
+        cmd_req.trans.complete();
+        Err(IMStatusCode::Success)
+    }
+
+    fn handle_command_keyset_read(&mut self, cmd_req: &mut CommandReq) -> Result<(), IMStatusCode> {
+        cmd_enter!("KeySetRead");
+        let fab_idx = Self::accessing_fabric(cmd_req)?;
+        let req = KeySetIdReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
+
+        let fabric = self
+            .fabric_mgr
+            .get_fabric(fab_idx)
+            .map_err(|_| IMStatusCode::UnsupportedAccess)?;
+        let key_set = fabric
+            .group_keys()
+            .get_key_set(req.key_set_id)
+            .ok_or(IMStatusCode::NotFound)?;
+
+        // The epoch keys themselves are never read back
+        let _ = ib::InvResp::cmd_new(
+            0,
+            ID,
+            Commands::KeySetReadResp as u16,
+            EncodeValue::Closure(&|tag, tw| {
+                let _ = tw.start_struct(tag);
+                let _ = key_set.to_tlv_with(tw, TagType::Context(0), false);
+                let _ = tw.end_container();
+            }),
+        )
+        .to_tlv(cmd_req.resp, TagType::Anonymous);
+        cmd_req.trans.complete();
+        Ok(())
+    }
+
+    fn handle_command_keyset_remove(
+        &mut self,
+        cmd_req: &mut CommandReq,
+    ) -> Result<(), IMStatusCode> {
+        cmd_enter!("KeySetRemove");
+        let fab_idx = Self::accessing_fabric(cmd_req)?;
+        let req = KeySetIdReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
+        if req.key_set_id == IPK_KEY_SET_ID {
+            error!("The IPK can't be removed");
+            return Err(IMStatusCode::InvalidCommand);
+        }
+
+        info!("Removing key set {} of fabric {}", req.key_set_id, fab_idx);
+        self.fabric_mgr
+            .update_group_keys(fab_idx, |gk| gk.remove_key_set(req.key_set_id))
+            .map_err(status_from)?;
+        self.base.cluster_changed();
+        cmd_req.trans.complete();
+        Err(IMStatusCode::Success)
+    }
+
+    fn handle_command_keyset_read_all_indices(
+        &mut self,
+        cmd_req: &mut CommandReq,
+    ) -> Result<(), IMStatusCode> {
+        cmd_enter!("KeySetReadAllIndices");
+        let fab_idx = Self::accessing_fabric(cmd_req)?;
+        let fabric = self
+            .fabric_mgr
+            .get_fabric(fab_idx)
+            .map_err(|_| IMStatusCode::UnsupportedAccess)?;
+
+        let _ = ib::InvResp::cmd_new(
+            0,
+            ID,
+            Commands::KeySetReadAllIndicesResp as u16,
+            EncodeValue::Closure(&|tag, tw| {
+                let _ = tw.start_struct(tag);
+                let _ = tw.start_array(TagType::Context(0));
+                for key_set in fabric.group_keys().key_sets() {
+                    let _ = tw.u16(TagType::Anonymous, key_set.id());
+                }
+                let _ = tw.end_container();
+                let _ = tw.end_container();
+            }),
+        )
+        .to_tlv(cmd_req.resp, TagType::Anonymous);
+        cmd_req.trans.complete();
+        Ok(())
+    }
+
+    /// Write the GroupKeyMap Attribute
+    ///
+    /// The entries are those of the accessing fabric, and must map a group to a key
+    /// set, other than the IPK, that the fabric has
+    fn write_key_map_attr(
+        &mut self,
+        op: &ListOperation,
+        data: &TLVElement,
+        fab_idx: u8,
+    ) -> Result<(), IMStatusCode> {
+        info!("Performing GroupKeyMap operation {:?}", op);
+        let entry = match op {
+            ListOperation::AddItem | ListOperation::EditItem(_) => {
+                Some(GroupKeyMapEntry::from_tlv(data).map_err(|_| IMStatusCode::InvalidDataType)?)
+            }
+            _ => None,
+        };
+        self.fabric_mgr
+            .update_group_keys(fab_idx, |gk| write_key_map(gk, op, entry))
+            .map_err(status_from)
+    }
+}
+
+// Check that the whole _list_ can replace the GroupKeyMap of the fabric _fab_idx_, by
+// writing it to a copy of its group keys
+fn check_key_map(
+    fabric_mgr: &FabricMgr,
+    list: &TLVElement,
+    fab_idx: u8,
+) -> Result<(), IMStatusCode> {
+    let mut gk = fabric_mgr
+        .get_fabric(fab_idx)
+        .map_err(status_from)?
+        .group_keys()
+        .clone();
+    gk.clear_key_map();
+    for item in list.enter().ok_or(IMStatusCode::InvalidDataType)? {
+        let entry = GroupKeyMapEntry::from_tlv(&item).map_err(|_| IMStatusCode::InvalidDataType)?;
+        write_key_map(&mut gk, &ListOperation::AddItem, Some(entry)).map_err(status_from)?;
+    }
+    Ok(())
+}
+
+// Apply a list operation of the GroupKeyMap to _gk_
+fn write_key_map(
+    gk: &mut GroupKeys,
+    op: &ListOperation,
+    entry: Option<GroupKeyMapEntry>,
+) -> Result<(), Error> {
+    if let Some(entry) = &entry {
+        if gk.get_key_set(entry.key_set_id).is_none() {
+            return Err(Error::Invalid);
+        }
+    }
+    match (op, entry) {
+        (ListOperation::AddItem, Some(entry)) => gk.add_key_map(entry),
+        (ListOperation::EditItem(index), Some(entry)) => gk.edit_key_map(*index as usize, entry),
+        (ListOperation::DeleteItem(index), _) => gk.delete_key_map(*index as usize),
+        (ListOperation::DeleteList, _) => {
+            gk.clear_key_map();
+            Ok(())
+        }
+        _ => Err(Error::Invalid),
+    }
+}
+
+impl ClusterType for GrpKeyMgmtCluster {
+    fn base(&self) -> &Cluster {
+        &self.base
+    }
+    fn base_mut(&mut self) -> &mut Cluster {
+        &mut self.base
+    }
+
+    fn handle_command(&mut self, cmd_req: &mut CommandReq) -> Result<(), IMStatusCode> {
+        let cmd = cmd_req
+            .cmd
+            .path
+            .leaf
+            .map(num::FromPrimitive::from_u32)
+            .ok_or(IMStatusCode::UnsupportedCommand)?
+            .ok_or(IMStatusCode::UnsupportedCommand)?;
+        match cmd {
+            Commands::KeySetWrite => self.handle_command_keyset_write(cmd_req),
+            Commands::KeySetRead => self.handle_command_keyset_read(cmd_req),
+            Commands::KeySetRemove => self.handle_command_keyset_remove(cmd_req),
+            Commands::KeySetReadAllIndices => self.handle_command_keyset_read_all_indices(cmd_req),
+            _ => Err(IMStatusCode::UnsupportedCommand),
+        }
+    }
+
+    fn read_custom_attribute(&self, encoder: &mut dyn Encoder, attr: &AttrDetails) {
+        match num::FromPrimitive::from_u16(attr.attr_id) {
+            Some(Attributes::GroupKeyMap) => encoder.encode(EncodeValue::Closure(&|tag, tw| {
+                let _ = tw.start_array(tag);
+                let _ = self.fabric_mgr.for_each(|fabric, fab_idx| {
+                    if !attr.fab_filter || attr.fab_idx == fab_idx {
+                        for entry in fabric.group_keys().key_map() {
+                            let entry = GroupKeyMapEntry {
+                                fab_idx: Some(fab_idx),
+                                ..*entry
+                            };
+                            let _ = entry.to_tlv(tw, TagType::Anonymous);
+                        }
+                    }
+                });
+                let _ = tw.end_container();
+            })),
+            Some(Attributes::GroupTable) => encoder.encode(EncodeValue::Closure(&|tag, tw| {
+                let _ = tw.start_array(tag);
+                let _ = self.fabric_mgr.for_each(|fabric, fab_idx| {
+                    if !attr.fab_filter || attr.fab_idx == fab_idx {
+                        for group in fabric.group_keys().groups() {
+                            let _ = group.to_tlv_with(tw, TagType::Anonymous, fab_idx);
+                        }
+                    }
+                });
+                let _ = tw.end_container();
+            })),
+            _ => {
+                error!("Attribute not supported: this shouldn't happen");
+            }
+        }
+    }
+
+    fn write_attribute(
+        &mut self,
+        attr: &AttrDetails,
+        data: &TLVElement,
+    ) -> Result<(), IMStatusCode> {
+        let result = match num::FromPrimitive::from_u16(attr.attr_id) {
+            Some(Attributes::GroupKeyMap) => {
+                if self.fabric_mgr.get_fabric(attr.fab_idx).is_err() {
+                    return Err(IMStatusCode::UnsupportedAccess);
+                }
+                let fabric_mgr = self.fabric_mgr.clone();
+                attr_list_write(
+                    attr,
+                    data,
+                    |list| check_key_map(&fabric_mgr, list, attr.fab_idx),
+                    |op, data| self.write_key_map_attr(&op, data, attr.fab_idx),
+                )
+            }
+            _ => {
+                error!("Attribute not supported: this shouldn't happen");
+                Err(IMStatusCode::NotFound)
+            }
+        };
+        if result.is_ok() {
+            self.base.cluster_changed();
+        }
+        result
+    }
+}
+
+fn attr_group_key_map_new() -> Attribute {
+    Attribute::new(
+        Attributes::GroupKeyMap as u16,
+        AttrValue::Custom,
+        Access::RWVM | Access::FAB_SCOPED,
+        Quality::NONE,
+    )
+}
+
+fn attr_group_table_new() -> Attribute {
+    Attribute::new(
+        Attributes::GroupTable as u16,
+        AttrValue::Custom,
+        Access::RV | Access::FAB_SCOPED,
+        Quality::NONE,
+    )
+}
+
+fn attr_max_groups_per_fabric_new() -> Attribute {
+    Attribute::new(
+        Attributes::MaxGroupsPerFabric as u16,
+        AttrValue::Uint16(MAX_GROUPS_PER_FABRIC as u16),
+        Access::RV,
+        Quality::FIXED,
+    )
+}
+
+fn attr_max_group_keys_per_fabric_new() -> Attribute {
+    Attribute::new(
+        Attributes::MaxGroupKeysPerFabric as u16,
+        AttrValue::Uint16(MAX_GROUP_KEYS_PER_FABRIC as u16),
+        Access::RV,
+        Quality::FIXED,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fabric::{
+            tests::{key_set, TestCa},
+            FabricMgr,
+        },
+        group_keys::GroupKeyMapEntry,
+        interaction_model::core::IMStatusCode,
+        tlv::{get_root_node, TLVWriter, TagType, ToTLV},
+        utils::writebuf::WriteBuf,
+    };
+
+    use super::check_key_map;
+
+    fn check(fm: &FabricMgr, fab_idx: u8, entries: &[(u16, u16)]) -> Result<(), IMStatusCode> {
+        let mut buf = [0u8; 100];
+        let mut wb = WriteBuf::new(&mut buf, 100);
+        let mut tw = TLVWriter::new(&mut wb);
+        tw.start_array(TagType::Anonymous).unwrap();
+        for (group_id, key_set_id) in entries {
+            GroupKeyMapEntry::new(*group_id, *key_set_id)
+                .to_tlv(&mut tw, TagType::Anonymous)
+                .unwrap();
+        }
+        tw.end_container().unwrap();
+        check_key_map(fm, &get_root_node(wb.as_slice()).unwrap(), fab_idx)
+    }
+
+    #[test]
+    fn test_check_key_map() {
+        let fm = FabricMgr::new_with(false).unwrap();
+        let fab_idx = fm.add(TestCa::new(8, 0xfab).fabric(0x1234)).unwrap();
+        let ks = key_set(
+            1,
+            fm.get_fabric(fab_idx).unwrap().get_compressed_fabric_id(),
+        );
+        fm.update_group_keys(fab_idx, |gk| {
+            gk.set_key_set(ks)?;
+            gk.add_key_map(GroupKeyMapEntry::new(0x10, 1))
+        })
+        .unwrap();
+
+        // The new list replaces the current one, so it may reuse its groups
+        assert_eq!(check(&fm, fab_idx, &[(0x10, 1), (0x20, 1)]), Ok(()));
+        assert_eq!(check(&fm, fab_idx, &[]), Ok(()));
+        assert_eq!(
+            check(&fm, fab_idx, &[(0x20, 1), (0x20, 1)]),
+            Err(IMStatusCode::ConstraintError)
+        );
+        assert_eq!(
+            check(&fm, fab_idx, &[(0x20, 1), (0x30, 2)]),
+            Err(IMStatusCode::ConstraintError)
+        );
+        let too_many: Vec<(u16, u16)> = (1..=5).map(|g| (g, 1)).collect();
+        assert_eq!(
+            check(&fm, fab_idx, &too_many),
+            Err(IMStatusCode::ResourceExhausted)
+        );
+
+        // Nothing is written by the checks
+        let gk_map = fm
+            .get_fabric(fab_idx)
+            .unwrap()
+            .group_keys()
+            .key_map()
+            .to_vec();
+        assert_eq!(gk_map, [GroupKeyMapEntry::new(0x10, 1)]);
+    }
+}
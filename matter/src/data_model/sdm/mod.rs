@@ -19,5 +19,6 @@ pub mod admin_commissioning;
 pub mod dev_att;
 pub mod failsafe;
 pub mod general_commissioning;
+pub mod group_key_management;
 pub mod noc;
 pub mod nw_commissioning;
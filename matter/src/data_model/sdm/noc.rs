@@ -20,15 +20,16 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::acl::{AclEntry, AclMgr, AuthMode};
 use crate::cert::Cert;
-use crate::crypto::{self, CryptoKeyPair, KeyPair};
+use crate::crypto::{self, CryptoKeyPair, StoredKeyPair};
 use crate::data_model::objects::*;
 use crate::data_model::sdm::dev_att;
-use crate::fabric::{Fabric, FabricMgr, MAX_SUPPORTED_FABRICS};
+use crate::fabric::{Fabric, FabricMgr};
 use crate::interaction_model::command::CommandReq;
 use crate::interaction_model::core::IMStatusCode;
 use crate::interaction_model::messages::ib;
 use crate::tlv::{FromTLV, OctetStr, TLVElement, TLVWriter, TagType, ToTLV, UtfStr};
 use crate::transport::session::SessionMode;
+use crate::utils::epoch;
 use crate::utils::writebuf::WriteBuf;
 use crate::{cmd_enter, error::*, secure_channel};
 use log::{error, info};
@@ -74,6 +75,7 @@ pub enum Commands {
     CSRReq = 0x04,
     CSRResp = 0x05,
     AddNOC = 0x06,
+    UpdateNOC = 0x07,
     NOCResp = 0x08,
     UpdateFabricLabel = 0x09,
     RemoveFabric = 0x0a,
@@ -98,15 +100,27 @@ pub struct NocCluster {
     failsafe: Arc<FailSafe>,
 }
 struct NocData {
-    pub key_pair: KeyPair,
-    pub root_ca: Cert,
+    // This is taken once the NOC is added, the key is deleted otherwise
+    pub key_pair: Option<StoredKeyPair>,
+    // Whether the CSR was requested for UpdateNOC rather than AddNOC
+    pub for_update_noc: bool,
 }
 
 impl NocData {
-    pub fn new(key_pair: KeyPair) -> Self {
+    pub fn new(key_pair: StoredKeyPair, for_update_noc: bool) -> Self {
         Self {
-            key_pair,
-            root_ca: Cert::default(),
+            key_pair: Some(key_pair),
+            for_update_noc,
+        }
+    }
+}
+
+impl Drop for NocData {
+    fn drop(&mut self) {
+        if let Some(key_pair) = self.key_pair.take() {
+            if let Err(e) = key_pair.delete() {
+                error!("Error deleting the unused NOC key pair: {:?}", e);
+            }
         }
     }
 }
@@ -118,6 +132,7 @@ impl NocCluster {
         acl_mgr: Arc<AclMgr>,
         failsafe: Arc<FailSafe>,
     ) -> Result<Box<Self>, Error> {
+        let supported_fabrics = fabric_mgr.capacity() as u8;
         let mut c = Box::new(Self {
             dev_att,
             fabric_mgr,
@@ -140,7 +155,7 @@ impl NocCluster {
             ),
             Attribute::new(
                 Attributes::SupportedFabrics as u16,
-                AttrValue::Uint8(MAX_SUPPORTED_FABRICS as u8),
+                AttrValue::Uint8(supported_fabrics),
                 Access::RV,
                 Quality::FIXED,
             ),
@@ -150,8 +165,24 @@ impl NocCluster {
                 Access::RV,
                 Quality::NONE,
             ),
+            Attribute::new(
+                Attributes::TrustedRootCerts as u16,
+                AttrValue::Custom,
+                Access::RV,
+                Quality::NONE,
+            ),
         ];
         c.base.add_attributes(&attrs[..])?;
+        c.base.add_commands(&[
+            Command::new(Commands::AttReq as CmdId, Access::IA),
+            Command::new(Commands::CertChainReq as CmdId, Access::IA),
+            Command::new(Commands::CSRReq as CmdId, Access::IA),
+            Command::new(Commands::AddNOC as CmdId, Access::IA),
+            Command::new(Commands::UpdateNOC as CmdId, Access::IA),
+            Command::new(Commands::UpdateFabricLabel as CmdId, Access::IA),
+            Command::new(Commands::RemoveFabric as CmdId, Access::IA),
+            Command::new(Commands::AddTrustedRootCert as CmdId, Access::IA),
+        ])?;
         Ok(c)
     }
 
@@ -162,11 +193,15 @@ impl NocCluster {
     }
 
     fn _handle_command_addnoc(&mut self, cmd_req: &mut CommandReq) -> Result<(), NocStatus> {
-        let noc_data = cmd_req
+        let mut noc_data = cmd_req
             .trans
             .session
             .take_data::<NocData>()
             .ok_or(NocStatus::MissingCsr)?;
+        if noc_data.for_update_noc {
+            error!("AddNOC with a CSR that was requested for UpdateNOC");
+            return Err(NocStatus::MissingCsr);
+        }
 
         if !self
             .failsafe
@@ -187,6 +222,10 @@ impl NocCluster {
         }
 
         let r = AddNocReq::from_tlv(&cmd_req.data).map_err(|_| NocStatus::InvalidNOC)?;
+        let root_ca = self.fabric_mgr.trust_store().pending_root().map_err(|_| {
+            error!("AddNOC without a trusted root");
+            NocStatus::InvalidNOC
+        })?;
 
         let noc_value = Cert::new(r.noc_value.0).map_err(|_| NocStatus::InvalidNOC)?;
         info!("Received NOC as: {}", noc_value);
@@ -197,19 +236,53 @@ impl NocCluster {
         } else {
             None
         };
+        noc_value
+            .verify_noc_chain(icac_value.as_ref(), &root_ca)
+            .map_err(|e| {
+                error!("Failed to verify the NOC chain: {}", e);
+                NocStatus::InvalidNOC
+            })?;
+        let fabric_id = noc_value
+            .get_fabric_id()
+            .map_err(|_| NocStatus::InvalidNOC)?;
+        if self
+            .fabric_mgr
+            .find_by_root(root_ca.get_pubkey(), fabric_id)
+            .is_some()
+        {
+            error!("The fabric is already commissioned");
+            return Err(NocStatus::FabricConflict);
+        }
+
+        // The latest Not Before of the chain is the best estimate of the current time
+        // that the commissioner gave us
+        let latest_not_before = [Some(&noc_value), icac_value.as_ref(), Some(&root_ca)]
+            .iter()
+            .flatten()
+            .map(|c| c.get_not_before())
+            .max()
+            .unwrap_or(0);
+        let key_pair = noc_data.key_pair.take().ok_or(NocStatus::MissingCsr)?;
         let fabric = Fabric::new(
-            noc_data.key_pair,
-            noc_data.root_ca,
+            key_pair,
+            root_ca,
             icac_value,
             noc_value,
             r.ipk_value.0,
             r.vendor_id,
         )
         .map_err(|_| NocStatus::TableFull)?;
-        let fab_idx = self
-            .fabric_mgr
-            .add(fabric)
-            .map_err(|_| NocStatus::TableFull)?;
+        let fab_idx = self.fabric_mgr.add(fabric).map_err(|e| match e {
+            Error::Duplicate => NocStatus::FabricConflict,
+            _ => NocStatus::TableFull,
+        })?;
+        if let Err(e) = self.fabric_mgr.trust_store().clear_pending_root() {
+            error!("Error clearing the pending trusted root: {}", e);
+        }
+
+        if epoch::update_last_known_good_time(latest_not_before).is_err() {
+            error!("Failed to update the Last Known Good UTC Time");
+        }
 
         if self.add_acl(fab_idx, r.case_admin_subject).is_err() {
             error!("Failed to add ACL, what to do?");
@@ -223,6 +296,61 @@ impl NocCluster {
         Ok(())
     }
 
+    fn _handle_command_updatenoc(&mut self, cmd_req: &mut CommandReq) -> Result<u8, NocStatus> {
+        // UpdateNOC applies to the fabric of the CASE session
+        let fab_idx = cmd_req
+            .trans
+            .session
+            .get_local_fabric_idx()
+            .ok_or(NocStatus::InvalidFabricIndex)?;
+        let mut noc_data = cmd_req
+            .trans
+            .session
+            .take_data::<NocData>()
+            .ok_or(NocStatus::MissingCsr)?;
+        if !noc_data.for_update_noc {
+            error!("UpdateNOC with a CSR that was requested for AddNOC");
+            return Err(NocStatus::MissingCsr);
+        }
+
+        if !self
+            .failsafe
+            .allow_noc_change()
+            .map_err(|_| NocStatus::InsufficientPrivlege)?
+        {
+            error!("UpdateNOC not allowed by Fail Safe");
+            return Err(NocStatus::InsufficientPrivlege);
+        }
+
+        let r = UpdateNocReq::from_tlv(&cmd_req.data).map_err(|_| NocStatus::InvalidNOC)?;
+        let noc_value = Cert::new(r.noc_value.0).map_err(|_| NocStatus::InvalidNOC)?;
+        info!("Received updated NOC as: {}", noc_value);
+        let icac_value = match r.icac_value {
+            Some(icac) if !icac.0.is_empty() => {
+                let cert = Cert::new(icac.0).map_err(|_| NocStatus::InvalidNOC)?;
+                info!("Received updated ICAC as: {}", cert);
+                Some(cert)
+            }
+            _ => None,
+        };
+
+        let key_pair = noc_data.key_pair.take().ok_or(NocStatus::MissingCsr)?;
+        self.fabric_mgr
+            .update(fab_idx, key_pair, icac_value, noc_value)
+            .map_err(|e| {
+                error!("Failed to update the NOC: {}", e);
+                match e {
+                    Error::NotFound => NocStatus::InvalidFabricIndex,
+                    _ => NocStatus::InvalidNOC,
+                }
+            })?;
+
+        if self.failsafe.record_update_noc(fab_idx).is_err() {
+            error!("Failed to record the NOC update in the FailSafe");
+        }
+        Ok(fab_idx)
+    }
+
     fn create_nocresponse(
         tw: &mut TLVWriter,
         status_code: NocStatus,
@@ -275,8 +403,8 @@ impl NocCluster {
         cmd_enter!("Remove Fabric");
         let req =
             RemoveFabricReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
+        // This takes the sessions, subscriptions and ACLs of the fabric with it
         if self.fabric_mgr.remove(req.fab_idx).is_ok() {
-            let _ = self.acl_mgr.delete_for_fabric(req.fab_idx);
             cmd_req.trans.terminate();
         } else {
             NocCluster::create_nocresponse(
@@ -289,6 +417,20 @@ impl NocCluster {
         Ok(())
     }
 
+    fn handle_command_updatenoc(&mut self, cmd_req: &mut CommandReq) -> Result<(), IMStatusCode> {
+        cmd_enter!("UpdateNOC");
+        if !self.failsafe.is_armed() {
+            return Err(IMStatusCode::FailSafeRequired);
+        }
+        let (status, fab_idx) = match self._handle_command_updatenoc(cmd_req) {
+            Ok(fab_idx) => (NocStatus::Ok, fab_idx),
+            Err(e) => (e, 0),
+        };
+        NocCluster::create_nocresponse(cmd_req.resp, status, fab_idx, "".to_owned());
+        cmd_req.trans.complete();
+        Ok(())
+    }
+
     fn handle_command_addnoc(&mut self, cmd_req: &mut CommandReq) -> Result<(), IMStatusCode> {
         cmd_enter!("AddNOC");
         if let Err(e) = self._handle_command_addnoc(cmd_req) {
@@ -367,14 +509,17 @@ impl NocCluster {
     fn handle_command_csrrequest(&mut self, cmd_req: &mut CommandReq) -> Result<(), IMStatusCode> {
         cmd_enter!("CSRRequest");
 
-        let req = CommonReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
-        info!("Received CSR Nonce:{:?}", req.str);
+        let req = CsrReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
+        info!("Received CSR Nonce:{:?}", req.nonce);
+        let for_update_noc = req.for_update_noc.unwrap_or(false);
 
         if !self.failsafe.is_armed() {
             return Err(IMStatusCode::UnsupportedAccess);
         }
 
-        let noc_keypair = KeyPair::new().map_err(|_| IMStatusCode::Failure)?;
+        let noc_keypair = crypto::get_key_store()
+            .and_then(StoredKeyPair::generate)
+            .map_err(|_| IMStatusCode::Failure)?;
         let mut attest_challenge = [0u8; crypto::SYMM_KEY_LEN_BYTES];
         attest_challenge.copy_from_slice(cmd_req.trans.session.get_att_challenge());
 
@@ -382,7 +527,7 @@ impl NocCluster {
             let mut buf: [u8; RESP_MAX] = [0; RESP_MAX];
             let mut nocsr_element = WriteBuf::new(&mut buf, RESP_MAX);
             let _ = t.start_struct(tag);
-            let _ = add_nocsrelement(&noc_keypair, req.str.0, &mut nocsr_element, t);
+            let _ = add_nocsrelement(&noc_keypair, req.nonce.0, &mut nocsr_element, t);
             let _ = add_attestation_signature(
                 self.dev_att.as_ref(),
                 &mut nocsr_element,
@@ -399,7 +544,7 @@ impl NocCluster {
         );
 
         let _ = resp.to_tlv(cmd_req.resp, TagType::Anonymous);
-        let noc_data = Box::new(NocData::new(noc_keypair));
+        let noc_data = Box::new(NocData::new(noc_keypair, for_update_noc));
         // Store this in the session data instead of cluster data, so it gets cleared
         // if the session goes away for some reason
         cmd_req.trans.session.set_data(noc_data);
@@ -413,27 +558,27 @@ impl NocCluster {
     ) -> Result<(), IMStatusCode> {
         cmd_enter!("AddTrustedRootCert");
         if !self.failsafe.is_armed() {
-            return Err(IMStatusCode::UnsupportedAccess);
+            return Err(IMStatusCode::FailSafeRequired);
         }
-
-        // This may happen on CASE or PASE. For PASE, the existence of NOC Data is necessary
-        match cmd_req.trans.session.get_session_mode() {
-            SessionMode::Case(_) => error!("CASE: AddTrustedRootCert handling pending"), // For a CASE Session, we just return success for now,
-            SessionMode::Pase => {
-                let noc_data = cmd_req
-                    .trans
-                    .session
-                    .get_data::<NocData>()
-                    .ok_or(IMStatusCode::Failure)?;
-
-                let req =
-                    CommonReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
-                info!("Received Trusted Cert:{:x?}", req.str);
-
-                noc_data.root_ca = Cert::new(req.str.0).map_err(|_| IMStatusCode::Failure)?;
-            }
-            _ => (),
+        if !self.failsafe.allow_root_add() {
+            error!("AddTrustedRootCert not allowed by Fail Safe");
+            return Err(IMStatusCode::ConstraintError);
         }
+
+        let req = CommonReq::from_tlv(&cmd_req.data).map_err(|_| IMStatusCode::InvalidCommand)?;
+        info!("Received Trusted Cert:{:x?}", req.str);
+
+        // This may happen on CASE or PASE, the root stays pending until AddNOC
+        self.fabric_mgr
+            .trust_store()
+            .add_pending_root(req.str.0)
+            .map_err(|e| {
+                error!("Invalid trusted root: {}", e);
+                IMStatusCode::InvalidCommand
+            })?;
+        self.failsafe
+            .record_add_root()
+            .map_err(|_| IMStatusCode::ConstraintError)?;
         cmd_req.trans.complete();
 
         Err(IMStatusCode::Success)
@@ -458,6 +603,7 @@ impl ClusterType for NocCluster {
             .ok_or(IMStatusCode::UnsupportedCommand)?;
         match cmd {
             Commands::AddNOC => self.handle_command_addnoc(cmd_req),
+            Commands::UpdateNOC => self.handle_command_updatenoc(cmd_req),
             Commands::CSRReq => self.handle_command_csrrequest(cmd_req),
             Commands::AddTrustedRootCert => self.handle_command_addtrustedrootcert(cmd_req),
             Commands::AttReq => self.handle_command_attrequest(cmd_req),
@@ -488,6 +634,15 @@ impl ClusterType for NocCluster {
                 let count = self.fabric_mgr.used_count() as u8;
                 encoder.encode(EncodeValue::Value(&count))
             }
+            Some(Attributes::TrustedRootCerts) => {
+                encoder.encode(EncodeValue::Closure(&|tag, tw| {
+                    let _ = tw.start_array(tag);
+                    let _ = self.fabric_mgr.trust_store().for_each_root(|root| {
+                        let _ = tw.str16(TagType::Anonymous, root);
+                    });
+                    let _ = tw.end_container();
+                }))
+            }
             _ => {
                 error!("Attribute not supported: this shouldn't happen");
             }
@@ -528,7 +683,7 @@ fn add_attestation_signature(
         let mut privkey = [0_u8; crypto::BIGNUM_LEN_BYTES];
         dev_att.get_devatt_data(dev_att::DataType::DACPubKey, &mut pubkey)?;
         dev_att.get_devatt_data(dev_att::DataType::DACPrivKey, &mut privkey)?;
-        KeyPair::new_from_components(&pubkey, &privkey)
+        crypto::get_provider().key_pair_from_components(&pubkey, &privkey)
     }?;
     attest_element.copy_from_slice(attest_challenge)?;
     let mut signature = [0u8; crypto::EC_SIGNATURE_LEN_BYTES];
@@ -537,7 +692,7 @@ fn add_attestation_signature(
 }
 
 fn add_nocsrelement(
-    noc_keypair: &KeyPair,
+    noc_keypair: &StoredKeyPair,
     csr_nonce: &[u8],
     write_buf: &mut WriteBuf,
     resp: &mut TLVWriter,
@@ -576,6 +731,20 @@ struct AddNocReq<'a> {
     vendor_id: u16,
 }
 
+#[derive(FromTLV)]
+#[tlvargs(lifetime = "'a")]
+struct UpdateNocReq<'a> {
+    noc_value: OctetStr<'a>,
+    icac_value: Option<OctetStr<'a>>,
+}
+
+#[derive(FromTLV)]
+#[tlvargs(lifetime = "'a")]
+struct CsrReq<'a> {
+    nonce: OctetStr<'a>,
+    for_update_noc: Option<bool>,
+}
+
 #[derive(FromTLV)]
 #[tlvargs(lifetime = "'a")]
 struct CommonReq<'a> {
@@ -15,16 +15,17 @@
  *    limitations under the License.
  */
 
+use std::convert::TryFrom;
 use std::sync::Arc;
 
 use num_derive::FromPrimitive;
 
-use crate::acl::{self, AclEntry, AclMgr};
+use crate::acl::{self, AclEntry, AclExtension, AclMgr, AuthMode};
 use crate::data_model::objects::*;
 use crate::error::*;
 use crate::interaction_model::core::IMStatusCode;
 use crate::interaction_model::messages::ib::{attr_list_write, ListOperation};
-use crate::tlv::{FromTLV, TLVElement, TagType, ToTLV};
+use crate::tlv::{FromTLV, Nullable, TLVElement, TLVWriter, TagType, ToTLV};
 use log::{error, info};
 
 pub const ID: u32 = 0x001F;
@@ -38,16 +39,80 @@ pub enum Attributes {
     EntriesPerFabric = 4,
 }
 
+// The events are kept in the EventLog of the Node, and are also given to the
+// AclAuditListeners
+#[derive(FromPrimitive)]
+pub enum Events {
+    AccessControlEntryChanged = 0,
+    AccessControlExtensionChanged = 1,
+}
+
+#[derive(FromPrimitive, Debug, Clone, Copy, PartialEq)]
+pub enum ChangeType {
+    Changed = 0,
+    Added = 1,
+    Removed = 2,
+}
+
+impl ToTLV for ChangeType {
+    fn to_tlv(&self, tw: &mut TLVWriter, tag: TagType) -> Result<(), Error> {
+        tw.u8(tag, *self as u8)
+    }
+}
+
+/// The fields of the AccessControlEntryChanged event, as they are read from the
+/// [EventLog] and given to the [AclAuditListener](crate::acl::AclAuditListener)s
+///
+/// The admin is identified by its node ID for CASE sessions, and by its passcode ID
+/// for PASE sessions
+#[derive(ToTLV, Debug, Clone, PartialEq)]
+#[tlvargs(start = 1)]
+pub struct AccessControlEntryChanged {
+    pub admin_node_id: Nullable<u64>,
+    pub admin_passcode_id: Nullable<u16>,
+    pub change_type: ChangeType,
+    pub latest_value: Nullable<AclEntry>,
+    #[tagval(0xFE)]
+    pub fab_idx: u8,
+}
+
+/// The fields of the AccessControlExtensionChanged event, as they are read from the
+/// [EventLog] and given to the [AclAuditListener](crate::acl::AclAuditListener)s
+#[derive(ToTLV, Debug, Clone, PartialEq)]
+#[tlvargs(start = 1)]
+pub struct AccessControlExtensionChanged {
+    pub admin_node_id: Nullable<u64>,
+    pub admin_passcode_id: Nullable<u16>,
+    pub change_type: ChangeType,
+    pub latest_value: Nullable<AclExtension>,
+    #[tagval(0xFE)]
+    pub fab_idx: u8,
+}
+
+// The passcode ID of the default commissioning passcode
+const DEFAULT_PASSCODE_ID: u16 = 0;
+
+// The admin that made a change, as its node ID and passcode ID
+fn admin_ids(attr: &AttrDetails) -> (Nullable<u64>, Nullable<u16>) {
+    match attr.auth_mode {
+        AuthMode::Case => (Nullable::NotNull(attr.node_id), Nullable::Null),
+        AuthMode::Pase => (Nullable::Null, Nullable::NotNull(DEFAULT_PASSCODE_ID)),
+        _ => (Nullable::Null, Nullable::Null),
+    }
+}
+
 pub struct AccessControlCluster {
     base: Cluster,
     acl_mgr: Arc<AclMgr>,
+    events: Arc<EventLog>,
 }
 
 impl AccessControlCluster {
-    pub fn new(acl_mgr: Arc<AclMgr>) -> Result<Box<Self>, Error> {
+    pub fn new(acl_mgr: Arc<AclMgr>, events: Arc<EventLog>) -> Result<Box<Self>, Error> {
         let mut c = Box::new(AccessControlCluster {
             base: Cluster::new(ID)?,
             acl_mgr,
+            events,
         });
         c.base.add_attribute(attr_acl_new())?;
         c.base.add_attribute(attr_extension_new())?;
@@ -65,32 +130,166 @@ impl AccessControlCluster {
         &mut self,
         op: &ListOperation,
         data: &TLVElement,
-        fab_idx: u8,
+        attr: &AttrDetails,
     ) -> Result<(), IMStatusCode> {
         info!("Performing ACL operation {:?}", op);
+        let fab_idx = attr.fab_idx;
+        // The entries that go away are audited, look them up before they do
+        let mut changes: Vec<(ChangeType, AclEntry)> = match op {
+            ListOperation::DeleteItem(index) => self
+                .fabric_entries(fab_idx)
+                .into_iter()
+                .nth(*index as usize)
+                .map(|e| (ChangeType::Removed, e))
+                .into_iter()
+                .collect(),
+            ListOperation::DeleteList => self
+                .fabric_entries(fab_idx)
+                .into_iter()
+                .map(|e| (ChangeType::Removed, e))
+                .collect(),
+            _ => Vec::new(),
+        };
         let result = match op {
             ListOperation::AddItem | ListOperation::EditItem(_) => {
-                let mut acl_entry =
-                    AclEntry::from_tlv(data).map_err(|_| IMStatusCode::ConstraintError)?;
+                let acl_entry = acl_entry(data, fab_idx)?;
                 info!("ACL  {:?}", acl_entry);
-                // Overwrite the fabric index with our accessing fabric index
-                acl_entry.fab_idx = Some(fab_idx);
 
                 if let ListOperation::EditItem(index) = op {
-                    self.acl_mgr.edit(*index as u8, fab_idx, acl_entry)
+                    changes.push((ChangeType::Changed, acl_entry));
+                    self.acl_mgr.edit(list_index(*index)?, fab_idx, acl_entry)
                 } else {
+                    changes.push((ChangeType::Added, acl_entry));
                     self.acl_mgr.add(acl_entry)
                 }
             }
-            ListOperation::DeleteItem(index) => self.acl_mgr.delete(*index as u8, fab_idx),
-            ListOperation::DeleteList => self.acl_mgr.delete_for_fabric(fab_idx),
+            ListOperation::DeleteItem(index) => self.acl_mgr.delete(list_index(*index)?, fab_idx),
+            ListOperation::DeleteList => self.acl_mgr.delete_entries_for_fabric(fab_idx),
+        };
+        match result {
+            Ok(_) => {
+                let (admin_node_id, admin_passcode_id) = admin_ids(attr);
+                for (change_type, entry) in changes {
+                    let event = AccessControlEntryChanged {
+                        admin_node_id,
+                        admin_passcode_id,
+                        change_type,
+                        latest_value: Nullable::NotNull(entry),
+                        fab_idx,
+                    };
+                    info!("ACL change {:?}", event);
+                    self.acl_mgr.notify_entry_changed(&event);
+                    self.record_event(Events::AccessControlEntryChanged, fab_idx, Box::new(event));
+                }
+                Ok(())
+            }
+            Err(Error::NoSpace) => Err(IMStatusCode::ResourceExhausted),
+            _ => Err(IMStatusCode::ConstraintError),
+        }
+    }
+
+    /// Write the Extension Attribute
+    ///
+    /// Like the ACL Attribute, the extensions are fabric-scoped
+    fn write_extension_attr(
+        &mut self,
+        op: &ListOperation,
+        data: &TLVElement,
+        attr: &AttrDetails,
+    ) -> Result<(), IMStatusCode> {
+        info!("Performing Extension operation {:?}", op);
+        let fab_idx = attr.fab_idx;
+        let mut changes: Vec<(ChangeType, AclExtension)> = match op {
+            ListOperation::DeleteItem(index) => self
+                .fabric_extensions(fab_idx)
+                .into_iter()
+                .nth(*index as usize)
+                .map(|e| (ChangeType::Removed, e))
+                .into_iter()
+                .collect(),
+            ListOperation::DeleteList => self
+                .fabric_extensions(fab_idx)
+                .into_iter()
+                .map(|e| (ChangeType::Removed, e))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let result = match op {
+            ListOperation::AddItem | ListOperation::EditItem(_) => {
+                let ext = acl_extension(data, fab_idx)?;
+
+                if let ListOperation::EditItem(index) = op {
+                    changes.push((ChangeType::Changed, ext.clone()));
+                    self.acl_mgr
+                        .edit_extension(list_index(*index)?, fab_idx, ext)
+                } else {
+                    changes.push((ChangeType::Added, ext.clone()));
+                    self.acl_mgr.add_extension(ext)
+                }
+            }
+            ListOperation::DeleteItem(index) => {
+                self.acl_mgr.delete_extension(list_index(*index)?, fab_idx)
+            }
+            ListOperation::DeleteList => self.acl_mgr.delete_extensions_for_fabric(fab_idx),
         };
         match result {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                let (admin_node_id, admin_passcode_id) = admin_ids(attr);
+                for (change_type, ext) in changes {
+                    let event = AccessControlExtensionChanged {
+                        admin_node_id,
+                        admin_passcode_id,
+                        change_type,
+                        latest_value: Nullable::NotNull(ext),
+                        fab_idx,
+                    };
+                    info!("ACL Extension change {:?}", event);
+                    self.acl_mgr.notify_extension_changed(&event);
+                    self.record_event(
+                        Events::AccessControlExtensionChanged,
+                        fab_idx,
+                        Box::new(event),
+                    );
+                }
+                Ok(())
+            }
             Err(Error::NoSpace) => Err(IMStatusCode::ResourceExhausted),
             _ => Err(IMStatusCode::ConstraintError),
         }
     }
+
+    // The events are fabric-sensitive, and only an Administer can read them
+    fn record_event(&self, id: Events, fab_idx: u8, data: Box<dyn ToTLV + Send + Sync>) {
+        self.events.record(
+            0,
+            ID,
+            id as EventId,
+            EventPriority::Info,
+            Access::READ | Access::NEED_ADMIN,
+            Some(fab_idx),
+            data,
+        );
+    }
+
+    fn fabric_entries(&self, fab_idx: u8) -> Vec<AclEntry> {
+        let mut entries = Vec::new();
+        let _ = self.acl_mgr.for_each_acl(|e| {
+            if e.fab_idx == Some(fab_idx) {
+                entries.push(*e);
+            }
+        });
+        entries
+    }
+
+    fn fabric_extensions(&self, fab_idx: u8) -> Vec<AclExtension> {
+        let mut exts = Vec::new();
+        let _ = self.acl_mgr.for_each_extension(|e| {
+            if e.fab_idx == Some(fab_idx) {
+                exts.push(e.clone());
+            }
+        });
+        exts
+    }
 }
 
 impl ClusterType for AccessControlCluster {
@@ -113,8 +312,12 @@ impl ClusterType for AccessControlCluster {
                 let _ = tw.end_container();
             })),
             Some(Attributes::Extension) => encoder.encode(EncodeValue::Closure(&|tag, tw| {
-                // Empty for now
                 let _ = tw.start_array(tag);
+                let _ = self.acl_mgr.for_each_extension(|ext| {
+                    if !attr.fab_filter || Some(attr.fab_idx) == ext.fab_idx {
+                        let _ = ext.to_tlv(tw, TagType::Anonymous);
+                    }
+                });
                 let _ = tw.end_container();
             })),
             _ => {
@@ -128,13 +331,31 @@ impl ClusterType for AccessControlCluster {
         attr: &AttrDetails,
         data: &TLVElement,
     ) -> Result<(), IMStatusCode> {
-        let result = if let Some(Attributes::Acl) = num::FromPrimitive::from_u16(attr.attr_id) {
-            attr_list_write(attr, data, |op, data| {
-                self.write_acl_attr(&op, data, attr.fab_idx)
-            })
-        } else {
-            error!("Attribute not yet supported: this shouldn't happen");
-            Err(IMStatusCode::NotFound)
+        let result = match num::FromPrimitive::from_u16(attr.attr_id) {
+            Some(Attributes::Acl) => attr_list_write(
+                attr,
+                data,
+                |list| {
+                    check_list(list, acl::ENTRIES_PER_FABRIC, |d| {
+                        acl_entry(d, attr.fab_idx)
+                    })
+                },
+                |op, data| self.write_acl_attr(&op, data, attr),
+            ),
+            Some(Attributes::Extension) => attr_list_write(
+                attr,
+                data,
+                |list| {
+                    check_list(list, acl::EXTENSIONS_PER_FABRIC, |d| {
+                        acl_extension(d, attr.fab_idx)
+                    })
+                },
+                |op, data| self.write_extension_attr(&op, data, attr),
+            ),
+            _ => {
+                error!("Attribute not yet supported: this shouldn't happen");
+                Err(IMStatusCode::NotFound)
+            }
         };
         if result.is_ok() {
             self.base.cluster_changed();
@@ -143,6 +364,42 @@ impl ClusterType for AccessControlCluster {
     }
 }
 
+// The entry of a write, with the fabric index of the accessing fabric
+fn acl_entry(data: &TLVElement, fab_idx: u8) -> Result<AclEntry, IMStatusCode> {
+    let mut acl_entry = AclEntry::from_tlv(data).map_err(|_| IMStatusCode::ConstraintError)?;
+    acl_entry.fab_idx = Some(fab_idx);
+    Ok(acl_entry)
+}
+
+fn acl_extension(data: &TLVElement, fab_idx: u8) -> Result<AclExtension, IMStatusCode> {
+    data.find_tag(1)
+        .and_then(|d| d.slice())
+        .and_then(|d| AclExtension::new(fab_idx, d))
+        .map_err(|_| IMStatusCode::ConstraintError)
+}
+
+// Check that the whole _list_ can replace the items of a fabric: each item must be
+// valid, and there can't be more than the _max_ items that a fabric may have
+fn check_list<T, F>(list: &TLVElement, max: usize, f: F) -> Result<(), IMStatusCode>
+where
+    F: Fn(&TLVElement) -> Result<T, IMStatusCode>,
+{
+    let mut count = 0;
+    for item in list.enter().ok_or(IMStatusCode::ConstraintError)? {
+        f(&item)?;
+        count += 1;
+    }
+    if count > max {
+        return Err(IMStatusCode::ResourceExhausted);
+    }
+    Ok(())
+}
+
+// The list index of a write, beyond our u8 range it can't match any item
+fn list_index(index: u16) -> Result<u8, IMStatusCode> {
+    u8::try_from(index).map_err(|_| IMStatusCode::ConstraintError)
+}
+
 fn attr_acl_new() -> Attribute {
     Attribute::new(
         Attributes::Acl as u16,
@@ -190,20 +447,40 @@ fn attr_entries_per_fabric_new() -> Attribute {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     use crate::{
-        acl::{AclEntry, AclMgr, AuthMode},
+        acl::{AclAuditListener, AclEntry, AclMgr, AuthMode},
         data_model::{
             core::read::AttrReadEncoder,
-            objects::{AttrDetails, ClusterType, Privilege},
+            objects::{AttrDetails, ClusterType, EventLog, Privilege},
         },
         interaction_model::messages::ib::ListOperation,
-        tlv::{get_root_node_struct, ElementType, TLVElement, TLVWriter, TagType, ToTLV},
+        tlv::{get_root_node_struct, ElementType, Nullable, TLVElement, TLVWriter, TagType, ToTLV},
         utils::writebuf::WriteBuf,
     };
 
-    use super::AccessControlCluster;
+    use super::{AccessControlCluster, AccessControlEntryChanged, ChangeType, ID};
+
+    // Details of a write from node 112233 on fabric _fab_idx_
+    fn attr_details(fab_idx: u8) -> AttrDetails {
+        AttrDetails {
+            attr_id: 0,
+            list_index: None,
+            fab_idx,
+            fab_filter: false,
+            auth_mode: AuthMode::Case,
+            node_id: 112233,
+        }
+    }
+
+    struct AuditRecorder(Mutex<Vec<AccessControlEntryChanged>>);
+
+    impl AclAuditListener for AuditRecorder {
+        fn entry_changed(&self, event: &AccessControlEntryChanged) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
 
     #[test]
     /// Add an ACL entry
@@ -214,7 +491,8 @@ mod tests {
         let mut tw = TLVWriter::new(&mut writebuf);
 
         let acl_mgr = Arc::new(AclMgr::new_with(false).unwrap());
-        let mut acl = AccessControlCluster::new(acl_mgr.clone()).unwrap();
+        let mut acl =
+            AccessControlCluster::new(acl_mgr.clone(), Arc::new(EventLog::new())).unwrap();
 
         let new = AclEntry::new(2, Privilege::VIEW, AuthMode::Case);
         new.to_tlv(&mut tw, TagType::Anonymous).unwrap();
@@ -222,7 +500,7 @@ mod tests {
 
         // Test, ACL has fabric index 2, but the accessing fabric is 1
         //    the fabric index in the TLV should be ignored and the ACL should be created with entry 1
-        let result = acl.write_acl_attr(&ListOperation::AddItem, &data, 1);
+        let result = acl.write_acl_attr(&ListOperation::AddItem, &data, &attr_details(1));
         assert_eq!(result, Ok(()));
 
         let verifier = AclEntry::new(1, Privilege::VIEW, AuthMode::Case);
@@ -251,14 +529,15 @@ mod tests {
         for i in verifier {
             acl_mgr.add(i).unwrap();
         }
-        let mut acl = AccessControlCluster::new(acl_mgr.clone()).unwrap();
+        let mut acl =
+            AccessControlCluster::new(acl_mgr.clone(), Arc::new(EventLog::new())).unwrap();
 
         let new = AclEntry::new(2, Privilege::VIEW, AuthMode::Case);
         new.to_tlv(&mut tw, TagType::Anonymous).unwrap();
         let data = get_root_node_struct(writebuf.as_borrow_slice()).unwrap();
 
         // Test, Edit Fabric 2's index 1 - with accessing fabring as 2 - allow
-        let result = acl.write_acl_attr(&ListOperation::EditItem(1), &data, 2);
+        let result = acl.write_acl_attr(&ListOperation::EditItem(1), &data, &attr_details(2));
         // Fabric 2's index 1, is actually our index 2, update the verifier
         verifier[2] = new;
         assert_eq!(result, Ok(()));
@@ -286,12 +565,13 @@ mod tests {
         for i in input {
             acl_mgr.add(i).unwrap();
         }
-        let mut acl = AccessControlCluster::new(acl_mgr.clone()).unwrap();
+        let mut acl =
+            AccessControlCluster::new(acl_mgr.clone(), Arc::new(EventLog::new())).unwrap();
         // data is don't-care actually
         let data = TLVElement::new(TagType::Anonymous, ElementType::True);
 
         // Test , Delete Fabric 1's index 0
-        let result = acl.write_acl_attr(&ListOperation::DeleteItem(0), &data, 1);
+        let result = acl.write_acl_attr(&ListOperation::DeleteItem(0), &data, &attr_details(1));
         assert_eq!(result, Ok(()));
 
         let verifier = [input[0], input[2]];
@@ -322,7 +602,7 @@ mod tests {
         for i in input {
             acl_mgr.add(i).unwrap();
         }
-        let acl = AccessControlCluster::new(acl_mgr).unwrap();
+        let acl = AccessControlCluster::new(acl_mgr, Arc::new(EventLog::new())).unwrap();
         // Test 1, all 3 entries are read in the response without fabric filtering
         {
             let mut tw = TLVWriter::new(&mut writebuf);
@@ -332,6 +612,8 @@ mod tests {
                 list_index: None,
                 fab_idx: 1,
                 fab_filter: false,
+                auth_mode: AuthMode::Case,
+                node_id: 112233,
             };
             acl.read_custom_attribute(&mut encoder, &attr_details);
             assert_eq!(
@@ -356,6 +638,8 @@ mod tests {
                 list_index: None,
                 fab_idx: 1,
                 fab_filter: true,
+                auth_mode: AuthMode::Case,
+                node_id: 112233,
             };
             acl.read_custom_attribute(&mut encoder, &attr_details);
             assert_eq!(
@@ -378,6 +662,8 @@ mod tests {
                 list_index: None,
                 fab_idx: 2,
                 fab_filter: true,
+                auth_mode: AuthMode::Case,
+                node_id: 112233,
             };
             acl.read_custom_attribute(&mut encoder, &attr_details);
             assert_eq!(
@@ -390,4 +676,71 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    /// - every change to the ACL is audited with the admin and the latest value
+    fn acl_cluster_audit() {
+        let mut buf: [u8; 100] = [0; 100];
+        let buf_len = buf.len();
+        let mut writebuf = WriteBuf::new(&mut buf, buf_len);
+        let mut tw = TLVWriter::new(&mut writebuf);
+
+        let acl_mgr = Arc::new(AclMgr::new_with(false).unwrap());
+        let recorder = Arc::new(AuditRecorder(Mutex::new(Vec::new())));
+        acl_mgr.add_audit_listener(recorder.clone());
+        let existing = AclEntry::new(1, Privilege::VIEW, AuthMode::Case);
+        acl_mgr.add(existing).unwrap();
+        let events = Arc::new(EventLog::new());
+        let mut acl = AccessControlCluster::new(acl_mgr, events.clone()).unwrap();
+
+        let new = AclEntry::new(1, Privilege::ADMIN, AuthMode::Case);
+        new.to_tlv(&mut tw, TagType::Anonymous).unwrap();
+        let data = get_root_node_struct(writebuf.as_borrow_slice()).unwrap();
+
+        // Overwrite the list: the existing entry is removed, and the new one added
+        acl.write_acl_attr(&ListOperation::DeleteList, &data, &attr_details(1))
+            .unwrap();
+        acl.write_acl_attr(&ListOperation::AddItem, &data, &attr_details(1))
+            .unwrap();
+        // A failed operation isn't audited
+        assert!(acl
+            .write_acl_attr(&ListOperation::DeleteItem(5), &data, &attr_details(1))
+            .is_err());
+
+        let event = |change_type, entry| AccessControlEntryChanged {
+            admin_node_id: Nullable::NotNull(112233),
+            admin_passcode_id: Nullable::Null,
+            change_type,
+            latest_value: Nullable::NotNull(entry),
+            fab_idx: 1,
+        };
+        let expected = [
+            event(ChangeType::Removed, existing),
+            event(ChangeType::Added, new),
+        ];
+        assert_eq!(*recorder.0.lock().unwrap(), expected);
+
+        // The same changes are kept as events, with the fields in TLV
+        let mut number = 0;
+        events
+            .for_each(|e| {
+                assert_eq!(e.number, number);
+                assert_eq!((e.endpoint, e.cluster, e.id), (0, ID, 0));
+                assert_eq!(e.fab_idx, Some(1));
+                assert_eq!(tlv_bytes(&*e.data), tlv_bytes(&expected[number as usize]));
+                number += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(number, 2);
+    }
+
+    fn tlv_bytes(data: &dyn ToTLV) -> Vec<u8> {
+        let mut buf: [u8; 100] = [0; 100];
+        let buf_len = buf.len();
+        let mut writebuf = WriteBuf::new(&mut buf, buf_len);
+        let mut tw = TLVWriter::new(&mut writebuf);
+        data.to_tlv(&mut tw, TagType::Anonymous).unwrap();
+        writebuf.as_borrow_slice().to_vec()
+    }
 }
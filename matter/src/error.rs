@@ -16,7 +16,8 @@
  */
 
 use std::{
-    array::TryFromSliceError, fmt, string::FromUtf8Error, sync::PoisonError, time::SystemTimeError,
+    array::TryFromSliceError, fmt, str::Utf8Error, string::FromUtf8Error, sync::PoisonError,
+    time::SystemTimeError,
 };
 
 use async_channel::{SendError, TryRecvError};
@@ -27,6 +28,10 @@ pub enum Error {
     AttributeNotFound,
     AttributeIsCustom,
     BufferTooSmall,
+    // The certificate's Not After time has passed
+    CertExpired,
+    // The certificate's Not Before time hasn't been reached yet
+    CertNotYetValid,
     ClusterNotFound,
     CommandNotFound,
     Duplicate,
@@ -51,7 +56,10 @@ pub enum Error {
     NotFound,
     PacketPoolExhaust,
     StdIoError,
+    // A persisted value failed its integrity check
+    StorageCorrupted,
     SysTimeFail,
+    Timeout,
     Invalid,
     InvalidAAD,
     InvalidData,
@@ -60,6 +68,9 @@ pub enum Error {
     InvalidPeerAddr,
     // Invalid Auth Key in the Matter Certificate
     InvalidAuthKey,
+    // The certificate doesn't follow the Matter certificate profile for its place in
+    // the chain
+    InvalidCertProfile,
     InvalidSignature,
     InvalidState,
     InvalidTime,
@@ -68,7 +79,11 @@ pub enum Error {
     TLVNotFound,
     TLVTypeMismatch,
     TruncatedPacket,
+    // No trusted PAA or Certification Declaration signer for the device attestation
+    UntrustedCert,
     Utf8Fail,
+    // The Vendor or Product IDs of the device attestation don't match
+    VidPidMismatch,
 }
 
 impl From<std::io::Error> for Error {
@@ -132,6 +147,12 @@ impl From<FromUtf8Error> for Error {
     }
 }
 
+impl From<Utf8Error> for Error {
+    fn from(_e: Utf8Error) -> Self {
+        Self::Utf8Fail
+    }
+}
+
 impl From<TryRecvError> for Error {
     fn from(e: TryRecvError) -> Self {
         error!("Error in channel try_recv {}", e);
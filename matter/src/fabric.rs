@@ -23,15 +23,17 @@ use owning_ref::RwLockReadGuardRef;
 
 use crate::{
     cert::Cert,
-    crypto::{self, crypto_dummy::KeyPairDummy, hkdf_sha256, CryptoKeyPair, HmacSha256, KeyPair},
+    crypto::{self, hkdf_sha256, CryptoKeyPair, KeyHandle, StoredKeyPair},
     error::Error,
-    group_keys::KeySet,
+    group_keys::{GroupKeys, KeySet},
     mdns::{self, Mdns},
     sys::{Psm, SysMdnsService},
     tlv::{OctetStr, TLVWriter, TagType, ToTLV, UtfStr},
+    transport::group::GroupKeyLookup,
+    trust_store::TrustStore,
 };
 
-const MAX_CERT_TLV_LEN: usize = 350;
+pub(crate) const MAX_CERT_TLV_LEN: usize = 350;
 const COMPRESSED_FABRIC_ID_LEN: usize = 8;
 
 macro_rules! fb_key {
@@ -41,11 +43,13 @@ macro_rules! fb_key {
 }
 
 const ST_VID: &str = "vid";
-const ST_RCA: &str = "rca";
 const ST_ICA: &str = "ica";
 const ST_NOC: &str = "noc";
 const ST_IPK: &str = "ipk";
 const ST_LBL: &str = "label";
+const ST_GRPKEYS: &str = "groupkeys";
+const ST_KEYH: &str = "keyhandle";
+// Older versions stored the key pair in plain-text, these are only used for migration
 const ST_PBKEY: &str = "pubkey";
 const ST_PRKEY: &str = "privkey";
 
@@ -54,11 +58,12 @@ pub struct Fabric {
     node_id: u64,
     fabric_id: u64,
     vendor_id: u16,
-    key_pair: Box<dyn CryptoKeyPair>,
+    key_pair: StoredKeyPair,
     pub root_ca: Cert,
     pub icac: Option<Cert>,
     pub noc: Cert,
-    pub ipk: KeySet,
+    // The IPK is its key set 0
+    group_keys: GroupKeys,
     label: String,
     compressed_id: [u8; COMPRESSED_FABRIC_ID_LEN],
     mdns_service: Option<SysMdnsService>,
@@ -79,7 +84,7 @@ pub struct FabricDescriptor<'a> {
 
 impl Fabric {
     pub fn new(
-        key_pair: KeyPair,
+        key_pair: StoredKeyPair,
         root_ca: Cert,
         icac: Option<Cert>,
         noc: Cert,
@@ -88,22 +93,22 @@ impl Fabric {
     ) -> Result<Self, Error> {
         let node_id = noc.get_node_id()?;
         let fabric_id = noc.get_fabric_id()?;
+        let mut compressed_id = [0; COMPRESSED_FABRIC_ID_LEN];
+        Fabric::get_compressed_id(root_ca.get_pubkey(), fabric_id, &mut compressed_id)?;
 
         let mut f = Self {
             node_id,
             fabric_id,
             vendor_id,
-            key_pair: Box::new(key_pair),
+            key_pair,
             root_ca,
             icac,
             noc,
-            ipk: KeySet::default(),
-            compressed_id: [0; COMPRESSED_FABRIC_ID_LEN],
+            group_keys: GroupKeys::new(KeySet::new(ipk, &compressed_id)?),
+            compressed_id,
             label: "".into(),
             mdns_service: None,
         };
-        Fabric::get_compressed_id(f.root_ca.get_pubkey(), fabric_id, &mut f.compressed_id)?;
-        f.ipk = KeySet::new(ipk, &f.compressed_id)?;
 
         let mut mdns_service_name = String::with_capacity(33);
         for c in f.compressed_id {
@@ -122,20 +127,11 @@ impl Fabric {
         Ok(f)
     }
 
-    pub fn dummy() -> Result<Self, Error> {
-        Ok(Self {
-            node_id: 0,
-            fabric_id: 0,
-            vendor_id: 0,
-            key_pair: Box::new(KeyPairDummy::new()?),
-            root_ca: Cert::default(),
-            icac: Some(Cert::default()),
-            noc: Cert::default(),
-            ipk: KeySet::default(),
-            label: "".into(),
-            compressed_id: [0; COMPRESSED_FABRIC_ID_LEN],
-            mdns_service: None,
-        })
+    /// Withdraw the operational mDNS record of the fabric
+    fn unpublish(&mut self) {
+        if self.mdns_service.take().is_some() {
+            info!("Withdrew the mDNS service of fabric {:x}", self.fabric_id);
+        }
     }
 
     fn get_compressed_id(root_pubkey: &[u8], fabric_id: u64, out: &mut [u8]) -> Result<(), Error> {
@@ -151,7 +147,7 @@ impl Fabric {
     }
 
     pub fn match_dest_id(&self, random: &[u8], target: &[u8]) -> Result<(), Error> {
-        let mut mac = HmacSha256::new(self.ipk.op_key())?;
+        let mut mac = crypto::get_provider().hmac_sha256(self.ipk().op_key())?;
 
         mac.update(random)?;
         mac.update(self.root_ca.get_pubkey())?;
@@ -180,10 +176,26 @@ impl Fabric {
         self.node_id
     }
 
+    pub fn get_vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
     pub fn get_fabric_id(&self) -> u64 {
         self.fabric_id
     }
 
+    pub fn get_compressed_fabric_id(&self) -> &[u8] {
+        &self.compressed_id
+    }
+
+    pub fn ipk(&self) -> &KeySet {
+        self.group_keys.ipk()
+    }
+
+    pub fn group_keys(&self) -> &GroupKeys {
+        &self.group_keys
+    }
+
     pub fn get_fabric_desc(&self, fab_idx: u8) -> FabricDescriptor {
         FabricDescriptor {
             root_public_key: OctetStr::new(self.root_ca.get_pubkey()),
@@ -195,22 +207,21 @@ impl Fabric {
         }
     }
 
-    fn rm_store(&self, index: usize, psm: &MutexGuard<Psm>) {
-        psm.rm(fb_key!(index, ST_RCA));
+    // The root is stored by the TrustStore
+    fn rm_store(&self, index: u8, psm: &MutexGuard<Psm>) {
         psm.rm(fb_key!(index, ST_ICA));
         psm.rm(fb_key!(index, ST_NOC));
         psm.rm(fb_key!(index, ST_IPK));
         psm.rm(fb_key!(index, ST_LBL));
+        psm.rm(fb_key!(index, ST_GRPKEYS));
+        psm.rm(fb_key!(index, ST_KEYH));
         psm.rm(fb_key!(index, ST_PBKEY));
         psm.rm(fb_key!(index, ST_PRKEY));
         psm.rm(fb_key!(index, ST_VID));
     }
 
-    fn store(&self, index: usize, psm: &MutexGuard<Psm>) -> Result<(), Error> {
+    fn store(&self, index: u8, psm: &MutexGuard<Psm>) -> Result<(), Error> {
         let mut key = [0u8; MAX_CERT_TLV_LEN];
-        let len = self.root_ca.as_tlv(&mut key)?;
-        psm.set_kv_slice(fb_key!(index, ST_RCA), &key[..len])?;
-
         let len = if let Some(icac) = &self.icac {
             icac.as_tlv(&mut key)?
         } else {
@@ -220,28 +231,44 @@ impl Fabric {
 
         let len = self.noc.as_tlv(&mut key)?;
         psm.set_kv_slice(fb_key!(index, ST_NOC), &key[..len])?;
-        psm.set_kv_slice(fb_key!(index, ST_IPK), self.ipk.epoch_key())?;
+        psm.set_kv_slice(fb_key!(index, ST_IPK), self.ipk().epoch_key())?;
         psm.set_kv_slice(fb_key!(index, ST_LBL), self.label.as_bytes())?;
+        self.store_group_keys(index, psm)?;
 
-        let mut key = [0_u8; crypto::EC_POINT_LEN_BYTES];
-        let len = self.key_pair.get_public_key(&mut key)?;
-        let key = &key[..len];
-        psm.set_kv_slice(fb_key!(index, ST_PBKEY), key)?;
-
-        let mut key = [0_u8; crypto::BIGNUM_LEN_BYTES];
-        let len = self.key_pair.get_private_key(&mut key)?;
-        let key = &key[..len];
-        psm.set_kv_slice(fb_key!(index, ST_PRKEY), key)?;
+        // Only the handle is stored, the key pair itself stays in the key store
+        psm.set_kv_u64(fb_key!(index, ST_KEYH), self.key_pair.handle().0.into())?;
 
         psm.set_kv_u64(fb_key!(index, ST_VID), self.vendor_id.into())?;
         Ok(())
     }
 
-    fn load(index: usize, psm: &MutexGuard<Psm>) -> Result<Self, Error> {
-        let mut root_ca = Vec::new();
-        psm.get_kv_slice(fb_key!(index, ST_RCA), &mut root_ca)?;
-        let root_ca = Cert::new(root_ca.as_slice())?;
+    fn store_group_keys(&self, index: u8, psm: &MutexGuard<Psm>) -> Result<(), Error> {
+        psm.set_kv_slice(fb_key!(index, ST_GRPKEYS), &self.group_keys.store()?)
+    }
+
+    fn load_key_handle(index: u8, psm: &MutexGuard<Psm>) -> Result<KeyHandle, Error> {
+        let mut key_handle = 0;
+        if psm
+            .get_kv_u64(fb_key!(index, ST_KEYH), &mut key_handle)
+            .is_ok()
+        {
+            return Ok(KeyHandle(key_handle as u32));
+        }
+
+        // Move a key pair that was stored in plain-text into the key store
+        let mut pub_key = Vec::new();
+        psm.get_kv_slice(fb_key!(index, ST_PBKEY), &mut pub_key)?;
+        let mut priv_key = Vec::new();
+        psm.get_kv_slice(fb_key!(index, ST_PRKEY), &mut priv_key)?;
+        let key_handle = crypto::get_key_store()?.import(&pub_key, &priv_key)?;
+        psm.set_kv_u64(fb_key!(index, ST_KEYH), key_handle.0.into())?;
+        psm.rm(fb_key!(index, ST_PBKEY));
+        psm.rm(fb_key!(index, ST_PRKEY));
+        info!("Migrated the key pair of fabric {} to the key store", index);
+        Ok(key_handle)
+    }
 
+    fn load(index: u8, psm: &MutexGuard<Psm>, root_ca: Cert) -> Result<Self, Error> {
         let mut icac = Vec::new();
         psm.get_kv_slice(fb_key!(index, ST_ICA), &mut icac)?;
         let icac = if !icac.is_empty() {
@@ -264,11 +291,8 @@ impl Fabric {
             Error::Invalid
         })?;
 
-        let mut pub_key = Vec::new();
-        psm.get_kv_slice(fb_key!(index, ST_PBKEY), &mut pub_key)?;
-        let mut priv_key = Vec::new();
-        psm.get_kv_slice(fb_key!(index, ST_PRKEY), &mut priv_key)?;
-        let keypair = KeyPair::new_from_components(pub_key.as_slice(), priv_key.as_slice())?;
+        let key_handle = Fabric::load_key_handle(index, psm)?;
+        let keypair = StoredKeyPair::new(crypto::get_key_store()?, key_handle);
 
         let mut vendor_id = 0;
         psm.get_kv_u64(fb_key!(index, ST_VID), &mut vendor_id)?;
@@ -283,120 +307,481 @@ impl Fabric {
         );
         f.map(|mut f| {
             f.label = label;
+            // Older versions only stored the IPK
+            let mut group_keys = Vec::new();
+            if psm
+                .get_kv_slice(fb_key!(index, ST_GRPKEYS), &mut group_keys)
+                .is_ok()
+            {
+                if let Err(e) = f.group_keys.load(&group_keys, &f.compressed_id) {
+                    error!("Error loading the group keys of fabric {}: {:?}", index, e);
+                }
+            }
             f
         })
     }
 }
 
+/// The number of fabrics that a [FabricMgr] holds by default
 pub const MAX_SUPPORTED_FABRICS: usize = 3;
-#[derive(Default)]
-pub struct FabricMgrInner {
-    // The outside world expects Fabric Index to be one more than the actual one
-    // since 0 is not allowed. Need to handle this cleanly somehow
-    pub fabrics: [Option<Fabric>; MAX_SUPPORTED_FABRICS],
+/// The Fabric Indices that are assigned to fabrics, 0 is reserved by the
+/// specification
+pub const MIN_FABRIC_INDEX: u8 = 1;
+pub const MAX_FABRIC_INDEX: u8 = 254;
+
+// Older versions stored the fabrics in fixed slots, with an unused slot 0
+const LEGACY_FABRIC_SLOTS: u8 = 3;
+
+const ST_FABRIC_INDICES: &str = "fabindices";
+const ST_NEXT_INDEX: &str = "fabnextidx";
+
+// The first free Fabric Index, starting the search at _start_ and wrapping around
+// after the last one
+fn next_fabric_index<T>(start: u8, in_use: T) -> Option<u8>
+where
+    T: Fn(u8) -> bool,
+{
+    let mut fab_idx = if (MIN_FABRIC_INDEX..=MAX_FABRIC_INDEX).contains(&start) {
+        start
+    } else {
+        MIN_FABRIC_INDEX
+    };
+    for _ in MIN_FABRIC_INDEX..=MAX_FABRIC_INDEX {
+        if !in_use(fab_idx) {
+            return Some(fab_idx);
+        }
+        fab_idx = if fab_idx == MAX_FABRIC_INDEX {
+            MIN_FABRIC_INDEX
+        } else {
+            fab_idx + 1
+        };
+    }
+    None
+}
+
+/// The fabrics of the node, by their Fabric Index
+///
+/// Fabric Indices are assigned in increasing order, wrapping around after
+/// [MAX_FABRIC_INDEX], so that the index of a removed fabric isn't reused right
+/// away.
+pub struct FabricTable {
+    // Sorted by the Fabric Index
+    fabrics: Vec<(u8, Fabric)>,
+    capacity: usize,
+    // Where the search for the next free Fabric Index starts
+    next_index: u8,
+    // The fabric as it was before UpdateNOC, until the update is committed
+    updated: Option<(u8, Fabric)>,
+}
+
+impl FabricTable {
+    fn new(capacity: usize) -> Self {
+        Self {
+            fabrics: Vec::with_capacity(capacity),
+            capacity,
+            next_index: MIN_FABRIC_INDEX,
+            updated: None,
+        }
+    }
+
+    pub fn get(&self, fab_idx: u8) -> Option<&Fabric> {
+        self.position(fab_idx).ok().map(|i| &self.fabrics[i].1)
+    }
+
+    fn get_mut(&mut self, fab_idx: u8) -> Option<&mut Fabric> {
+        match self.position(fab_idx) {
+            Ok(i) => Some(&mut self.fabrics[i].1),
+            Err(_) => None,
+        }
+    }
+
+    /// The fabrics with their Fabric Index, in the order of the index
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Fabric)> {
+        self.fabrics.iter().map(|(fab_idx, f)| (*fab_idx, f))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fabrics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fabrics.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The Fabric Index of the fabric with the compressed fabric ID _id_
+    pub fn find_by_compressed_id(&self, id: &[u8]) -> Option<u8> {
+        self.iter()
+            .find(|(_, f)| f.get_compressed_fabric_id() == id)
+            .map(|(fab_idx, _)| fab_idx)
+    }
+
+    /// The Fabric Index of the fabric with _fabric_id_ under the root with the
+    /// public key _root_pubkey_
+    pub fn find_by_root(&self, root_pubkey: &[u8], fabric_id: u64) -> Option<u8> {
+        self.iter()
+            .find(|(_, f)| f.fabric_id == fabric_id && f.root_ca.get_pubkey() == root_pubkey)
+            .map(|(fab_idx, _)| fab_idx)
+    }
+
+    fn position(&self, fab_idx: u8) -> Result<usize, usize> {
+        self.fabrics.binary_search_by_key(&fab_idx, |(i, _)| *i)
+    }
+
+    // Reserve the Fabric Index of the next fabric
+    fn alloc_index(&mut self) -> Result<u8, Error> {
+        if self.len() >= self.capacity {
+            return Err(Error::NoSpace);
+        }
+        let fab_idx = next_fabric_index(self.next_index, |i| self.position(i).is_ok())
+            .ok_or(Error::NoSpace)?;
+        self.next_index = if fab_idx == MAX_FABRIC_INDEX {
+            MIN_FABRIC_INDEX
+        } else {
+            fab_idx + 1
+        };
+        Ok(fab_idx)
+    }
+
+    // Returns the fabric that was replaced
+    fn insert(&mut self, fab_idx: u8, fabric: Fabric) -> Option<Fabric> {
+        match self.position(fab_idx) {
+            Ok(i) => Some(std::mem::replace(&mut self.fabrics[i].1, fabric)),
+            Err(i) => {
+                self.fabrics.insert(i, (fab_idx, fabric));
+                None
+            }
+        }
+    }
+
+    // The fabric as it was before UpdateNOC
+    fn take_updated(&mut self, fab_idx: u8) -> Option<Fabric> {
+        match &self.updated {
+            Some((i, _)) if *i == fab_idx => self.updated.take().map(|(_, f)| f),
+            _ => None,
+        }
+    }
+
+    fn remove(&mut self, fab_idx: u8) -> Option<Fabric> {
+        let i = self.position(fab_idx).ok()?;
+        Some(self.fabrics.remove(i).1)
+    }
+
+    fn store(&self, psm: &MutexGuard<Psm>) -> Result<(), Error> {
+        let indices: Vec<u8> = self.fabrics.iter().map(|(fab_idx, _)| *fab_idx).collect();
+        psm.set_kv_slice(ST_FABRIC_INDICES, &indices)?;
+        psm.set_kv_u64(ST_NEXT_INDEX, self.next_index.into())
+    }
+}
+
+/// Notified of the fabrics that are removed, so that the state scoped to a
+/// fabric, like its sessions and ACLs, doesn't outlive it
+pub trait FabricListener: Send + Sync {
+    fn fabric_removed(&self, fab_idx: u8);
 }
 
 pub struct FabricMgr {
-    inner: RwLock<FabricMgrInner>,
-    psm: Arc<Mutex<Psm>>,
+    inner: RwLock<FabricTable>,
+    // The Option<> is solely because test execution is faster
+    psm: Option<Arc<Mutex<Psm>>>,
+    trust_store: TrustStore,
+    listeners: RwLock<Vec<Arc<dyn FabricListener>>>,
 }
 
 impl FabricMgr {
     pub fn new() -> Result<Self, Error> {
-        let dummy_fabric = Fabric::dummy()?;
-        let mut mgr = FabricMgrInner::default();
-        mgr.fabrics[0] = Some(dummy_fabric);
-        let mut fm = Self {
-            inner: RwLock::new(mgr),
-            psm: Psm::get()?,
+        FabricMgr::new_with(true)
+    }
+
+    /// A fabric manager that persists its fabrics only if _psm_support_ is set
+    pub fn new_with(psm_support: bool) -> Result<Self, Error> {
+        FabricMgr::create(MAX_SUPPORTED_FABRICS, psm_support)
+    }
+
+    /// A fabric manager that holds up to _capacity_ fabrics
+    pub fn new_with_capacity(capacity: usize) -> Result<Self, Error> {
+        FabricMgr::create(capacity, true)
+    }
+
+    fn create(capacity: usize, psm_support: bool) -> Result<Self, Error> {
+        if capacity == 0 || capacity > (MAX_FABRIC_INDEX - MIN_FABRIC_INDEX + 1) as usize {
+            return Err(Error::InvalidArgument);
+        }
+        let psm = if psm_support { Some(Psm::get()?) } else { None };
+        let fm = Self {
+            inner: RwLock::new(FabricTable::new(capacity)),
+            psm,
+            trust_store: TrustStore::new_with(psm_support)?,
+            listeners: RwLock::new(Vec::new()),
         };
         fm.load()?;
         Ok(fm)
     }
 
-    fn store(&self, index: usize, fabric: &Fabric) -> Result<(), Error> {
-        let psm = self.psm.lock().unwrap();
-        fabric.store(index, &psm)
+    // Write to the storage with _f_, if the fabrics are persisted
+    fn persist<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&MutexGuard<Psm>) -> Result<(), Error>,
+    {
+        match &self.psm {
+            Some(psm) => f(&psm.lock()?),
+            None => Ok(()),
+        }
     }
 
-    fn load(&mut self) -> Result<(), Error> {
-        let mut mgr = self.inner.write()?;
-        let psm = self.psm.lock().unwrap();
-        for i in 0..MAX_SUPPORTED_FABRICS {
-            let result = Fabric::load(i, &psm);
-            if let Ok(fabric) = result {
-                info!("Adding new fabric at index {}", i);
-                mgr.fabrics[i] = Some(fabric);
+    fn load(&self) -> Result<(), Error> {
+        let psm = match &self.psm {
+            Some(psm) => psm,
+            None => return Ok(()),
+        };
+        let mut table = self.inner.write()?;
+
+        let mut indices = Vec::new();
+        let legacy = {
+            let psm = psm.lock()?;
+            let mut next_index = 0;
+            if psm.get_kv_u64(ST_NEXT_INDEX, &mut next_index).is_ok() {
+                table.next_index = next_index as u8;
+            }
+            match psm.get_kv_slice(ST_FABRIC_INDICES, &mut indices) {
+                Ok(_) => false,
+                Err(e) => {
+                    info!(
+                        "Can't read the fabric indices ({:?}), looking in the legacy slots",
+                        e
+                    );
+                    indices = (MIN_FABRIC_INDEX..LEGACY_FABRIC_SLOTS).collect();
+                    true
+                }
+            }
+        };
+
+        for fab_idx in indices {
+            if table.len() >= table.capacity {
+                error!(
+                    "More fabrics are stored than the capacity of {}, ignoring the rest",
+                    table.capacity
+                );
+                break;
+            }
+            // The trust store takes the PSM lock itself
+            let root_ca = match self.trust_store.get_root(fab_idx) {
+                Ok(root_ca) => root_ca,
+                // Most legacy slots are simply empty
+                Err(Error::NotFound) if legacy => continue,
+                Err(e) => {
+                    error!("Error loading the root of fabric {}: {:?}", fab_idx, e);
+                    continue;
+                }
+            };
+            match Fabric::load(fab_idx, &psm.lock()?, root_ca) {
+                Ok(fabric) => {
+                    info!("Adding new fabric at index {}", fab_idx);
+                    table.insert(fab_idx, fabric);
+                }
+                Err(Error::StorageCorrupted) => {
+                    error!("Fabric at index {} is corrupted, ignoring it", fab_idx);
+                }
+                Err(e) => error!("Error loading fabric {}: {:?}", fab_idx, e),
             }
         }
         Ok(())
     }
 
+    /// Add the fabric _f_, returning its Fabric Index
+    ///
+    /// A fabric with the same root and Fabric ID as an existing one is rejected
+    /// with [Error::Duplicate].
     pub fn add(&self, f: Fabric) -> Result<u8, Error> {
-        let mut mgr = self.inner.write()?;
-        let index = mgr
-            .fabrics
-            .iter()
-            .position(|f| f.is_none())
-            .ok_or(Error::NoSpace)?;
+        let mut table = self.inner.write()?;
+        if table
+            .find_by_root(f.root_ca.get_pubkey(), f.fabric_id)
+            .is_some()
+        {
+            return Err(Error::Duplicate);
+        }
+        let fab_idx = table.alloc_index()?;
+
+        self.persist(|psm| f.store(fab_idx, psm))?;
+        self.trust_store.set_root(fab_idx, &f.root_ca)?;
 
-        self.store(index, &f)?;
+        table.insert(fab_idx, f);
+        self.persist(|psm| table.store(psm))?;
+        Ok(fab_idx)
+    }
 
-        mgr.fabrics[index] = Some(f);
-        Ok(index as u8)
+    /// Register _listener_ to be notified of every fabric that is removed
+    pub fn add_listener(&self, listener: Arc<dyn FabricListener>) {
+        self.listeners.write().unwrap().push(listener);
     }
 
+    /// Remove the fabric at _fab_idx_, along with everything that is scoped to it
     pub fn remove(&self, fab_idx: u8) -> Result<(), Error> {
-        let fab_idx = fab_idx as usize;
-        let mut mgr = self.inner.write().unwrap();
-        let psm = self.psm.lock().unwrap();
-        if let Some(f) = &mgr.fabrics[fab_idx] {
-            f.rm_store(fab_idx, &psm);
-            mgr.fabrics[fab_idx] = None;
-            Ok(())
-        } else {
-            Err(Error::NotFound)
+        self.remove_fabric(fab_idx)?;
+        info!("Removed fabric {}", fab_idx);
+        // The table isn't locked here, the listeners may look up the other fabrics
+        for listener in self.listeners.read().unwrap().iter() {
+            listener.fabric_removed(fab_idx);
         }
+        Ok(())
     }
 
-    pub fn match_dest_id(&self, random: &[u8], target: &[u8]) -> Result<usize, Error> {
-        let mgr = self.inner.read()?;
-        for i in 0..MAX_SUPPORTED_FABRICS {
-            if let Some(fabric) = &mgr.fabrics[i] {
-                if fabric.match_dest_id(random, target).is_ok() {
-                    return Ok(i);
-                }
+    /// Remove all the fabrics, as a factory reset does
+    pub fn remove_all(&self) -> Result<(), Error> {
+        let mut indices = Vec::new();
+        self.for_each(|_, fab_idx| indices.push(fab_idx))?;
+        for fab_idx in indices {
+            self.remove(fab_idx)?;
+        }
+        self.trust_store.clear_pending_root()
+    }
+
+    fn remove_fabric(&self, fab_idx: u8) -> Result<(), Error> {
+        let mut table = self.inner.write().unwrap();
+        let mut f = table.remove(fab_idx).ok_or(Error::NotFound)?;
+        f.unpublish();
+        let stored = self.persist(|psm| {
+            f.rm_store(fab_idx, psm);
+            table.store(psm)
+        });
+        if let Err(e) = stored {
+            error!("Error storing the fabric table: {:?}", e);
+        }
+        if let Err(e) = self.trust_store.remove_root(fab_idx) {
+            error!("Error removing the root of fabric {}: {:?}", fab_idx, e);
+        }
+        if let Err(e) = f.key_pair.delete() {
+            error!("Error deleting the key of fabric {}: {:?}", fab_idx, e);
+        }
+        if let Some(mut old) = table.take_updated(fab_idx) {
+            old.unpublish();
+            if let Err(e) = old.key_pair.delete() {
+                error!("Error deleting the key of fabric {}: {:?}", fab_idx, e);
             }
         }
-        Err(Error::NotFound)
+        Ok(())
+    }
+
+    /// Replace the NOC, ICAC and key pair of the fabric at _fab_idx_, as UpdateNOC
+    /// does
+    ///
+    /// The new chain must be issued under the trusted root of the fabric, for the
+    /// same Fabric ID. The previous credentials remain in the storage until the
+    /// update is committed with [FabricMgr::commit_update], or are restored with
+    /// [FabricMgr::revert_update].
+    pub fn update(
+        &self,
+        fab_idx: u8,
+        key_pair: StoredKeyPair,
+        icac: Option<Cert>,
+        noc: Cert,
+    ) -> Result<(), Error> {
+        let mut table = self.inner.write()?;
+        if table.updated.is_some() {
+            error!("An update of fabric {} is already pending", fab_idx);
+            return Err(Error::InvalidState);
+        }
+        let old = table.get(fab_idx).ok_or(Error::NotFound)?;
+        if noc.get_fabric_id()? != old.fabric_id {
+            error!("The updated NOC is for another fabric");
+            return Err(Error::Invalid);
+        }
+        let root_ca = self.trust_store.get_root(fab_idx)?;
+        noc.verify_noc_chain(icac.as_ref(), &root_ca)?;
+
+        let mut fabric = Fabric::new(
+            key_pair,
+            root_ca,
+            icac,
+            noc,
+            old.ipk().epoch_key(),
+            old.vendor_id,
+        )?;
+        fabric.label = old.label.clone();
+        fabric.group_keys = old.group_keys.clone();
+        if let Some(old) = table.insert(fab_idx, fabric) {
+            table.updated = Some((fab_idx, old));
+        }
+        Ok(())
+    }
+
+    /// Keep the credentials of UpdateNOC for the fabric at _fab_idx_
+    pub fn commit_update(&self, fab_idx: u8) -> Result<(), Error> {
+        let mut table = self.inner.write()?;
+        let old = table.take_updated(fab_idx).ok_or(Error::NotFound)?;
+        let fabric = table.get(fab_idx).ok_or(Error::NotFound)?;
+        self.persist(|psm| fabric.store(fab_idx, psm))?;
+        if let Err(e) = old.key_pair.delete() {
+            error!("Error deleting the old key of fabric {}: {:?}", fab_idx, e);
+        }
+        info!("Committed the updated NOC of fabric {}", fab_idx);
+        Ok(())
+    }
+
+    /// Restore the credentials that UpdateNOC replaced for the fabric at _fab_idx_
+    pub fn revert_update(&self, fab_idx: u8) -> Result<(), Error> {
+        let mut table = self.inner.write()?;
+        let old = table.take_updated(fab_idx).ok_or(Error::NotFound)?;
+        if let Some(new) = table.insert(fab_idx, old) {
+            if let Err(e) = new.key_pair.delete() {
+                error!("Error deleting the new key of fabric {}: {:?}", fab_idx, e);
+            }
+        }
+        info!("Restored the previous NOC of fabric {}", fab_idx);
+        Ok(())
+    }
+
+    /// The trusted roots of the fabrics
+    pub fn trust_store(&self) -> &TrustStore {
+        &self.trust_store
+    }
+
+    pub fn match_dest_id(&self, random: &[u8], target: &[u8]) -> Result<u8, Error> {
+        let table = self.inner.read()?;
+        let fab_idx = table
+            .iter()
+            .find(|(_, f)| f.match_dest_id(random, target).is_ok())
+            .map(|(fab_idx, _)| fab_idx)
+            .ok_or(Error::NotFound);
+        fab_idx
     }
 
     pub fn get_fabric<'ret, 'me: 'ret>(
         &'me self,
-        idx: usize,
-    ) -> Result<RwLockReadGuardRef<'ret, FabricMgrInner, Option<Fabric>>, Error> {
-        Ok(RwLockReadGuardRef::new(self.inner.read()?).map(|fm| &fm.fabrics[idx]))
+        fab_idx: u8,
+    ) -> Result<RwLockReadGuardRef<'ret, FabricTable, Fabric>, Error> {
+        RwLockReadGuardRef::new(self.inner.read()?)
+            .try_map(|table| table.get(fab_idx).ok_or(Error::NotFound))
+    }
+
+    /// The Fabric Index of the fabric with the compressed fabric ID _id_
+    pub fn find_by_compressed_id(&self, id: &[u8]) -> Option<u8> {
+        self.inner.read().unwrap().find_by_compressed_id(id)
+    }
+
+    /// The Fabric Index of the fabric with _fabric_id_ under the root with the
+    /// public key _root_pubkey_
+    pub fn find_by_root(&self, root_pubkey: &[u8], fabric_id: u64) -> Option<u8> {
+        self.inner
+            .read()
+            .unwrap()
+            .find_by_root(root_pubkey, fabric_id)
     }
 
     pub fn is_empty(&self) -> bool {
-        let mgr = self.inner.read().unwrap();
-        for i in 1..MAX_SUPPORTED_FABRICS {
-            if mgr.fabrics[i].is_some() {
-                return false;
-            }
-        }
-        true
+        self.inner.read().unwrap().is_empty()
     }
 
     pub fn used_count(&self) -> usize {
-        let mgr = self.inner.read().unwrap();
-        let mut count = 0;
-        for i in 1..MAX_SUPPORTED_FABRICS {
-            if mgr.fabrics[i].is_some() {
-                count += 1;
-            }
-        }
-        count
+        self.inner.read().unwrap().len()
+    }
+
+    /// The number of fabrics that can be added
+    pub fn capacity(&self) -> usize {
+        self.inner.read().unwrap().capacity()
     }
 
     // Parameters to T are the Fabric and its Fabric Index
@@ -404,36 +789,277 @@ impl FabricMgr {
     where
         T: FnMut(&Fabric, u8),
     {
-        let mgr = self.inner.read().unwrap();
-        for i in 1..MAX_SUPPORTED_FABRICS {
-            if let Some(fabric) = &mgr.fabrics[i] {
-                f(fabric, i as u8)
-            }
+        let table = self.inner.read().unwrap();
+        for (fab_idx, fabric) in table.iter() {
+            f(fabric, fab_idx)
         }
         Ok(())
     }
 
-    pub fn set_label(&self, index: u8, label: String) -> Result<(), Error> {
-        let index = index as usize;
-        let mut mgr = self.inner.write()?;
-        if !label.is_empty() {
-            for i in 1..MAX_SUPPORTED_FABRICS {
-                if let Some(fabric) = &mgr.fabrics[i] {
-                    if fabric.label == label {
-                        return Err(Error::Invalid);
-                    }
-                }
-            }
+    pub fn set_label(&self, fab_idx: u8, label: String) -> Result<(), Error> {
+        let mut table = self.inner.write()?;
+        if !label.is_empty()
+            && table
+                .iter()
+                .any(|(i, fabric)| i != fab_idx && fabric.label == label)
+        {
+            return Err(Error::Invalid);
         }
-        if let Some(fabric) = &mut mgr.fabrics[index] {
+        if let Some(fabric) = table.get_mut(fab_idx) {
             let old = fabric.label.clone();
             fabric.label = label;
-            let psm = self.psm.lock().unwrap();
-            if fabric.store(index, &psm).is_err() {
+            if self.persist(|psm| fabric.store(fab_idx, psm)).is_err() {
                 fabric.label = old;
                 return Err(Error::StdIoError);
             }
         }
         Ok(())
     }
+
+    /// Modify the group keys of the fabric at _fab_idx_ with _f_, and store them
+    ///
+    /// The group keys are left as they were if _f_ fails.
+    pub fn update_group_keys<F, R>(&self, fab_idx: u8, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut GroupKeys) -> Result<R, Error>,
+    {
+        let mut table = self.inner.write()?;
+        let fabric = table.get_mut(fab_idx).ok_or(Error::NotFound)?;
+        let old = fabric.group_keys.clone();
+        let result = f(&mut fabric.group_keys).and_then(|result| {
+            self.persist(|psm| fabric.store_group_keys(fab_idx, psm))?;
+            Ok(result)
+        });
+        if result.is_err() {
+            fabric.group_keys = old;
+        }
+        result
+    }
+}
+
+impl GroupKeyLookup for FabricMgr {
+    fn op_keys(&self, group_id: u16, sess_id: u16) -> Vec<(u8, [u8; crypto::SYMM_KEY_LEN_BYTES])> {
+        let mut keys = Vec::new();
+        if let Ok(table) = self.inner.read() {
+            for (fab_idx, fabric) in table.iter() {
+                for op_key in fabric.group_keys.op_keys(group_id, sess_id) {
+                    let mut key = [0; crypto::SYMM_KEY_LEN_BYTES];
+                    key.copy_from_slice(op_key);
+                    keys.push((fab_idx, key));
+                }
+            }
+        }
+        keys
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    use crate::{
+        acl::{AclEntry, AclMgr, AuthMode},
+        cert::{Cert, CertBuilder, CertType},
+        crypto::{self, CryptoKeyPair, KeyStore, StoredKeyPair, EC_POINT_LEN_BYTES},
+        data_model::objects::Privilege,
+        group_keys::{GroupKeyMapEntry, KeySet},
+        sys::FileKeyStore,
+        tlv::{get_root_node_struct, TLVWriter, TagType},
+        transport::{
+            exchange::ExchangeMgr,
+            group::GroupKeyLookup,
+            network::Address,
+            session::{CaseDetails, CloneData, SessionMgr, SessionMode},
+        },
+        utils::writebuf::WriteBuf,
+    };
+
+    use super::{
+        next_fabric_index, Fabric, FabricListener, FabricMgr, MAX_FABRIC_INDEX, MIN_FABRIC_INDEX,
+    };
+
+    const IPK: [u8; 16] = [0x5a; 16];
+
+    // The keys of the test fabrics, kept apart from the keys of the device
+    fn test_key_store() -> Arc<dyn KeyStore> {
+        static KEY_STORE: OnceLock<Arc<dyn KeyStore>> = OnceLock::new();
+        KEY_STORE
+            .get_or_init(|| {
+                let dir =
+                    std::env::temp_dir().join(format!("matter_keys_fabric_{}", std::process::id()));
+                let _ = std::fs::remove_dir_all(&dir);
+                Arc::new(FileKeyStore::new_at(dir.to_str().unwrap()).unwrap())
+            })
+            .clone()
+    }
+
+    fn pub_key(key: &dyn CryptoKeyPair) -> Vec<u8> {
+        let mut pub_key = [0u8; EC_POINT_LEN_BYTES];
+        let len = key.get_public_key(&mut pub_key).unwrap();
+        pub_key[..len].to_vec()
+    }
+
+    /// A root CA that issues the NOCs of a fabric
+    pub(crate) struct TestCa {
+        key: Box<dyn CryptoKeyPair>,
+        rcac: Cert,
+        fabric_id: u64,
+    }
+
+    impl TestCa {
+        pub(crate) fn new(rcac_id: u64, fabric_id: u64) -> Self {
+            let key = crypto::get_provider().generate_key_pair().unwrap();
+            let rcac = CertBuilder::new(CertType::Rcac)
+                .subject_rcac_id(rcac_id)
+                .public_key(&pub_key(key.as_ref()))
+                .unwrap()
+                .sign(key.as_ref())
+                .unwrap();
+            Self {
+                key,
+                rcac,
+                fabric_id,
+            }
+        }
+
+        pub(crate) fn rcac(&self) -> Cert {
+            let mut buf = [0u8; 1024];
+            let len = self.rcac.as_tlv(&mut buf).unwrap();
+            Cert::new(&buf[..len]).unwrap()
+        }
+
+        /// Issue a NOC for _node_id_, along with its key pair
+        pub(crate) fn noc(&self, node_id: u64) -> (StoredKeyPair, Cert) {
+            let key_pair = StoredKeyPair::generate(test_key_store()).unwrap();
+            let noc = CertBuilder::new(CertType::Noc)
+                .subject_node_id(node_id)
+                .subject_fabric_id(self.fabric_id)
+                .public_key(&pub_key(&key_pair))
+                .unwrap()
+                .issuer(&self.rcac)
+                .sign(self.key.as_ref())
+                .unwrap();
+            (key_pair, noc)
+        }
+
+        pub(crate) fn fabric(&self, node_id: u64) -> Fabric {
+            let (key_pair, noc) = self.noc(node_id);
+            Fabric::new(key_pair, self.rcac(), None, noc, &IPK, 0xFFF1).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_next_fabric_index() {
+        assert_eq!(Some(1), next_fabric_index(1, |_| false));
+        assert_eq!(Some(3), next_fabric_index(1, |i| i < 3));
+        // Indices aren't reused before wrapping around
+        assert_eq!(Some(5), next_fabric_index(5, |i| i == 2));
+        assert_eq!(
+            Some(MIN_FABRIC_INDEX),
+            next_fabric_index(MAX_FABRIC_INDEX, |i| i == MAX_FABRIC_INDEX)
+        );
+        // 0 is never assigned
+        assert_eq!(Some(MIN_FABRIC_INDEX), next_fabric_index(0, |_| false));
+        assert_eq!(Some(2), next_fabric_index(255, |i| i == 1));
+        assert_eq!(None, next_fabric_index(7, |_| true));
+    }
+
+    // Records the removed fabrics, like the queue of the transport does to close
+    // their sessions
+    #[derive(Default)]
+    struct RemovedFabrics(Mutex<Vec<u8>>);
+
+    impl FabricListener for RemovedFabrics {
+        fn fabric_removed(&self, fab_idx: u8) {
+            self.0.lock().unwrap().push(fab_idx);
+        }
+    }
+
+    pub(crate) fn key_set(id: u16, compressed_id: &[u8]) -> KeySet {
+        let mut buf = [0u8; 100];
+        let mut wb = WriteBuf::new(&mut buf, 100);
+        let mut tw = TLVWriter::new(&mut wb);
+        tw.start_struct(TagType::Anonymous).unwrap();
+        tw.u16(TagType::Context(0), id).unwrap();
+        tw.u8(TagType::Context(1), 0).unwrap();
+        tw.str8(TagType::Context(2), &[0x11; 16]).unwrap();
+        tw.u64(TagType::Context(3), 0).unwrap();
+        tw.end_container().unwrap();
+        let data = wb.as_slice().to_vec();
+        KeySet::parse(&get_root_node_struct(&data).unwrap(), compressed_id).unwrap()
+    }
+
+    #[test]
+    fn test_remove_fabric() {
+        let fm = FabricMgr::new_with(false).unwrap();
+        let acl_mgr = Arc::new(AclMgr::new_with(false).unwrap());
+        let removed = Arc::new(RemovedFabrics::default());
+        fm.add_listener(acl_mgr.clone());
+        fm.add_listener(removed.clone());
+        let mut exch_mgr = ExchangeMgr::new(SessionMgr::new());
+
+        let fab_idx = fm.add(TestCa::new(5, 0xfab).fabric(0x1234)).unwrap();
+        let other = fm.add(TestCa::new(6, 0xfab).fabric(0x1234)).unwrap();
+        let mut group_sess_ids = Vec::new();
+        for (i, idx) in [fab_idx, other].iter().enumerate() {
+            acl_mgr
+                .add(AclEntry::new(*idx, Privilege::ADMIN, AuthMode::Case))
+                .unwrap();
+
+            let ks = key_set(1, &fm.get_fabric(*idx).unwrap().compressed_id);
+            group_sess_ids.push(ks.epoch_keys()[0].session_id());
+            fm.update_group_keys(*idx, |gk| {
+                gk.set_key_set(ks)?;
+                gk.add_key_map(GroupKeyMapEntry::new(0x10, 1))
+            })
+            .unwrap();
+
+            let mode = SessionMode::Case(CaseDetails::new(*idx, &[0; 3]));
+            let clone_data = CloneData::new(
+                0x1234,
+                0x5678,
+                100 + i as u16,
+                1 + i as u16,
+                Address::default(),
+                mode,
+            );
+            exch_mgr.add_session(&clone_data).unwrap();
+        }
+
+        fm.remove(fab_idx).unwrap();
+        for idx in removed.0.lock().unwrap().iter() {
+            exch_mgr.close_fabric_sessions(*idx).unwrap();
+        }
+
+        assert_eq!(*removed.0.lock().unwrap(), vec![fab_idx]);
+        assert!(fm.get_fabric(fab_idx).is_err());
+        for (idx, gone) in [(fab_idx, true), (other, false)] {
+            let mut acls = 0;
+            acl_mgr
+                .for_each_acl(|e| acls += (e.fab_idx == Some(idx)) as usize)
+                .unwrap();
+            assert_eq!(acls == 0, gone);
+
+            let sessions = exch_mgr
+                .get_sess_mgr()
+                .find_all(|s| s.get_local_fabric_idx() == Some(idx));
+            assert_eq!(sessions.is_empty(), gone);
+        }
+        // The group keys go away with the fabric
+        let has_keys = |idx, sess_id| fm.op_keys(0x10, sess_id).iter().any(|(i, _)| *i == idx);
+        assert!(!has_keys(fab_idx, group_sess_ids[0]));
+        assert!(has_keys(other, group_sess_ids[1]));
+
+        fm.remove(other).unwrap();
+    }
+
+    #[test]
+    fn test_unpublish() {
+        let mut f = TestCa::new(7, 0xfab).fabric(0x1234);
+        assert!(f.mdns_service.is_some());
+        f.unpublish();
+        assert!(f.mdns_service.is_none());
+        // Nothing left to withdraw
+        f.unpublish();
+    }
 }
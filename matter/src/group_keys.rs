@@ -15,47 +15,191 @@
  *    limitations under the License.
  */
 
-use std::sync::{Arc, Mutex, Once};
+use byteorder::{BigEndian, ByteOrder};
+use num_derive::FromPrimitive;
 
-use crate::{crypto, error::Error};
+use crate::{
+    crypto,
+    error::Error,
+    tlv::{get_root_node_struct, FromTLV, TLVElement, TLVWriter, TagType, ToTLV},
+    utils::writebuf::WriteBuf,
+};
 
-// This is just makeshift implementation for now, not used anywhere
-pub struct GroupKeys {}
+/// The ID of the key set of the IPK
+pub const IPK_KEY_SET_ID: u16 = 0;
+/// The epoch keys that a key set holds
+pub const EPOCH_KEYS_PER_KEY_SET: usize = 3;
+/// The key sets that a fabric can have, including the IPK
+pub const MAX_GROUP_KEYS_PER_FABRIC: usize = 3;
+/// The groups that a fabric can have
+pub const MAX_GROUPS_PER_FABRIC: usize = 4;
 
-static mut G_GRP_KEYS: Option<Arc<Mutex<GroupKeys>>> = None;
-static INIT: Once = Once::new();
+const MAX_STORE_LEN: usize = 1024;
 
-impl GroupKeys {
-    fn new() -> Self {
-        Self {}
-    }
+#[derive(FromPrimitive, Debug, Clone, Copy, PartialEq)]
+pub enum SecurityPolicy {
+    TrustFirst = 0,
+    CacheAndSync = 1,
+}
 
-    pub fn get() -> Result<Arc<Mutex<Self>>, Error> {
-        unsafe {
-            INIT.call_once(|| {
-                G_GRP_KEYS = Some(Arc::new(Mutex::new(GroupKeys::new())));
-            });
-            Ok(G_GRP_KEYS.as_ref().ok_or(Error::Invalid)?.clone())
+/// An epoch key, with the operational group key and the group session ID
+/// that are derived from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochKey {
+    key: [u8; crypto::SYMM_KEY_LEN_BYTES],
+    // In microseconds since the Matter epoch
+    start_time: u64,
+    op_key: [u8; crypto::SYMM_KEY_LEN_BYTES],
+    session_id: u16,
+}
+
+impl EpochKey {
+    pub fn new(key: &[u8], start_time: u64, compressed_id: &[u8]) -> Result<Self, Error> {
+        if key.len() != crypto::SYMM_KEY_LEN_BYTES {
+            return Err(Error::InvalidArgument);
         }
+        let mut ek = Self {
+            key: [0; crypto::SYMM_KEY_LEN_BYTES],
+            start_time,
+            op_key: [0; crypto::SYMM_KEY_LEN_BYTES],
+            session_id: 0,
+        };
+        ek.key.copy_from_slice(key);
+        KeySet::op_key_from_ipk(key, compressed_id, &mut ek.op_key)?;
+        ek.session_id = KeySet::session_id_from_op_key(&ek.op_key)?;
+        Ok(ek)
     }
 
-    pub fn insert_key() -> Result<(), Error> {
-        Ok(())
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    pub fn op_key(&self) -> &[u8] {
+        &self.op_key
+    }
+
+    pub fn session_id(&self) -> u16 {
+        self.session_id
     }
 }
 
-#[derive(Debug, Default)]
+/// A Group Key Set, of up to [EPOCH_KEYS_PER_KEY_SET] epoch keys
+///
+/// The IPK is the key set [IPK_KEY_SET_ID], with a single epoch key
+#[derive(Debug, Clone, PartialEq)]
 pub struct KeySet {
-    pub epoch_key: [u8; crypto::SYMM_KEY_LEN_BYTES],
-    pub op_key: [u8; crypto::SYMM_KEY_LEN_BYTES],
+    id: u16,
+    policy: SecurityPolicy,
+    // In the increasing order of their start time
+    epoch_keys: Vec<EpochKey>,
 }
 
 impl KeySet {
+    /// The key set of the IPK, for the fabric with the compressed ID _compressed_id_
     pub fn new(epoch_key: &[u8], compressed_id: &[u8]) -> Result<Self, Error> {
-        let mut ks = KeySet::default();
-        KeySet::op_key_from_ipk(epoch_key, compressed_id, &mut ks.op_key)?;
-        ks.epoch_key.copy_from_slice(epoch_key);
-        Ok(ks)
+        Ok(Self {
+            id: IPK_KEY_SET_ID,
+            policy: SecurityPolicy::TrustFirst,
+            epoch_keys: vec![EpochKey::new(epoch_key, 0, compressed_id)?],
+        })
+    }
+
+    /// Parse a GroupKeySetStruct, deriving the keys for the fabric with the
+    /// compressed ID _compressed_id_
+    ///
+    /// The first epoch key is mandatory, and every epoch key must have a start time
+    /// that is later than that of the previous one.
+    pub fn parse(t: &TLVElement, compressed_id: &[u8]) -> Result<Self, Error> {
+        let id = t.find_tag(0)?.u16()?;
+        let policy =
+            num::FromPrimitive::from_u8(t.find_tag(1)?.u8()?).ok_or(Error::InvalidArgument)?;
+
+        let mut epoch_keys: Vec<EpochKey> = Vec::new();
+        let mut absent = false;
+        for i in 0..EPOCH_KEYS_PER_KEY_SET as u32 {
+            let key = t.find_tag(2 + 2 * i).ok().filter(|k| k.null().is_err());
+            let start_time = t.find_tag(3 + 2 * i).ok().filter(|s| s.null().is_err());
+            match (key, start_time) {
+                (Some(key), Some(start_time)) if !absent => {
+                    let start_time = start_time.u64()?;
+                    if matches!(epoch_keys.last(), Some(k) if k.start_time >= start_time) {
+                        return Err(Error::Invalid);
+                    }
+                    epoch_keys.push(EpochKey::new(key.slice()?, start_time, compressed_id)?);
+                }
+                (None, None) => absent = true,
+                _ => return Err(Error::Invalid),
+            }
+        }
+        if epoch_keys.is_empty() {
+            return Err(Error::Invalid);
+        }
+
+        Ok(Self {
+            id,
+            policy,
+            epoch_keys,
+        })
+    }
+
+    /// Write the key set as a GroupKeySetStruct
+    ///
+    /// The epoch keys are only included if _with_keys_ is set, they are otherwise
+    /// null, as KeySetRead requires
+    pub fn to_tlv_with(
+        &self,
+        tw: &mut TLVWriter,
+        tag: TagType,
+        with_keys: bool,
+    ) -> Result<(), Error> {
+        tw.start_struct(tag)?;
+        tw.u16(TagType::Context(0), self.id)?;
+        tw.u8(TagType::Context(1), self.policy as u8)?;
+        for i in 0..EPOCH_KEYS_PER_KEY_SET {
+            let key_tag = TagType::Context(2 + 2 * i as u8);
+            let start_time_tag = TagType::Context(3 + 2 * i as u8);
+            match self.epoch_keys.get(i) {
+                Some(k) => {
+                    if with_keys {
+                        tw.str8(key_tag, &k.key)?;
+                    } else {
+                        tw.null(key_tag)?;
+                    }
+                    tw.u64(start_time_tag, k.start_time)?;
+                }
+                None => {
+                    tw.null(key_tag)?;
+                    tw.null(start_time_tag)?;
+                }
+            }
+        }
+        tw.end_container()
+    }
+
+    pub fn id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn policy(&self) -> SecurityPolicy {
+        self.policy
+    }
+
+    pub fn epoch_keys(&self) -> &[EpochKey] {
+        &self.epoch_keys
+    }
+
+    /// The operational key of the first epoch key, this is how the IPK is used
+    pub fn op_key(&self) -> &[u8] {
+        &self.epoch_keys[0].op_key
+    }
+
+    /// The first epoch key, this is the IPK itself for the IPK key set
+    pub fn epoch_key(&self) -> &[u8] {
+        &self.epoch_keys[0].key
     }
 
     fn op_key_from_ipk(ipk: &[u8], compressed_id: &[u8], opkey: &mut [u8]) -> Result<(), Error> {
@@ -66,11 +210,417 @@ impl KeySet {
         crypto::hkdf_sha256(compressed_id, ipk, &GRP_KEY_INFO, opkey).map_err(|_| Error::NoSpace)
     }
 
-    pub fn op_key(&self) -> &[u8] {
-        &self.op_key
+    fn session_id_from_op_key(op_key: &[u8]) -> Result<u16, Error> {
+        // "GroupKeyHash"
+        const GRP_KEY_HASH_INFO: [u8; 12] = [
+            0x47, 0x72, 0x6f, 0x75, 0x70, 0x4b, 0x65, 0x79, 0x48, 0x61, 0x73, 0x68,
+        ];
+
+        let mut session_id = [0u8; 2];
+        crypto::hkdf_sha256(&[], op_key, &GRP_KEY_HASH_INFO, &mut session_id)
+            .map_err(|_| Error::NoSpace)?;
+        Ok(BigEndian::read_u16(&session_id))
     }
+}
 
-    pub fn epoch_key(&self) -> &[u8] {
-        &self.epoch_key
+/// An entry of the GroupKeyMap attribute: the key set that a group uses
+#[derive(ToTLV, FromTLV, Copy, Clone, Debug, PartialEq)]
+#[tlvargs(start = 1)]
+pub struct GroupKeyMapEntry {
+    pub group_id: u16,
+    pub key_set_id: u16,
+    #[tagval(0xFE)]
+    pub fab_idx: Option<u8>,
+}
+
+impl GroupKeyMapEntry {
+    pub fn new(group_id: u16, key_set_id: u16) -> Self {
+        Self {
+            group_id,
+            key_set_id,
+            fab_idx: None,
+        }
+    }
+}
+
+/// An entry of the GroupTable attribute: the endpoints that are members of a group
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupInfo {
+    pub group_id: u16,
+    pub endpoints: Vec<u16>,
+    pub name: String,
+}
+
+impl GroupInfo {
+    /// Write the group as a GroupInfoMapStruct of the fabric _fab_idx_
+    pub fn to_tlv_with(&self, tw: &mut TLVWriter, tag: TagType, fab_idx: u8) -> Result<(), Error> {
+        tw.start_struct(tag)?;
+        tw.u16(TagType::Context(1), self.group_id)?;
+        tw.start_array(TagType::Context(2))?;
+        for endpoint in &self.endpoints {
+            tw.u16(TagType::Anonymous, *endpoint)?;
+        }
+        tw.end_container()?;
+        tw.utf8(TagType::Context(3), self.name.as_bytes())?;
+        tw.u8(TagType::Context(0xFE), fab_idx)?;
+        tw.end_container()
+    }
+
+    fn from_tlv(t: &TLVElement) -> Result<Self, Error> {
+        let mut endpoints = Vec::new();
+        if let Some(iter) = t.find_tag(2)?.confirm_array()?.enter() {
+            for e in iter {
+                endpoints.push(e.u16()?);
+            }
+        }
+        Ok(Self {
+            group_id: t.find_tag(1)?.u16()?,
+            endpoints,
+            name: String::from_tlv(&t.find_tag(3)?)?,
+        })
+    }
+}
+
+/// The group keys of a fabric
+///
+/// These are the key sets of the fabric, with the IPK as the key set [IPK_KEY_SET_ID],
+/// the key set that each group uses, and the endpoints that are members of each group.
+#[derive(Debug, Clone)]
+pub struct GroupKeys {
+    // The IPK is always the first one
+    key_sets: Vec<KeySet>,
+    key_map: Vec<GroupKeyMapEntry>,
+    groups: Vec<GroupInfo>,
+}
+
+impl GroupKeys {
+    pub fn new(ipk: KeySet) -> Self {
+        Self {
+            key_sets: vec![ipk],
+            key_map: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn ipk(&self) -> &KeySet {
+        &self.key_sets[0]
+    }
+
+    pub fn key_sets(&self) -> &[KeySet] {
+        &self.key_sets
+    }
+
+    pub fn get_key_set(&self, id: u16) -> Option<&KeySet> {
+        self.key_sets.iter().find(|ks| ks.id == id)
+    }
+
+    /// Add the key set _ks_, or replace the one with the same ID
+    ///
+    /// The IPK can't be replaced this way
+    pub fn set_key_set(&mut self, ks: KeySet) -> Result<(), Error> {
+        if ks.id == IPK_KEY_SET_ID {
+            return Err(Error::Invalid);
+        }
+        if let Some(old) = self.key_sets.iter_mut().find(|old| old.id == ks.id) {
+            *old = ks;
+        } else if self.key_sets.len() < MAX_GROUP_KEYS_PER_FABRIC {
+            self.key_sets.push(ks);
+        } else {
+            return Err(Error::NoSpace);
+        }
+        Ok(())
+    }
+
+    /// Remove the key set with _id_, the groups that use it are removed from the key map
+    ///
+    /// The IPK can't be removed
+    pub fn remove_key_set(&mut self, id: u16) -> Result<(), Error> {
+        if id == IPK_KEY_SET_ID {
+            return Err(Error::Invalid);
+        }
+        let i = self
+            .key_sets
+            .iter()
+            .position(|ks| ks.id == id)
+            .ok_or(Error::NotFound)?;
+        self.key_sets.remove(i);
+        self.key_map.retain(|e| e.key_set_id != id);
+        Ok(())
+    }
+
+    pub fn key_map(&self) -> &[GroupKeyMapEntry] {
+        &self.key_map
+    }
+
+    /// The operational keys of the key set of _group_id_, whose group session ID is _sess_id_
+    pub fn op_keys(&self, group_id: u16, sess_id: u16) -> impl Iterator<Item = &[u8]> {
+        self.key_map
+            .iter()
+            .filter(move |e| e.group_id == group_id)
+            .filter_map(move |e| self.get_key_set(e.key_set_id))
+            .flat_map(|ks| ks.epoch_keys().iter())
+            .filter(move |ek| ek.session_id() == sess_id)
+            .map(|ek| ek.op_key())
+    }
+
+    // The IPK can't be used for groups, and a group only maps to a single key set
+    fn check_key_map_entry(
+        &self,
+        entry: &GroupKeyMapEntry,
+        skip: Option<usize>,
+    ) -> Result<(), Error> {
+        if entry.key_set_id == IPK_KEY_SET_ID {
+            return Err(Error::Invalid);
+        }
+        if self
+            .key_map
+            .iter()
+            .enumerate()
+            .any(|(i, e)| Some(i) != skip && e.group_id == entry.group_id)
+        {
+            return Err(Error::Duplicate);
+        }
+        Ok(())
+    }
+
+    pub fn add_key_map(&mut self, entry: GroupKeyMapEntry) -> Result<(), Error> {
+        self.check_key_map_entry(&entry, None)?;
+        if self.key_map.len() >= MAX_GROUPS_PER_FABRIC {
+            return Err(Error::NoSpace);
+        }
+        self.key_map.push(GroupKeyMapEntry {
+            fab_idx: None,
+            ..entry
+        });
+        Ok(())
+    }
+
+    pub fn edit_key_map(&mut self, index: usize, entry: GroupKeyMapEntry) -> Result<(), Error> {
+        if index >= self.key_map.len() {
+            return Err(Error::NotFound);
+        }
+        self.check_key_map_entry(&entry, Some(index))?;
+        self.key_map[index] = GroupKeyMapEntry {
+            fab_idx: None,
+            ..entry
+        };
+        Ok(())
+    }
+
+    pub fn delete_key_map(&mut self, index: usize) -> Result<(), Error> {
+        if index >= self.key_map.len() {
+            return Err(Error::NotFound);
+        }
+        self.key_map.remove(index);
+        Ok(())
+    }
+
+    pub fn clear_key_map(&mut self) {
+        self.key_map.clear();
+    }
+
+    pub fn groups(&self) -> &[GroupInfo] {
+        &self.groups
+    }
+
+    /// Make _endpoint_ a member of the group _group_id_, creating the group if needed
+    pub fn add_group(&mut self, group_id: u16, endpoint: u16, name: &str) -> Result<(), Error> {
+        if let Some(g) = self.groups.iter_mut().find(|g| g.group_id == group_id) {
+            if !g.endpoints.contains(&endpoint) {
+                g.endpoints.push(endpoint);
+            }
+            g.name = name.to_owned();
+        } else if self.groups.len() < MAX_GROUPS_PER_FABRIC {
+            self.groups.push(GroupInfo {
+                group_id,
+                endpoints: vec![endpoint],
+                name: name.to_owned(),
+            });
+        } else {
+            return Err(Error::NoSpace);
+        }
+        Ok(())
+    }
+
+    /// Remove _endpoint_ from the group _group_id_, the group goes away with its last endpoint
+    pub fn remove_group(&mut self, group_id: u16, endpoint: u16) -> Result<(), Error> {
+        let i = self
+            .groups
+            .iter()
+            .position(|g| g.group_id == group_id && g.endpoints.contains(&endpoint))
+            .ok_or(Error::NotFound)?;
+        self.groups[i].endpoints.retain(|e| *e != endpoint);
+        if self.groups[i].endpoints.is_empty() {
+            self.groups.remove(i);
+        }
+        Ok(())
+    }
+
+    /// Serialise everything but the IPK, which is stored along with the fabric
+    pub fn store(&self) -> Result<Vec<u8>, Error> {
+        let mut buf = [0u8; MAX_STORE_LEN];
+        let mut wb = WriteBuf::new(&mut buf, MAX_STORE_LEN);
+        let mut tw = TLVWriter::new(&mut wb);
+        tw.start_struct(TagType::Anonymous)?;
+        tw.start_array(TagType::Context(0))?;
+        for ks in self.key_sets.iter().skip(1) {
+            ks.to_tlv_with(&mut tw, TagType::Anonymous, true)?;
+        }
+        tw.end_container()?;
+        tw.start_array(TagType::Context(1))?;
+        for e in &self.key_map {
+            e.to_tlv(&mut tw, TagType::Anonymous)?;
+        }
+        tw.end_container()?;
+        tw.start_array(TagType::Context(2))?;
+        for g in &self.groups {
+            g.to_tlv_with(&mut tw, TagType::Anonymous, 0)?;
+        }
+        tw.end_container()?;
+        tw.end_container()?;
+        Ok(wb.as_slice().to_vec())
+    }
+
+    /// Load what [GroupKeys::store] serialised, for the fabric with the compressed ID _compressed_id_
+    pub fn load(&mut self, data: &[u8], compressed_id: &[u8]) -> Result<(), Error> {
+        let root = get_root_node_struct(data)?;
+
+        let mut key_sets = vec![self.ipk().clone()];
+        if let Some(iter) = root.find_tag(0)?.confirm_array()?.enter() {
+            for ks in iter {
+                key_sets.push(KeySet::parse(&ks, compressed_id)?);
+            }
+        }
+        let mut key_map = Vec::new();
+        if let Some(iter) = root.find_tag(1)?.confirm_array()?.enter() {
+            for e in iter {
+                key_map.push(GroupKeyMapEntry::from_tlv(&e)?);
+            }
+        }
+        let mut groups = Vec::new();
+        if let Some(iter) = root.find_tag(2)?.confirm_array()?.enter() {
+            for g in iter {
+                groups.push(GroupInfo::from_tlv(&g)?);
+            }
+        }
+
+        self.key_sets = key_sets;
+        self.key_map = key_map;
+        self.groups = groups;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPRESSED_ID: [u8; 8] = [0x87, 0xe1, 0xb0, 0x04, 0xe2, 0x35, 0xa1, 0x30];
+    const IPK: [u8; 16] = [
+        0x23, 0x5b, 0xf7, 0xe6, 0x28, 0x23, 0xd3, 0x58, 0xdc, 0xa4, 0xba, 0x50, 0xb1, 0x53, 0x5f,
+        0x4b,
+    ];
+
+    fn key_set(id: u16, start_times: &[u64]) -> KeySet {
+        let mut buf = [0u8; 200];
+        let mut wb = WriteBuf::new(&mut buf, 200);
+        let mut tw = TLVWriter::new(&mut wb);
+        tw.start_struct(TagType::Anonymous).unwrap();
+        tw.u16(TagType::Context(0), id).unwrap();
+        tw.u8(TagType::Context(1), 0).unwrap();
+        for (i, start_time) in start_times.iter().enumerate() {
+            tw.str8(TagType::Context(2 + 2 * i as u8), &[i as u8; 16])
+                .unwrap();
+            tw.u64(TagType::Context(3 + 2 * i as u8), *start_time)
+                .unwrap();
+        }
+        tw.end_container().unwrap();
+        let data = wb.as_slice().to_vec();
+        KeySet::parse(&get_root_node_struct(&data).unwrap(), &COMPRESSED_ID).unwrap()
+    }
+
+    #[test]
+    fn test_ipk_op_key() {
+        // The group key derivation example of the specification
+        const OP_KEY: [u8; 16] = [
+            0xa6, 0xf5, 0x30, 0x6b, 0xaf, 0x6d, 0x05, 0x0a, 0xf2, 0x3b, 0xa4, 0xbd, 0x6b, 0x9d,
+            0xd9, 0x60,
+        ];
+        let ipk = KeySet::new(&IPK, &COMPRESSED_ID).unwrap();
+        assert_eq!(ipk.id(), IPK_KEY_SET_ID);
+        assert_eq!(ipk.op_key(), OP_KEY);
+        assert_eq!(ipk.epoch_key(), IPK);
+        assert_eq!(ipk.epoch_keys()[0].session_id(), 0xb9f7);
+    }
+
+    #[test]
+    fn test_parse_key_set() {
+        let ks = key_set(1, &[10, 20]);
+        assert_eq!(ks.epoch_keys().len(), 2);
+        assert_eq!(ks.epoch_keys()[1].start_time(), 20);
+        // The keys differ, and so do the keys derived from them
+        assert_ne!(ks.epoch_keys()[0].op_key(), ks.epoch_keys()[1].op_key());
+
+        // The start times must increase
+        let mut buf = [0u8; 200];
+        let mut wb = WriteBuf::new(&mut buf, 200);
+        let mut tw = TLVWriter::new(&mut wb);
+        tw.start_struct(TagType::Anonymous).unwrap();
+        tw.u16(TagType::Context(0), 1).unwrap();
+        tw.u8(TagType::Context(1), 0).unwrap();
+        tw.str8(TagType::Context(2), &IPK).unwrap();
+        tw.u64(TagType::Context(3), 20).unwrap();
+        tw.str8(TagType::Context(4), &IPK).unwrap();
+        tw.u64(TagType::Context(5), 10).unwrap();
+        tw.end_container().unwrap();
+        let data = wb.as_slice().to_vec();
+        let t = get_root_node_struct(&data).unwrap();
+        assert_eq!(KeySet::parse(&t, &COMPRESSED_ID), Err(Error::Invalid));
+    }
+
+    #[test]
+    fn test_group_keys() {
+        let mut gk = GroupKeys::new(KeySet::new(&IPK, &COMPRESSED_ID).unwrap());
+        assert_eq!(gk.set_key_set(key_set(0, &[10])), Err(Error::Invalid));
+        gk.set_key_set(key_set(1, &[10])).unwrap();
+        gk.set_key_set(key_set(2, &[10])).unwrap();
+        assert_eq!(gk.set_key_set(key_set(3, &[10])), Err(Error::NoSpace));
+        // Replacing doesn't take more space
+        gk.set_key_set(key_set(2, &[10, 20])).unwrap();
+        assert_eq!(gk.get_key_set(2).unwrap().epoch_keys().len(), 2);
+
+        assert_eq!(
+            gk.add_key_map(GroupKeyMapEntry::new(0x100, IPK_KEY_SET_ID)),
+            Err(Error::Invalid)
+        );
+        gk.add_key_map(GroupKeyMapEntry::new(0x100, 1)).unwrap();
+        gk.add_key_map(GroupKeyMapEntry::new(0x101, 2)).unwrap();
+        assert_eq!(
+            gk.add_key_map(GroupKeyMapEntry::new(0x100, 2)),
+            Err(Error::Duplicate)
+        );
+        gk.add_group(0x100, 1, "Kitchen").unwrap();
+
+        // The keys of a group's messages are found through its key set
+        let ks = gk.get_key_set(2).unwrap();
+        let epoch_key = &ks.epoch_keys()[1];
+        let op_keys: Vec<&[u8]> = gk.op_keys(0x101, epoch_key.session_id()).collect();
+        assert_eq!(op_keys, [epoch_key.op_key()]);
+        assert_eq!(gk.op_keys(0x100, epoch_key.session_id()).count(), 0);
+        assert_eq!(gk.op_keys(0x102, epoch_key.session_id()).count(), 0);
+
+        // Round trip through the storage
+        let stored = gk.store().unwrap();
+        let mut loaded = GroupKeys::new(KeySet::new(&IPK, &COMPRESSED_ID).unwrap());
+        loaded.load(&stored, &COMPRESSED_ID).unwrap();
+        assert_eq!(loaded.key_sets(), gk.key_sets());
+        assert_eq!(loaded.key_map(), gk.key_map());
+        assert_eq!(loaded.groups(), gk.groups());
+
+        // The groups that use a key set go with it
+        assert_eq!(gk.remove_key_set(IPK_KEY_SET_ID), Err(Error::Invalid));
+        gk.remove_key_set(1).unwrap();
+        assert_eq!(gk.key_map(), [GroupKeyMapEntry::new(0x101, 2)]);
+        assert_eq!(gk.remove_key_set(1), Err(Error::NotFound));
     }
 }
@@ -221,6 +221,11 @@ impl proto_demux::HandleProto for InteractionModel {
     fn get_proto_id(&self) -> usize {
         PROTO_ID_INTERACTION_MODEL
     }
+
+    fn handle_timer_tick(&mut self) -> Result<(), Error> {
+        self.consumer.handle_timer_tick();
+        Ok(())
+    }
 }
 
 #[derive(FromPrimitive, Debug, Clone, Copy, PartialEq)]
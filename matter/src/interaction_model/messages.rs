@@ -74,7 +74,7 @@ pub mod msg {
 
     use super::ib::{
         self, AttrData, AttrPath, AttrResp, AttrStatus, CmdData, DataVersionFilter, EventFilter,
-        EventPath,
+        EventPath, EventResp,
     };
 
     #[derive(Default, FromTLV, ToTLV)]
@@ -178,8 +178,8 @@ pub mod msg {
     #[tlvargs(lifetime = "'a")]
     pub struct ReadReq<'a> {
         pub attr_requests: Option<TLVArray<'a, AttrPath>>,
-        event_requests: Option<TLVArray<'a, EventPath>>,
-        event_filters: Option<TLVArray<'a, EventFilter>>,
+        pub event_requests: Option<TLVArray<'a, EventPath>>,
+        pub event_filters: Option<TLVArray<'a, EventFilter>>,
         pub fabric_filtered: bool,
         pub dataver_filters: Option<TLVArray<'a, DataVersionFilter>>,
     }
@@ -196,6 +196,16 @@ pub mod msg {
             self.attr_requests = Some(TLVArray::new(requests));
             self
         }
+
+        pub fn set_event_requests(mut self, requests: &'a [EventPath]) -> Self {
+            self.event_requests = Some(TLVArray::new(requests));
+            self
+        }
+
+        pub fn set_event_filters(mut self, filters: &'a [EventFilter]) -> Self {
+            self.event_filters = Some(TLVArray::new(filters));
+            self
+        }
     }
 
     #[derive(ToTLV, FromTLV)]
@@ -228,8 +238,7 @@ pub mod msg {
     pub struct ReportDataMsg<'a> {
         pub subscription_id: Option<u32>,
         pub attr_reports: Option<TLVArray<'a, AttrResp<'a>>>,
-        // TODO
-        pub event_reports: Option<bool>,
+        pub event_reports: Option<TLVArray<'a, EventResp<'a>>>,
         pub more_chunks: Option<bool>,
         pub suppress_response: Option<bool>,
     }
@@ -237,7 +246,7 @@ pub mod msg {
     pub enum ReportDataTag {
         SubscriptionId = 0,
         AttributeReports = 1,
-        _EventReport = 2,
+        EventReports = 2,
         MoreChunkedMsgs = 3,
         SupressResponse = 4,
     }
@@ -399,37 +408,54 @@ pub mod ib {
     }
 
     /// Attribute Lists in Attribute Data are special. Infer the correct meaning using this function
-    pub fn attr_list_write<F>(
+    ///
+    /// A write of the whole list is given to _check_ before the current list is deleted, so
+    /// that it can reject a list that couldn't be written in full, instead of leaving the
+    /// attribute with only part of it.
+    pub fn attr_list_write<C, F>(
         attr: &AttrDetails,
         data: &TLVElement,
+        check: C,
         mut f: F,
     ) -> Result<(), IMStatusCode>
     where
+        C: FnOnce(&TLVElement) -> Result<(), IMStatusCode>,
         F: FnMut(ListOperation, &TLVElement) -> Result<(), IMStatusCode>,
     {
-        if let Some(Nullable::NotNull(index)) = attr.list_index {
-            // If list index is valid,
-            //    - this is a modify item or delete item operation
-            if data.null().is_ok() {
-                // If data is NULL, delete item
-                f(ListOperation::DeleteItem(index), data)
-            } else {
-                f(ListOperation::EditItem(index), data)
+        match attr.list_index {
+            Some(Nullable::NotNull(index)) => {
+                // If list index is valid,
+                //    - this is a modify item or delete item operation
+                if data.null().is_ok() {
+                    // If data is NULL, delete item
+                    f(ListOperation::DeleteItem(index), data)
+                } else {
+                    f(ListOperation::EditItem(index), data)
+                }
             }
-        } else if data.confirm_array().is_ok() {
-            // If data is list, this is either Delete List or OverWrite List operation
-            // in either case, we have to first delete the whole list
-            f(ListOperation::DeleteList, data)?;
-            // Now the data must be a list, that should be added item by item
-
-            let container = data.enter().ok_or(Error::Invalid)?;
-            for d in container {
-                f(ListOperation::AddItem, &d)?;
+            Some(Nullable::Null) => {
+                // A NULL list index always appends the data as a single item. This is
+                // also how the items of a chunked list write arrive, after the first
+                // chunk has replaced the list with an empty (or partial) list
+                f(ListOperation::AddItem, data)
+            }
+            None => {
+                // Without a list index, the data must be the whole list. This is either
+                // a Delete List or an OverWrite List operation, in either case, we have
+                // to first delete the whole list
+                if data.confirm_array().is_err() {
+                    return Err(IMStatusCode::ConstraintError);
+                }
+                check(data)?;
+                f(ListOperation::DeleteList, data)?;
+
+                // Now the data must be a list, that should be added item by item
+                let container = data.enter().ok_or(Error::Invalid)?;
+                for d in container {
+                    f(ListOperation::AddItem, &d)?;
+                }
+                Ok(())
             }
-            Ok(())
-        } else {
-            // If data is not a list, this must be an add operation
-            f(ListOperation::AddItem, data)
         }
     }
 
@@ -545,7 +571,7 @@ pub mod ib {
         pub data_ver: u32,
     }
 
-    #[derive(FromTLV, ToTLV, Copy, Clone)]
+    #[derive(Default, FromTLV, ToTLV, Copy, Clone, PartialEq, Debug)]
     #[tlvargs(datatype = "list")]
     pub struct EventPath {
         pub node: Option<u64>,
@@ -555,9 +581,79 @@ pub mod ib {
         pub is_urgent: Option<bool>,
     }
 
+    impl EventPath {
+        pub fn new(
+            endpoint: Option<EndptId>,
+            cluster: Option<ClusterId>,
+            event: Option<u32>,
+        ) -> Self {
+            Self {
+                endpoint,
+                cluster,
+                event,
+                ..Default::default()
+            }
+        }
+
+        /// Whether the event _event_ of _cluster_ on _endpoint_ is on this path
+        pub fn matches(&self, endpoint: EndptId, cluster: ClusterId, event: u32) -> bool {
+            self.endpoint.is_none_or(|e| e == endpoint)
+                && self.cluster.is_none_or(|c| c == cluster)
+                && self.event.is_none_or(|e| e == event)
+        }
+    }
+
     #[derive(FromTLV, ToTLV, Copy, Clone)]
     pub struct EventFilter {
         pub node: Option<u64>,
         pub event_min: Option<u64>,
     }
+
+    impl EventFilter {
+        pub fn new(event_min: u64) -> Self {
+            Self {
+                node: None,
+                event_min: Some(event_min),
+            }
+        }
+    }
+
+    // Event Response
+    #[derive(Clone, Copy, FromTLV, ToTLV, PartialEq, Debug)]
+    #[tlvargs(lifetime = "'a")]
+    pub enum EventResp<'a> {
+        Status(EventStatus),
+        Data(EventData<'a>),
+    }
+
+    impl<'a> EventResp<'a> {
+        pub fn unwrap_data(self) -> EventData<'a> {
+            match self {
+                EventResp::Data(d) => d,
+                _ => {
+                    panic!("No data exists");
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, FromTLV, ToTLV)]
+    pub struct EventStatus {
+        pub path: EventPath,
+        pub status: Status,
+    }
+
+    // The timestamp of an event is always given as an epoch timestamp, in milliseconds
+    #[derive(Debug, Clone, Copy, PartialEq, FromTLV, ToTLV)]
+    #[tlvargs(lifetime = "'a")]
+    pub struct EventData<'a> {
+        pub path: EventPath,
+        pub event_number: u64,
+        pub priority: u8,
+        pub epoch_timestamp: Option<u64>,
+        pub system_timestamp: Option<u64>,
+        pub delta_epoch_timestamp: Option<u64>,
+        pub delta_system_timestamp: Option<u64>,
+        pub data: EncodeValue<'a>,
+    }
 }
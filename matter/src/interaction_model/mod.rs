@@ -75,6 +75,9 @@ pub trait InteractionConsumer {
         _trans: &mut Transaction,
         _tw: &mut TLVWriter,
     ) -> Result<(OpCode, ResponseRequired), Error>;
+
+    /// Called periodically from the transport's loop, for the timers of the consumer
+    fn handle_timer_tick(&self) {}
 }
 
 pub struct InteractionModel {
@@ -69,6 +69,7 @@
 //! Start off exploring by going to the [Matter] object.
 
 pub mod acl;
+pub mod attestation;
 pub mod cert;
 pub mod codec;
 pub mod core;
@@ -84,6 +85,7 @@ pub mod secure_channel;
 pub mod sys;
 pub mod tlv;
 pub mod transport;
+pub mod trust_store;
 pub mod utils;
 
 pub use crate::core::*;
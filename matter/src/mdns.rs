@@ -44,11 +44,22 @@ const SHORT_DISCRIMINATOR_SHIFT: u16 = 8;
 static mut G_MDNS: Option<Arc<Mdns>> = None;
 static INIT: Once = Once::new();
 
+/// The commissioning mode, as advertised in the CM key of the commissionable service
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CommissioningMode {
+    /// Not in commissioning mode
+    Disabled = 0,
+    /// Commissioning window opened with the device's own onboarding payload
+    Basic = 1,
+    /// Commissioning window opened by an administrator with a new verifier
+    Enhanced = 2,
+}
+
 pub enum ServiceMode {
     /// The commissioned state
     Commissioned,
-    /// The commissionable state with the discriminator that should be used
-    Commissionable(u16),
+    /// The commissionable state with the discriminator and commissioning mode that should be used
+    Commissionable(u16, CommissioningMode),
 }
 
 impl Mdns {
@@ -89,15 +100,16 @@ impl Mdns {
             ServiceMode::Commissioned => {
                 sys_publish_service(name, "_matter._tcp", MATTER_PORT, &[])
             }
-            ServiceMode::Commissionable(discriminator) => {
+            ServiceMode::Commissionable(discriminator, comm_mode) => {
                 let inner = self.inner.lock().unwrap();
                 let short = compute_short_discriminator(discriminator);
                 let serv_type = format!("_matterc._udp,_S{},_L{}", short, discriminator);
 
                 let str_discriminator = format!("{}", discriminator);
+                let str_comm_mode = format!("{}", comm_mode as u8);
                 let txt_kvs = [
                     ["D", &str_discriminator],
-                    ["CM", "1"],
+                    ["CM", &str_comm_mode],
                     ["DN", &inner.device_name],
                     ["VP", &format!("{}+{}", inner.vid, inner.pid)],
                     ["SII", "5000"], /* Sleepy Idle Interval */
@@ -18,14 +18,13 @@
 use std::sync::Arc;
 
 use log::{error, trace};
-use owning_ref::RwLockReadGuardRef;
 use rand::prelude::*;
 
 use crate::{
     cert::Cert,
-    crypto::{self, CryptoKeyPair, KeyPair, Sha256},
+    crypto::{self, CryptoSha256},
     error::Error,
-    fabric::{Fabric, FabricMgr, FabricMgrInner},
+    fabric::{Fabric, FabricMgr},
     secure_channel::common::SCStatusCodes,
     secure_channel::common::{self, OpCode},
     tlv::{get_root_node_struct, FromTLV, OctetStr, TLVElement, TLVWriter, TagType},
@@ -48,11 +47,11 @@ pub struct CaseSession {
     state: State,
     peer_sessid: u16,
     local_sessid: u16,
-    tt_hash: Sha256,
+    tt_hash: Box<dyn CryptoSha256>,
     shared_secret: [u8; crypto::ECDH_SHARED_SECRET_LEN_BYTES],
     our_pub_key: [u8; crypto::EC_POINT_LEN_BYTES],
     peer_pub_key: [u8; crypto::EC_POINT_LEN_BYTES],
-    local_fabric_idx: usize,
+    local_fabric_idx: u8,
 }
 impl CaseSession {
     pub fn new(peer_sessid: u16, local_sessid: u16) -> Result<Self, Error> {
@@ -60,7 +59,7 @@ impl CaseSession {
             state: State::Sigma1Rx,
             peer_sessid,
             local_sessid,
-            tt_hash: Sha256::new()?,
+            tt_hash: crypto::get_provider().sha256()?,
             shared_secret: [0; crypto::ECDH_SHARED_SECRET_LEN_BYTES],
             our_pub_key: [0; crypto::EC_POINT_LEN_BYTES],
             peer_pub_key: [0; crypto::EC_POINT_LEN_BYTES],
@@ -89,18 +88,18 @@ impl Case {
         }
         case_session.state = State::Sigma3Rx;
 
-        let fabric = self.fabric_mgr.get_fabric(case_session.local_fabric_idx)?;
-        if fabric.is_none() {
-            common::create_sc_status_report(
-                &mut ctx.tx,
-                common::SCStatusCodes::NoSharedTrustRoots,
-                None,
-            )?;
-            ctx.exch_ctx.exch.close();
-            return Ok(ResponseRequired::Yes);
-        }
-        // Safe to unwrap here
-        let fabric = fabric.as_ref().as_ref().unwrap();
+        let fabric = match self.fabric_mgr.get_fabric(case_session.local_fabric_idx) {
+            Ok(fabric) => fabric,
+            Err(_) => {
+                common::create_sc_status_report(
+                    &mut ctx.tx,
+                    common::SCStatusCodes::NoSharedTrustRoots,
+                    None,
+                )?;
+                ctx.exch_ctx.exch.close();
+                return Ok(ResponseRequired::Yes);
+            }
+        };
 
         let root = get_root_node_struct(ctx.rx.as_borrow_slice())?;
         let encrypted = root.find_tag(1)?.slice()?;
@@ -113,7 +112,7 @@ impl Case {
         let decrypted = &mut decrypted[..encrypted.len()];
         decrypted.copy_from_slice(encrypted);
 
-        let len = Case::get_sigma3_decryption(fabric.ipk.op_key(), &case_session, decrypted)?;
+        let len = Case::get_sigma3_decryption(fabric.ipk().op_key(), &case_session, decrypted)?;
         let decrypted = &decrypted[..len];
 
         let root = get_root_node_struct(decrypted)?;
@@ -124,7 +123,7 @@ impl Case {
         if let Some(icac) = d.initiator_icac {
             initiator_icac = Some(Cert::new(icac.0)?);
         }
-        if let Err(e) = Case::validate_certs(fabric, &initiator_noc, &initiator_icac) {
+        if let Err(e) = Case::validate_certs(&fabric, &initiator_noc, &initiator_icac) {
             error!("Certificate Chain doesn't match: {}", e);
             common::create_sc_status_report(
                 &mut ctx.tx,
@@ -159,7 +158,7 @@ impl Case {
         initiator_noc.get_cat_ids(&mut peer_catids);
         case_session.tt_hash.update(ctx.rx.as_borrow_slice())?;
         let clone_data = Case::get_session_clone_data(
-            fabric.ipk.op_key(),
+            fabric.ipk().op_key(),
             fabric.get_node_id(),
             initiator_noc.get_node_id()?,
             ctx.exch_ctx.sess.get_peer_addr(),
@@ -216,7 +215,7 @@ impl Case {
         );
 
         // Create an ephemeral Key Pair
-        let key_pair = KeyPair::new()?;
+        let key_pair = crypto::get_provider().generate_key_pair()?;
         let _ = key_pair.get_public_key(&mut case_session.our_pub_key)?;
 
         // Derive the Shared Secret
@@ -236,16 +235,18 @@ impl Case {
         let mut encrypted: [u8; MAX_ENCRYPTED_SIZE] = [0; MAX_ENCRYPTED_SIZE];
         let encrypted_len = {
             let mut signature = [0u8; crypto::EC_SIGNATURE_LEN_BYTES];
-            let fabric = self.fabric_mgr.get_fabric(case_session.local_fabric_idx)?;
-            if fabric.is_none() {
-                common::create_sc_status_report(
-                    &mut ctx.tx,
-                    common::SCStatusCodes::NoSharedTrustRoots,
-                    None,
-                )?;
-                ctx.exch_ctx.exch.close();
-                return Ok(ResponseRequired::Yes);
-            }
+            let fabric = match self.fabric_mgr.get_fabric(case_session.local_fabric_idx) {
+                Ok(fabric) => fabric,
+                Err(_) => {
+                    common::create_sc_status_report(
+                        &mut ctx.tx,
+                        common::SCStatusCodes::NoSharedTrustRoots,
+                        None,
+                    )?;
+                    ctx.exch_ctx.exch.close();
+                    return Ok(ResponseRequired::Yes);
+                }
+            };
 
             let sign_len = Case::get_sigma2_sign(
                 &fabric,
@@ -289,7 +290,7 @@ impl Case {
         let mut session_keys = [0_u8; 3 * crypto::SYMM_KEY_LEN_BYTES];
         Case::get_session_keys(
             ipk,
-            &case_session.tt_hash,
+            case_session.tt_hash.as_ref(),
             &case_session.shared_secret,
             &mut session_keys,
         )?;
@@ -300,10 +301,7 @@ impl Case {
             case_session.peer_sessid,
             case_session.local_sessid,
             peer_addr,
-            SessionMode::Case(CaseDetails::new(
-                case_session.local_fabric_idx as u8,
-                peer_catids,
-            )),
+            SessionMode::Case(CaseDetails::new(case_session.local_fabric_idx, peer_catids)),
         );
 
         clone_data.dec_key.copy_from_slice(&session_keys[0..16]);
@@ -334,35 +332,22 @@ impl Case {
         tw.str8(TagType::Context(4), &case_session.our_pub_key)?;
         tw.end_container()?;
 
-        let key = KeyPair::new_from_public(initiator_noc_cert.get_pubkey())?;
+        let key = crypto::get_provider().key_pair_from_public(initiator_noc_cert.get_pubkey())?;
         key.verify_msg(write_buf.as_slice(), sign)?;
         Ok(())
     }
 
     fn validate_certs(fabric: &Fabric, noc: &Cert, icac: &Option<Cert>) -> Result<(), Error> {
-        let mut verifier = noc.verify_chain_start();
-
         if fabric.get_fabric_id() != noc.get_fabric_id()? {
             return Err(Error::Invalid);
         }
 
-        if let Some(icac) = icac {
-            // If ICAC is present handle it
-            if let Ok(fid) = icac.get_fabric_id() {
-                if fid != fabric.get_fabric_id() {
-                    return Err(Error::Invalid);
-                }
-            }
-            verifier = verifier.add_cert(icac)?;
-        }
-
-        verifier.add_cert(&fabric.root_ca)?.finalise()?;
-        Ok(())
+        noc.verify_noc_chain(icac.as_ref(), &fabric.root_ca)
     }
 
     fn get_session_keys(
         ipk: &[u8],
-        tt: &Sha256,
+        tt: &dyn CryptoSha256,
         shared_secret: &[u8],
         key: &mut [u8],
     ) -> Result<(), Error> {
@@ -374,7 +359,7 @@ impl Case {
         }
         let mut salt = Vec::<u8>::with_capacity(256);
         salt.extend_from_slice(ipk);
-        let tt = tt.clone();
+        let tt = tt.box_clone();
         let mut tt_hash = [0u8; crypto::SHA256_HASH_LEN_BYTES];
         tt.finish(&mut tt_hash)?;
         salt.extend_from_slice(&tt_hash);
@@ -395,7 +380,7 @@ impl Case {
         let mut sigma3_key = [0_u8; crypto::SYMM_KEY_LEN_BYTES];
         Case::get_sigma3_key(
             ipk,
-            &case_session.tt_hash,
+            case_session.tt_hash.as_ref(),
             &case_session.shared_secret,
             &mut sigma3_key,
         )?;
@@ -412,7 +397,7 @@ impl Case {
 
     fn get_sigma3_key(
         ipk: &[u8],
-        tt: &Sha256,
+        tt: &dyn CryptoSha256,
         shared_secret: &[u8],
         key: &mut [u8],
     ) -> Result<(), Error> {
@@ -423,7 +408,7 @@ impl Case {
         let mut salt = Vec::<u8>::with_capacity(256);
         salt.extend_from_slice(ipk);
 
-        let tt = tt.clone();
+        let tt = tt.box_clone();
 
         let mut tt_hash = [0u8; crypto::SHA256_HASH_LEN_BYTES];
         tt.finish(&mut tt_hash)?;
@@ -452,7 +437,7 @@ impl Case {
         salt.extend_from_slice(our_random);
         salt.extend_from_slice(&case_session.our_pub_key);
 
-        let tt = case_session.tt_hash.clone();
+        let tt = case_session.tt_hash.box_clone();
 
         let mut tt_hash = [0u8; crypto::SHA256_HASH_LEN_BYTES];
         tt.finish(&mut tt_hash)?;
@@ -467,7 +452,7 @@ impl Case {
     }
 
     fn get_sigma2_encryption(
-        fabric: &RwLockReadGuardRef<FabricMgrInner, Option<Fabric>>,
+        fabric: &Fabric,
         our_random: &[u8],
         case_session: &mut CaseSession,
         signature: &[u8],
@@ -476,12 +461,9 @@ impl Case {
         let mut resumption_id: [u8; 16] = [0; 16];
         rand::thread_rng().fill_bytes(&mut resumption_id);
 
-        // We are guaranteed this unwrap will work
-        let fabric = fabric.as_ref().as_ref().unwrap();
-
         let mut sigma2_key = [0_u8; crypto::SYMM_KEY_LEN_BYTES];
         Case::get_sigma2_key(
-            fabric.ipk.op_key(),
+            fabric.ipk().op_key(),
             our_random,
             case_session,
             &mut sigma2_key,
@@ -521,13 +503,11 @@ impl Case {
     }
 
     fn get_sigma2_sign(
-        fabric: &RwLockReadGuardRef<FabricMgrInner, Option<Fabric>>,
+        fabric: &Fabric,
         our_pub_key: &[u8],
         peer_pub_key: &[u8],
         signature: &mut [u8],
     ) -> Result<usize, Error> {
-        // We are guaranteed this unwrap will work
-        let fabric = fabric.as_ref().as_ref().unwrap();
         const MAX_TBS_SIZE: usize = 800;
         let mut buf: [u8; MAX_TBS_SIZE] = [0; MAX_TBS_SIZE];
         let mut write_buf = WriteBuf::new(&mut buf, MAX_TBS_SIZE);
@@ -27,7 +27,7 @@ use crate::{
 use log::{error, info};
 use num;
 
-use super::{case::Case, pake::PaseMgr};
+use super::{case::Case, msg_ctr_sync::MsgCtrSync, pake::PaseMgr};
 
 /* Handle messages related to the Secure Channel
  */
@@ -35,6 +35,7 @@ use super::{case::Case, pake::PaseMgr};
 pub struct SecureChannel {
     case: Case,
     pase: PaseMgr,
+    msg_ctr_sync: MsgCtrSync,
 }
 
 impl SecureChannel {
@@ -42,8 +43,13 @@ impl SecureChannel {
         SecureChannel {
             pase,
             case: Case::new(fabric_mgr),
+            msg_ctr_sync: MsgCtrSync::new(),
         }
     }
+
+    pub fn msg_ctr_sync(&mut self) -> &mut MsgCtrSync {
+        &mut self.msg_ctr_sync
+    }
 }
 
 impl proto_demux::HandleProto for SecureChannel {
@@ -56,6 +62,8 @@ impl proto_demux::HandleProto for SecureChannel {
         tlv::print_tlv_list(ctx.rx.as_borrow_slice());
         let result = match proto_opcode {
             OpCode::MRPStandAloneAck => Ok(ResponseRequired::No),
+            OpCode::MsgCounterSyncReq => self.msg_ctr_sync.sync_req_handler(ctx),
+            OpCode::MsgCounterSyncResp => self.msg_ctr_sync.sync_rsp_handler(ctx),
             OpCode::PBKDFParamRequest => self.pase.pbkdfparamreq_handler(ctx),
             OpCode::PASEPake1 => self.pase.pasepake1_handler(ctx),
             OpCode::PASEPake3 => self.pase.pasepake3_handler(ctx),
@@ -76,4 +84,9 @@ impl proto_demux::HandleProto for SecureChannel {
     fn get_proto_id(&self) -> usize {
         PROTO_ID_SECURE_CHANNEL
     }
+
+    fn handle_timer_tick(&mut self) -> Result<(), Error> {
+        self.pase.handle_comm_window_expiry();
+        Ok(())
+    }
 }
@@ -0,0 +1,165 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+use log::{error, info};
+use rand::prelude::*;
+
+use crate::{
+    error::Error,
+    transport::{
+        dedup::{GroupPeerCtrs, MSG_CTR_SYNC_CHALLENGE_LEN},
+        packet::Packet,
+        proto_demux::{ProtoCtx, ResponseRequired},
+    },
+};
+
+use super::common::{OpCode, PROTO_ID_SECURE_CHANNEL};
+
+// The Global Group Encrypted Data Message Counter is initialised to a random
+// value in this range
+const MAX_INITIAL_GROUP_DATA_CTR: u32 = 1 << 28;
+
+const MSG_CTR_SYNC_RSP_LEN: usize = 4 + MSG_CTR_SYNC_CHALLENGE_LEN;
+
+/// The Message Counter Synchronization protocol
+///
+/// As a responder, we share our group data message counter with peers that want to
+/// accept our group messages. As a requester, we synchronize the counters of the
+/// peers whose group messages we receive, these are maintained in [GroupPeerCtrs].
+///
+/// The clones share the counter state, so that the group receive path
+/// ([GroupRx](crate::transport::group::GroupRx)) can start the synchronizations
+/// whose responses the secure channel handles.
+#[derive(Clone)]
+pub struct MsgCtrSync {
+    group_ctrs: Arc<Mutex<GroupPeerCtrs>>,
+    group_data_ctr: Arc<AtomicU32>,
+}
+
+impl Default for MsgCtrSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MsgCtrSync {
+    pub fn new() -> Self {
+        Self {
+            group_ctrs: Arc::new(Mutex::new(GroupPeerCtrs::new())),
+            group_data_ctr: Arc::new(AtomicU32::new(
+                rand::thread_rng().gen_range(1..=MAX_INITIAL_GROUP_DATA_CTR),
+            )),
+        }
+    }
+
+    /// The counter state of the peers that send us group messages
+    pub fn group_ctrs(&self) -> Arc<Mutex<GroupPeerCtrs>> {
+        self.group_ctrs.clone()
+    }
+
+    /// Advance our group data message counter, returning the counter of the next
+    /// group message that we send
+    ///
+    /// The peers that synchronized with us only accept counters beyond the one
+    /// we shared in the MsgCounterSyncRsp
+    pub fn next_group_data_ctr(&self) -> u32 {
+        self.group_data_ctr
+            .fetch_add(1, Ordering::SeqCst)
+            .wrapping_add(1)
+    }
+
+    /// Create a MsgCounterSyncReq for a peer whose group messages we want to accept
+    ///
+    /// This should be sent over a unicast secure session with the peer
+    pub fn create_sync_req(&self, fab_idx: u8, node_id: u64, tx: &mut Packet) -> Result<(), Error> {
+        let challenge = self.group_ctrs.lock()?.start_sync(fab_idx, node_id);
+        tx.set_proto_id(PROTO_ID_SECURE_CHANNEL as u16);
+        tx.set_proto_opcode(OpCode::MsgCounterSyncReq as u8);
+        tx.get_writebuf()?.append(&challenge)
+    }
+
+    pub fn sync_req_handler(&mut self, ctx: &mut ProtoCtx) -> Result<ResponseRequired, Error> {
+        if !ctx.exch_ctx.sess.is_encrypted() {
+            error!("MsgCounterSyncReq is only allowed over a secure session");
+            return Err(Error::Invalid);
+        }
+        let challenge = ctx.rx.as_borrow_slice();
+        if challenge.len() != MSG_CTR_SYNC_CHALLENGE_LEN {
+            error!("Invalid MsgCounterSyncReq length: {}", challenge.len());
+            return Err(Error::Invalid);
+        }
+        let mut response = [0_u8; MSG_CTR_SYNC_CHALLENGE_LEN];
+        response.copy_from_slice(challenge);
+
+        ctx.tx.set_proto_opcode(OpCode::MsgCounterSyncResp as u8);
+        let wb = ctx.tx.get_writebuf()?;
+        wb.le_u32(self.group_data_ctr.load(Ordering::SeqCst))?;
+        wb.append(&response)?;
+        ctx.exch_ctx.exch.close();
+        Ok(ResponseRequired::Yes)
+    }
+
+    pub fn sync_rsp_handler(&mut self, ctx: &mut ProtoCtx) -> Result<ResponseRequired, Error> {
+        ctx.exch_ctx.exch.close();
+        let (fab_idx, node_id) = match (
+            ctx.exch_ctx.sess.get_local_fabric_idx(),
+            ctx.exch_ctx.sess.get_peer_node_id(),
+        ) {
+            (Some(fab_idx), Some(node_id)) => (fab_idx, node_id),
+            _ => {
+                error!("MsgCounterSyncRsp is only allowed over a CASE session");
+                return Err(Error::Invalid);
+            }
+        };
+
+        let rsp = ctx.rx.as_borrow_slice();
+        if rsp.len() != MSG_CTR_SYNC_RSP_LEN {
+            error!("Invalid MsgCounterSyncRsp length: {}", rsp.len());
+            return Err(Error::Invalid);
+        }
+        let sync_ctr = LittleEndian::read_u32(&rsp[..4]);
+        self.group_ctrs
+            .lock()?
+            .complete_sync(fab_idx, node_id, &rsp[4..], sync_ctr)?;
+        info!(
+            "Synchronized group counter of node {:x} to {}",
+            node_id, sync_ctr
+        );
+        Ok(ResponseRequired::No)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MsgCtrSync;
+
+    #[test]
+    fn test_group_data_ctr_is_shared() {
+        let msg_ctr_sync = MsgCtrSync::new();
+        let clone = msg_ctr_sync.clone();
+
+        let ctr = msg_ctr_sync.next_group_data_ctr();
+        assert_eq!(clone.next_group_data_ctr(), ctr + 1);
+        assert_eq!(msg_ctr_sync.next_group_data_ctr(), ctr + 2);
+    }
+}
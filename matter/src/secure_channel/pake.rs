@@ -17,7 +17,7 @@
 
 use std::{
     sync::{Arc, Mutex},
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 
 use super::{
@@ -27,7 +27,7 @@ use super::{
 use crate::{
     crypto,
     error::Error,
-    mdns::{self, Mdns},
+    mdns::{self, CommissioningMode, Mdns},
     secure_channel::common::OpCode,
     sys::SysMdnsService,
     tlv::{self, get_root_node_struct, FromTLV, OctetStr, TLVElement, TLVWriter, TagType, ToTLV},
@@ -42,8 +42,45 @@ use crate::{
 use log::{error, info};
 use rand::prelude::*;
 
+/// The minimum duration for which a commissioning window can be opened
+pub const MIN_COMM_WINDOW_TIMEOUT: Duration = Duration::from_secs(3 * 60);
+/// The maximum duration for which a commissioning window can be opened
+pub const MAX_COMM_WINDOW_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// The commissioning window is closed after these many failed PASE attempts
+pub const MAX_FAILED_PASE_ATTEMPTS: u8 = 20;
+
+struct CommWindow {
+    mode: CommissioningMode,
+    // The window stays open until the device is commissioned if this isn't set
+    expiry: Option<Instant>,
+    failed_attempts: u8,
+    // The fabric index of the administrator that opened this window, if any
+    admin_fab_idx: Option<u8>,
+}
+
+impl CommWindow {
+    fn new(mode: CommissioningMode, timeout: Option<Duration>, admin_fab_idx: Option<u8>) -> Self {
+        Self {
+            mode,
+            expiry: timeout.map(|timeout| Instant::now() + timeout),
+            failed_attempts: 0,
+            admin_fab_idx,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expiry.is_some_and(|expiry| Instant::now() >= expiry)
+    }
+
+    /// Records a failed PASE attempt, returns true if the window should now be closed
+    fn record_failed_attempt(&mut self) -> bool {
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+        self.failed_attempts >= MAX_FAILED_PASE_ATTEMPTS
+    }
+}
+
 enum PaseMgrState {
-    Enabled(PAKE, SysMdnsService),
+    Enabled(Box<PAKE>, SysMdnsService, CommWindow),
     Disabled,
 }
 
@@ -51,6 +88,17 @@ pub struct PaseMgrInternal {
     state: PaseMgrState,
 }
 
+impl PaseMgrInternal {
+    fn close_if_expired(&mut self) {
+        if let PaseMgrState::Enabled(_, _, window) = &self.state {
+            if window.is_expired() {
+                info!("Commissioning window expired, closing it");
+                self.state = PaseMgrState::Disabled;
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 // Could this lock be avoided?
 pub struct PaseMgr(Arc<Mutex<PaseMgrInternal>>);
@@ -62,37 +110,89 @@ impl PaseMgr {
         })))
     }
 
+    /// Opens a commissioning window
+    ///
+    /// The window is closed automatically after _timeout_, after a successful PASE
+    /// session establishment, or after [MAX_FAILED_PASE_ATTEMPTS] failed attempts.
+    /// Without a _timeout_, the window stays open until the device is commissioned.
+    /// _admin_fab_idx_ is the fabric index of the administrator opening the window, if any.
     pub fn enable_pase_session(
         &mut self,
         verifier: VerifierData,
         discriminator: u16,
+        mode: CommissioningMode,
+        timeout: Option<Duration>,
+        admin_fab_idx: Option<u8>,
     ) -> Result<(), Error> {
         let mut s = self.0.lock().unwrap();
+        s.close_if_expired();
+        if let PaseMgrState::Enabled(_, _, _) = &s.state {
+            error!("Commissioning window is already open");
+            return Err(Error::InvalidState);
+        }
+
         let name: u64 = rand::thread_rng().gen_range(0..0xFFFFFFFFFFFFFFFF);
         let name = format!("{:016X}", name);
-        let mdns = Mdns::get()?
-            .publish_service(&name, mdns::ServiceMode::Commissionable(discriminator))?;
-        s.state = PaseMgrState::Enabled(PAKE::new(verifier), mdns);
+        let mdns = Mdns::get()?.publish_service(
+            &name,
+            mdns::ServiceMode::Commissionable(discriminator, mode),
+        )?;
+        s.state = PaseMgrState::Enabled(
+            Box::new(PAKE::new(verifier)),
+            mdns,
+            CommWindow::new(mode, timeout, admin_fab_idx),
+        );
         Ok(())
     }
 
+    /// Closes the commissioning window, if one is open
+    ///
+    /// This also withdraws the commissionable mDNS service.
     pub fn disable_pase_session(&mut self) {
         let mut s = self.0.lock().unwrap();
         s.state = PaseMgrState::Disabled;
     }
 
+    /// Closes the commissioning window if its timeout has elapsed
+    pub fn handle_comm_window_expiry(&mut self) {
+        self.0.lock().unwrap().close_if_expired();
+    }
+
+    /// Returns the mode of the currently open commissioning window
+    pub fn get_comm_window_mode(&self) -> CommissioningMode {
+        let mut s = self.0.lock().unwrap();
+        s.close_if_expired();
+        match &s.state {
+            PaseMgrState::Enabled(_, _, window) => window.mode,
+            PaseMgrState::Disabled => CommissioningMode::Disabled,
+        }
+    }
+
+    /// Returns the fabric index of the administrator that opened the current
+    /// commissioning window
+    pub fn get_comm_window_admin(&self) -> Option<u8> {
+        let mut s = self.0.lock().unwrap();
+        s.close_if_expired();
+        match &s.state {
+            PaseMgrState::Enabled(_, _, window) => window.admin_fab_idx,
+            PaseMgrState::Disabled => None,
+        }
+    }
+
     /// If the PASE Session is enabled, execute the closure,
     /// if not enabled, generate SC Status Report
-    fn if_enabled<F>(&mut self, ctx: &mut ProtoCtx, f: F) -> Result<(), Error>
+    fn if_enabled<F, T>(&mut self, ctx: &mut ProtoCtx, f: F) -> Result<Option<T>, Error>
     where
-        F: FnOnce(&mut PAKE, &mut ProtoCtx) -> Result<(), Error>,
+        F: FnOnce(&mut PAKE, &mut ProtoCtx) -> Result<T, Error>,
     {
         let mut s = self.0.lock().unwrap();
-        if let PaseMgrState::Enabled(pake, _) = &mut s.state {
-            f(pake, ctx)
+        s.close_if_expired();
+        if let PaseMgrState::Enabled(pake, _, _) = &mut s.state {
+            f(pake, ctx).map(Some)
         } else {
             error!("PASE Not enabled");
-            create_sc_status_report(&mut ctx.tx, SCStatusCodes::InvalidParameter, None)
+            create_sc_status_report(&mut ctx.tx, SCStatusCodes::InvalidParameter, None)?;
+            Ok(None)
         }
     }
 
@@ -109,8 +209,19 @@ impl PaseMgr {
     }
 
     pub fn pasepake3_handler(&mut self, ctx: &mut ProtoCtx) -> Result<ResponseRequired, Error> {
-        self.if_enabled(ctx, |pake, ctx| pake.handle_pasepake3(ctx))?;
-        self.disable_pase_session();
+        match self.if_enabled(ctx, |pake, ctx| pake.handle_pasepake3(ctx))? {
+            Some(true) => self.disable_pase_session(),
+            Some(false) => {
+                let mut s = self.0.lock().unwrap();
+                if let PaseMgrState::Enabled(_, _, window) = &mut s.state {
+                    if window.record_failed_attempt() {
+                        error!("Too many failed PASE attempts, closing commissioning window");
+                        s.state = PaseMgrState::Disabled;
+                    }
+                }
+            }
+            None => (),
+        }
         Ok(ResponseRequired::Yes)
     }
 }
@@ -209,14 +320,16 @@ impl PAKE {
         }
     }
 
+    /// Returns true if the PASE session was successfully established
     #[allow(non_snake_case)]
-    pub fn handle_pasepake3(&mut self, ctx: &mut ProtoCtx) -> Result<(), Error> {
+    pub fn handle_pasepake3(&mut self, ctx: &mut ProtoCtx) -> Result<bool, Error> {
         let mut sd = self.state.take_sess_data(&ctx.exch_ctx)?;
 
         let cA = extract_pasepake_1_or_3_params(ctx.rx.as_borrow_slice())?;
         let (status_code, Ke) = sd.spake2p.handle_cA(cA);
+        let success = status_code == SCStatusCodes::SessionEstablishmentSuccess;
 
-        if status_code == SCStatusCodes::SessionEstablishmentSuccess {
+        if success {
             // Get the keys
             let Ke = Ke.ok_or(Error::Invalid)?;
             let mut session_keys: [u8; 48] = [0; 48];
@@ -247,7 +360,7 @@ impl PAKE {
 
         create_sc_status_report(&mut ctx.tx, status_code, None)?;
         ctx.exch_ctx.exch.close();
-        Ok(())
+        Ok(success)
     }
 
     #[allow(non_snake_case)]
@@ -363,3 +476,41 @@ struct PBKDFParamReq<'a> {
     passcode_id: u16,
     has_params: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{CommWindow, MAX_FAILED_PASE_ATTEMPTS};
+    use crate::mdns::CommissioningMode;
+
+    #[test]
+    fn test_comm_window_expiry() {
+        let mut w = CommWindow::new(
+            CommissioningMode::Basic,
+            Some(Duration::from_secs(180)),
+            None,
+        );
+        assert!(!w.is_expired());
+
+        w.expiry = Some(Instant::now() - Duration::from_secs(1));
+        assert!(w.is_expired());
+
+        // Without a timeout, the window doesn't expire
+        let w = CommWindow::new(CommissioningMode::Basic, None, None);
+        assert!(!w.is_expired());
+    }
+
+    #[test]
+    fn test_comm_window_failed_attempts() {
+        let mut w = CommWindow::new(
+            CommissioningMode::Enhanced,
+            Some(Duration::from_secs(180)),
+            Some(1),
+        );
+        for _ in 1..MAX_FAILED_PASE_ATTEMPTS {
+            assert!(!w.record_failed_attempt());
+        }
+        assert!(w.record_failed_attempt());
+    }
+}
@@ -15,32 +15,17 @@
  *    limitations under the License.
  */
 
-use crate::{
-    crypto::{self, HmacSha256},
-    sys,
-};
+use crate::{crypto, sys};
 use byteorder::{ByteOrder, LittleEndian};
 use log::error;
 use rand::prelude::*;
 use subtle::ConstantTimeEq;
 
 use crate::{
-    crypto::{pbkdf2_hmac, Sha256},
+    crypto::{pbkdf2_hmac, CryptoSha256},
     error::Error,
 };
 
-#[cfg(feature = "crypto_openssl")]
-use super::crypto_openssl::CryptoOpenSSL;
-
-#[cfg(feature = "crypto_mbedtls")]
-use super::crypto_mbedtls::CryptoMbedTLS;
-
-#[cfg(feature = "crypto_esp_mbedtls")]
-use super::crypto_esp_mbedtls::CryptoEspMbedTls;
-
-#[cfg(feature = "crypto_rustcrypto")]
-use super::crypto_rustcrypto::CryptoRustCrypto;
-
 use super::{common::SCStatusCodes, crypto::CryptoSpake2};
 
 // This file handle Spake2+ specific instructions. In itself, this file is
@@ -71,7 +56,7 @@ pub enum Spake2Mode {
 #[allow(non_snake_case)]
 pub struct Spake2P {
     mode: Spake2Mode,
-    context: Option<Sha256>,
+    context: Option<Box<dyn CryptoSha256>>,
     Ke: [u8; 16],
     cA: [u8; 32],
     crypto_spake2: Option<Box<dyn CryptoSpake2>>,
@@ -84,27 +69,11 @@ const CRYPTO_GROUP_SIZE_BYTES: usize = 32;
 const CRYPTO_W_SIZE_BYTES: usize = CRYPTO_GROUP_SIZE_BYTES + 8;
 const CRYPTO_PUBLIC_KEY_SIZE_BYTES: usize = (2 * CRYPTO_GROUP_SIZE_BYTES) + 1;
 
-const MAX_SALT_SIZE_BYTES: usize = 32;
-const VERIFIER_SIZE_BYTES: usize = CRYPTO_GROUP_SIZE_BYTES + CRYPTO_PUBLIC_KEY_SIZE_BYTES;
-
-#[cfg(feature = "crypto_openssl")]
-fn crypto_spake2_new() -> Result<Box<dyn CryptoSpake2>, Error> {
-    Ok(Box::new(CryptoOpenSSL::new()?))
-}
-
-#[cfg(feature = "crypto_mbedtls")]
-fn crypto_spake2_new() -> Result<Box<dyn CryptoSpake2>, Error> {
-    Ok(Box::new(CryptoMbedTLS::new()?))
-}
-
-#[cfg(feature = "crypto_esp_mbedtls")]
-fn crypto_spake2_new() -> Result<Box<dyn CryptoSpake2>, Error> {
-    Ok(Box::new(CryptoEspMbedTls::new()?))
-}
+pub const MAX_SALT_SIZE_BYTES: usize = 32;
+pub const VERIFIER_SIZE_BYTES: usize = CRYPTO_GROUP_SIZE_BYTES + CRYPTO_PUBLIC_KEY_SIZE_BYTES;
 
-#[cfg(feature = "crypto_rustcrypto")]
 fn crypto_spake2_new() -> Result<Box<dyn CryptoSpake2>, Error> {
-    Ok(Box::new(CryptoRustCrypto::new()?))
+    crypto::get_provider().spake2()
 }
 
 impl Default for Spake2P {
@@ -113,6 +82,7 @@ impl Default for Spake2P {
     }
 }
 
+#[derive(Clone)]
 pub struct VerifierData {
     pub data: VerifierOption,
     // For the VerifierOption::Verifier, the following fields only serve
@@ -121,6 +91,7 @@ pub struct VerifierData {
     pub count: u32,
 }
 
+#[derive(Clone)]
 pub enum VerifierOption {
     /// With Password
     Password(u32),
@@ -178,7 +149,7 @@ impl Spake2P {
     }
 
     pub fn set_context(&mut self, buf1: &[u8], buf2: &[u8]) -> Result<(), Error> {
-        let mut context = Sha256::new()?;
+        let mut context = crypto::get_provider().sha256()?;
         context.update(&SPAKE2P_CONTEXT_PREFIX)?;
         context.update(buf1)?;
         context.update(buf2)?;
@@ -290,11 +261,12 @@ impl Spake2P {
         let KcB = &KcAKcB[(KcAKcB.len() / 2)..];
 
         // Step 3: cA = HMAC(KcA, pB), cB = HMAC(KcB, pA)
-        let mut mac = HmacSha256::new(KcA)?;
+        let provider = crypto::get_provider();
+        let mut mac = provider.hmac_sha256(KcA)?;
         mac.update(pB)?;
         mac.finish(cA)?;
 
-        let mut mac = HmacSha256::new(KcB)?;
+        let mut mac = provider.hmac_sha256(KcB)?;
         mac.update(pA)?;
         mac.finish(cB)?;
         Ok(())
@@ -340,7 +312,7 @@ mod tests {
             let mut cA: [u8; 32] = [0; 32];
             let mut cB: [u8; 32] = [0; 32];
             let mut TT_hash = [0u8; crypto::SHA256_HASH_LEN_BYTES];
-            let mut h = crypto::Sha256::new().unwrap();
+            let mut h = crypto::get_provider().sha256().unwrap();
             h.update(&t.TT[0..t.TT_len]).unwrap();
             h.finish(&mut TT_hash).unwrap();
             Spake2P::get_Ke_and_cAcB(&TT_hash, &t.X, &t.Y, &mut Ke, &mut cA, &mut cB).unwrap();
@@ -17,12 +17,20 @@
 
 use std::{
     convert::TryInto,
-    fs::{remove_file, DirBuilder, File},
+    fs::{self, remove_file, DirBuilder, File, OpenOptions},
     io::{Read, Write},
-    sync::{Arc, Mutex, Once},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::PathBuf,
+    sync::{Arc, Mutex, Once, RwLock},
 };
 
-use crate::error::Error;
+use log::error;
+use rand::prelude::*;
+
+use crate::{
+    crypto::{self, CryptoKeyPair, KeyHandle, KeyStore},
+    error::Error,
+};
 
 pub const SPAKE2_ITERATION_COUNT: u32 = 2000;
 
@@ -30,11 +38,39 @@ pub const SPAKE2_ITERATION_COUNT: u32 = 2000;
 // higher values unlike embedded systems
 pub const MAX_PACKET_POOL_SIZE: usize = 25;
 
+/// The persistent storage
+///
+/// Values are stored as plain files by default. Once [Psm::enable_encryption] is
+/// called, every value is sealed with AES-CCM using a key derived from the device key,
+/// and values that fail the integrity check on load are rejected with
+/// [Error::StorageCorrupted].
 pub struct Psm {}
 
 static mut G_PSM: Option<Arc<Mutex<Psm>>> = None;
 static INIT: Once = Once::new();
 
+// The storage key is kept outside of the Psm lock, since the key store also needs it
+static G_STORAGE_KEY: RwLock<Option<[u8; crypto::SYMM_KEY_LEN_BYTES]>> = RwLock::new(None);
+
+// Seal the value if encryption is enabled
+fn seal_value(key: &str, val: &[u8]) -> Result<Vec<u8>, Error> {
+    match G_STORAGE_KEY.read()?.as_ref() {
+        Some(storage_key) => seal(storage_key, key, val),
+        None => Ok(val.to_vec()),
+    }
+}
+
+// Unseal the value if encryption is enabled
+fn unseal_value(key: &str, val: Vec<u8>) -> Result<Vec<u8>, Error> {
+    match G_STORAGE_KEY.read()?.as_ref() {
+        Some(storage_key) => unseal(storage_key, key, &val).map_err(|e| {
+            error!("Integrity check failed for the stored value: {}", key);
+            e
+        }),
+        None => Ok(val),
+    }
+}
+
 const PSM_DIR: &str = "/tmp/matter_psm";
 
 macro_rules! psm_path {
@@ -64,28 +100,44 @@ impl Psm {
         }
     }
 
+    /// Encrypt all the values with a key derived from _device_key_
+    ///
+    /// The device key must be unique to the device and at least 16 bytes long. This
+    /// should be called before the [Matter](crate::Matter) object is created. Values
+    /// that were stored before encryption was enabled can't be read anymore.
+    pub fn enable_encryption(&self, device_key: &[u8]) -> Result<(), Error> {
+        if device_key.len() < crypto::SYMM_KEY_LEN_BYTES {
+            return Err(Error::InvalidKeyLength);
+        }
+        let mut key = [0_u8; crypto::SYMM_KEY_LEN_BYTES];
+        crypto::hkdf_sha256(&[], device_key, &STORAGE_KEY_INFO, &mut key)?;
+        *G_STORAGE_KEY.write()? = Some(key);
+        Ok(())
+    }
+
     pub fn set_kv_slice(&self, key: &str, val: &[u8]) -> Result<(), Error> {
+        let val = seal_value(key, val)?;
         let mut f = File::create(psm_path!(key))?;
-        f.write_all(val)?;
+        f.write_all(&val)?;
         Ok(())
     }
 
     pub fn get_kv_slice(&self, key: &str, val: &mut Vec<u8>) -> Result<usize, Error> {
         let mut f = File::open(psm_path!(key))?;
-        let len = f.read_to_end(val)?;
-        Ok(len)
+        let mut stored = Vec::new();
+        f.read_to_end(&mut stored)?;
+        let stored = unseal_value(key, stored)?;
+        val.extend_from_slice(&stored);
+        Ok(stored.len())
     }
 
     pub fn set_kv_u64(&self, key: &str, val: u64) -> Result<(), Error> {
-        let mut f = File::create(psm_path!(key))?;
-        f.write_all(&val.to_be_bytes())?;
-        Ok(())
+        self.set_kv_slice(key, &val.to_be_bytes())
     }
 
     pub fn get_kv_u64(&self, key: &str, val: &mut u64) -> Result<(), Error> {
-        let mut f = File::open(psm_path!(key))?;
         let mut vec = Vec::new();
-        let _ = f.read_to_end(&mut vec)?;
+        self.get_kv_slice(key, &mut vec)?;
         *val = u64::from_be_bytes(vec.as_slice().try_into()?);
         Ok(())
     }
@@ -94,3 +146,226 @@ impl Psm {
         let _ = remove_file(psm_path!(key));
     }
 }
+
+const STORAGE_KEY_INFO: [u8; 15] = *b"PSM Storage Key";
+// Marks a sealed value, followed by the nonce and the cipher text with its MIC
+const SEALED_MAGIC: [u8; 4] = *b"MPS1";
+const SEALED_HDR_LEN: usize = SEALED_MAGIC.len() + crypto::AEAD_NONCE_LEN_BYTES;
+
+// The name of the value is used as the AAD, so that sealed values can't be swapped
+fn seal(storage_key: &[u8], name: &str, val: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut nonce = [0_u8; crypto::AEAD_NONCE_LEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut out = Vec::with_capacity(SEALED_HDR_LEN + val.len() + crypto::AEAD_MIC_LEN_BYTES);
+    out.extend_from_slice(&SEALED_MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(val);
+    out.resize(out.len() + crypto::AEAD_MIC_LEN_BYTES, 0);
+    crypto::encrypt_in_place(
+        storage_key,
+        &nonce,
+        name.as_bytes(),
+        &mut out[SEALED_HDR_LEN..],
+        val.len(),
+    )?;
+    Ok(out)
+}
+
+fn unseal(storage_key: &[u8], name: &str, val: &[u8]) -> Result<Vec<u8>, Error> {
+    if val.len() < SEALED_HDR_LEN + crypto::AEAD_MIC_LEN_BYTES
+        || val[..SEALED_MAGIC.len()] != SEALED_MAGIC
+    {
+        return Err(Error::StorageCorrupted);
+    }
+    let nonce = &val[SEALED_MAGIC.len()..SEALED_HDR_LEN];
+    let mut data = val[SEALED_HDR_LEN..].to_vec();
+    let len = crypto::decrypt_in_place(storage_key, nonce, name.as_bytes(), &mut data)
+        .map_err(|_| Error::StorageCorrupted)?;
+    data.truncate(len);
+    Ok(data)
+}
+
+const KEY_STORE_DIR: &str = "/tmp/matter_keys";
+const KEY_FILE_PREFIX: &str = "key_";
+
+/// A software key store that keeps each key pair in a file of its own
+///
+/// This is a stand-in for platforms that don't have a secure element, the key
+/// files are only protected by the file-system permissions.
+pub struct FileKeyStore {
+    dir: PathBuf,
+    next_handle: Mutex<u32>,
+}
+
+impl FileKeyStore {
+    pub fn new() -> Result<Self, Error> {
+        Self::new_at(KEY_STORE_DIR)
+    }
+
+    pub fn new_at(dir: &str) -> Result<Self, Error> {
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+        // Continue allocating handles after the largest one that is in use
+        let mut last_handle = 0;
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name();
+            if let Some(handle) = name
+                .to_str()
+                .and_then(|n| n.strip_prefix(KEY_FILE_PREFIX))
+                .and_then(|n| n.parse::<u32>().ok())
+            {
+                last_handle = last_handle.max(handle);
+            }
+        }
+
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            next_handle: Mutex::new(last_handle + 1),
+        })
+    }
+
+    fn key_name(handle: KeyHandle) -> String {
+        format!("{}{}", KEY_FILE_PREFIX, handle.0)
+    }
+
+    fn key_path(&self, handle: KeyHandle) -> PathBuf {
+        self.dir.join(Self::key_name(handle))
+    }
+
+    // The key file is: public key length, public key, private key
+    fn save(&self, pub_key: &[u8], priv_key: &[u8]) -> Result<KeyHandle, Error> {
+        let handle = {
+            let mut next_handle = self.next_handle.lock().unwrap();
+            let handle = KeyHandle(*next_handle);
+            *next_handle += 1;
+            handle
+        };
+
+        let mut val = Vec::with_capacity(1 + pub_key.len() + priv_key.len());
+        val.push(pub_key.len() as u8);
+        val.extend_from_slice(pub_key);
+        val.extend_from_slice(priv_key);
+        // The key files are sealed along with the rest of the storage
+        let val = seal_value(&Self::key_name(handle), &val)?;
+
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(self.key_path(handle))?;
+        f.write_all(&val)?;
+        Ok(handle)
+    }
+
+    fn load(&self, handle: KeyHandle) -> Result<Box<dyn CryptoKeyPair>, Error> {
+        let mut buf = Vec::new();
+        File::open(self.key_path(handle))?.read_to_end(&mut buf)?;
+        let buf = unseal_value(&Self::key_name(handle), buf)?;
+
+        let pub_len = *buf.first().ok_or(Error::Invalid)? as usize;
+        if buf.len() <= pub_len + 1 {
+            return Err(Error::Invalid);
+        }
+        let (pub_key, priv_key) = buf[1..].split_at(pub_len);
+        crypto::get_provider().key_pair_from_components(pub_key, priv_key)
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    fn generate(&self) -> Result<KeyHandle, Error> {
+        let key_pair = crypto::get_provider().generate_key_pair()?;
+
+        let mut pub_key = [0_u8; crypto::EC_POINT_LEN_BYTES];
+        let pub_len = key_pair.get_public_key(&mut pub_key)?;
+        let mut priv_key = [0_u8; crypto::BIGNUM_LEN_BYTES];
+        let priv_len = key_pair.get_private_key(&mut priv_key)?;
+
+        self.save(&pub_key[..pub_len], &priv_key[..priv_len])
+    }
+
+    fn import(&self, pub_key: &[u8], priv_key: &[u8]) -> Result<KeyHandle, Error> {
+        self.save(pub_key, priv_key)
+    }
+
+    fn get_public_key(&self, handle: KeyHandle, pub_key: &mut [u8]) -> Result<usize, Error> {
+        self.load(handle)?.get_public_key(pub_key)
+    }
+
+    fn get_csr<'a>(&self, handle: KeyHandle, csr: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        self.load(handle)?.get_csr(csr)
+    }
+
+    fn sign_msg(
+        &self,
+        handle: KeyHandle,
+        msg: &[u8],
+        signature: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.load(handle)?.sign_msg(msg, signature)
+    }
+
+    fn derive_secret(
+        &self,
+        handle: KeyHandle,
+        peer_pub_key: &[u8],
+        secret: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.load(handle)?.derive_secret(peer_pub_key, secret)
+    }
+
+    fn delete(&self, handle: KeyHandle) -> Result<(), Error> {
+        Ok(remove_file(self.key_path(handle))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{seal, unseal};
+    use crate::error::Error;
+
+    const KEY: [u8; 16] = [0x5a; 16];
+
+    #[test]
+    fn test_seal_unseal() {
+        let sealed = seal(&KEY, "fb1noc", b"some value").unwrap();
+        assert_ne!(&sealed[sealed.len() - 10..], b"some value");
+        assert_eq!(unseal(&KEY, "fb1noc", &sealed).unwrap(), b"some value");
+    }
+
+    #[test]
+    fn test_unseal_tampered() {
+        let mut sealed = seal(&KEY, "fb1noc", b"some value").unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 0x01;
+        assert_eq!(
+            unseal(&KEY, "fb1noc", &sealed),
+            Err(Error::StorageCorrupted)
+        );
+    }
+
+    #[test]
+    fn test_unseal_wrong_name_or_key() {
+        let sealed = seal(&KEY, "fb1noc", b"some value").unwrap();
+        assert_eq!(
+            unseal(&KEY, "fb2noc", &sealed),
+            Err(Error::StorageCorrupted)
+        );
+        assert_eq!(
+            unseal(&[0xa5; 16], "fb1noc", &sealed),
+            Err(Error::StorageCorrupted)
+        );
+    }
+
+    #[test]
+    fn test_unseal_plain_text() {
+        assert_eq!(
+            unseal(&KEY, "fb1noc", b"plain text value that is long enough"),
+            Err(Error::StorageCorrupted)
+        );
+        assert_eq!(
+            unseal(&KEY, "fb1noc", b"MPS1"),
+            Err(Error::StorageCorrupted)
+        );
+    }
+}
@@ -15,6 +15,14 @@
  *    limitations under the License.
  */
 
+use std::time::{Duration, SystemTime};
+
+use log::error;
+use rand::prelude::*;
+use subtle::ConstantTimeEq;
+
+use crate::error::Error;
+
 const MSG_RX_STATE_BITMAP_LEN: u32 = 16;
 
 #[derive(Debug)]
@@ -83,10 +91,132 @@ impl RxCtrState {
     }
 }
 
+pub const MSG_CTR_SYNC_CHALLENGE_LEN: usize = 8;
+// The time for which we wait for a MsgCounterSyncRsp
+const MSG_CTR_SYNC_TIMEOUT: Duration = Duration::from_secs(2);
+// The number of peers whose group message counters are tracked
+const MAX_GROUP_PEERS: usize = 16;
+
+#[derive(Debug)]
+enum GroupPeerState {
+    // A MsgCounterSyncReq with this challenge is pending
+    Syncing {
+        challenge: [u8; MSG_CTR_SYNC_CHALLENGE_LEN],
+        expiry: SystemTime,
+    },
+    Synced(RxCtrState),
+}
+
+#[derive(Debug)]
+struct GroupPeer {
+    fab_idx: u8,
+    node_id: u64,
+    state: GroupPeerState,
+}
+
+/// The message counter state of the peers that send us group messages
+///
+/// Group messages from a peer can only be trusted once its counter has been
+/// synchronized through the MsgCounterSyncReq/Rsp exchange.
+#[derive(Debug, Default)]
+pub struct GroupPeerCtrs {
+    peers: Vec<GroupPeer>,
+}
+
+impl GroupPeerCtrs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn find(&mut self, fab_idx: u8, node_id: u64) -> Option<&mut GroupPeer> {
+        self.peers
+            .iter_mut()
+            .find(|p| p.fab_idx == fab_idx && p.node_id == node_id)
+    }
+
+    /// Start synchronizing with a peer, returns the challenge that should be sent
+    /// in the MsgCounterSyncReq
+    pub fn start_sync(&mut self, fab_idx: u8, node_id: u64) -> [u8; MSG_CTR_SYNC_CHALLENGE_LEN] {
+        let mut challenge = [0_u8; MSG_CTR_SYNC_CHALLENGE_LEN];
+        rand::thread_rng().fill_bytes(&mut challenge);
+        let state = GroupPeerState::Syncing {
+            challenge,
+            expiry: SystemTime::now() + MSG_CTR_SYNC_TIMEOUT,
+        };
+
+        if let Some(peer) = self.find(fab_idx, node_id) {
+            peer.state = state;
+        } else {
+            if self.peers.len() >= MAX_GROUP_PEERS {
+                // Make space by dropping the oldest peer
+                self.peers.remove(0);
+            }
+            self.peers.push(GroupPeer {
+                fab_idx,
+                node_id,
+                state,
+            });
+        }
+        challenge
+    }
+
+    /// Complete the synchronization with the contents of the MsgCounterSyncRsp
+    pub fn complete_sync(
+        &mut self,
+        fab_idx: u8,
+        node_id: u64,
+        response: &[u8],
+        sync_ctr: u32,
+    ) -> Result<(), Error> {
+        let peer = self.find(fab_idx, node_id).ok_or(Error::NotFound)?;
+        match &peer.state {
+            GroupPeerState::Syncing { challenge, expiry } => {
+                if SystemTime::now() > *expiry {
+                    error!("MsgCounterSyncRsp received after the timeout");
+                    return Err(Error::Timeout);
+                }
+                if challenge[..].ct_eq(response).unwrap_u8() != 1 {
+                    error!("MsgCounterSyncRsp with an incorrect response");
+                    return Err(Error::Invalid);
+                }
+                // All the messages up to the synchronized counter are treated as duplicates
+                peer.state = GroupPeerState::Synced(RxCtrState::new(sync_ctr));
+                Ok(())
+            }
+            GroupPeerState::Synced(_) => {
+                error!("Unsolicited MsgCounterSyncRsp");
+                Err(Error::InvalidState)
+            }
+        }
+    }
+
+    /// Receive a group message from a peer
+    ///
+    /// Returns None if the peer's counter isn't synchronized yet, otherwise
+    /// whether the message is a duplicate.
+    pub fn recv(&mut self, fab_idx: u8, node_id: u64, msg_ctr: u32) -> Option<bool> {
+        match &mut self.find(fab_idx, node_id)?.state {
+            GroupPeerState::Synced(rx_ctr_state) => Some(rx_ctr_state.recv(msg_ctr, true)),
+            GroupPeerState::Syncing { .. } => None,
+        }
+    }
+
+    /// Whether a MsgCounterSyncReq to the peer is still waiting for its response
+    pub fn is_syncing(&mut self, fab_idx: u8, node_id: u64) -> bool {
+        matches!(
+            self.find(fab_idx, node_id).map(|p| &p.state),
+            Some(GroupPeerState::Syncing { expiry, .. }) if SystemTime::now() <= *expiry
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use super::RxCtrState;
+    use std::time::{Duration, SystemTime};
+
+    use super::{GroupPeerCtrs, GroupPeerState, RxCtrState};
+    use crate::error::Error;
 
     const ENCRYPTED: bool = true;
     const NOT_ENCRYPTED: bool = false;
@@ -203,4 +333,65 @@ mod tests {
         assert_ndup(s.recv(20011, NOT_ENCRYPTED));
         assert_ndup(s.recv(0, NOT_ENCRYPTED));
     }
+
+    #[test]
+    fn group_ctr_sync() {
+        let mut g = GroupPeerCtrs::new();
+        assert_eq!(g.recv(1, 0x1234, 500), None);
+
+        assert!(!g.is_syncing(1, 0x1234));
+
+        let challenge = g.start_sync(1, 0x1234);
+        assert_eq!(g.recv(1, 0x1234, 500), None);
+        assert!(g.is_syncing(1, 0x1234));
+
+        g.complete_sync(1, 0x1234, &challenge, 500).unwrap();
+        assert_eq!(g.recv(1, 0x1234, 500), Some(true));
+        assert_eq!(g.recv(1, 0x1234, 501), Some(false));
+        assert_eq!(g.recv(1, 0x1234, 501), Some(true));
+        assert!(!g.is_syncing(1, 0x1234));
+
+        // Another fabric is tracked independently
+        assert_eq!(g.recv(2, 0x1234, 502), None);
+
+        // A second response is rejected
+        assert_eq!(
+            g.complete_sync(1, 0x1234, &challenge, 10),
+            Err(Error::InvalidState)
+        );
+    }
+
+    #[test]
+    fn group_ctr_sync_bad_response() {
+        let mut g = GroupPeerCtrs::new();
+        let mut challenge = g.start_sync(1, 0x1234);
+        challenge[0] ^= 0x01;
+        assert_eq!(
+            g.complete_sync(1, 0x1234, &challenge, 500),
+            Err(Error::Invalid)
+        );
+        assert_eq!(
+            g.complete_sync(1, 0x1234, &challenge[..4], 500),
+            Err(Error::Invalid)
+        );
+        assert_eq!(
+            g.complete_sync(1, 0x5678, &challenge, 500),
+            Err(Error::NotFound)
+        );
+        assert_eq!(g.recv(1, 0x1234, 501), None);
+    }
+
+    #[test]
+    fn group_ctr_sync_timeout() {
+        let mut g = GroupPeerCtrs::new();
+        let challenge = g.start_sync(1, 0x1234);
+        if let GroupPeerState::Syncing { expiry, .. } = &mut g.peers[0].state {
+            *expiry = SystemTime::now() - Duration::from_secs(1);
+        }
+        assert!(!g.is_syncing(1, 0x1234));
+        assert_eq!(
+            g.complete_sync(1, 0x1234, &challenge, 500),
+            Err(Error::Timeout)
+        );
+    }
 }
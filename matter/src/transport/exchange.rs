@@ -18,6 +18,7 @@
 use boxslab::{BoxSlab, Slab};
 use colored::*;
 use log::{error, info, trace};
+use rand::Rng;
 use std::any::Any;
 use std::fmt;
 use std::time::SystemTime;
@@ -27,8 +28,9 @@ use crate::secure_channel;
 
 use heapless::LinearMap;
 
+use super::group::{GroupRx, GroupRxStatus};
 use super::packet::PacketPool;
-use super::session::CloneData;
+use super::session::{CloneData, GroupDetails, Session, SessionMode};
 use super::{mrp::ReliableMessage, packet::Packet, session::SessionHandle, session::SessionMgr};
 
 pub struct ExchangeCtx<'a> {
@@ -184,6 +186,11 @@ impl Exchange {
             info!("Skipping tx for terminated exchange {}", self.id);
             return Ok(());
         }
+        if session.is_group() {
+            info!("Not responding to a group message on exchange {}", self.id);
+            self.terminate();
+            return Ok(());
+        }
 
         trace!("payload: {:x?}", proto_tx.as_borrow_slice());
         info!(
@@ -237,6 +244,9 @@ pub struct ExchangeMgr {
     // keys: exch-id
     exchanges: LinearMap<u16, Exchange, MAX_EXCHANGES>,
     sess_mgr: SessionMgr,
+    // The ID of the next exchange that we initiate
+    next_exch_id: u16,
+    group_rx: Option<GroupRx>,
 }
 
 pub const MAX_MRP_ENTRIES: usize = 4;
@@ -246,9 +256,16 @@ impl ExchangeMgr {
         Self {
             sess_mgr,
             exchanges: Default::default(),
+            next_exch_id: rand::thread_rng().gen(),
+            group_rx: None,
         }
     }
 
+    /// Accept group messages through _group_rx_
+    pub fn set_group_rx(&mut self, group_rx: GroupRx) {
+        self.group_rx = Some(group_rx);
+    }
+
     pub fn get_sess_mgr(&mut self) -> &mut SessionMgr {
         &mut self.sess_mgr
     }
@@ -304,19 +321,28 @@ impl ExchangeMgr {
         // Get the session
         let (mut proto_rx, index) = self.sess_mgr.recv()?;
 
-        let index = if let Some(s) = index {
-            s
+        let index = if proto_rx.plain.is_group() {
+            match self.recv_group(&mut proto_rx)? {
+                Some(index) => index,
+                None => return Ok(None),
+            }
         } else {
-            // The sessions were full, evict one session, and re-perform post-recv
-            let evict_index = self.sess_mgr.get_lru();
-            self.evict_session(evict_index)?;
-            info!("Reattempting session creation");
-            self.sess_mgr.post_recv(&proto_rx)?.ok_or(Error::Invalid)?
+            let index = if let Some(s) = index {
+                s
+            } else {
+                // The sessions were full, evict one session, and re-perform post-recv
+                let evict_index = self.sess_mgr.get_lru();
+                self.evict_session(evict_index)?;
+                info!("Reattempting session creation");
+                self.sess_mgr.post_recv(&proto_rx)?.ok_or(Error::Invalid)?
+            };
+            // Decrypt the message
+            self.sess_mgr
+                .get_session_handle(index)
+                .recv(&mut proto_rx)?;
+            index
         };
-        let mut session = self.sess_mgr.get_session_handle(index);
-
-        // Decrypt the message
-        session.recv(&mut proto_rx)?;
+        let session = self.sess_mgr.get_session_handle(index);
 
         // Get the exchange
         let exch = ExchangeMgr::_get(
@@ -346,6 +372,111 @@ impl ExchangeMgr {
         }
     }
 
+    // Decrypt a group message, returning the index of the group session that it
+    // is dispatched on once it is accepted
+    fn recv_group(&mut self, proto_rx: &mut Packet) -> Result<Option<usize>, Error> {
+        let group_rx = match &self.group_rx {
+            Some(group_rx) => group_rx,
+            None => {
+                info!("Dropping group message, group messages aren't accepted");
+                return Ok(None);
+            }
+        };
+
+        match group_rx.recv(proto_rx)? {
+            GroupRxStatus::Accepted(fab_idx) => {
+                if proto_rx.get_proto_id() == secure_channel::common::PROTO_ID_SECURE_CHANNEL as u16
+                {
+                    error!("Dropping secure channel group message");
+                    return Err(Error::Invalid);
+                }
+                // GroupRx only accepts messages with a source node and a group
+                let node_id = proto_rx.plain.get_src_u64().ok_or(Error::Invalid)?;
+                let group_id = proto_rx.plain.get_dest_group_id().ok_or(Error::Invalid)?;
+                let group = GroupDetails::new(fab_idx, group_id);
+                self.get_or_add_group_session(proto_rx, node_id, group)
+                    .map(Some)
+            }
+            GroupRxStatus::SyncPending => {
+                info!("Dropping group message, the sender's counter is being synchronized");
+                Ok(None)
+            }
+            GroupRxStatus::SyncRequired { fab_idx, node_id } => {
+                info!(
+                    "Dropping group message, synchronizing the counter of node {:x}",
+                    node_id
+                );
+                self.start_msg_ctr_sync(fab_idx, node_id).map(|_| None)
+            }
+        }
+    }
+
+    fn get_or_add_group_session(
+        &mut self,
+        proto_rx: &Packet,
+        node_id: u64,
+        group: GroupDetails,
+    ) -> Result<usize, Error> {
+        if let Some(index) = self.sess_mgr.get_group(node_id, group) {
+            return Ok(index);
+        }
+        let new_session = || Session::new_group(proto_rx.peer, node_id, group);
+        match self.sess_mgr.add_session(new_session()) {
+            Err(Error::NoSpace) => {
+                let evict_index = self.sess_mgr.get_lru();
+                self.evict_session(evict_index)?;
+                self.sess_mgr.add_session(new_session())
+            }
+            result => result,
+        }
+    }
+
+    // Send a MsgCounterSyncReq to a peer over our CASE session with it, the response
+    // is handled by the secure channel
+    fn start_msg_ctr_sync(&mut self, fab_idx: u8, node_id: u64) -> Result<(), Error> {
+        let sess_idx = match self.sess_mgr.find_all(|s| {
+            s.get_local_fabric_idx() == Some(fab_idx) && s.get_peer_node_id() == Some(node_id)
+        })[..]
+        {
+            [sess_idx, ..] => sess_idx,
+            [] => {
+                error!(
+                    "No session with node {:x} to synchronize its group counter",
+                    node_id
+                );
+                return Err(Error::NoSession);
+            }
+        };
+
+        let mut proto_tx =
+            Slab::<PacketPool>::try_new(Packet::new_tx()?).ok_or(Error::PacketPoolExhaust)?;
+        self.group_rx
+            .as_ref()
+            .ok_or(Error::InvalidState)?
+            .msg_ctr_sync()
+            .create_sync_req(fab_idx, node_id, &mut proto_tx)?;
+
+        let exch_id = self.get_next_exch_id();
+        ExchangeMgr::_get(
+            &mut self.exchanges,
+            sess_idx,
+            exch_id,
+            Role::Initiator,
+            true,
+        )?;
+        self.send(exch_id, proto_tx)
+    }
+
+    fn get_next_exch_id(&mut self) -> u16 {
+        // The exchanges that peers initiate use IDs of their choosing
+        while self.exchanges.contains_key(&self.next_exch_id) {
+            self.next_exch_id = self.next_exch_id.wrapping_add(1);
+        }
+        let exch_id = self.next_exch_id;
+        self.next_exch_id = self.next_exch_id.wrapping_add(1);
+        exch_id
+    }
+
     pub fn send(&mut self, exch_id: u16, proto_tx: BoxSlab<PacketPool>) -> Result<(), Error> {
         let exchange =
             ExchangeMgr::_get_with_id(&mut self.exchanges, exch_id).ok_or(Error::NoExchange)?;
@@ -357,7 +488,13 @@ impl ExchangeMgr {
         let mut to_purge: LinearMap<u16, (), MAX_EXCHANGES> = LinearMap::new();
 
         for (exch_id, exchange) in self.exchanges.iter() {
-            if exchange.is_purgeable() {
+            // Nothing is sent back on a group session, its exchanges are done once
+            // the message has been handled
+            let is_group = self
+                .sess_mgr
+                .mut_by_index(exchange.sess_idx)
+                .is_some_and(|s| s.is_group());
+            if exchange.is_purgeable() || is_group {
                 let _ = to_purge.insert(*exch_id, ());
             }
         }
@@ -378,7 +515,32 @@ impl ExchangeMgr {
         info!("Sessions full, vacating session with index: {}", index);
         // If we enter here, we have an LRU session that needs to be reclaimed
         // As per the spec, we need to send a CLOSE here
+        self.close_session(index)
+    }
+
+    /// Close all the sessions that match _f_, like the PASE sessions once the
+    /// fail-safe expires
+    pub fn close_sessions<T>(&mut self, f: T) -> Result<(), Error>
+    where
+        T: Fn(&Session) -> bool,
+    {
+        for index in self.sess_mgr.find_all(f) {
+            info!("Closing session with index: {}", index);
+            self.close_session(index)?;
+        }
+        Ok(())
+    }
 
+    /// Close the CASE sessions of the fabric at _fab_idx_, once it is removed
+    pub fn close_fabric_sessions(&mut self, fab_idx: u8) -> Result<(), Error> {
+        self.close_sessions(|s| match s.get_session_mode() {
+            SessionMode::Case(c) => c.fab_idx == fab_idx,
+            SessionMode::Group(g) => g.fab_idx == fab_idx,
+            _ => false,
+        })
+    }
+
+    fn close_session(&mut self, index: usize) -> Result<(), Error> {
         let mut session = self.sess_mgr.get_session_handle(index);
         let mut tx = Slab::<PacketPool>::try_new(Packet::new_tx()?).ok_or(Error::NoSpace)?;
         secure_channel::common::create_sc_status_report(
@@ -450,12 +612,29 @@ impl fmt::Display for ExchangeMgr {
 #[allow(clippy::bool_assert_comparison)]
 mod tests {
 
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    };
+
+    use boxslab::Slab;
+    use byteorder::{ByteOrder, LittleEndian};
+
     use crate::{
+        crypto,
         error::Error,
+        secure_channel::{common::OpCode, msg_ctr_sync::MsgCtrSync},
         transport::{
+            group::{GroupKeyLookup, GroupRx},
             network::{Address, NetworkInterface},
-            session::{CloneData, SessionMgr, SessionMode, MAX_SESSIONS},
+            packet::{Packet, PacketPool},
+            proto_demux::ProtoCtx,
+            proto_hdr::ProtoHdr,
+            session::{
+                CaseDetails, CloneData, GroupDetails, SessionMgr, SessionMode, MAX_SESSIONS,
+            },
         },
+        utils::writebuf::WriteBuf,
     };
 
     use super::{ExchangeMgr, Role};
@@ -601,4 +780,219 @@ mod tests {
         }
         //        println!("Session mgr {}", mgr.sess_mgr);
     }
+
+    const FAB_IDX: u8 = 1;
+    const LOCAL_NODE: u64 = 0x1111;
+    const PEER_NODE: u64 = 0x2222;
+    const LOCAL_SESS_ID: u16 = 10;
+    const PEER_SESS_ID: u16 = 20;
+    // The CASE session uses the default, all zeroes, keys
+    const SESSION_KEY: [u8; crypto::SYMM_KEY_LEN_BYTES] = [0; crypto::SYMM_KEY_LEN_BYTES];
+    const GROUP_ID: u16 = 0x0101;
+    const GROUP_SESS_ID: u16 = 0x4567;
+    const GROUP_KEY: [u8; crypto::SYMM_KEY_LEN_BYTES] = [0xa5; crypto::SYMM_KEY_LEN_BYTES];
+
+    // A network that receives the queued packets, and keeps the packets that are sent
+    struct TestNetwork {
+        rx: Arc<Mutex<VecDeque<Vec<u8>>>>,
+        tx: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl NetworkInterface for TestNetwork {
+        fn recv(&self, in_buf: &mut [u8]) -> Result<(usize, Address), Error> {
+            let msg = self.rx.lock().unwrap().pop_front().ok_or(Error::Timeout)?;
+            in_buf[..msg.len()].copy_from_slice(&msg);
+            Ok((msg.len(), Address::default()))
+        }
+
+        fn send(&self, out_buf: &[u8], _addr: Address) -> Result<usize, Error> {
+            self.tx.lock().unwrap().push(out_buf.to_vec());
+            Ok(out_buf.len())
+        }
+    }
+
+    struct TestGroupKeys;
+
+    impl GroupKeyLookup for TestGroupKeys {
+        fn op_keys(
+            &self,
+            group_id: u16,
+            sess_id: u16,
+        ) -> Vec<(u8, [u8; crypto::SYMM_KEY_LEN_BYTES])> {
+            if group_id == GROUP_ID && sess_id == GROUP_SESS_ID {
+                vec![(FAB_IDX, GROUP_KEY)]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    // Encrypt a message from _src_node_ with the plain-text header _plain_hdr_
+    fn encrypt_msg(
+        plain_hdr: &[u8],
+        src_node: u64,
+        key: &[u8],
+        proto: &mut ProtoHdr,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut buf = [0_u8; 200];
+        let mut wb = WriteBuf::new(&mut buf, 200);
+        proto.encode(&mut wb).unwrap();
+        wb.append(payload).unwrap();
+        wb.append(&[0; crypto::AEAD_MIC_LEN_BYTES]).unwrap();
+
+        // The nonce is the security flags, the message counter and the source node
+        let mut nonce = [0_u8; crypto::AEAD_NONCE_LEN_BYTES];
+        nonce[0] = plain_hdr[3];
+        nonce[1..5].copy_from_slice(&plain_hdr[4..8]);
+        LittleEndian::write_u64(&mut nonce[5..], src_node);
+
+        let cipher_text = wb.as_mut_slice();
+        let len = cipher_text.len();
+        crypto::encrypt_in_place(
+            key,
+            &nonce,
+            plain_hdr,
+            cipher_text,
+            len - crypto::AEAD_MIC_LEN_BYTES,
+        )
+        .unwrap();
+        [plain_hdr, cipher_text].concat()
+    }
+
+    // A group message from the peer, encrypted with _key_
+    fn group_msg(ctr: u32, key: &[u8]) -> Vec<u8> {
+        let mut plain_hdr = [0_u8; 18];
+        // Source node present, destination is a group
+        plain_hdr[0] = 0x06;
+        LittleEndian::write_u16(&mut plain_hdr[1..], GROUP_SESS_ID);
+        // Group session
+        plain_hdr[3] = 0x01;
+        LittleEndian::write_u32(&mut plain_hdr[4..], ctr);
+        LittleEndian::write_u64(&mut plain_hdr[8..], PEER_NODE);
+        LittleEndian::write_u16(&mut plain_hdr[16..], GROUP_ID);
+
+        let mut proto = ProtoHdr::default();
+        proto.set_initiator();
+        // An Interaction Model Invoke Request
+        proto.proto_id = 1;
+        proto.proto_opcode = 8;
+        encrypt_msg(&plain_hdr, PEER_NODE, key, &mut proto, &[0x15, 0x18])
+    }
+
+    fn new_rx(msg: &[u8]) -> Packet<'static> {
+        let mut rx = Packet::new_rx().unwrap();
+        rx.as_borrow_slice()[..msg.len()].copy_from_slice(msg);
+        rx.get_parsebuf().unwrap().set_len(msg.len());
+        rx.plain_hdr_decode().unwrap();
+        rx
+    }
+
+    #[test]
+    /// Group messages from a peer are only accepted once its counter is synchronized
+    /// through a MsgCounterSyncReq over its CASE session, which we send when we
+    /// receive its first group message
+    fn test_group_msg_ctr_sync() {
+        let rx_q = Arc::new(Mutex::new(VecDeque::new()));
+        let tx_q = Arc::new(Mutex::new(Vec::new()));
+        let mut sess_mgr = SessionMgr::new();
+        let network = Box::new(TestNetwork {
+            rx: rx_q.clone(),
+            tx: tx_q.clone(),
+        });
+        sess_mgr.add_network_interface(network).unwrap();
+        let mut mgr = ExchangeMgr::new(sess_mgr);
+        let mut msg_ctr_sync = MsgCtrSync::new();
+        mgr.set_group_rx(GroupRx::new(Arc::new(TestGroupKeys), msg_ctr_sync.clone()));
+        mgr.add_session(&CloneData::new(
+            LOCAL_NODE,
+            PEER_NODE,
+            PEER_SESS_ID,
+            LOCAL_SESS_ID,
+            Address::default(),
+            SessionMode::Case(CaseDetails::new(FAB_IDX, &[0; 3])),
+        ))
+        .unwrap();
+
+        // The first group message is dropped, and a sync request is sent to the peer
+        rx_q.lock().unwrap().push_back(group_msg(500, &GROUP_KEY));
+        assert!(matches!(mgr.recv(), Ok(None)));
+        let req = tx_q.lock().unwrap().pop().unwrap();
+        let mut req = new_rx(&req);
+        assert_eq!(req.plain.sess_id, PEER_SESS_ID);
+        req.proto_decode(LOCAL_NODE, Some(&SESSION_KEY)).unwrap();
+        assert!(req.proto.is_initiator());
+        assert_eq!(req.get_proto_opcode(), OpCode::MsgCounterSyncReq as u8);
+        let challenge = req.as_borrow_slice().to_vec();
+        assert_eq!(challenge.len(), 8);
+
+        // Until the response arrives, the group messages are dropped without another request
+        rx_q.lock().unwrap().push_back(group_msg(501, &GROUP_KEY));
+        assert!(matches!(mgr.recv(), Ok(None)));
+        assert!(tx_q.lock().unwrap().is_empty());
+
+        // The response comes back on the exchange of the request
+        let mut plain_hdr = [0_u8; 8];
+        LittleEndian::write_u16(&mut plain_hdr[1..], LOCAL_SESS_ID);
+        LittleEndian::write_u32(&mut plain_hdr[4..], 7000);
+        let mut proto = ProtoHdr {
+            exch_id: req.proto.exch_id,
+            proto_id: 0,
+            proto_opcode: OpCode::MsgCounterSyncResp as u8,
+            ..Default::default()
+        };
+        proto.set_ack(req.plain.ctr);
+        let mut payload = vec![0; 4];
+        LittleEndian::write_u32(&mut payload, 600);
+        payload.extend_from_slice(&challenge);
+        let rsp = encrypt_msg(&plain_hdr, PEER_NODE, &SESSION_KEY, &mut proto, &payload);
+        rx_q.lock().unwrap().push_back(rsp);
+        let (rx, exch_ctx) = mgr.recv().unwrap().unwrap();
+        assert_eq!(exch_ctx.exch.get_role(), Role::Initiator);
+        let tx = Slab::<PacketPool>::try_new(Packet::new_tx().unwrap()).unwrap();
+        let mut ctx = ProtoCtx::new(exch_ctx, rx, tx);
+        msg_ctr_sync.sync_rsp_handler(&mut ctx).unwrap();
+        drop(ctx);
+
+        // Now the group messages after the synchronized counter are accepted, just once
+        rx_q.lock().unwrap().push_back(group_msg(600, &GROUP_KEY));
+        assert!(matches!(mgr.recv(), Err(Error::Duplicate)));
+        rx_q.lock().unwrap().push_back(group_msg(601, &GROUP_KEY));
+        let (mut rx, exch_ctx) = mgr.recv().unwrap().unwrap();
+        assert_eq!(
+            exch_ctx.sess.get_session_mode(),
+            SessionMode::Group(GroupDetails::new(FAB_IDX, GROUP_ID))
+        );
+        assert_eq!(exch_ctx.sess.get_peer_node_id(), Some(PEER_NODE));
+        assert_eq!(rx.get_proto_id(), 1);
+        assert_eq!(rx.get_proto_opcode(), 8);
+        assert_eq!(rx.as_borrow_slice(), [0x15, 0x18]);
+
+        // Nothing is sent back to a group message, and its exchange is purged
+        let exch_id = exch_ctx.exch.get_id();
+        let tx = Slab::<PacketPool>::try_new(Packet::new_tx().unwrap()).unwrap();
+        mgr.send(exch_id, tx).unwrap();
+        assert!(tx_q.lock().unwrap().is_empty());
+        mgr.purge();
+        assert!(mgr.get_with_id(exch_id).is_none());
+
+        rx_q.lock().unwrap().push_back(group_msg(601, &GROUP_KEY));
+        assert!(matches!(mgr.recv(), Err(Error::Duplicate)));
+        assert!(tx_q.lock().unwrap().is_empty());
+
+        // Messages that don't decrypt with the group's key are rejected
+        rx_q.lock()
+            .unwrap()
+            .push_back(group_msg(602, &[0x5a; crypto::SYMM_KEY_LEN_BYTES]));
+        assert!(mgr.recv().is_err());
+        rx_q.lock().unwrap().push_back(group_msg(602, &GROUP_KEY));
+        assert!(mgr.recv().unwrap().is_some());
+
+        // The group sessions go away with the fabric
+        mgr.close_fabric_sessions(FAB_IDX).unwrap();
+        assert!(mgr
+            .sess_mgr
+            .get_group(PEER_NODE, GroupDetails::new(FAB_IDX, GROUP_ID))
+            .is_none());
+    }
 }
@@ -0,0 +1,95 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use log::{error, info};
+
+use crate::{crypto, error::Error, secure_channel::msg_ctr_sync::MsgCtrSync};
+
+use super::packet::Packet;
+
+/// Looks up the keys of the group messages that we receive
+pub trait GroupKeyLookup {
+    /// The operational group keys, with their fabric index, that a message to
+    /// _group_id_ with the group session ID _sess_id_ may be encrypted with
+    fn op_keys(&self, group_id: u16, sess_id: u16) -> Vec<(u8, [u8; crypto::SYMM_KEY_LEN_BYTES])>;
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GroupRxStatus {
+    /// The message is authentic and new, it was sent within this fabric
+    Accepted(u8),
+    /// The counter of the peer isn't synchronized, a MsgCounterSyncReq has to be sent
+    /// to it before its messages are accepted
+    SyncRequired { fab_idx: u8, node_id: u64 },
+    /// The counter of the peer is still being synchronized
+    SyncPending,
+}
+
+/// The receive path of group messages
+///
+/// Group messages aren't received over a session, they are decrypted with the
+/// operational key of the group, and are only accepted once the counter of the
+/// sender has been synchronized through [MsgCtrSync].
+pub struct GroupRx {
+    keys: Arc<dyn GroupKeyLookup>,
+    msg_ctr_sync: MsgCtrSync,
+}
+
+impl GroupRx {
+    pub fn new(keys: Arc<dyn GroupKeyLookup>, msg_ctr_sync: MsgCtrSync) -> Self {
+        Self { keys, msg_ctr_sync }
+    }
+
+    pub fn msg_ctr_sync(&self) -> &MsgCtrSync {
+        &self.msg_ctr_sync
+    }
+
+    /// Decrypt a group message and check its counter
+    ///
+    /// Duplicate messages are rejected with [Error::Duplicate]
+    pub fn recv(&self, rx: &mut Packet) -> Result<GroupRxStatus, Error> {
+        let (node_id, group_id) = match (rx.plain.get_src_u64(), rx.plain.get_dest_group_id()) {
+            (Some(node_id), Some(group_id)) => (node_id, group_id),
+            _ => {
+                error!("Group message without a source node or a destination group");
+                return Err(Error::Invalid);
+            }
+        };
+
+        let candidates = self.keys.op_keys(group_id, rx.plain.sess_id);
+        if candidates.is_empty() {
+            info!("No key for the messages to group {:x}", group_id);
+            return Err(Error::NotFound);
+        }
+        let keys: Vec<&[u8]> = candidates.iter().map(|(_, key)| &key[..]).collect();
+        let fab_idx = candidates[rx.proto_decode_with_keys(node_id, &keys)?].0;
+
+        let group_ctrs = self.msg_ctr_sync.group_ctrs();
+        let mut group_ctrs = group_ctrs.lock()?;
+        match group_ctrs.recv(fab_idx, node_id, rx.plain.ctr) {
+            Some(false) => Ok(GroupRxStatus::Accepted(fab_idx)),
+            Some(true) => {
+                info!("Dropping duplicate group message");
+                Err(Error::Duplicate)
+            }
+            None if group_ctrs.is_syncing(fab_idx, node_id) => Ok(GroupRxStatus::SyncPending),
+            None => Ok(GroupRxStatus::SyncRequired { fab_idx, node_id }),
+        }
+    }
+}
@@ -26,6 +26,7 @@ use crate::transport::mrp::ReliableMessage;
 use crate::transport::packet::PacketPool;
 use crate::transport::{exchange, packet::Packet, proto_demux, queue, session, udp};
 
+use super::group::GroupRx;
 use super::proto_demux::ProtoCtx;
 use super::queue::Msg;
 
@@ -33,6 +34,7 @@ pub struct Mgr {
     exch_mgr: exchange::ExchangeMgr,
     proto_demux: proto_demux::ProtoDemux,
     rx_q: Receiver<Msg>,
+    work_q: queue::WorkQ,
 }
 
 impl Mgr {
@@ -44,6 +46,7 @@ impl Mgr {
             proto_demux: proto_demux::ProtoDemux::new(),
             exch_mgr: exchange::ExchangeMgr::new(sess_mgr),
             rx_q: queue::WorkQ::init()?,
+            work_q: queue::WorkQ::get()?,
         })
     }
 
@@ -55,6 +58,11 @@ impl Mgr {
         self.proto_demux.register(proto_id_handle)
     }
 
+    /// Accept group messages through _group_rx_
+    pub fn set_group_rx(&mut self, group_rx: GroupRx) {
+        self.exch_mgr.set_group_rx(group_rx)
+    }
+
     fn send_to_exchange(
         &mut self,
         exch_id: u16,
@@ -65,7 +73,9 @@ impl Mgr {
 
     fn handle_rxtx(&mut self) -> Result<(), Error> {
         let result = self.exch_mgr.recv().map_err(|e| {
-            error!("Error in recv: {:?}", e);
+            if e != Error::Timeout {
+                error!("Error in recv: {:?}", e);
+            }
             e
         })?;
 
@@ -112,6 +122,12 @@ impl Mgr {
     }
 
     fn handle_queue_msgs(&mut self) -> Result<(), Error> {
+        for fab_idx in self.work_q.take_fabric_closes() {
+            let _ = self
+                .exch_mgr
+                .close_fabric_sessions(fab_idx)
+                .map_err(|e| error!("Error closing the sessions of fabric {:?}", e));
+        }
         if let Ok(msg) = self.rx_q.try_recv() {
             match msg {
                 Msg::NewSession(clone_data) => {
@@ -121,6 +137,18 @@ impl Mgr {
                         .add_session(&clone_data)
                         .map_err(|e| error!("Error adding new session {:?}", e));
                 }
+                Msg::ClosePaseSessions => {
+                    let _ = self
+                        .exch_mgr
+                        .close_sessions(|s| s.get_session_mode() == session::SessionMode::Pase)
+                        .map_err(|e| error!("Error closing the PASE sessions {:?}", e));
+                }
+                Msg::CloseFabricSessions(fab_idx) => {
+                    let _ = self
+                        .exch_mgr
+                        .close_fabric_sessions(fab_idx)
+                        .map_err(|e| error!("Error closing the sessions of fabric {:?}", e));
+                }
                 _ => {
                     error!("Queue Message Type not yet handled {:?}", msg);
                 }
@@ -132,9 +160,13 @@ impl Mgr {
     pub fn start(&mut self) -> Result<(), Error> {
         loop {
             // Handle network operations
-            if self.handle_rxtx().is_err() {
-                error!("Error in handle_rxtx");
-                continue;
+            match self.handle_rxtx() {
+                // Nothing was received in this turn, carry on with the periodic work
+                Ok(_) | Err(Error::Timeout) => (),
+                Err(_) => {
+                    error!("Error in handle_rxtx");
+                    continue;
+                }
             }
 
             if self.handle_queue_msgs().is_err() {
@@ -165,7 +197,10 @@ impl Mgr {
             //    This need not be done in each turn of the loop, maybe once in 5 times or so?
             self.exch_mgr.purge();
 
-            info!("Exchange Mgr: {}", self.exch_mgr);
+            // Let the protocols expire any time-bound state
+            self.proto_demux.handle_timer_tick();
+
+            debug!("Exchange Mgr: {}", self.exch_mgr);
         }
     }
 
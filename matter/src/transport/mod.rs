@@ -15,8 +15,9 @@
  *    limitations under the License.
  */
 
-mod dedup;
+pub mod dedup;
 pub mod exchange;
+pub mod group;
 pub mod mgr;
 pub mod mrp;
 pub mod network;
@@ -50,6 +50,8 @@ impl Debug for Address {
 }
 
 pub trait NetworkInterface {
+    /// Receive a packet, this returns [Error::Timeout] if nothing was received
+    /// within the interface's poll interval
     fn recv(&self, in_buf: &mut [u8]) -> Result<(usize, Address), Error>;
     fn send(&self, out_buf: &[u8], addr: Address) -> Result<usize, Error>;
 }
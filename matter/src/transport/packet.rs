@@ -203,6 +203,42 @@ impl<'a> Packet<'a> {
         }
     }
 
+    /// Decrypt and decode a message that could be encrypted with any of _keys_, like
+    /// group messages are
+    ///
+    /// Returns the position of the key that decrypted the message
+    pub fn proto_decode_with_keys(
+        &mut self,
+        peer_nodeid: u64,
+        keys: &[&[u8]],
+    ) -> Result<usize, Error> {
+        match &mut self.data {
+            Direction::Rx(pb, state) => {
+                if *state != RxState::PlainDecode {
+                    error!("Invalid state for proto_decode");
+                    return Err(Error::InvalidState);
+                }
+                *state = RxState::ProtoDecode;
+                let cipher_text = pb.as_borrow_slice().to_vec();
+                let mut result = Err(Error::NotFound);
+                for (i, key) in keys.iter().enumerate() {
+                    result = self
+                        .proto
+                        .decrypt_and_decode(&self.plain, pb, peer_nodeid, Some(key))
+                        .map(|_| i);
+                    // Once the decryption succeeds, the buffer has been consumed
+                    if result.is_ok() || pb.as_borrow_slice().len() != cipher_text.len() {
+                        break;
+                    }
+                    // The backend may have clobbered the buffer, restore it for the next key
+                    pb.as_borrow_slice().copy_from_slice(&cipher_text);
+                }
+                result
+            }
+            _ => Err(Error::InvalidState),
+        }
+    }
+
     pub fn is_plain_hdr_decoded(&self) -> Result<bool, Error> {
         match &self.data {
             Direction::Rx(_, state) => match state {
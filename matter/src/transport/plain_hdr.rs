@@ -25,6 +25,7 @@ use log::info;
 pub enum SessionType {
     None,
     Encrypted,
+    Group,
 }
 
 impl Default for SessionType {
@@ -42,14 +43,20 @@ bitflags! {
     }
 }
 
+// The Session Type field of the Security Flags
+const SEC_FLAGS_SESSION_TYPE_MASK: u8 = 0x03;
+const SEC_FLAGS_GROUP_SESSION: u8 = 0x01;
+
 // This is the unencrypted message
 #[derive(Debug, Default)]
 pub struct PlainHdr {
     pub flags: MsgFlags,
+    pub sec_flags: u8,
     pub sess_type: SessionType,
     pub sess_id: u16,
     pub ctr: u32,
     peer_nodeid: Option<u64>,
+    dest_group_id: Option<u16>,
 }
 
 impl PlainHdr {
@@ -65,6 +72,10 @@ impl PlainHdr {
             None
         }
     }
+
+    pub fn get_dest_group_id(&self) -> Option<u16> {
+        self.dest_group_id
+    }
 }
 
 impl PlainHdr {
@@ -72,8 +83,11 @@ impl PlainHdr {
     pub fn decode(&mut self, msg: &mut ParseBuf) -> Result<(), Error> {
         self.flags = MsgFlags::from_bits(msg.le_u8()?).ok_or(Error::Invalid)?;
         self.sess_id = msg.le_u16()?;
-        let _sec_flags = msg.le_u8()?;
-        self.sess_type = if self.sess_id != 0 {
+        self.sec_flags = msg.le_u8()?;
+        self.sess_type = if self.sec_flags & SEC_FLAGS_SESSION_TYPE_MASK == SEC_FLAGS_GROUP_SESSION
+        {
+            SessionType::Group
+        } else if self.sess_id != 0 {
             SessionType::Encrypted
         } else {
             SessionType::None
@@ -83,6 +97,12 @@ impl PlainHdr {
         if self.flags.contains(MsgFlags::SRC_ADDR_PRESENT) {
             self.peer_nodeid = Some(msg.le_u64()?);
         }
+        if self.flags.contains(MsgFlags::DSIZ_UNICAST_NODEID) {
+            // We are the destination
+            msg.le_u64()?;
+        } else if self.flags.contains(MsgFlags::DSIZ_GROUPCAST_NODEID) {
+            self.dest_group_id = Some(msg.le_u16()?);
+        }
 
         info!(
             "[decode] flags: {:?}, session type: {:#?}, sess_id: {}, ctr: {}",
@@ -105,6 +125,10 @@ impl PlainHdr {
     pub fn is_encrypted(&self) -> bool {
         self.sess_type == SessionType::Encrypted
     }
+
+    pub fn is_group(&self) -> bool {
+        self.sess_type == SessionType::Group
+    }
 }
 
 pub const fn max_plain_hdr_len() -> usize {
@@ -16,6 +16,7 @@
  */
 
 use boxslab::BoxSlab;
+use log::error;
 
 use crate::error::*;
 
@@ -61,6 +62,12 @@ pub trait HandleProto {
     fn handle_session_event(&self) -> Result<(), Error> {
         Ok(())
     }
+
+    /// Called periodically from the transport's main loop, even if no traffic is
+    /// received, so that any time-bound state can be expired
+    fn handle_timer_tick(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 impl Default for ProtoDemux {
@@ -92,4 +99,16 @@ impl ProtoDemux {
             .ok_or(Error::NoHandler)?
             .handle_proto_id(proto_ctx);
     }
+
+    pub fn handle_timer_tick(&mut self) {
+        for handler in self.proto_id_handlers.iter_mut().flatten() {
+            if let Err(e) = handler.handle_timer_tick() {
+                error!(
+                    "Error in timer tick of proto {}: {:?}",
+                    handler.get_proto_id(),
+                    e
+                );
+            }
+        }
+    }
 }
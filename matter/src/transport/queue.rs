@@ -15,11 +15,12 @@
  *    limitations under the License.
  */
 
-use std::sync::Once;
+use std::sync::{Arc, Mutex, Once};
 
-use async_channel::{bounded, Receiver, Sender};
+use async_channel::{bounded, Receiver, Sender, TrySendError};
+use log::{error, info};
 
-use crate::error::Error;
+use crate::{error::Error, fabric::FabricListener};
 
 use super::session::CloneData;
 
@@ -28,11 +29,18 @@ pub enum Msg {
     Tx(),
     Rx(),
     NewSession(CloneData),
+    /// Close the PASE sessions, once the fail-safe expires
+    ClosePaseSessions,
+    /// Close the CASE sessions of a fabric, once it is removed
+    CloseFabricSessions(u8),
 }
 
 #[derive(Clone)]
 pub struct WorkQ {
     tx: Sender<Msg>,
+    // The fabrics whose sessions couldn't be closed through the queue, because it
+    // was full
+    closes: Arc<Mutex<Vec<u8>>>,
 }
 
 static mut G_WQ: Option<WorkQ> = None;
@@ -48,11 +56,18 @@ impl WorkQ {
     fn configure(tx: Sender<Msg>) {
         unsafe {
             INIT.call_once(|| {
-                G_WQ = Some(WorkQ { tx });
+                G_WQ = Some(WorkQ::new(tx));
             });
         }
     }
 
+    fn new(tx: Sender<Msg>) -> Self {
+        Self {
+            tx,
+            closes: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
     pub fn get() -> Result<WorkQ, Error> {
         unsafe { G_WQ.as_ref().cloned().ok_or(Error::Invalid) }
     }
@@ -61,7 +76,81 @@ impl WorkQ {
         smol::block_on(self.send(msg))
     }
 
+    /// Send without blocking, for senders that run in the loop that drains the
+    /// queue
+    pub fn try_send(&self, msg: Msg) -> Result<(), Error> {
+        self.tx.try_send(msg).map_err(|_| Error::NoSpace)
+    }
+
     pub async fn send(&self, msg: Msg) -> Result<(), Error> {
         self.tx.send(msg).await.map_err(|e| e.into())
     }
+
+    /// Take the fabrics whose sessions are still to be closed, because the queue
+    /// had no space for them when they were removed
+    pub fn take_fabric_closes(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.closes.lock().unwrap())
+    }
+}
+
+impl FabricListener for WorkQ {
+    fn fabric_removed(&self, fab_idx: u8) {
+        // The subscriptions and the other exchanges go away with the sessions.
+        // This runs in the loop that drains the queue, which can't wait for space.
+        match self.tx.try_send(Msg::CloseFabricSessions(fab_idx)) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) => {
+                info!("Queue full, deferring the close of fabric {}", fab_idx);
+                self.closes.lock().unwrap().push(fab_idx);
+            }
+            Err(TrySendError::Closed(_)) => {
+                error!(
+                    "Can't close the sessions of fabric {}, no transport",
+                    fab_idx
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_channel::bounded;
+
+    use crate::fabric::FabricListener;
+
+    use super::{Msg, WorkQ};
+
+    #[test]
+    fn test_fabric_removed_closes_sessions() {
+        let (tx, rx) = bounded(3);
+        let wq = WorkQ::new(tx);
+        wq.fabric_removed(2);
+        assert!(matches!(rx.try_recv(), Ok(Msg::CloseFabricSessions(2))));
+        assert!(wq.take_fabric_closes().is_empty());
+    }
+
+    #[test]
+    fn test_fabric_removed_defers_when_full() {
+        let (tx, rx) = bounded(1);
+        let wq = WorkQ::new(tx);
+        wq.try_send(Msg::ClosePaseSessions).unwrap();
+        wq.fabric_removed(2);
+        wq.clone().fabric_removed(3);
+        // Only the message that was queued before is there
+        assert!(matches!(rx.try_recv(), Ok(Msg::ClosePaseSessions)));
+        assert!(rx.try_recv().is_err());
+        // The clones share the deferred closes
+        assert_eq!(wq.take_fabric_closes(), vec![2, 3]);
+        assert!(wq.take_fabric_closes().is_empty());
+    }
+
+    #[test]
+    fn test_fabric_removed_without_transport() {
+        let (tx, rx) = bounded(3);
+        let wq = WorkQ::new(tx);
+        drop(rx);
+        wq.fabric_removed(2);
+        assert!(wq.take_fabric_closes().is_empty());
+    }
 }
@@ -58,12 +58,28 @@ impl CaseDetails {
     }
 }
 
+/// The group that a peer sends its group messages to
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct GroupDetails {
+    pub fab_idx: u8,
+    pub group_id: u16,
+}
+
+impl GroupDetails {
+    pub fn new(fab_idx: u8, group_id: u16) -> Self {
+        Self { fab_idx, group_id }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum SessionMode {
     // The Case session will capture the local fabric index
     Case(CaseDetails),
     Pase,
     PlainText,
+    // The group messages of a peer, they are decrypted with the keys of the group,
+    // and nothing is sent back
+    Group(GroupDetails),
 }
 
 impl Default for SessionMode {
@@ -147,6 +163,14 @@ impl Session {
         }
     }
 
+    /// The session of the group messages that _peer_nodeid_ sends to a group
+    pub fn new_group(peer_addr: Address, peer_nodeid: u64, group: GroupDetails) -> Session {
+        Session {
+            mode: SessionMode::Group(group),
+            ..Session::new(peer_addr, Some(peer_nodeid))
+        }
+    }
+
     // A new encrypted session always clones from a previous 'new' session
     pub fn clone(clone_from: &CloneData) -> Session {
         Session {
@@ -202,10 +226,14 @@ impl Session {
     pub fn is_encrypted(&self) -> bool {
         match self.mode {
             SessionMode::Case(_) | SessionMode::Pase => true,
-            SessionMode::PlainText => false,
+            SessionMode::PlainText | SessionMode::Group(_) => false,
         }
     }
 
+    pub fn is_group(&self) -> bool {
+        matches!(self.mode, SessionMode::Group(_))
+    }
+
     pub fn get_peer_node_id(&self) -> Option<u64> {
         self.peer_nodeid
     }
@@ -237,14 +265,14 @@ impl Session {
     pub fn get_dec_key(&self) -> Option<&[u8]> {
         match self.mode {
             SessionMode::Case(_) | SessionMode::Pase => Some(&self.dec_key),
-            SessionMode::PlainText => None,
+            SessionMode::PlainText | SessionMode::Group(_) => None,
         }
     }
 
     pub fn get_enc_key(&self) -> Option<&[u8]> {
         match self.mode {
             SessionMode::Case(_) | SessionMode::Pase => Some(&self.enc_key),
-            SessionMode::PlainText => None,
+            SessionMode::PlainText | SessionMode::Group(_) => None,
         }
     }
 
@@ -403,6 +431,21 @@ impl SessionMgr {
         self.add_session(session)
     }
 
+    /// The indices of all the sessions that match _f_
+    pub fn find_all<T>(&self, f: T) -> Vec<usize>
+    where
+        T: Fn(&Session) -> bool,
+    {
+        self.sessions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| match s {
+                Some(s) if f(s) => Some(i),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// This assumes that the higher layer has taken care of doing anything required
     /// as per the spec before the session is erased
     pub fn remove(&mut self, idx: usize) {
@@ -440,9 +483,11 @@ impl SessionMgr {
                 {
                     nodeid_matches = false;
                 }
+                // The group sessions aren't looked up by their session ID
                 x.local_sess_id == sess_id
                     && x.peer_addr == peer_addr
                     && x.is_encrypted() == is_encrypted
+                    && !x.is_group()
                     && nodeid_matches
             } else {
                 false
@@ -450,6 +495,15 @@ impl SessionMgr {
         })
     }
 
+    /// The session of the group messages that _peer_nodeid_ sends to _group_
+    pub fn get_group(&self, peer_nodeid: u64, group: GroupDetails) -> Option<usize> {
+        self.sessions.iter().position(|x| {
+            x.as_ref().is_some_and(|x| {
+                x.mode == SessionMode::Group(group) && x.peer_nodeid == Some(peer_nodeid)
+            })
+        })
+    }
+
     pub fn get_with_id(&mut self, sess_id: u16) -> Option<SessionHandle> {
         let index = self
             .sessions
@@ -520,8 +574,12 @@ impl SessionMgr {
         // Read unencrypted packet header
         rx.plain_hdr_decode()?;
 
-        // Get session
-        let sess_handle = self.post_recv(&rx)?;
+        // Get session, group messages are received outside of any session
+        let sess_handle = if rx.plain.is_group() {
+            None
+        } else {
+            self.post_recv(&rx)?
+        };
 
         Ok((rx, sess_handle))
     }
@@ -15,8 +15,14 @@
  *    limitations under the License.
  */
 
+use std::time::Duration;
+
 use crate::error::*;
-use smol::net::{Ipv6Addr, UdpSocket};
+use smol::{
+    future,
+    net::{Ipv6Addr, UdpSocket},
+    Timer,
+};
 
 use super::network::{Address, NetworkInterface};
 
@@ -32,6 +38,10 @@ pub const MAX_RX_BUF_SIZE: usize = 1583;
 /* The Matter Port */
 pub const MATTER_PORT: u16 = 5540;
 
+// The maximum time that a receive blocks for, this lets the transport loop
+// run its periodic work even if there is no traffic
+const RX_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 impl UdpListener {
     pub fn new() -> Result<UdpListener, Error> {
         Ok(UdpListener {
@@ -42,10 +52,18 @@ impl UdpListener {
 
 impl NetworkInterface for UdpListener {
     fn recv(&self, in_buf: &mut [u8]) -> Result<(usize, Address), Error> {
-        let (size, addr) = smol::block_on(self.socket.recv_from(in_buf)).map_err(|e| {
-            println!("Error on the network: {:?}", e);
-            Error::Network
-        })?;
+        let (size, addr) = smol::block_on(future::or(
+            async {
+                self.socket.recv_from(in_buf).await.map_err(|e| {
+                    println!("Error on the network: {:?}", e);
+                    Error::Network
+                })
+            },
+            async {
+                Timer::after(RX_POLL_INTERVAL).await;
+                Err(Error::Timeout)
+            },
+        ))?;
         Ok((size, Address::Udp(addr)))
     }
 
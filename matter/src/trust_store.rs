@@ -0,0 +1,262 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! The trusted root CA certificates of the operational fabrics
+//!
+//! Each fabric has a single trusted root, that is stored by its Fabric Index. The
+//! PAAs that device attestation is verified against are a separate set of roots,
+//! those are kept in an [AttestationTrustStore](crate::attestation::AttestationTrustStore).
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use log::{error, info};
+
+use crate::{
+    cert::{Cert, CertType},
+    error::Error,
+    fabric::{MAX_CERT_TLV_LEN, MAX_FABRIC_INDEX, MIN_FABRIC_INDEX},
+    sys::Psm,
+};
+
+// The fabrics stored their root under this key before, so existing storage remains
+// valid
+fn root_key(fab_idx: u8) -> String {
+    format!("fb{}rca", fab_idx)
+}
+
+#[derive(Default)]
+struct TrustStoreInner {
+    // The TLV encoded root of each fabric, by Fabric Index
+    roots: Vec<(u8, Vec<u8>)>,
+    // The root from AddTrustedRootCert, until a NOC that chains up to it is added
+    pending: Option<Vec<u8>>,
+}
+
+impl TrustStoreInner {
+    fn get(&self, fab_idx: u8) -> Option<&[u8]> {
+        self.roots
+            .iter()
+            .find(|(i, _)| *i == fab_idx)
+            .map(|(_, root)| root.as_slice())
+    }
+
+    fn set(&mut self, fab_idx: u8, root: Vec<u8>) {
+        self.roots.retain(|(i, _)| *i != fab_idx);
+        self.roots.push((fab_idx, root));
+    }
+}
+
+pub struct TrustStore {
+    inner: RwLock<TrustStoreInner>,
+    // The Option<> is solely because test execution is faster
+    psm: Option<Arc<Mutex<Psm>>>,
+}
+
+impl TrustStore {
+    pub fn new() -> Result<Self, Error> {
+        TrustStore::new_with(true)
+    }
+
+    pub fn new_with(psm_support: bool) -> Result<Self, Error> {
+        let psm = if psm_support { Some(Psm::get()?) } else { None };
+        Ok(Self {
+            inner: RwLock::new(TrustStoreInner::default()),
+            psm,
+        })
+    }
+
+    /// Hold the TLV encoded root CA certificate _root_ as the pending root
+    ///
+    /// This is what AddTrustedRootCert does under the fail-safe. The root must be a
+    /// valid self-signed RCAC. It only becomes the trusted root of a fabric once the
+    /// NOC of the fabric is added.
+    pub fn add_pending_root(&self, root: &[u8]) -> Result<(), Error> {
+        let cert = Cert::new(root)?;
+        if cert.get_cert_type() != Some(CertType::Rcac) {
+            error!("The trusted root isn't a RCAC");
+            return Err(Error::InvalidCertProfile);
+        }
+        // Verifies the self-signature and the validity period
+        cert.verify_chain_start().finalise()?;
+
+        info!("Added a pending trusted root");
+        self.inner.write()?.pending = Some(root.to_vec());
+        Ok(())
+    }
+
+    /// The pending root, as added with [TrustStore::add_pending_root]
+    pub fn pending_root(&self) -> Result<Cert, Error> {
+        match &self.inner.read()?.pending {
+            Some(root) => Cert::new(root),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Discard the pending root, once it is committed or the fail-safe is over
+    pub fn clear_pending_root(&self) -> Result<(), Error> {
+        self.inner.write()?.pending = None;
+        Ok(())
+    }
+
+    /// The trusted root of the fabric at _fab_idx_
+    ///
+    /// Roots are read from the storage the first time they are needed, which is
+    /// when the fabric is loaded.
+    pub fn get_root(&self, fab_idx: u8) -> Result<Cert, Error> {
+        if let Some(root) = self.inner.read()?.get(fab_idx) {
+            return Cert::new(root);
+        }
+        let psm = self.psm.as_ref().ok_or(Error::NotFound)?;
+        let mut tlv = Vec::new();
+        match psm.lock()?.get_kv_slice(&root_key(fab_idx), &mut tlv) {
+            Ok(_) if !tlv.is_empty() => (),
+            Err(Error::StorageCorrupted) => {
+                error!(
+                    "Trusted root at index {} is corrupted, ignoring it",
+                    fab_idx
+                );
+                return Err(Error::StorageCorrupted);
+            }
+            _ => return Err(Error::NotFound),
+        }
+        let cert = Cert::new(&tlv)?;
+        self.inner.write()?.set(fab_idx, tlv);
+        Ok(cert)
+    }
+
+    /// Trust _root_ for the fabric at _fab_idx_
+    pub fn set_root(&self, fab_idx: u8, root: &Cert) -> Result<(), Error> {
+        if !(MIN_FABRIC_INDEX..=MAX_FABRIC_INDEX).contains(&fab_idx) {
+            return Err(Error::Invalid);
+        }
+        let mut buf = [0u8; MAX_CERT_TLV_LEN];
+        let len = root.as_tlv(&mut buf)?;
+        let tlv = &buf[..len];
+
+        let mut inner = self.inner.write()?;
+        if let Some(psm) = self.psm.as_ref() {
+            psm.lock()?.set_kv_slice(&root_key(fab_idx), tlv)?;
+        }
+        inner.set(fab_idx, tlv.to_vec());
+        Ok(())
+    }
+
+    /// Stop trusting the root of the fabric at _fab_idx_
+    ///
+    /// The root is removed from the storage even if it was never read from it, and
+    /// there is nothing to do if the fabric has no root.
+    pub fn remove_root(&self, fab_idx: u8) -> Result<(), Error> {
+        let mut inner = self.inner.write()?;
+        inner.roots.retain(|(i, _)| *i != fab_idx);
+        if let Some(psm) = self.psm.as_ref() {
+            psm.lock()?.rm(&root_key(fab_idx));
+        }
+        Ok(())
+    }
+
+    /// Call _f_ with the TLV encoding of every trusted root, the pending one
+    /// included
+    ///
+    /// Fabrics that share a root only report it once, as the TrustedRootCerts
+    /// attribute requires.
+    pub fn for_each_root<T>(&self, mut f: T) -> Result<(), Error>
+    where
+        T: FnMut(&[u8]),
+    {
+        let inner = self.inner.read()?;
+        let mut reported: Vec<&[u8]> = Vec::new();
+        let roots = inner.roots.iter().map(|(_, root)| root);
+        for root in roots.chain(inner.pending.as_ref()) {
+            if !reported.contains(&root.as_slice()) {
+                f(root);
+                reported.push(root);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrustStore;
+    use crate::cert::{CertBuilder, CertType};
+    use crate::crypto::{get_provider, EC_POINT_LEN_BYTES};
+    use crate::error::Error;
+
+    fn build_root() -> Vec<u8> {
+        let key = get_provider().generate_key_pair().unwrap();
+        let mut pub_key = [0u8; EC_POINT_LEN_BYTES];
+        key.get_public_key(&mut pub_key).unwrap();
+        let root = CertBuilder::new(CertType::Rcac)
+            .subject_rcac_id(1)
+            .public_key(&pub_key)
+            .unwrap()
+            .sign(key.as_ref())
+            .unwrap();
+        let mut buf = [0u8; 400];
+        let len = root.as_tlv(&mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    fn roots(store: &TrustStore) -> Vec<Vec<u8>> {
+        let mut roots = Vec::new();
+        store.for_each_root(|r| roots.push(r.to_vec())).unwrap();
+        roots
+    }
+
+    #[test]
+    fn test_pending_root() {
+        let store = TrustStore::new_with(false).unwrap();
+        assert_eq!(Err(Error::NotFound), store.pending_root().map(|_| ()));
+
+        let root = build_root();
+        store.add_pending_root(&root).unwrap();
+        assert_eq!(vec![root.clone()], roots(&store));
+
+        // Committed to a fabric
+        let cert = store.pending_root().unwrap();
+        store.set_root(1, &cert).unwrap();
+        store.clear_pending_root().unwrap();
+        assert_eq!(Err(Error::NotFound), store.pending_root().map(|_| ()));
+        assert_eq!(vec![root.clone()], roots(&store));
+        assert_eq!(cert.get_pubkey(), store.get_root(1).unwrap().get_pubkey());
+
+        // Shared by two fabrics
+        store.set_root(2, &cert).unwrap();
+        assert_eq!(vec![root], roots(&store));
+
+        store.remove_root(1).unwrap();
+        store.remove_root(2).unwrap();
+        assert!(roots(&store).is_empty());
+        assert_eq!(Err(Error::NotFound), store.get_root(1).map(|_| ()));
+        // A fabric without a root has nothing to remove
+        store.remove_root(1).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_root() {
+        let store = TrustStore::new_with(false).unwrap();
+        assert!(store.add_pending_root(&[0x15, 0x18]).is_err());
+
+        // A certificate that isn't self-signed
+        let mut root = build_root();
+        let last = root.len() - 2;
+        root[last] ^= 0x01;
+        assert_eq!(Err(Error::InvalidSignature), store.add_pending_root(&root));
+        assert!(roots(&store).is_empty());
+    }
+}
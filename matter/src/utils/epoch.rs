@@ -0,0 +1,228 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! UTC time in the Matter epoch
+//!
+//! All times in Matter are in seconds since the Matter epoch (2000-01-01 00:00:00 UTC).
+//! Devices without an RTC may not know the current time, for these the Last Known
+//! Good UTC Time is persisted and used as a lower bound of the current time.
+
+use std::{
+    convert::TryFrom,
+    sync::{Arc, OnceLock, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{error, info};
+
+use crate::{error::Error, sys::Psm};
+
+/// Seconds between the UNIX epoch and the Matter epoch
+pub const MATTER_EPOCH_SECS: u64 = 946684800;
+
+const ST_LKGT: &str = "lkgt";
+
+/// A source of the current UTC time
+pub trait UtcTimeSource: Send + Sync {
+    /// The current time in seconds since the Matter epoch, or None if it isn't known
+    fn utc_time(&self) -> Option<u32>;
+}
+
+/// The system clock
+///
+/// A clock that reports a time before the Matter epoch is considered unset.
+pub struct SysTimeSource;
+
+impl UtcTimeSource for SysTimeSource {
+    fn utc_time(&self) -> Option<u32> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        now.checked_sub(MATTER_EPOCH_SECS)
+            .and_then(|t| u32::try_from(t).ok())
+    }
+}
+
+/// A time source that always returns the same time
+pub struct FixedTimeSource(pub Option<u32>);
+
+impl UtcTimeSource for FixedTimeSource {
+    fn utc_time(&self) -> Option<u32> {
+        self.0
+    }
+}
+
+/// The time against which certificates are validated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationTime {
+    /// The current UTC time
+    Current(u32),
+    /// The current time isn't known, but it is no earlier than the Last Known Good
+    /// UTC Time
+    LastKnownGood(u32),
+    /// Nothing is known about the current time
+    Unknown,
+}
+
+impl ValidationTime {
+    /// Pick the validation time, given the time source's time and the Last Known Good
+    /// UTC Time
+    ///
+    /// A time source that reports a time earlier than the Last Known Good UTC Time
+    /// is not trusted.
+    pub fn new(utc_time: Option<u32>, lkgt: Option<u32>) -> Self {
+        match (utc_time, lkgt) {
+            (Some(now), Some(lkgt)) if now < lkgt => {
+                error!(
+                    "UTC time {} is earlier than the Last Known Good UTC Time {}, ignoring",
+                    now, lkgt
+                );
+                ValidationTime::LastKnownGood(lkgt)
+            }
+            (Some(now), _) => ValidationTime::Current(now),
+            (None, Some(lkgt)) => ValidationTime::LastKnownGood(lkgt),
+            (None, None) => ValidationTime::Unknown,
+        }
+    }
+
+    /// Check a validity period against this time
+    ///
+    /// A _not_after_ of 0 means that there is no well-defined expiration date. With
+    /// only the Last Known Good UTC Time available, _not_before_ can't be checked,
+    /// since the current time may well be past it.
+    pub fn check(&self, not_before: u32, not_after: u32) -> Result<(), Error> {
+        let expired = |now: u32| not_after != 0 && now > not_after;
+        match *self {
+            ValidationTime::Current(now) => {
+                if now < not_before {
+                    return Err(Error::CertNotYetValid);
+                }
+                if expired(now) {
+                    return Err(Error::CertExpired);
+                }
+            }
+            ValidationTime::LastKnownGood(lkgt) => {
+                if expired(lkgt) {
+                    return Err(Error::CertExpired);
+                }
+            }
+            ValidationTime::Unknown => (),
+        }
+        Ok(())
+    }
+}
+
+struct EpochState {
+    source: Arc<dyn UtcTimeSource>,
+    // Loaded from the PSM on first use
+    lkgt: Option<Option<u32>>,
+}
+
+static G_EPOCH: OnceLock<RwLock<EpochState>> = OnceLock::new();
+
+fn epoch_state() -> &'static RwLock<EpochState> {
+    G_EPOCH.get_or_init(|| {
+        RwLock::new(EpochState {
+            source: Arc::new(SysTimeSource),
+            lkgt: None,
+        })
+    })
+}
+
+fn load_lkgt() -> Option<u32> {
+    let psm = Psm::get().ok()?;
+    let psm = psm.lock().ok()?;
+    let mut lkgt = 0;
+    match psm.get_kv_u64(ST_LKGT, &mut lkgt) {
+        Ok(()) => u32::try_from(lkgt).ok(),
+        Err(Error::StorageCorrupted) => {
+            error!("The Last Known Good UTC Time is corrupted, ignoring");
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Replace the source of the current UTC time
+pub fn set_time_source(source: Arc<dyn UtcTimeSource>) {
+    epoch_state().write().unwrap().source = source;
+}
+
+/// The current UTC time as reported by the time source
+pub fn utc_time() -> Option<u32> {
+    epoch_state().read().unwrap().source.utc_time()
+}
+
+/// The persisted Last Known Good UTC Time
+pub fn last_known_good_time() -> Option<u32> {
+    if let Some(lkgt) = epoch_state().read().unwrap().lkgt {
+        return lkgt;
+    }
+    let lkgt = load_lkgt();
+    epoch_state().write().unwrap().lkgt = Some(lkgt);
+    lkgt
+}
+
+/// Advance the Last Known Good UTC Time to _time_
+///
+/// The Last Known Good UTC Time never moves backwards, an earlier _time_ is ignored.
+pub fn update_last_known_good_time(time: u32) -> Result<(), Error> {
+    if matches!(last_known_good_time(), Some(lkgt) if lkgt >= time) {
+        return Ok(());
+    }
+    Psm::get()?.lock()?.set_kv_u64(ST_LKGT, time as u64)?;
+    epoch_state().write()?.lkgt = Some(Some(time));
+    info!("Last Known Good UTC Time updated to {}", time);
+    Ok(())
+}
+
+/// The time against which certificates should be validated right now
+pub fn get_validation_time() -> ValidationTime {
+    ValidationTime::new(utc_time(), last_known_good_time())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SysTimeSource, UtcTimeSource, ValidationTime};
+
+    #[test]
+    fn test_validation_time() {
+        assert_eq!(
+            ValidationTime::new(Some(1000), Some(500)),
+            ValidationTime::Current(1000)
+        );
+        assert_eq!(
+            ValidationTime::new(Some(1000), None),
+            ValidationTime::Current(1000)
+        );
+        assert_eq!(
+            ValidationTime::new(None, Some(500)),
+            ValidationTime::LastKnownGood(500)
+        );
+        assert_eq!(ValidationTime::new(None, None), ValidationTime::Unknown);
+        // A clock that is behind the Last Known Good UTC Time is not trusted
+        assert_eq!(
+            ValidationTime::new(Some(100), Some(500)),
+            ValidationTime::LastKnownGood(500)
+        );
+    }
+
+    #[test]
+    fn test_sys_time_source() {
+        // 2023-01-01 00:00:00 UTC in the Matter epoch
+        let now = SysTimeSource.utc_time().unwrap();
+        assert!(now > 725846400);
+    }
+}
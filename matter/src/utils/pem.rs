@@ -0,0 +1,187 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+//! PEM (RFC 7468) encoding of DER structures
+
+use log::error;
+
+use crate::error::Error;
+
+pub const LABEL_CERTIFICATE: &str = "CERTIFICATE";
+pub const LABEL_CSR: &str = "CERTIFICATE REQUEST";
+pub const LABEL_EC_PRIVATE_KEY: &str = "EC PRIVATE KEY";
+pub const LABEL_PRIVATE_KEY: &str = "PRIVATE KEY";
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const LINE_LEN: usize = 64;
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_CHARS[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Only the canonical encoding is accepted: the padding is complete and comes
+// last, and the unused bits of the last character are zero
+fn base64_decode(data: &str) -> Result<Vec<u8>, Error> {
+    if !data.len().is_multiple_of(4) {
+        return Err(Error::InvalidData);
+    }
+    let padding = data.len() - data.trim_end_matches('=').len();
+    if padding > 2 {
+        return Err(Error::InvalidData);
+    }
+    let data = &data[..data.len() - padding];
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut acc = 0_u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let val = BASE64_CHARS
+            .iter()
+            .position(|b| *b == c)
+            .ok_or(Error::InvalidData)? as u32;
+        acc = (acc << 6) | val;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    if acc != 0 {
+        return Err(Error::InvalidData);
+    }
+    Ok(out)
+}
+
+/// PEM encode _der_ with the label _label_
+pub fn encode(label: &str, der: &[u8]) -> String {
+    let b64 = base64_encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in b64.as_bytes().chunks(LINE_LEN) {
+        // The base64 alphabet is ASCII
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// Decode the first PEM block in _pem_, returning its label and its DER contents
+///
+/// Any text around the block is ignored, like the explanatory text that OpenSSL
+/// puts before certificates.
+pub fn decode(pem: &str) -> Result<(String, Vec<u8>), Error> {
+    const BEGIN: &str = "-----BEGIN ";
+    const END: &str = "-----END ";
+    const DASHES: &str = "-----";
+
+    let start = pem.find(BEGIN).ok_or(Error::InvalidData)? + BEGIN.len();
+    let label_end = start + pem[start..].find(DASHES).ok_or(Error::InvalidData)?;
+    let label = &pem[start..label_end];
+
+    let body_start = label_end + DASHES.len();
+    let end_marker = format!("{}{}{}", END, label, DASHES);
+    let body_end = body_start
+        + pem[body_start..].find(&end_marker).ok_or_else(|| {
+            error!("No PEM end marker for {}", label);
+            Error::InvalidData
+        })?;
+    let body: String = pem[body_start..body_end]
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    Ok((label.to_owned(), base64_decode(&body)?))
+}
+
+/// Decode the first PEM block in _pem_, which must have the label _label_
+pub fn decode_label(label: &str, pem: &str) -> Result<Vec<u8>, Error> {
+    let (found, der) = decode(pem)?;
+    if found != label {
+        error!("Expected PEM label {}, found {}", label, found);
+        return Err(Error::InvalidData);
+    }
+    Ok(der)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_decode, base64_encode, decode, decode_label, encode};
+    use crate::error::Error;
+
+    #[test]
+    fn test_base64() {
+        // RFC 4648 test vectors
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors {
+            assert_eq!(base64_encode(plain.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+        }
+        for invalid in [
+            "Zm9v!AAA", "Zm9", "Zg=", "Zg===", "Zm9v=", "Zg==Zg==", "Zh==", "Zm9=",
+        ] {
+            assert_eq!(
+                base64_decode(invalid),
+                Err(Error::InvalidData),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_pem_round_trip() {
+        let der: Vec<u8> = (0..=255).collect();
+        let pem = encode("TEST", &der);
+        assert!(pem.starts_with("-----BEGIN TEST-----\n"));
+        assert!(pem.lines().all(|l| l.len() <= 64));
+
+        let with_text = format!("Some explanatory text\n{}", pem);
+        assert_eq!(
+            decode(&with_text).unwrap(),
+            ("TEST".to_owned(), der.clone())
+        );
+        assert_eq!(decode_label("TEST", &pem).unwrap(), der);
+        assert_eq!(decode_label("OTHER", &pem), Err(Error::InvalidData));
+        assert_eq!(
+            decode("-----BEGIN TEST-----\nAAAA\n"),
+            Err(Error::InvalidData)
+        );
+    }
+}
@@ -0,0 +1,317 @@
+/*
+ *
+ *    Copyright (c) 2023 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+// The test PKI in tests/data/attestation was generated with OpenSSL: a PAA with
+// VID 0xFFF1, a PAI and a DAC with PID 0x8000, and a Certification Declaration
+// for PIDs 0x8000 and 0x8001 signed by a test signer.
+
+use std::path::PathBuf;
+
+use matter::{
+    attestation::{
+        verify_csr_response, AttestationInfo, AttestationTrustStore, AttestationVerifier,
+        CertificationDeclaration, CsrResponseInfo,
+    },
+    crypto::{get_provider, key_pair_from_pem, EC_POINT_LEN_BYTES, EC_SIGNATURE_LEN_BYTES},
+    data_model::cluster_basic_information::BasicInfoConfig,
+    error::Error,
+    tlv::{TLVWriter, TagType},
+    utils::{epoch::ValidationTime, writebuf::WriteBuf},
+};
+
+const NOW: ValidationTime = ValidationTime::Current(800000000);
+const NONCE: [u8; 32] = [0x11; 32];
+const CHALLENGE: [u8; 16] = [0x22; 16];
+
+fn data_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/attestation")
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(data_dir().join(name)).unwrap()
+}
+
+fn trust_store(with_cd_signer: bool) -> AttestationTrustStore {
+    let mut store = AttestationTrustStore::new();
+    assert_eq!(store.load_paa_dir(&data_dir().join("paa")).unwrap(), 1);
+    if with_cd_signer {
+        store.add_cd_signer(&read("cd_signer.der")).unwrap();
+    }
+    store
+}
+
+fn basic_info(vid: u16, pid: u16) -> BasicInfoConfig {
+    BasicInfoConfig {
+        vid,
+        pid,
+        ..Default::default()
+    }
+}
+
+// What the device returns in the Attestation Response
+struct Attestation {
+    elements: Vec<u8>,
+    signature: [u8; EC_SIGNATURE_LEN_BYTES],
+}
+
+fn sign_with_dac(elements: &[u8]) -> [u8; EC_SIGNATURE_LEN_BYTES] {
+    let dac_key = key_pair_from_pem(&String::from_utf8(read("dac_key.pem")).unwrap()).unwrap();
+    let mut msg = elements.to_vec();
+    msg.extend_from_slice(&CHALLENGE);
+    let mut signature = [0u8; EC_SIGNATURE_LEN_BYTES];
+    dac_key.sign_msg(&msg, &mut signature).unwrap();
+    signature
+}
+
+fn attest(nonce: &[u8]) -> Attestation {
+    let mut buf = [0u8; 1024];
+    let len = buf.len();
+    let mut wb = WriteBuf::new(&mut buf, len);
+    let mut tw = TLVWriter::new(&mut wb);
+    tw.start_struct(TagType::Anonymous).unwrap();
+    tw.str16(TagType::Context(1), &read("cd.der")).unwrap();
+    tw.str8(TagType::Context(2), nonce).unwrap();
+    tw.u32(TagType::Context(3), 0).unwrap();
+    tw.end_container().unwrap();
+    let elements = wb.as_borrow_slice().to_vec();
+
+    let signature = sign_with_dac(&elements);
+    Attestation {
+        elements,
+        signature,
+    }
+}
+
+fn verify(
+    store: AttestationTrustStore,
+    attestation: &Attestation,
+    basic_info: &BasicInfoConfig,
+    time: ValidationTime,
+) -> Result<CertificationDeclaration, Error> {
+    let dac = read("dac.der");
+    let pai = read("pai.der");
+    let info = AttestationInfo {
+        dac: &dac,
+        pai: &pai,
+        attestation_elements: &attestation.elements,
+        attestation_signature: &attestation.signature,
+        attestation_nonce: &NONCE,
+        attestation_challenge: &CHALLENGE,
+    };
+    AttestationVerifier::new(store).verify_at(&info, basic_info, time)
+}
+
+#[test]
+fn test_attestation_success() {
+    let cd = verify(
+        trust_store(true),
+        &attest(&NONCE),
+        &basic_info(0xFFF1, 0x8000),
+        NOW,
+    )
+    .unwrap();
+    assert_eq!(cd.vendor_id, 0xFFF1);
+    assert_eq!(cd.certificate_id, "ZIG20141ZB330001-24");
+}
+
+#[test]
+fn test_attestation_wrong_nonce() {
+    assert_eq!(
+        verify(
+            trust_store(true),
+            &attest(&[0x33; 32]),
+            &basic_info(0xFFF1, 0x8000),
+            NOW
+        )
+        .err(),
+        Some(Error::InvalidData)
+    );
+}
+
+#[test]
+fn test_attestation_bad_signature() {
+    let mut attestation = attest(&NONCE);
+    attestation.signature[0] ^= 0x01;
+    assert_eq!(
+        verify(
+            trust_store(true),
+            &attestation,
+            &basic_info(0xFFF1, 0x8000),
+            NOW
+        )
+        .err(),
+        Some(Error::InvalidSignature)
+    );
+}
+
+#[test]
+fn test_attestation_untrusted() {
+    // No PAA at all
+    assert_eq!(
+        verify(
+            AttestationTrustStore::new(),
+            &attest(&NONCE),
+            &basic_info(0xFFF1, 0x8000),
+            NOW
+        )
+        .err(),
+        Some(Error::UntrustedCert)
+    );
+    // A trusted PAA, but an unknown Certification Declaration signer
+    assert_eq!(
+        verify(
+            trust_store(false),
+            &attest(&NONCE),
+            &basic_info(0xFFF1, 0x8000),
+            NOW
+        )
+        .err(),
+        Some(Error::UntrustedCert)
+    );
+}
+
+#[test]
+fn test_load_paa_dir_skips_bad_files() {
+    let dir = std::env::temp_dir().join(format!("matter_paa_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(data_dir().join("paa/paa_fff1.pem"), dir.join("paa.pem")).unwrap();
+    // Can't be read as a file
+    std::fs::create_dir(dir.join("unreadable.der")).unwrap();
+    // Not a certificate
+    std::fs::write(dir.join("garbage.der"), [0x30, 0x03, 0x02, 0x01]).unwrap();
+    std::fs::write(
+        dir.join("garbage.pem"),
+        "-----BEGIN CERTIFICATE-----\nZg=\n",
+    )
+    .unwrap();
+
+    let mut store = AttestationTrustStore::new();
+    assert_eq!(store.load_paa_dir(&dir).unwrap(), 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_attestation_basic_info_mismatch() {
+    // The PID is in the Certification Declaration, but it isn't the DAC's
+    assert_eq!(
+        verify(
+            trust_store(true),
+            &attest(&NONCE),
+            &basic_info(0xFFF1, 0x8001),
+            NOW
+        )
+        .err(),
+        Some(Error::VidPidMismatch)
+    );
+    assert_eq!(
+        verify(
+            trust_store(true),
+            &attest(&NONCE),
+            &basic_info(0xFFF2, 0x8000),
+            NOW
+        )
+        .err(),
+        Some(Error::VidPidMismatch)
+    );
+}
+
+#[test]
+fn test_attestation_not_yet_valid() {
+    // The test certificates are valid from 2023-01-01
+    assert_eq!(
+        verify(
+            trust_store(true),
+            &attest(&NONCE),
+            &basic_info(0xFFF1, 0x8000),
+            ValidationTime::Current(700000000)
+        )
+        .err(),
+        Some(Error::CertNotYetValid)
+    );
+}
+
+// The fields of a CSRResponse command with _csr_, or with the CSR of a newly
+// generated key, whose public key is returned as well
+fn csr_response(nonce: &[u8], csr: Option<&[u8]>) -> (Vec<u8>, [u8; EC_POINT_LEN_BYTES]) {
+    let key = get_provider().generate_key_pair().unwrap();
+    let mut pubkey = [0u8; EC_POINT_LEN_BYTES];
+    key.get_public_key(&mut pubkey).unwrap();
+    let mut csr_buf = [0u8; 1024];
+    let csr = match csr {
+        Some(csr) => csr,
+        None => key.get_csr(&mut csr_buf).unwrap(),
+    };
+
+    let mut buf = [0u8; 1024];
+    let len = buf.len();
+    let mut wb = WriteBuf::new(&mut buf, len);
+    let mut tw = TLVWriter::new(&mut wb);
+    tw.start_struct(TagType::Anonymous).unwrap();
+    tw.str8(TagType::Context(1), csr).unwrap();
+    tw.str8(TagType::Context(2), nonce).unwrap();
+    tw.end_container().unwrap();
+    let elements = wb.as_borrow_slice().to_vec();
+    let signature = sign_with_dac(&elements);
+
+    let mut buf = [0u8; 1024];
+    let mut wb = WriteBuf::new(&mut buf, len);
+    let mut tw = TLVWriter::new(&mut wb);
+    tw.start_struct(TagType::Anonymous).unwrap();
+    tw.str16(TagType::Context(0), &elements).unwrap();
+    tw.str8(TagType::Context(1), &signature).unwrap();
+    tw.end_container().unwrap();
+    (wb.as_borrow_slice().to_vec(), pubkey)
+}
+
+fn verify_csr(resp: &[u8]) -> Result<[u8; EC_POINT_LEN_BYTES], Error> {
+    let dac = read("dac.der");
+    let info = CsrResponseInfo {
+        csr_response: resp,
+        csr_nonce: &NONCE,
+        attestation_challenge: &CHALLENGE,
+        dac: &dac,
+    };
+    let csr = verify_csr_response(&info)?;
+    let mut pubkey = [0u8; EC_POINT_LEN_BYTES];
+    pubkey.copy_from_slice(csr.get_pubkey());
+    Ok(pubkey)
+}
+
+#[test]
+fn test_csr_response_success() {
+    let (resp, pubkey) = csr_response(&NONCE, None);
+    assert_eq!(verify_csr(&resp), Ok(pubkey));
+}
+
+#[test]
+fn test_csr_response_wrong_nonce() {
+    let (resp, _) = csr_response(&[0x33; 32], None);
+    assert_eq!(verify_csr(&resp), Err(Error::InvalidData));
+}
+
+#[test]
+fn test_csr_response_bad_csr_signature() {
+    // A CSR whose signature doesn't match its key
+    let key = get_provider().generate_key_pair().unwrap();
+    let mut csr = [0u8; 1024];
+    let mut csr = key.get_csr(&mut csr).unwrap().to_vec();
+    let last = csr.len() - 1;
+    csr[last] ^= 0x01;
+    let (resp, _) = csr_response(&NONCE, Some(&csr));
+    assert_eq!(verify_csr(&resp), Err(Error::InvalidSignature));
+}
@@ -121,7 +121,9 @@ impl ClusterType for EchoCluster {
     ) -> Result<(), IMStatusCode> {
         match num::FromPrimitive::from_u16(attr.attr_id) {
             Some(Attributes::AttWriteList) => {
-                attr_list_write(attr, data, |op, data| self.write_attr_list(&op, data))
+                attr_list_write(attr, data, check_attr_list, |op, data| {
+                    self.write_attr_list(&op, data)
+                })
             }
             _ => self.base.write_attribute_from_tlv(attr.attr_id, data),
         }
@@ -224,20 +226,23 @@ impl EchoCluster {
             }
             ListOperation::EditItem(index) => {
                 let data = data.u16().map_err(|_| IMStatusCode::Failure)?;
-                if tc.write_list[*index as usize].is_some() {
-                    tc.write_list[*index as usize] = Some(data);
-                    Ok(())
-                } else {
-                    Err(IMStatusCode::InvalidAction)
+                match tc.write_list.get_mut(*index as usize) {
+                    Some(Some(item)) => {
+                        *item = data;
+                        Ok(())
+                    }
+                    _ => Err(IMStatusCode::InvalidAction),
                 }
             }
             ListOperation::DeleteItem(index) => {
-                if tc.write_list[*index as usize].is_some() {
-                    tc.write_list[*index as usize] = None;
-                    Ok(())
-                } else {
-                    Err(IMStatusCode::InvalidAction)
+                let index = *index as usize;
+                if tc.write_list.get(index).copied().flatten().is_none() {
+                    return Err(IMStatusCode::InvalidAction);
                 }
+                // The items after it move up, so that the list has no holes
+                tc.write_list[index..].rotate_left(1);
+                tc.write_list[WRITE_LIST_MAX - 1] = None;
+                Ok(())
             }
             ListOperation::DeleteList => {
                 for i in 0..WRITE_LIST_MAX {
@@ -248,3 +253,16 @@ impl EchoCluster {
         }
     }
 }
+
+// A whole list must fit, and hold only u16s
+fn check_attr_list(list: &TLVElement) -> Result<(), IMStatusCode> {
+    let mut count = 0;
+    for item in list.enter().ok_or(IMStatusCode::Failure)? {
+        item.u16().map_err(|_| IMStatusCode::Failure)?;
+        count += 1;
+    }
+    if count > WRITE_LIST_MAX {
+        return Err(IMStatusCode::ResourceExhausted);
+    }
+    Ok(())
+}
@@ -70,6 +70,7 @@ pub struct ImInput<'a> {
     data: &'a dyn ToTLV,
     peer_id: u64,
     cat_ids: NocCatIds,
+    fab_idx: u8,
 }
 
 pub const IM_ENGINE_PEER_ID: u64 = 445566;
@@ -80,6 +81,7 @@ impl<'a> ImInput<'a> {
             data,
             peer_id: IM_ENGINE_PEER_ID,
             cat_ids: Default::default(),
+            fab_idx: 1,
         }
     }
 
@@ -90,6 +92,10 @@ impl<'a> ImInput<'a> {
     pub fn set_cat_ids(&mut self, cat_ids: &NocCatIds) {
         self.cat_ids = *cat_ids;
     }
+
+    pub fn set_fab_idx(&mut self, fab_idx: u8) {
+        self.fab_idx = fab_idx;
+    }
 }
 
 impl ImEngine {
@@ -152,7 +158,7 @@ impl ImEngine {
                 std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 5542,
             )),
-            SessionMode::Case(CaseDetails::new(1, &input.cat_ids)),
+            SessionMode::Case(CaseDetails::new(input.fab_idx, &input.cat_ids)),
         );
         let sess_idx = sess_mgr.clone_session(&clone_data).unwrap();
         let sess = sess_mgr.get_session_handle(sess_idx);
@@ -16,7 +16,7 @@
  */
 
 use matter::{
-    acl::{gen_noc_cat, AclEntry, AuthMode, Target},
+    acl::{gen_noc_cat, AclEntry, AclExtension, AuthMode, Target},
     data_model::{
         objects::{AttrValue, EncodeValue, Privilege},
         system_model::access_control,
@@ -29,7 +29,7 @@ use matter::{
         },
         messages::{msg, GenericPath},
     },
-    tlv::{self, ElementType, FromTLV, TLVArray, TLVElement, TLVWriter, TagType},
+    tlv::{self, ElementType, FromTLV, Nullable, TLVArray, TLVElement, TLVWriter, TagType, ToTLV},
     transport::session::NocCatIds,
 };
 
@@ -511,11 +511,10 @@ fn write_with_runtime_acl_add() {
         Some(access_control::ID),
         Some(access_control::Attributes::Acl as u32),
     );
-    let acl_input = AttrData::new(
-        None,
-        AttrPath::new(&acl_att),
-        EncodeValue::Value(&allow_acl),
-    );
+    // A NULL list index appends the entry to the ACL
+    let mut acl_path = AttrPath::new(&acl_att);
+    acl_path.list_index = Some(Nullable::Null);
+    let acl_input = AttrData::new(None, acl_path, EncodeValue::Value(&allow_acl));
 
     // Create ACL that only allows write to the ACL Cluster
     let mut basic_acl = AclEntry::new(1, Privilege::ADMIN, AuthMode::Case);
@@ -541,6 +540,102 @@ fn write_with_runtime_acl_add() {
     assert_eq!(AttrValue::Uint16(val0), read_cluster_id_write_attr(&im, 0));
 }
 
+#[test]
+/// A write of the whole ACL replaces the entries of the fabric, or leaves them as
+/// they are if the list can't be written in full
+///    - Write Attr to the ACL with an invalid entry (the ACL is kept)
+///    - Write Attr to the ACL with too many entries (the ACL is kept)
+///    - Write Attr to Echo Cluster (permission denied)
+///    - Write Attr to the ACL with an entry that grants universal access
+///    - Write Attr to Echo Cluster again (successful this time)
+fn write_with_runtime_acl_replace() {
+    let _ = env_logger::try_init();
+    let peer = 98765;
+    let mut im = ImEngine::new();
+
+    let val0 = 10;
+    let attr_data0 = |tag, t: &mut TLVWriter| {
+        let _ = t.u16(tag, val0);
+    };
+    let ep0_att = GenericPath::new(
+        Some(0),
+        Some(echo_cluster::ID),
+        Some(echo_cluster::Attributes::AttWrite as u32),
+    );
+    let input0 = AttrData::new(
+        None,
+        AttrPath::new(&ep0_att),
+        EncodeValue::Closure(&attr_data0),
+    );
+
+    // Create ACL to allow our peer ADMIN on everything
+    let mut allow_acl = AclEntry::new(1, Privilege::ADMIN, AuthMode::Case);
+    allow_acl.add_subject(peer).unwrap();
+
+    let acl_att = GenericPath::new(
+        Some(0),
+        Some(access_control::ID),
+        Some(access_control::Attributes::Acl as u32),
+    );
+    let invalid_list = |tag, t: &mut TLVWriter| {
+        let _ = t.start_array(tag);
+        let _ = allow_acl.to_tlv(t, TagType::Anonymous);
+        let _ = t.u8(TagType::Anonymous, 5);
+        let _ = t.end_container();
+    };
+    let long_list = |tag, t: &mut TLVWriter| {
+        let _ = t.start_array(tag);
+        for _ in 0..4 {
+            let _ = allow_acl.to_tlv(t, TagType::Anonymous);
+        }
+        let _ = t.end_container();
+    };
+    let allow_list = |tag, t: &mut TLVWriter| {
+        let _ = t.start_array(tag);
+        let _ = allow_acl.to_tlv(t, TagType::Anonymous);
+        let _ = t.end_container();
+    };
+    let acl_path = AttrPath::new(&acl_att);
+    let invalid_input = AttrData::new(None, acl_path, EncodeValue::Closure(&invalid_list));
+    let long_input = AttrData::new(None, acl_path, EncodeValue::Closure(&long_list));
+    let acl_input = AttrData::new(None, acl_path, EncodeValue::Closure(&allow_list));
+
+    // Create ACL that only allows write to the ACL Cluster
+    let mut basic_acl = AclEntry::new(1, Privilege::ADMIN, AuthMode::Case);
+    basic_acl.add_subject(peer).unwrap();
+    basic_acl
+        .add_target(Target::new(Some(0), Some(access_control::ID), None))
+        .unwrap();
+    im.acl_mgr.add(basic_acl).unwrap();
+    // Replacing the ACL doesn't touch the extensions
+    im.acl_mgr
+        .add_extension(AclExtension::new(1, &[0x15, 0x18]).unwrap())
+        .unwrap();
+
+    handle_write_reqs(
+        &mut im,
+        peer,
+        None,
+        &[invalid_input, long_input, input0, acl_input, input0],
+        &[
+            AttrStatus::new(&acl_att, IMStatusCode::ConstraintError, 0),
+            AttrStatus::new(&acl_att, IMStatusCode::ResourceExhausted, 0),
+            AttrStatus::new(&ep0_att, IMStatusCode::UnsupportedAccess, 0),
+            AttrStatus::new(&acl_att, IMStatusCode::Success, 0),
+            AttrStatus::new(&ep0_att, IMStatusCode::Success, 0),
+        ],
+    );
+    assert_eq!(AttrValue::Uint16(val0), read_cluster_id_write_attr(&im, 0));
+
+    let mut acls = Vec::new();
+    im.acl_mgr.for_each_acl(|e| acls.push(*e)).unwrap();
+    allow_acl.fab_idx = Some(1);
+    assert_eq!(acls, [allow_acl]);
+    let mut exts = 0;
+    im.acl_mgr.for_each_extension(|_| exts += 1).unwrap();
+    assert_eq!(exts, 1);
+}
+
 #[test]
 /// Data Version filtering should ignore the attributes that are filtered
 /// - in case of wildcard reads
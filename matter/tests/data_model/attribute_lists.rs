@@ -25,7 +25,7 @@ use matter::{
             msg::{WriteReq, WriteResp},
         },
     },
-    tlv::{self, FromTLV, Nullable},
+    tlv::{self, FromTLV, Nullable, TagType},
 };
 
 use crate::common::{
@@ -48,7 +48,8 @@ fn handle_write_reqs(input: &[AttrData], expected: &[AttrStatus]) -> DataModel {
 
 #[test]
 /// This tests all the attribute list operations
-/// add item, edit item, delete item, overwrite list, delete list
+/// add item, edit item, delete item, overwrite list, delete list, chunked overwrite,
+/// failed overwrite
 fn attr_list_ops() {
     let val0: u16 = 10;
     let val1: u16 = 15;
@@ -72,6 +73,7 @@ fn attr_list_ops() {
     let mut att_path = AttrPath::new(&att_data);
 
     // Test 1: Add Operation - add val0
+    att_path.list_index = Some(Nullable::Null);
     let input = &[AttrData::new(None, att_path, EncodeValue::Value(&val0))];
     let expected = &[AttrStatus::new(&att_data, IMStatusCode::Success, 0)];
     let _ = handle_write_reqs(input, expected);
@@ -102,7 +104,7 @@ fn attr_list_ops() {
         assert_eq!([Some(val0), Some(val0), None, None, None], tc.write_list);
     }
 
-    // Test 4: Delete Operation - delete index 0
+    // Test 4: Delete Operation - delete index 0, the next item moves up
     att_path.list_index = Some(Nullable::NotNull(0));
     let input = &[AttrData::new(None, att_path, delete_item)];
     let expected = &[AttrStatus::new(&att_data, IMStatusCode::Success, 0)];
@@ -110,7 +112,7 @@ fn attr_list_ops() {
 
     {
         let tc = tc_handle.lock().unwrap();
-        assert_eq!([None, Some(val0), None, None, None], tc.write_list);
+        assert_eq!([Some(val0), None, None, None, None], tc.write_list);
     }
 
     // Test 5: Overwrite Operation - overwrite first 2 entries
@@ -139,4 +141,92 @@ fn attr_list_ops() {
         let tc = tc_handle.lock().unwrap();
         assert_eq!([None, None, None, None, None], tc.write_list);
     }
+
+    // Test 7: Chunked Overwrite Operation - an empty list followed by appends
+    let mut append_path = att_path;
+    append_path.list_index = Some(Nullable::Null);
+    let input = &[
+        AttrData::new(None, att_path, delete_all),
+        AttrData::new(None, append_path, EncodeValue::Value(&val1)),
+        AttrData::new(None, append_path, EncodeValue::Value(&val0)),
+    ];
+    let expected = &[
+        AttrStatus::new(&att_data, IMStatusCode::Success, 0),
+        AttrStatus::new(&att_data, IMStatusCode::Success, 0),
+        AttrStatus::new(&att_data, IMStatusCode::Success, 0),
+    ];
+    let _ = handle_write_reqs(input, expected);
+
+    {
+        let tc = tc_handle.lock().unwrap();
+        assert_eq!([Some(val1), Some(val0), None, None, None], tc.write_list);
+    }
+
+    // Test 8: Overwrite Operations that can't be written in full leave the list as it is
+    let invalid_val = EncodeValue::Closure(&|tag, t| {
+        let _ = t.start_array(tag);
+        let _ = t.u16(TagType::Anonymous, 20);
+        let _ = t.utf8(TagType::Anonymous, b"21");
+        let _ = t.end_container();
+    });
+    let long_val: [u16; 6] = [20, 21, 22, 23, 24, 25];
+    let input = &[
+        AttrData::new(None, att_path, invalid_val),
+        AttrData::new(None, att_path, EncodeValue::Value(&long_val)),
+    ];
+    let expected = &[
+        AttrStatus::new(&att_data, IMStatusCode::Failure, 0),
+        AttrStatus::new(&att_data, IMStatusCode::ResourceExhausted, 0),
+    ];
+    let _ = handle_write_reqs(input, expected);
+
+    {
+        let tc = tc_handle.lock().unwrap();
+        assert_eq!([Some(val1), Some(val0), None, None, None], tc.write_list);
+    }
+
+    // Test 9: Delete index 0 and add, the item is appended after the remaining one
+    let mut delete_path = att_path;
+    delete_path.list_index = Some(Nullable::NotNull(0));
+    let input = &[
+        AttrData::new(None, delete_path, delete_item),
+        AttrData::new(None, append_path, EncodeValue::Value(&val1)),
+    ];
+    let expected = &[
+        AttrStatus::new(&att_data, IMStatusCode::Success, 0),
+        AttrStatus::new(&att_data, IMStatusCode::Success, 0),
+    ];
+    let _ = handle_write_reqs(input, expected);
+
+    {
+        let tc = tc_handle.lock().unwrap();
+        assert_eq!([Some(val0), Some(val1), None, None, None], tc.write_list);
+    }
+}
+
+#[test]
+/// A write without a list index must carry the whole list, and a list index
+/// must point to an existing item
+fn attr_list_invalid_ops() {
+    let val0: u16 = 10;
+
+    let _ = env_logger::try_init();
+
+    let att_data = GenericPath::new(
+        Some(0),
+        Some(echo_cluster::ID),
+        Some(echo_cluster::Attributes::AttWriteList as u32),
+    );
+    let mut att_path = AttrPath::new(&att_data);
+
+    // A single item without a list index is rejected
+    let input = &[AttrData::new(None, att_path, EncodeValue::Value(&val0))];
+    let expected = &[AttrStatus::new(&att_data, IMStatusCode::ConstraintError, 0)];
+    let _ = handle_write_reqs(input, expected);
+
+    // An edit beyond the end of the list
+    att_path.list_index = Some(Nullable::NotNull(10));
+    let input = &[AttrData::new(None, att_path, EncodeValue::Value(&val0))];
+    let expected = &[AttrStatus::new(&att_data, IMStatusCode::InvalidAction, 0)];
+    let _ = handle_write_reqs(input, expected);
 }
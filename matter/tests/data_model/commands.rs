@@ -17,12 +17,21 @@
 
 use crate::{
     cmd_data,
-    common::{commands::*, echo_cluster, im_engine::im_engine},
+    common::{
+        commands::*,
+        echo_cluster,
+        im_engine::{ImEngine, ImInput, IM_ENGINE_PEER_ID},
+    },
     echo_req, echo_resp,
 };
 
 use matter::{
-    data_model::{cluster_on_off, objects::EncodeValue},
+    acl::{AclEntry, AuthMode, Target},
+    data_model::{
+        cluster_on_off,
+        objects::{EncodeValue, Privilege},
+        sdm::admin_commissioning,
+    },
     interaction_model::{
         core::{IMStatusCode, OpCode},
         messages::{
@@ -36,6 +45,16 @@ use matter::{
 
 // Helper for handling Invoke Command sequences
 fn handle_commands(input: &[CmdData], expected: &[ExpectedInvResp]) {
+    let mut im = ImEngine::new();
+    handle_commands_from_peer(&mut im, IM_ENGINE_PEER_ID, input, expected)
+}
+
+fn handle_commands_from_peer(
+    im: &mut ImEngine,
+    peer_node_id: u64,
+    input: &[CmdData],
+    expected: &[ExpectedInvResp],
+) {
     let mut out_buf = [0u8; 400];
     let req = InvReq {
         suppress_response: Some(false),
@@ -43,7 +62,9 @@ fn handle_commands(input: &[CmdData], expected: &[ExpectedInvResp]) {
         inv_requests: Some(TLVArray::Slice(input)),
     };
 
-    let (_, _, out_buf) = im_engine(OpCode::InvokeRequest, &req, &mut out_buf);
+    let mut input = ImInput::new(OpCode::InvokeRequest, &req);
+    input.set_peer_node_id(peer_node_id);
+    let (_, out_buf) = im.process(&input, &mut out_buf);
     tlv::print_tlv_list(out_buf);
     let root = tlv::get_root_node_struct(out_buf).unwrap();
     let resp = msg::InvResp::from_tlv(&root).unwrap();
@@ -156,3 +177,55 @@ fn test_invoke_cmd_wc_endpoint_only_1_has_cluster() {
     ))];
     handle_commands(input, expected);
 }
+
+#[test]
+fn test_invoke_cmd_insufficient_perms() {
+    // The peer can only operate endpoint 1. Echo Request isn't declared by the
+    // cluster, so it needs the Operate privilege.
+    // - echo request on endpoint 0 - UnsupportedAccess
+    // - echo request with wildcard endpoint - only endpoint 1 responds
+    let _ = env_logger::try_init();
+
+    let peer = 98765;
+    let mut im = ImEngine::new();
+    let mut acl = AclEntry::new(1, Privilege::OPERATE, AuthMode::Case);
+    acl.add_subject(peer).unwrap();
+    acl.add_target(Target::new(Some(1), None, None)).unwrap();
+    im.acl_mgr.add(acl).unwrap();
+
+    let ep0_echo = CmdPath::new(
+        Some(0),
+        Some(echo_cluster::ID),
+        Some(echo_cluster::Commands::EchoReq as u16),
+    );
+    let wc_echo = CmdPath::new(
+        None,
+        Some(echo_cluster::ID),
+        Some(echo_cluster::Commands::EchoReq as u16),
+    );
+    let input = &[cmd_data!(ep0_echo, 5), cmd_data!(wc_echo, 5)];
+    let expected = &[
+        ExpectedInvResp::Status(CmdStatus::new(ep0_echo, IMStatusCode::UnsupportedAccess, 0)),
+        echo_resp!(1, 15),
+    ];
+    handle_commands_from_peer(&mut im, peer, input, expected);
+}
+
+#[test]
+fn test_invoke_cmd_needs_timed() {
+    // Revoke Commissioning may only be invoked in a timed interaction
+    let _ = env_logger::try_init();
+
+    let revoke = CmdPath::new(
+        Some(0),
+        Some(admin_commissioning::ID),
+        Some(admin_commissioning::Commands::RevokeComm as u16),
+    );
+    let input = &[cmd_data!(revoke, 0)];
+    let expected = &[ExpectedInvResp::Status(CmdStatus::new(
+        revoke,
+        IMStatusCode::NeedsTimedInteraction,
+        0,
+    ))];
+    handle_commands(input, expected);
+}
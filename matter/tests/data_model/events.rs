@@ -0,0 +1,199 @@
+/*
+ *
+ *    Copyright (c) 2020-2022 Project CHIP Authors
+ *
+ *    Licensed under the Apache License, Version 2.0 (the "License");
+ *    you may not use this file except in compliance with the License.
+ *    You may obtain a copy of the License at
+ *
+ *        http://www.apache.org/licenses/LICENSE-2.0
+ *
+ *    Unless required by applicable law or agreed to in writing, software
+ *    distributed under the License is distributed on an "AS IS" BASIS,
+ *    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ *    See the License for the specific language governing permissions and
+ *    limitations under the License.
+ */
+
+use matter::{
+    acl::{AclEntry, AuthMode},
+    data_model::{
+        objects::{EncodeValue, Privilege},
+        system_model::access_control::{self, ChangeType},
+    },
+    interaction_model::{
+        core::{IMStatusCode, OpCode},
+        messages::{
+            ib::{AttrData, AttrPath, AttrStatus, EventData, EventFilter, EventPath},
+            msg::{ReadReq, ReportDataMsg, WriteReq},
+            GenericPath,
+        },
+    },
+    tlv::{self, FromTLV, TLVWriter, TagType, ToTLV},
+};
+
+use crate::common::im_engine::{ImEngine, ImInput, IM_ENGINE_PEER_ID};
+
+// Read the events on _paths_ from _peer_ on _fab_idx_, newer than _event_min_
+fn read_events<'a>(
+    im: &mut ImEngine,
+    peer: u64,
+    fab_idx: u8,
+    paths: &[EventPath],
+    event_min: Option<u64>,
+    out_buf: &'a mut [u8],
+) -> Vec<EventData<'a>> {
+    let filters = event_min.map(|e| [EventFilter::new(e)]);
+    let mut read_req = ReadReq::new(true).set_event_requests(paths);
+    if let Some(filters) = &filters {
+        read_req = read_req.set_event_filters(filters);
+    }
+
+    let mut input = ImInput::new(OpCode::ReadRequest, &read_req);
+    input.set_peer_node_id(peer);
+    input.set_fab_idx(fab_idx);
+    let (_, out_buf) = im.process(&input, out_buf);
+
+    tlv::print_tlv_list(out_buf);
+    let root = tlv::get_root_node_struct(out_buf).unwrap();
+    let report = ReportDataMsg::from_tlv(&root).unwrap();
+    assert!(report.attr_reports.is_none());
+    report
+        .event_reports
+        .unwrap()
+        .iter()
+        .map(|e| e.unwrap_data())
+        .collect()
+}
+
+// Check the fields of an AccessControlEntryChanged event
+fn assert_entry_changed(event: &EventData, number: u64, change_type: ChangeType, entry: AclEntry) {
+    assert_eq!(
+        event.path,
+        EventPath::new(
+            Some(0),
+            Some(access_control::ID),
+            Some(access_control::Events::AccessControlEntryChanged as u32)
+        )
+    );
+    assert_eq!(event.event_number, number);
+    // Info priority
+    assert_eq!(event.priority, 1);
+    assert!(event.epoch_timestamp.unwrap() > 0);
+
+    let data = match event.data {
+        EncodeValue::Tlv(t) => t,
+        _ => panic!("No TLV data"),
+    };
+    assert_eq!(data.find_tag(1).unwrap().u64().unwrap(), IM_ENGINE_PEER_ID);
+    assert!(data.find_tag(2).unwrap().null().is_ok());
+    assert_eq!(data.find_tag(3).unwrap().u8().unwrap(), change_type as u8);
+    assert_eq!(
+        AclEntry::from_tlv(&data.find_tag(4).unwrap()).unwrap(),
+        entry
+    );
+    assert_eq!(data.find_tag(0xFE).unwrap().u8().unwrap(), 1);
+}
+
+#[test]
+/// The changes to the ACL are read as AccessControlEntryChanged events
+fn read_acl_events() {
+    let _ = env_logger::try_init();
+    let peer = 98765;
+    let mut im = ImEngine::new();
+
+    let mut default_acl = AclEntry::new(1, Privilege::ADMIN, AuthMode::Case);
+    default_acl.add_subject(IM_ENGINE_PEER_ID).unwrap();
+    let mut view_acl = AclEntry::new(1, Privilege::VIEW, AuthMode::Case);
+    view_acl.add_subject(peer).unwrap();
+    let acl_list = |tag, t: &mut TLVWriter| {
+        let _ = t.start_array(tag);
+        let _ = default_acl.to_tlv(t, TagType::Anonymous);
+        let _ = view_acl.to_tlv(t, TagType::Anonymous);
+        let _ = t.end_container();
+    };
+    let acl_att = GenericPath::new(
+        Some(0),
+        Some(access_control::ID),
+        Some(access_control::Attributes::Acl as u32),
+    );
+    let input = [AttrData::new(
+        None,
+        AttrPath::new(&acl_att),
+        EncodeValue::Closure(&acl_list),
+    )];
+
+    // Replace the ACL: the default entry is removed, and both entries added
+    let mut out_buf = [0u8; 400];
+    let write_req = WriteReq::new(false, &input);
+    let (_, out) = im.process(
+        &ImInput::new(OpCode::WriteRequest, &write_req),
+        &mut out_buf,
+    );
+    let root = tlv::get_root_node_struct(out).unwrap();
+    let status = root.find_tag(0).unwrap().enter().unwrap().next().unwrap();
+    assert_eq!(
+        AttrStatus::from_tlv(&status).unwrap(),
+        AttrStatus::new(&acl_att, IMStatusCode::Success, 0)
+    );
+
+    let acl_events = [EventPath::new(Some(0), Some(access_control::ID), None)];
+    let mut out_buf = [0u8; 800];
+    let events = read_events(
+        &mut im,
+        IM_ENGINE_PEER_ID,
+        1,
+        &acl_events,
+        None,
+        &mut out_buf,
+    );
+    assert_eq!(events.len(), 3);
+    assert_entry_changed(&events[0], 0, ChangeType::Removed, default_acl);
+    assert_entry_changed(&events[1], 1, ChangeType::Added, default_acl);
+    assert_entry_changed(&events[2], 2, ChangeType::Added, view_acl);
+
+    // Only the events from event_min on
+    let mut out_buf = [0u8; 800];
+    let events = read_events(
+        &mut im,
+        IM_ENGINE_PEER_ID,
+        1,
+        &acl_events,
+        Some(2),
+        &mut out_buf,
+    );
+    assert_eq!(events.len(), 1);
+    assert_entry_changed(&events[0], 2, ChangeType::Added, view_acl);
+
+    // The events of another cluster
+    let mut out_buf = [0u8; 800];
+    let events = read_events(
+        &mut im,
+        IM_ENGINE_PEER_ID,
+        1,
+        &[EventPath::new(None, Some(0x28), None)],
+        None,
+        &mut out_buf,
+    );
+    assert!(events.is_empty());
+
+    // Reading the events needs Administer
+    let mut out_buf = [0u8; 800];
+    let events = read_events(&mut im, peer, 1, &acl_events, None, &mut out_buf);
+    assert!(events.is_empty());
+
+    // An Administer of another fabric doesn't see them
+    let mut other_acl = AclEntry::new(2, Privilege::ADMIN, AuthMode::Case);
+    other_acl.add_subject(IM_ENGINE_PEER_ID).unwrap();
+    im.acl_mgr.add(other_acl).unwrap();
+    let mut out_buf = [0u8; 800];
+    let events = read_events(
+        &mut im,
+        IM_ENGINE_PEER_ID,
+        2,
+        &acl_events,
+        None,
+        &mut out_buf,
+    );
+    assert!(events.is_empty());
+}
@@ -19,7 +19,10 @@ use matter::{
     data_model::{
         cluster_basic_information as basic_info, cluster_on_off as onoff,
         objects::{EncodeValue, GlobalElements},
-        sdm::{admin_commissioning as adm_comm, general_commissioning as gen_comm, noc},
+        sdm::{
+            admin_commissioning as adm_comm, general_commissioning as gen_comm,
+            group_key_management as grp_key_mgmt, noc,
+        },
         system_model::{access_control as acl, descriptor},
     },
     interaction_model::{
@@ -113,6 +116,23 @@ fn wildcard_read_resp(part: u8) -> Vec<AttrResp<'static>> {
         attr_data!(0, 62, noc::Attributes::Fabrics, dont_care),
         attr_data!(0, 62, noc::Attributes::SupportedFabrics, dont_care),
         attr_data!(0, 62, noc::Attributes::CommissionedFabrics, dont_care),
+        attr_data!(0, 62, noc::Attributes::TrustedRootCerts, dont_care),
+        attr_data!(0, 63, GlobalElements::FeatureMap, dont_care),
+        attr_data!(0, 63, GlobalElements::AttributeList, dont_care),
+        attr_data!(0, 63, grp_key_mgmt::Attributes::GroupKeyMap, dont_care),
+        attr_data!(0, 63, grp_key_mgmt::Attributes::GroupTable, dont_care),
+        attr_data!(
+            0,
+            63,
+            grp_key_mgmt::Attributes::MaxGroupsPerFabric,
+            dont_care
+        ),
+        attr_data!(
+            0,
+            63,
+            grp_key_mgmt::Attributes::MaxGroupKeysPerFabric,
+            dont_care
+        ),
         attr_data!(0, 31, GlobalElements::FeatureMap, dont_care),
         attr_data!(0, 31, GlobalElements::AttributeList, dont_care),
         attr_data!(0, 31, acl::Attributes::Acl, dont_care),
@@ -122,15 +142,15 @@ fn wildcard_read_resp(part: u8) -> Vec<AttrResp<'static>> {
         attr_data!(0, 31, acl::Attributes::EntriesPerFabric, dont_care),
         attr_data!(0, echo::ID, GlobalElements::FeatureMap, dont_care),
         attr_data!(0, echo::ID, GlobalElements::AttributeList, dont_care),
+    ];
+
+    let part2 = vec![
         attr_data!(0, echo::ID, echo::Attributes::Att1, dont_care),
         attr_data!(0, echo::ID, echo::Attributes::Att2, dont_care),
         attr_data!(0, echo::ID, echo::Attributes::AttCustom, dont_care),
         attr_data!(1, 29, GlobalElements::FeatureMap, dont_care),
         attr_data!(1, 29, GlobalElements::AttributeList, dont_care),
         attr_data!(1, 29, descriptor::Attributes::DeviceTypeList, dont_care),
-    ];
-
-    let part2 = vec![
         attr_data!(1, 29, descriptor::Attributes::ServerList, dont_care),
         attr_data!(1, 29, descriptor::Attributes::PartsList, dont_care),
         attr_data!(1, 29, descriptor::Attributes::ClientList, dont_care),
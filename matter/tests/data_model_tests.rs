@@ -22,6 +22,7 @@ mod data_model {
     mod attribute_lists;
     mod attributes;
     mod commands;
+    mod events;
     mod long_reads;
     mod timed_requests;
 }
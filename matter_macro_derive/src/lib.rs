@@ -47,12 +47,13 @@ impl Default for TlvArgs {
 fn parse_tlvargs(ast: &DeriveInput) -> TlvArgs {
     let mut tlvargs: TlvArgs = Default::default();
 
-    if !ast.attrs.is_empty() {
-        if let List(MetaList {
+    // The doc comments are attributes too, look for the tlvargs among all of them
+    for attr in ast.attrs.iter() {
+        if let Ok(List(MetaList {
             path,
             paren_token: _,
             nested,
-        }) = ast.attrs[0].parse_meta().unwrap()
+        })) = attr.parse_meta()
         {
             if path.is_ident("tlvargs") {
                 for a in nested {
@@ -87,12 +88,12 @@ fn parse_tlvargs(ast: &DeriveInput) -> TlvArgs {
 }
 
 fn parse_tag_val(field: &syn::Field) -> Option<u8> {
-    if !field.attrs.is_empty() {
-        if let List(MetaList {
+    for attr in field.attrs.iter() {
+        if let Ok(List(MetaList {
             path,
             paren_token: _,
             nested,
-        }) = field.attrs[0].parse_meta().unwrap()
+        })) = attr.parse_meta()
         {
             if path.is_ident("tagval") {
                 for a in nested {
@@ -54,6 +54,11 @@ fn main() {
                 .long("as-asn1")
                 .help("Decode a Matter-encoded Certificate and encode as ASN1"),
         )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Decode a Matter-encoded Certificate and print it as JSON"),
+        )
         .arg(Arg::with_name("tlvs").help("List of TLVs").required(true))
         .get_matches();
 
@@ -93,6 +98,9 @@ fn main() {
     if m.is_present("cert") {
         let cert = cert::Cert::new(&tlv_list[..index]).unwrap();
         println!("{}", cert);
+    } else if m.is_present("json") {
+        let cert = cert::Cert::new(&tlv_list[..index]).unwrap();
+        print!("{}", cert.to_json().unwrap());
     } else if m.is_present("as-asn1") {
         let mut asn1_cert = [0_u8; 1024];
         let cert = cert::Cert::new(&tlv_list[..index]).unwrap();